@@ -0,0 +1,125 @@
+//! Messages per second of the parsers and updates per second of the order book.
+//!
+//! Run with `cargo bench`, add `--features simd-json` to compare the parsing backends.
+//! `parse/*/untagged` is the parse before messages were told apart by their event type.
+
+use std::path::Path;
+
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion, Throughput};
+use rust_decimal::Decimal;
+use serde::Deserialize;
+
+use binance_api_async::book::OrderBook;
+use binance_api_async::messages::{
+    AggTrade, BookTicker, DepthUpdate, ErrorResponse, Kline, PartialDepth, Trade,
+};
+use binance_api_async::{Message, MessageRef, Symbol};
+
+/// [`Message`] as it was parsed before the event type decided, the first variant whose fields
+/// are present, to compare against.
+#[derive(Deserialize)]
+#[serde(untagged)]
+#[allow(dead_code, clippy::large_enum_variant)]
+enum Untagged {
+    AggTrade(AggTrade),
+    Trade(Trade),
+    PartialDepth(PartialDepth),
+    BookTicker(BookTicker),
+    DepthUpdate(DepthUpdate),
+    Kline(Kline),
+    Error(ErrorResponse),
+    SubscribeSuccess { result: Option<String>, id: u64 },
+}
+
+/// The golden fixtures of every message type, compacted like Binance sends them.
+fn payloads() -> Vec<(String, String)> {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures");
+    let mut payloads: Vec<_> = std::fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| {
+            let path = entry.unwrap().path();
+            let name = path.file_stem().unwrap().to_str().unwrap().to_string();
+            let value: serde_json::Value =
+                serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+            (name, value.to_string())
+        })
+        // one payload per type, not the edge cases in `<event type>_<case>.json`
+        .filter(|(name, payload)| Message::from_json(payload).unwrap().event_type() == name)
+        .collect();
+    payloads.sort();
+    payloads
+}
+
+fn parsing(c: &mut Criterion) {
+    let mut group = c.benchmark_group("parse");
+    group.throughput(Throughput::Elements(1));
+    for (name, payload) in payloads() {
+        group.bench_function(format!("{name}/message"), |b| {
+            b.iter_batched(
+                || payload.clone(),
+                |payload| Message::from_frame(payload).unwrap(),
+                BatchSize::SmallInput,
+            )
+        });
+        group.bench_function(format!("{name}/message_ref"), |b| {
+            b.iter(|| MessageRef::parse(&payload).unwrap())
+        });
+        group.bench_function(format!("{name}/from_json"), |b| {
+            b.iter(|| Message::from_json(&payload).unwrap())
+        });
+        // payloads of the newer types are not parsed by the untagged enum
+        if serde_json::from_str::<Untagged>(&payload).is_ok() {
+            group.bench_function(format!("{name}/untagged"), |b| {
+                b.iter(|| serde_json::from_str::<Untagged>(&payload).unwrap())
+            });
+        }
+    }
+    group.finish();
+}
+
+fn level(price: u64, qty: u64) -> [Decimal; 2] {
+    [Decimal::new(price as i64, 2), Decimal::new(qty as i64, 3)]
+}
+
+fn order_book(c: &mut Criterion) {
+    let snapshot = PartialDepth {
+        last_update_id: 0,
+        bids: (0..1000).map(|i| level(100_000 - i, 1 + i)).collect(),
+        asks: (0..1000).map(|i| level(100_001 + i, 1 + i)).collect(),
+    };
+    let book = OrderBook::from_snapshot(Symbol::BTCUSDT, &snapshot);
+    // updates of 10 levels near the top, every fourth removes its level
+    let updates: Vec<DepthUpdate> = (1..=1000)
+        .map(|id| DepthUpdate {
+            event_time: id,
+            symbol: Symbol::BTCUSDT,
+            first_update_id: id,
+            final_update_id: id,
+            bids: (0..10)
+                .map(|i| level(100_000 - (id * 7 + i) % 50, (id + i) % 4))
+                .collect(),
+            asks: (0..10)
+                .map(|i| level(100_001 + (id * 3 + i) % 50, (id + i) % 4))
+                .collect(),
+        })
+        .collect();
+
+    let mut group = c.benchmark_group("order_book");
+    group.throughput(Throughput::Elements(updates.len() as u64));
+    group.bench_function("apply", |b| {
+        b.iter_batched(
+            || book.clone(),
+            |mut book| {
+                for update in &updates {
+                    book.apply(update).unwrap();
+                }
+                book
+            },
+            BatchSize::LargeInput,
+        )
+    });
+    group.finish();
+}
+
+criterion_group!(benches, parsing, order_book);
+criterion_main!(benches);
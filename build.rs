@@ -0,0 +1,25 @@
+fn main() {
+    // The gRPC service is generated without protoc, the messages are written by hand
+    // in src/sink/grpc.rs, matching proto/market_data.proto.
+    #[cfg(feature = "grpc")]
+    {
+        use tonic_build::manual::{Builder, Method, Service};
+
+        let service = Service::builder()
+            .name("MarketData")
+            .package("binance")
+            .method(
+                Method::builder()
+                    .name("subscribe")
+                    .route_name("Subscribe")
+                    .input_type("super::SubscribeRequest")
+                    .output_type("super::MarketMessage")
+                    .codec_path("tonic::codec::ProstCodec")
+                    .server_streaming()
+                    .build(),
+            )
+            .build();
+        Builder::new().compile(&[service]);
+    }
+    println!("cargo:rerun-if-changed=build.rs");
+}
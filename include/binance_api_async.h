@@ -0,0 +1,79 @@
+/*
+ * C ABI of binance_api_async, built with the `ffi` feature:
+ *
+ *     cargo rustc --release --lib --features ffi --crate-type cdylib
+ *
+ * See the documentation of the `ffi` module for the semantics of every function.
+ */
+
+#ifndef BINANCE_API_ASYNC_H
+#define BINANCE_API_ASYNC_H
+
+#include <stdbool.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define BINANCE_SYMBOL_LEN 32
+
+/* Markets of binance_client_new_market(). */
+#define BINANCE_MARKET_SPOT 0
+#define BINANCE_MARKET_USD_FUTURES 1
+
+typedef struct BinanceClient BinanceClient;
+
+typedef enum BinanceEventKind {
+    BINANCE_AGG_TRADE = 1,
+    BINANCE_TRADE = 2,
+    BINANCE_PARTIAL_DEPTH = 3,
+    BINANCE_BOOK_TICKER = 4,
+    BINANCE_DEPTH_UPDATE = 5,
+    BINANCE_KLINE = 6,
+    BINANCE_ERROR = 7,
+    BINANCE_SUBSCRIBE_SUCCESS = 8,
+    BINANCE_CONTINUOUS_KLINE = 9,
+    BINANCE_FUTURES_DEPTH_UPDATE = 10,
+} BinanceEventKind;
+
+typedef struct BinanceEvent {
+    uint32_t kind; /* BinanceEventKind */
+    char symbol[BINANCE_SYMBOL_LEN];
+    uint64_t event_time;
+    uint64_t recv_time;
+    uint64_t id;
+    double price;
+    double quantity;
+    double open;
+    double high;
+    double low;
+    double bid_price;
+    double bid_quantity;
+    double ask_price;
+    double ask_quantity;
+    bool flag;
+} BinanceEvent;
+
+/* Return 0 to go on, anything else to stop binance_client_run(). */
+typedef int (*BinanceCallback)(const BinanceEvent *event, const char *json, void *user_data);
+
+BinanceClient *binance_client_new(const char *url);
+BinanceClient *binance_client_new_market(const char *url, int market);
+void binance_client_free(BinanceClient *client);
+int binance_client_allow_plaintext(BinanceClient *client, bool allow);
+int binance_client_connect(BinanceClient *client);
+int binance_client_subscribe(BinanceClient *client, const char *symbol, const char *feed,
+                             uint64_t id);
+int binance_client_unsubscribe(BinanceClient *client, const char *symbol, const char *feed);
+int binance_client_poll(BinanceClient *client, uint64_t timeout_ms, BinanceEvent *event);
+int binance_client_poll_json(BinanceClient *client, uint64_t timeout_ms, char **json);
+int binance_client_run(BinanceClient *client, BinanceCallback callback, void *user_data);
+const char *binance_last_error(void);
+void binance_string_free(char *s);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* BINANCE_API_ASYNC_H */
@@ -0,0 +1,467 @@
+//! Aggregate streamed trades into bars.
+//!
+//! Feed [`AggTrade`](crate::messages::AggTrade) or [`Trade`](crate::messages::Trade) messages
+//! for **one** symbol into a builder, a finished [`Candle`] is returned once a trade
+//! belongs to the next bar.
+//!
+//! - [`CandleBuilder`] time based bars.
+//! - [`BarBuilder`] tick, volume and dollar bars.
+//! - [`KlineResampler`] higher timeframes from streamed or historical klines.
+
+#[cfg(feature = "kline")]
+use std::collections::BTreeMap;
+#[cfg(any(feature = "trade", feature = "kline"))]
+use std::time::Duration;
+
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "trade")]
+use crate::clock::Clock;
+#[cfg(feature = "kline")]
+use crate::messages::KlineData;
+#[cfg(feature = "trade")]
+use crate::messages::TradeEvent;
+
+/// OHLCV bar, times are in milliseconds since epoch.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Candle {
+    pub open_time: u64,
+    pub close_time: u64,
+    pub open: Decimal,
+    pub high: Decimal,
+    pub low: Decimal,
+    pub close: Decimal,
+    /// Traded volume in the base asset.
+    pub volume: Decimal,
+    /// Traded volume in the quote asset.
+    pub quote_volume: Decimal,
+    pub trades: u64,
+}
+
+#[cfg(feature = "trade")]
+impl Candle {
+    pub(crate) fn new(open_time: u64, close_time: u64, price: Decimal, qty: Decimal) -> Self {
+        Self {
+            open_time,
+            close_time,
+            open: price,
+            high: price,
+            low: price,
+            close: price,
+            volume: qty,
+            quote_volume: price * qty,
+            trades: 1,
+        }
+    }
+
+    pub(crate) fn update(&mut self, price: Decimal, qty: Decimal) {
+        self.high = self.high.max(price);
+        self.low = self.low.min(price);
+        self.close = price;
+        self.volume += qty;
+        self.quote_volume += price * qty;
+        self.trades += 1;
+    }
+}
+
+#[cfg(feature = "trade")]
+/// Builds time based [`Candle`]s aligned to the clock,
+/// a 1 minute interval gives bars starting at every whole minute.
+///
+/// Intervals without any trades do not produce a bar.
+#[derive(Debug, Clone)]
+pub struct CandleBuilder {
+    interval: u64,
+    current: Option<Candle>,
+}
+
+#[cfg(feature = "trade")]
+impl CandleBuilder {
+    /// # Panic
+    /// If `interval` is shorter than one millisecond.
+    pub fn new(interval: Duration) -> Self {
+        let interval = interval.as_millis() as u64;
+        assert!(interval > 0, "interval must be at least one millisecond");
+        Self {
+            interval,
+            current: None,
+        }
+    }
+
+    /// Add a trade, returns the previous bar if the trade starts a new one.
+    pub fn push(&mut self, trade: &impl TradeEvent) -> Option<Candle> {
+        self.update(trade.price(), trade.quantity(), trade.trade_time())
+    }
+
+    /// Add a trade at `price` and `qty` made at `time`, see [`CandleBuilder::push()`].
+    ///
+    /// Trades older than the current bar are added to the current bar.
+    pub fn update(&mut self, price: Decimal, qty: Decimal, time: u64) -> Option<Candle> {
+        if let Some(candle) = self.current.as_mut() {
+            if time <= candle.close_time {
+                candle.update(price, qty);
+                return None;
+            }
+        }
+
+        let open_time = time - time % self.interval;
+        let close_time = open_time + self.interval - 1;
+        self.current
+            .replace(Candle::new(open_time, close_time, price, qty))
+    }
+
+    /// The bar currently being built.
+    pub fn partial(&self) -> Option<&Candle> {
+        self.current.as_ref()
+    }
+
+    /// Returns the current bar if `now` is past its close time.
+    ///
+    /// Call this on a timer to get bars without waiting for the next trade.
+    pub fn close(&mut self, now: u64) -> Option<Candle> {
+        if self.current.as_ref()?.close_time < now {
+            return self.current.take();
+        }
+        None
+    }
+
+    /// Returns the current bar if the time of `clock` is past its close time,
+    /// see [`CandleBuilder::close()`].
+    pub fn close_now(&mut self, clock: &dyn Clock) -> Option<Candle> {
+        self.close(clock.now_millis())
+    }
+}
+
+#[cfg(feature = "trade")]
+/// When a [`BarBuilder`] closes the current bar.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BarThreshold {
+    /// Every N trades.
+    Ticks(u64),
+    /// Every X traded in the base asset.
+    Volume(Decimal),
+    /// Every Y traded in the quote asset, also known as dollar bars.
+    QuoteVolume(Decimal),
+}
+
+#[cfg(feature = "trade")]
+/// Builds activity based [`Candle`]s, see [`BarThreshold`].
+///
+/// A bar is finished by the trade that reaches the threshold, trades are not split between bars
+/// so volume bars can overshoot the threshold. Open and close time are the times of the first
+/// and last trade in the bar.
+#[derive(Debug, Clone)]
+pub struct BarBuilder {
+    threshold: BarThreshold,
+    current: Option<Candle>,
+}
+
+#[cfg(feature = "trade")]
+impl BarBuilder {
+    pub fn new(threshold: BarThreshold) -> Self {
+        Self {
+            threshold,
+            current: None,
+        }
+    }
+
+    /// Add a trade, returns the bar if the trade reached the threshold.
+    pub fn push(&mut self, trade: &impl TradeEvent) -> Option<Candle> {
+        self.update(trade.price(), trade.quantity(), trade.trade_time())
+    }
+
+    /// Add a trade at `price` and `qty` made at `time`, see [`BarBuilder::push()`].
+    pub fn update(&mut self, price: Decimal, qty: Decimal, time: u64) -> Option<Candle> {
+        match self.current.as_mut() {
+            Some(candle) => {
+                candle.update(price, qty);
+                candle.close_time = time;
+            }
+            None => {
+                self.current = Some(Candle::new(time, time, price, qty));
+            }
+        }
+
+        let candle = self.current.as_ref()?;
+        let done = match &self.threshold {
+            BarThreshold::Ticks(n) => candle.trades >= *n,
+            BarThreshold::Volume(v) => candle.volume >= *v,
+            BarThreshold::QuoteVolume(v) => candle.quote_volume >= *v,
+        };
+
+        if done {
+            return self.current.take();
+        }
+        None
+    }
+
+    /// The bar currently being built.
+    pub fn partial(&self) -> Option<&Candle> {
+        self.current.as_ref()
+    }
+}
+
+#[cfg(feature = "kline")]
+impl From<&KlineData> for Candle {
+    fn from(k: &KlineData) -> Self {
+        Self {
+            open_time: k.open_time,
+            close_time: k.close_time,
+            open: k.open,
+            high: k.high,
+            low: k.low,
+            close: k.close,
+            volume: k.volume,
+            quote_volume: k.quote_volume,
+            trades: k.trades,
+        }
+    }
+}
+
+#[cfg(feature = "kline")]
+/// A [`Candle`] resampled from klines by [`KlineResampler`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Resampled {
+    pub candle: Candle,
+    /// Number of klines missing from the period, 0 if the candle is complete.
+    pub missing: u64,
+}
+
+#[cfg(feature = "kline")]
+/// Combines klines of one symbol into a higher timeframe, e.g. 1m klines into 15m candles.
+///
+/// Periods are aligned to whole multiples of the target interval since epoch,
+/// which is how Binance aligns intervals up to one day.
+///
+/// Streamed klines can be pushed as they update, the latest update of each kline is used.
+/// Missing klines are reported in [`Resampled::missing`], periods without any klines are skipped.
+/// A period is returned once a kline of a later period is pushed, or by
+/// [`KlineResampler::finish()`].
+#[derive(Debug, Clone)]
+pub struct KlineResampler {
+    interval: u64,
+    /// open time of the current period
+    period: Option<u64>,
+    /// latest version of each kline in the current period, by open time
+    klines: BTreeMap<u64, Candle>,
+    base_interval: u64,
+}
+
+#[cfg(feature = "kline")]
+impl KlineResampler {
+    /// # Panic
+    /// If `interval` is shorter than one millisecond.
+    pub fn new(interval: Duration) -> Self {
+        let interval = interval.as_millis() as u64;
+        assert!(interval > 0, "interval must be at least one millisecond");
+        Self {
+            interval,
+            period: None,
+            klines: BTreeMap::new(),
+            base_interval: 0,
+        }
+    }
+
+    /// Add or update a kline, returns the previous period if the kline starts a new one.
+    ///
+    /// Klines older than the current period are ignored.
+    pub fn push(&mut self, kline: &KlineData) -> Option<Resampled> {
+        let period = kline.open_time - kline.open_time % self.interval;
+        let mut finished = None;
+
+        match self.period {
+            Some(current) if period < current => return None,
+            Some(current) if period > current => finished = self.finish(),
+            _ => {}
+        }
+
+        self.period = Some(period);
+        self.base_interval = kline.close_time + 1 - kline.open_time;
+        self.klines.insert(kline.open_time, kline.into());
+        finished
+    }
+
+    /// The current, unfinished, period.
+    pub fn partial(&self) -> Option<Resampled> {
+        let period = self.period?;
+        let mut klines = self.klines.values();
+        let mut candle = klines.next()?.clone();
+        for k in klines {
+            candle.high = candle.high.max(k.high);
+            candle.low = candle.low.min(k.low);
+            candle.close = k.close;
+            candle.volume += k.volume;
+            candle.quote_volume += k.quote_volume;
+            candle.trades += k.trades;
+        }
+        candle.open_time = period;
+        candle.close_time = period + self.interval - 1;
+
+        let expected = self.interval / self.base_interval.max(1);
+        Some(Resampled {
+            candle,
+            missing: expected.saturating_sub(self.klines.len() as u64),
+        })
+    }
+
+    /// Take the current period, even if it has not ended.
+    pub fn finish(&mut self) -> Option<Resampled> {
+        let resampled = self.partial();
+        self.klines.clear();
+        resampled
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_util::dec;
+
+    #[test]
+    fn builds_aligned_candles() {
+        let mut builder = CandleBuilder::new(Duration::from_secs(60));
+
+        assert_eq!(builder.update(dec("10"), dec("1"), 60_500), None);
+        assert_eq!(builder.update(dec("12"), dec("2"), 61_000), None);
+        assert_eq!(builder.update(dec("9"), dec("1"), 90_000), None);
+        assert_eq!(builder.partial().unwrap().close, dec("9"));
+
+        let candle = builder.update(dec("11"), dec("1"), 120_000).unwrap();
+        assert_eq!(
+            candle,
+            Candle {
+                open_time: 60_000,
+                close_time: 119_999,
+                open: dec("10"),
+                high: dec("12"),
+                low: dec("9"),
+                close: dec("9"),
+                volume: dec("4"),
+                quote_volume: dec("43"),
+                trades: 3,
+            }
+        );
+
+        let partial = builder.partial().unwrap();
+        assert_eq!(partial.open_time, 120_000);
+        assert_eq!(partial.trades, 1);
+    }
+
+    #[test]
+    fn tick_bars() {
+        let mut builder = BarBuilder::new(BarThreshold::Ticks(2));
+        assert_eq!(builder.update(dec("10"), dec("1"), 1), None);
+        let bar = builder.update(dec("11"), dec("1"), 2).unwrap();
+        assert_eq!((bar.open, bar.close, bar.trades), (dec("10"), dec("11"), 2));
+        assert_eq!((bar.open_time, bar.close_time), (1, 2));
+        assert_eq!(builder.partial(), None);
+    }
+
+    #[test]
+    fn volume_bars() {
+        let mut builder = BarBuilder::new(BarThreshold::Volume(dec("5")));
+        assert_eq!(builder.update(dec("10"), dec("3"), 1), None);
+        // overshoots, the trade is not split
+        let bar = builder.update(dec("10"), dec("4"), 2).unwrap();
+        assert_eq!(bar.volume, dec("7"));
+        assert_eq!(builder.update(dec("10"), dec("1"), 3), None);
+        assert_eq!(builder.partial().unwrap().volume, dec("1"));
+    }
+
+    #[test]
+    fn dollar_bars() {
+        let mut builder = BarBuilder::new(BarThreshold::QuoteVolume(dec("100")));
+        assert_eq!(builder.update(dec("20"), dec("2"), 1), None);
+        assert_eq!(builder.update(dec("30"), dec("1"), 2), None);
+        let bar = builder.update(dec("30"), dec("1"), 3).unwrap();
+        assert_eq!(bar.quote_volume, dec("100"));
+        assert_eq!((bar.high, bar.low), (dec("30"), dec("20")));
+    }
+
+    fn kline(open_time: u64, open: &str, high: &str, low: &str, close: &str) -> KlineData {
+        KlineData {
+            open_time,
+            close_time: open_time + 59_999,
+            interval: "1m".into(),
+            first_trade_id: 1,
+            last_trade_id: 2,
+            open: dec(open),
+            close: dec(close),
+            high: dec(high),
+            low: dec(low),
+            volume: dec("1"),
+            trades: 2,
+            is_closed: true,
+            quote_volume: dec("10"),
+            taker_buy_volume: dec("0.5"),
+            taker_buy_quote_volume: dec("5"),
+        }
+    }
+
+    #[test]
+    fn resample_klines() {
+        let mut resampler = KlineResampler::new(Duration::from_secs(180));
+
+        assert_eq!(resampler.push(&kline(0, "10", "12", "9", "11")), None);
+        // updated kline replaces the earlier version
+        assert_eq!(resampler.push(&kline(0, "10", "13", "9", "12")), None);
+        assert_eq!(resampler.push(&kline(60_000, "12", "12", "8", "9")), None);
+        assert_eq!(resampler.partial().unwrap().missing, 1);
+        assert_eq!(resampler.push(&kline(120_000, "9", "10", "9", "10")), None);
+
+        let resampled = resampler
+            .push(&kline(180_000, "10", "10", "10", "10"))
+            .unwrap();
+        assert_eq!(resampled.missing, 0);
+        assert_eq!(
+            resampled.candle,
+            Candle {
+                open_time: 0,
+                close_time: 179_999,
+                open: dec("10"),
+                high: dec("13"),
+                low: dec("8"),
+                close: dec("10"),
+                volume: dec("3"),
+                quote_volume: dec("30"),
+                trades: 6,
+            }
+        );
+
+        // late kline for a finished period is ignored
+        assert_eq!(resampler.push(&kline(120_000, "1", "1", "1", "1")), None);
+        assert_eq!(resampler.partial().unwrap().candle.open_time, 180_000);
+    }
+
+    #[test]
+    fn resample_with_gap() {
+        let mut resampler = KlineResampler::new(Duration::from_secs(180));
+        resampler.push(&kline(0, "10", "10", "10", "10"));
+
+        // the whole second period is missing
+        let resampled = resampler
+            .push(&kline(420_000, "10", "10", "10", "10"))
+            .unwrap();
+        assert_eq!(resampled.missing, 2);
+        assert_eq!(resampler.finish().unwrap().candle.open_time, 360_000);
+        assert_eq!(resampler.partial(), None);
+    }
+
+    #[test]
+    fn close_on_timer() {
+        let mut builder = CandleBuilder::new(Duration::from_secs(1));
+        assert_eq!(builder.close(5_000), None);
+
+        builder.update(dec("10"), dec("1"), 1_200);
+        assert_eq!(builder.close(1_999), None);
+        assert_eq!(builder.close(2_000).unwrap().open_time, 1_000);
+        assert_eq!(builder.partial(), None);
+
+        let clock = crate::clock::TestClock::new(3_500);
+        builder.update(dec("10"), dec("1"), 3_000);
+        assert_eq!(builder.close_now(&clock), None);
+        clock.advance(Duration::from_millis(500));
+        assert_eq!(builder.close_now(&clock).unwrap().open_time, 3_000);
+    }
+}
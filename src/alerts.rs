@@ -0,0 +1,245 @@
+//! Alert rules evaluated on the message stream.
+//!
+//! Register [`Condition`]s for symbols in [`Alerts`] and pass every [`Message`] to
+//! [`Alerts::check()`], an [`Alert`] is returned when a condition becomes true.
+//!
+//! Alerts are edge triggered, a rule fires once when its condition goes from false to true
+//! and again only after the condition has been false in between.
+
+use std::time::Duration;
+
+use rust_decimal::Decimal;
+
+use crate::messages::{BookTicker, TradeEvent};
+use crate::stats::Stats;
+use crate::{Message, Symbol};
+
+/// Condition of an alert rule.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Condition {
+    /// Trade price crosses above the price.
+    PriceAbove(Decimal),
+    /// Trade price crosses below the price.
+    PriceBelow(Decimal),
+    /// [`BookTicker`] spread exceeds the number of basis points of the mid price.
+    SpreadAbove(Decimal),
+    /// Base asset volume traded during the window exceeds the volume.
+    VolumeAbove { volume: Decimal, window: Duration },
+}
+
+/// Emitted when a rule triggers.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Alert {
+    /// Id returned by [`Alerts::add()`].
+    pub id: usize,
+    pub symbol: Symbol,
+    pub condition: Condition,
+    /// The price, spread in basis points or volume that triggered the alert.
+    pub value: Decimal,
+}
+
+#[derive(Debug, Clone)]
+struct Rule {
+    id: usize,
+    symbol: Symbol,
+    condition: Condition,
+    /// Result of the last evaluation, `None` before the first.
+    active: Option<bool>,
+    volume: Option<Stats>,
+}
+
+impl Rule {
+    /// Returns the value if the condition is true for `msg`, `None` if `msg` does not apply.
+    fn evaluate(&mut self, msg: &Message) -> Option<(bool, Decimal)> {
+        match (&self.condition, msg) {
+            (Condition::PriceAbove(p), Message::AggTrade(t)) => Some((t.price >= *p, t.price)),
+            (Condition::PriceAbove(p), Message::Trade(t)) => Some((t.price >= *p, t.price)),
+            (Condition::PriceBelow(p), Message::AggTrade(t)) => Some((t.price <= *p, t.price)),
+            (Condition::PriceBelow(p), Message::Trade(t)) => Some((t.price <= *p, t.price)),
+            (Condition::SpreadAbove(bps), Message::BookTicker(bt)) => {
+                let spread = spread_bps(bt)?;
+                Some((spread > *bps, spread))
+            }
+            (Condition::VolumeAbove { volume, .. }, Message::AggTrade(t)) => {
+                traded_volume(&mut self.volume, t).map(|v| (v > *volume, v))
+            }
+            (Condition::VolumeAbove { volume, .. }, Message::Trade(t)) => {
+                traded_volume(&mut self.volume, t).map(|v| (v > *volume, v))
+            }
+            _ => None,
+        }
+    }
+}
+
+fn traded_volume(stats: &mut Option<Stats>, trade: &impl TradeEvent) -> Option<Decimal> {
+    let stats = stats.as_mut()?;
+    stats.push(trade);
+    Some(stats.volume())
+}
+
+fn spread_bps(bt: &BookTicker) -> Option<Decimal> {
+    let mid = bt.mid_price();
+    if mid.is_zero() {
+        return None;
+    }
+    Some(bt.spread() / mid * Decimal::from(10_000))
+}
+
+/// A set of alert rules.
+#[derive(Debug, Clone, Default)]
+pub struct Alerts {
+    rules: Vec<Rule>,
+    next_id: usize,
+}
+
+impl Alerts {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a rule for `symbol`, returns the id used in the [`Alert`]s of the rule.
+    pub fn add(&mut self, symbol: Symbol, condition: Condition) -> usize {
+        let id = self.next_id;
+        self.next_id += 1;
+
+        let volume = match &condition {
+            Condition::VolumeAbove { window, .. } => Some(Stats::new(*window)),
+            _ => None,
+        };
+        self.rules.push(Rule {
+            id,
+            symbol,
+            condition,
+            active: None,
+            volume,
+        });
+        id
+    }
+
+    /// Remove the rule with `id`, returns false if there is no such rule.
+    pub fn remove(&mut self, id: usize) -> bool {
+        let len = self.rules.len();
+        self.rules.retain(|r| r.id != id);
+        len != self.rules.len()
+    }
+
+    /// Evaluate the rules for the symbol of `msg`, returns the alerts that triggered.
+    ///
+    /// Price crossings need a price on the other side first, so a price already
+    /// above [`Condition::PriceAbove`] when the rule is added does not trigger.
+    pub fn check(&mut self, msg: &Message) -> Vec<Alert> {
+        let mut alerts = Vec::new();
+        let Some(symbol) = msg.symbol() else {
+            return alerts;
+        };
+
+        for rule in self.rules.iter_mut().filter(|r| &r.symbol == symbol) {
+            let Some((active, value)) = rule.evaluate(msg) else {
+                continue;
+            };
+
+            let crossing = matches!(
+                rule.condition,
+                Condition::PriceAbove(_) | Condition::PriceBelow(_)
+            );
+            let triggered = match rule.active {
+                Some(was_active) => active && !was_active,
+                None => active && !crossing,
+            };
+            rule.active = Some(active);
+
+            if triggered {
+                alerts.push(Alert {
+                    id: rule.id,
+                    symbol: symbol.clone(),
+                    condition: rule.condition.clone(),
+                    value,
+                });
+            }
+        }
+        alerts
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::messages::AggTrade;
+    use crate::test_util::{self, dec};
+
+    fn trade(symbol: Symbol, price: &str, qty: &str, time: u64) -> Message {
+        Message::AggTrade(AggTrade {
+            event_time: time,
+            price: dec(price),
+            quantity: dec(qty),
+            trade_time: time,
+            ..test_util::agg_trade(symbol, 1)
+        })
+    }
+
+    fn ticker(bid: &str, ask: &str) -> Message {
+        Message::BookTicker(test_util::ticker(Symbol::BTCUSDT, bid, ask))
+    }
+
+    #[test]
+    fn price_crossing() {
+        let mut alerts = Alerts::new();
+        let id = alerts.add(Symbol::BTCUSDT, Condition::PriceAbove(dec("100")));
+
+        // already above when the first price is seen, no crossing
+        assert!(alerts
+            .check(&trade(Symbol::BTCUSDT, "101", "1", 0))
+            .is_empty());
+        assert!(alerts
+            .check(&trade(Symbol::BTCUSDT, "99", "1", 1))
+            .is_empty());
+        // other symbol
+        assert!(alerts
+            .check(&trade(Symbol::ETHUSDT, "101", "1", 2))
+            .is_empty());
+
+        let triggered = alerts.check(&trade(Symbol::BTCUSDT, "100", "1", 3));
+        assert_eq!(
+            triggered,
+            vec![Alert {
+                id,
+                symbol: Symbol::BTCUSDT,
+                condition: Condition::PriceAbove(dec("100")),
+                value: dec("100"),
+            }]
+        );
+        assert!(alerts
+            .check(&trade(Symbol::BTCUSDT, "102", "1", 4))
+            .is_empty());
+
+        assert!(alerts.remove(id));
+        assert!(!alerts.remove(id));
+    }
+
+    #[test]
+    fn spread_and_volume() {
+        let mut alerts = Alerts::new();
+        alerts.add(Symbol::BTCUSDT, Condition::SpreadAbove(dec("10")));
+        alerts.add(
+            Symbol::BTCUSDT,
+            Condition::VolumeAbove {
+                volume: dec("5"),
+                window: Duration::from_secs(1),
+            },
+        );
+
+        // 5 bps
+        assert!(alerts.check(&ticker("99.975", "100.025")).is_empty());
+        // 20 bps
+        let triggered = alerts.check(&ticker("99.9", "100.1"));
+        assert_eq!(triggered.len(), 1);
+        assert_eq!(triggered[0].value, dec("20"));
+        assert!(alerts.check(&ticker("99.9", "100.1")).is_empty());
+
+        assert!(alerts
+            .check(&trade(Symbol::BTCUSDT, "100", "3", 0))
+            .is_empty());
+        let triggered = alerts.check(&trade(Symbol::BTCUSDT, "100", "3", 500));
+        assert_eq!(triggered[0].value, dec("6"));
+    }
+}
@@ -0,0 +1,281 @@
+//! Conversion of messages to Arrow record batches, requires the `arrow` feature.
+//!
+//! [`ToArrow::to_arrow()`] turns a batch of one message type into a [`RecordBatch`] with one
+//! column per field, ready for DataFusion, Polars or a Parquet writer.
+//!
+//! Prices and quantities are `Decimal128` with [`SCALE`] digits, times are `UInt64`
+//! milliseconds since epoch and symbols are upper case strings.
+//! Order book messages have one row per price level, with an `is_bid` column for the side.
+//!
+//! ```no_run
+//! use binance_api_async::arrow::ToArrow;
+//! use binance_api_async::messages::AggTrade;
+//!
+//! # fn trades() -> Vec<AggTrade> { vec![] }
+//! let trades: Vec<AggTrade> = trades();
+//! let batch = AggTrade::to_arrow(&trades).unwrap();
+//! assert_eq!(batch.num_rows(), trades.len());
+//! ```
+
+use std::sync::Arc;
+
+use ::arrow::array::{
+    ArrayRef, BooleanArray, Decimal128Array, Int64Array, StringArray, UInt32Array, UInt64Array,
+};
+pub use ::arrow::record_batch::RecordBatch;
+use rust_decimal::Decimal;
+
+use crate::messages::{AggTrade, BookTicker, DepthUpdate, Kline, PartialDepth, Trade};
+use crate::sink::symbol_name;
+
+/// Number of decimal digits of prices and quantities, enough for every Binance spot market.
+pub const SCALE: i8 = 8;
+
+/// A message type that can be converted to an Arrow [`RecordBatch`].
+pub trait ToArrow: Sized {
+    /// Convert `batch` to a record batch, an empty batch gives the schema without rows.
+    fn to_arrow<'a>(batch: impl IntoIterator<Item = &'a Self>) -> crate::Result<RecordBatch>
+    where
+        Self: 'a;
+}
+
+impl ToArrow for AggTrade {
+    fn to_arrow<'a>(batch: impl IntoIterator<Item = &'a Self>) -> crate::Result<RecordBatch> {
+        let rows: Vec<&AggTrade> = batch.into_iter().collect();
+        let columns = vec![
+            ("symbol", symbols(rows.iter().map(|t| &t.symbol))),
+            ("event_time", u64s(rows.iter().map(|t| t.event_time))),
+            ("trade_id", u64s(rows.iter().map(|t| t.trade_id))),
+            ("price", decimals(rows.iter().map(|t| t.price))?),
+            ("quantity", decimals(rows.iter().map(|t| t.quantity))?),
+            (
+                "first_trade_id",
+                u64s(rows.iter().map(|t| t.first_trade_id)),
+            ),
+            ("last_trade_id", u64s(rows.iter().map(|t| t.last_trade_id))),
+            ("trade_time", u64s(rows.iter().map(|t| t.trade_time))),
+            (
+                "is_market_maker",
+                bools(rows.iter().map(|t| t.is_market_maker)),
+            ),
+        ];
+        Ok(RecordBatch::try_from_iter(columns)?)
+    }
+}
+
+impl ToArrow for Trade {
+    fn to_arrow<'a>(batch: impl IntoIterator<Item = &'a Self>) -> crate::Result<RecordBatch> {
+        let rows: Vec<&Trade> = batch.into_iter().collect();
+        let columns = vec![
+            ("symbol", symbols(rows.iter().map(|t| &t.symbol))),
+            ("event_time", u64s(rows.iter().map(|t| t.event_time))),
+            ("trade_id", u64s(rows.iter().map(|t| t.trade_id))),
+            ("price", decimals(rows.iter().map(|t| t.price))?),
+            ("quantity", decimals(rows.iter().map(|t| t.quantity))?),
+            ("trade_time", u64s(rows.iter().map(|t| t.trade_time))),
+            (
+                "is_market_maker",
+                bools(rows.iter().map(|t| t.is_market_maker)),
+            ),
+        ];
+        Ok(RecordBatch::try_from_iter(columns)?)
+    }
+}
+
+impl ToArrow for BookTicker {
+    fn to_arrow<'a>(batch: impl IntoIterator<Item = &'a Self>) -> crate::Result<RecordBatch> {
+        let rows: Vec<&BookTicker> = batch.into_iter().collect();
+        let columns = vec![
+            ("symbol", symbols(rows.iter().map(|t| &t.symbol))),
+            ("update_id", u64s(rows.iter().map(|t| t.update_id))),
+            (
+                "best_bid_price",
+                decimals(rows.iter().map(|t| t.best_bid_price))?,
+            ),
+            (
+                "best_bid_qty",
+                decimals(rows.iter().map(|t| t.best_bid_qty))?,
+            ),
+            (
+                "best_ask_price",
+                decimals(rows.iter().map(|t| t.best_ask_price))?,
+            ),
+            (
+                "best_ask_qty",
+                decimals(rows.iter().map(|t| t.best_ask_qty))?,
+            ),
+        ];
+        Ok(RecordBatch::try_from_iter(columns)?)
+    }
+}
+
+impl ToArrow for DepthUpdate {
+    /// One row per price level, bids first.
+    fn to_arrow<'a>(batch: impl IntoIterator<Item = &'a Self>) -> crate::Result<RecordBatch> {
+        let levels: Vec<(&DepthUpdate, bool, &[Decimal; 2])> = batch
+            .into_iter()
+            .flat_map(|du| {
+                let bids = du.bids.iter().map(move |l| (du, true, l));
+                let asks = du.asks.iter().map(move |l| (du, false, l));
+                bids.chain(asks)
+            })
+            .collect();
+        let columns = vec![
+            ("symbol", symbols(levels.iter().map(|l| &l.0.symbol))),
+            ("event_time", u64s(levels.iter().map(|l| l.0.event_time))),
+            (
+                "first_update_id",
+                u64s(levels.iter().map(|l| l.0.first_update_id)),
+            ),
+            (
+                "final_update_id",
+                u64s(levels.iter().map(|l| l.0.final_update_id)),
+            ),
+            ("is_bid", bools(levels.iter().map(|l| l.1))),
+            ("price", decimals(levels.iter().map(|l| l.2[0]))?),
+            ("quantity", decimals(levels.iter().map(|l| l.2[1]))?),
+        ];
+        Ok(RecordBatch::try_from_iter(columns)?)
+    }
+}
+
+impl ToArrow for PartialDepth {
+    /// One row per price level, bids first, `level` is 0 for the best price of each side.
+    fn to_arrow<'a>(batch: impl IntoIterator<Item = &'a Self>) -> crate::Result<RecordBatch> {
+        let levels: Vec<(u64, bool, u32, &[Decimal; 2])> = batch
+            .into_iter()
+            .flat_map(|pd| {
+                let id = pd.last_update_id;
+                let bids = (0..).zip(&pd.bids).map(move |(i, l)| (id, true, i, l));
+                let asks = (0..).zip(&pd.asks).map(move |(i, l)| (id, false, i, l));
+                bids.chain(asks)
+            })
+            .collect();
+        let level: UInt32Array = levels.iter().map(|l| Some(l.2)).collect();
+        let columns = vec![
+            ("last_update_id", u64s(levels.iter().map(|l| l.0))),
+            ("is_bid", bools(levels.iter().map(|l| l.1))),
+            ("level", Arc::new(level) as ArrayRef),
+            ("price", decimals(levels.iter().map(|l| l.3[0]))?),
+            ("quantity", decimals(levels.iter().map(|l| l.3[1]))?),
+        ];
+        Ok(RecordBatch::try_from_iter(columns)?)
+    }
+}
+
+impl ToArrow for Kline {
+    fn to_arrow<'a>(batch: impl IntoIterator<Item = &'a Self>) -> crate::Result<RecordBatch> {
+        let rows: Vec<&Kline> = batch.into_iter().collect();
+        let k = || rows.iter().map(|k| &k.kline);
+        let interval: StringArray = k().map(|k| Some(k.interval.as_str())).collect();
+        let first_trade_id: Int64Array = k().map(|k| Some(k.first_trade_id)).collect();
+        let last_trade_id: Int64Array = k().map(|k| Some(k.last_trade_id)).collect();
+        let columns = vec![
+            ("symbol", symbols(rows.iter().map(|k| &k.symbol))),
+            ("event_time", u64s(rows.iter().map(|k| k.event_time))),
+            ("interval", Arc::new(interval) as ArrayRef),
+            ("open_time", u64s(k().map(|k| k.open_time))),
+            ("close_time", u64s(k().map(|k| k.close_time))),
+            ("open", decimals(k().map(|k| k.open))?),
+            ("high", decimals(k().map(|k| k.high))?),
+            ("low", decimals(k().map(|k| k.low))?),
+            ("close", decimals(k().map(|k| k.close))?),
+            ("volume", decimals(k().map(|k| k.volume))?),
+            ("quote_volume", decimals(k().map(|k| k.quote_volume))?),
+            (
+                "taker_buy_volume",
+                decimals(k().map(|k| k.taker_buy_volume))?,
+            ),
+            (
+                "taker_buy_quote_volume",
+                decimals(k().map(|k| k.taker_buy_quote_volume))?,
+            ),
+            ("trades", u64s(k().map(|k| k.trades))),
+            ("first_trade_id", Arc::new(first_trade_id) as ArrayRef),
+            ("last_trade_id", Arc::new(last_trade_id) as ArrayRef),
+            ("is_closed", bools(k().map(|k| k.is_closed))),
+        ];
+        Ok(RecordBatch::try_from_iter(columns)?)
+    }
+}
+
+pub(crate) fn u64s(values: impl Iterator<Item = u64>) -> ArrayRef {
+    Arc::new(UInt64Array::from_iter_values(values))
+}
+
+fn bools(values: impl Iterator<Item = bool>) -> ArrayRef {
+    Arc::new(values.map(Some).collect::<BooleanArray>())
+}
+
+fn symbols<'a>(values: impl Iterator<Item = &'a crate::Symbol>) -> ArrayRef {
+    Arc::new(
+        values
+            .map(|s| Some(symbol_name(s)))
+            .collect::<StringArray>(),
+    )
+}
+
+fn decimals(values: impl Iterator<Item = Decimal>) -> crate::Result<ArrayRef> {
+    let array = Decimal128Array::from_iter_values(values.map(|mut d| {
+        d.rescale(SCALE as u32);
+        d.mantissa()
+    }))
+    .with_precision_and_scale(38, SCALE)?;
+    Ok(Arc::new(array))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_util::dec;
+    use crate::Symbol;
+    use ::arrow::array::AsArray;
+    use ::arrow::datatypes::Decimal128Type;
+    use smallvec::smallvec;
+
+    #[test]
+    fn trades_to_columns() {
+        let trade = AggTrade {
+            event_time: 2,
+            trade_id: 7,
+            symbol: Symbol::BTCUSDT,
+            price: dec("65000.01"),
+            quantity: dec("0.5"),
+            first_trade_id: 1,
+            last_trade_id: 3,
+            trade_time: 1,
+            is_market_maker: true,
+        };
+        let batch = AggTrade::to_arrow(&[trade.clone(), trade]).unwrap();
+
+        assert_eq!(batch.num_rows(), 2);
+        assert_eq!(batch.num_columns(), 9);
+        let price = batch.column_by_name("price").unwrap();
+        assert_eq!(
+            price.as_primitive::<Decimal128Type>().value_as_string(0),
+            "65000.01000000"
+        );
+        let symbol = batch.column_by_name("symbol").unwrap().as_string::<i32>();
+        assert_eq!(symbol.value(1), "BTCUSDT");
+
+        assert_eq!(AggTrade::to_arrow(&[]).unwrap().num_rows(), 0);
+    }
+
+    #[test]
+    fn depth_levels_to_rows() {
+        let depth = PartialDepth {
+            last_update_id: 5,
+            bids: smallvec![[dec("2"), dec("1")], [dec("1"), dec("1")]],
+            asks: smallvec![[dec("3"), dec("1")]],
+        };
+        let batch = PartialDepth::to_arrow([&depth]).unwrap();
+
+        assert_eq!(batch.num_rows(), 3);
+        let level = batch.column_by_name("level").unwrap();
+        let level: Vec<u32> = level
+            .as_primitive::<::arrow::datatypes::UInt32Type>()
+            .values()
+            .to_vec();
+        assert_eq!(level, vec![0, 1, 0]);
+    }
+}
@@ -0,0 +1,261 @@
+//! Gap free aggregate trade streams.
+//!
+//! Aggregate trade ids increase by one per trade, a jump in the ids of a symbol means trades
+//! were missed, e.g. while reconnecting. [`GaplessAggTrades`] wraps a [`MarketDataSource`],
+//! fetches the missed trades with [`RestClient::agg_trades()`] and returns them in order before
+//! the trade after the gap:
+//! ```no_run
+//! use binance_api_async::backfill::GaplessAggTrades;
+//! use binance_api_async::rest::RestClient;
+//! use binance_api_async::{BinanceApi, MarketDataSource};
+//!
+//! # async fn run() -> Result<(), binance_api_async::Error> {
+//! let mut api = BinanceApi::new();
+//! api.connect().await?;
+//! let mut trades = GaplessAggTrades::new(api, RestClient::new());
+//! while let Some(msg) = trades.next_message().await {
+//!     println!("{msg}");
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::{HashMap, VecDeque};
+
+use tracing::warn;
+
+use crate::messages::AggTrade;
+use crate::rest::{RestClient, MAX_AGG_TRADES};
+use crate::{MarketDataSource, Message, SubscribeInfo, Symbol};
+
+/// Source returning every aggregate trade once and in order, see the [module](self)
+/// documentation.
+///
+/// Other messages are passed through. Trades already returned, e.g. resent after a reconnect,
+/// are dropped. When the backfill fails the gap is logged and the stream continues after it.
+///
+/// [`next_message()`](MarketDataSource::next_message) is cancel safe if the source's is: a
+/// gap being backfilled is kept, and a later call repeats the request that was cut off.
+#[derive(Debug)]
+pub struct GaplessAggTrades<S> {
+    source: S,
+    rest: RestClient,
+    /// last returned trade id of each symbol
+    last_ids: HashMap<Symbol, u64>,
+    pending: VecDeque<Message>,
+    gap: Option<Gap>,
+}
+
+/// Trades missing before `trade`, fetched from `from_id` on.
+#[derive(Debug)]
+struct Gap {
+    trade: AggTrade,
+    from_id: u64,
+}
+
+impl<S: MarketDataSource + Send> GaplessAggTrades<S> {
+    /// Backfill the aggregate trades of `source` from `rest`.
+    pub fn new(source: S, rest: RestClient) -> Self {
+        Self {
+            source,
+            rest,
+            last_ids: HashMap::new(),
+            pending: VecDeque::new(),
+            gap: None,
+        }
+    }
+
+    /// The wrapped source.
+    pub fn source_mut(&mut self) -> &mut S {
+        &mut self.source
+    }
+
+    /// Unwrap the source.
+    pub fn into_inner(self) -> S {
+        self.source
+    }
+
+    /// Queue `trade`, or start backfilling the trades missed before it.
+    fn splice(&mut self, trade: AggTrade) {
+        match self.last_ids.get(&trade.symbol).copied() {
+            Some(last_id) if trade.trade_id <= last_id => {}
+            Some(last_id) if trade.trade_id > last_id + 1 => {
+                self.gap = Some(Gap {
+                    trade,
+                    from_id: last_id + 1,
+                })
+            }
+            _ => self.push(trade),
+        }
+    }
+
+    fn push(&mut self, trade: AggTrade) {
+        self.last_ids.insert(trade.symbol.clone(), trade.trade_id);
+        self.pending.push_back(Message::AggTrade(trade));
+    }
+
+    /// Queue the next page of the trades missing in the gap, and the trade after it once the
+    /// gap is filled.
+    async fn backfill(&mut self) {
+        let Some(gap) = &self.gap else { return };
+        let (symbol, from_id, to_id) = (&gap.trade.symbol, gap.from_id, gap.trade.trade_id);
+        let limit = (to_id - from_id).min(MAX_AGG_TRADES as u64) as u16;
+        let result = self.rest.agg_trades(symbol, from_id, limit).await;
+        // nothing below awaits, a call cancelled above leaves the gap as it was
+        let Some(mut gap) = self.gap.take() else {
+            return;
+        };
+        let symbol = &gap.trade.symbol;
+        match result {
+            Ok(trades) => match trades.last() {
+                Some(last) => {
+                    gap.from_id = last.trade_id + 1;
+                    for trade in trades.into_iter().filter(|t| t.trade_id < to_id) {
+                        self.pending.push_back(Message::AggTrade(trade));
+                    }
+                    if gap.from_id < to_id {
+                        self.gap = Some(gap);
+                        return;
+                    }
+                }
+                None => warn!("No {symbol} trades from {from_id} to backfill until {to_id}"),
+            },
+            Err(e) => warn!("Could not backfill {symbol} trades {from_id} to {to_id}: {e}"),
+        }
+        self.push(gap.trade);
+    }
+}
+
+impl<S: MarketDataSource + Send> MarketDataSource for GaplessAggTrades<S> {
+    async fn next_message(&mut self) -> Option<Message> {
+        loop {
+            if let Some(msg) = self.pending.pop_front() {
+                return Some(msg);
+            }
+            if self.gap.is_some() {
+                self.backfill().await;
+                continue;
+            }
+            match self.source.next_message().await? {
+                Message::AggTrade(trade) => self.splice(trade),
+                msg => return Some(msg),
+            }
+        }
+    }
+
+    async fn subscribe(&mut self, symbols: &[SubscribeInfo], id: Option<u64>) -> crate::Result<()> {
+        self.source.subscribe(symbols, id).await
+    }
+
+    async fn unsubscribe(&mut self, symbols: Vec<SubscribeInfo>) -> crate::Result<()> {
+        for info in &symbols {
+            self.last_ids.remove(&info.symbol);
+        }
+        if let Some(gap) = &self.gap {
+            if symbols.iter().any(|info| info.symbol == gap.trade.symbol) {
+                self.gap = None;
+            }
+        }
+        self.source.unsubscribe(symbols).await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::test_util::{self, FakeSource};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    fn agg_trade(trade_id: u64) -> AggTrade {
+        AggTrade {
+            event_time: trade_id,
+            trade_time: trade_id,
+            ..test_util::agg_trade(Symbol::BTCUSDT, trade_id)
+        }
+    }
+
+    /// Answers aggregate trade requests with trades 1 to 100, at most 2 per request, the first
+    /// one after `first_delay`.
+    async fn rest_server(first_delay: Duration) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            let mut delay = first_delay;
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let mut request = vec![0; 4096];
+                let len = stream.read(&mut request).await.unwrap();
+                let request = String::from_utf8_lossy(&request[..len]);
+                let from_id: u64 = request
+                    .split(['?', '&', ' '])
+                    .find_map(|param| param.strip_prefix("fromId="))
+                    .unwrap()
+                    .parse()
+                    .unwrap();
+                let trades: Vec<_> = (from_id..(from_id + 2).min(101))
+                    .map(|id| {
+                        format!(r#"{{"a":{id},"p":"1","q":"1","f":{id},"l":{id},"T":{id},"m":false,"M":true}}"#)
+                    })
+                    .collect();
+                let body = format!("[{}]", trades.join(","));
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                    body.len()
+                );
+                tokio::time::sleep(std::mem::take(&mut delay)).await;
+                // the client may be gone after the delay
+                let _ = stream.write_all(response.as_bytes()).await;
+            }
+        });
+        url
+    }
+
+    #[tokio::test]
+    async fn fills_gaps() {
+        let rest = RestClient::with_url(&rest_server(Duration::ZERO).await);
+        let fake = FakeSource::new();
+        fake.extend([1, 2, 7, 5, 8].map(|id| Message::AggTrade(agg_trade(id))));
+
+        let mut trades = GaplessAggTrades::new(fake.clone(), rest.clone());
+        let mut received = Vec::new();
+        while let Some(msg) = trades.next_message().await {
+            received.push(msg);
+        }
+        let expected: Vec<_> = (1..=8).map(|id| Message::AggTrade(agg_trade(id))).collect();
+        assert_eq!(received, expected);
+
+        // trades past the end of the history are not invented
+        fake.push(Message::AggTrade(agg_trade(105)));
+        let mut ids = Vec::new();
+        while let Some(Message::AggTrade(trade)) = trades.next_message().await {
+            ids.push(trade.trade_id);
+        }
+        assert_eq!(ids, (9..=100).chain([105]).collect::<Vec<_>>());
+    }
+
+    #[tokio::test]
+    async fn cancel_safe() {
+        let rest = RestClient::with_url(&rest_server(Duration::from_millis(500)).await);
+        let fake = FakeSource::new();
+        fake.extend([1, 7].map(|id| Message::AggTrade(agg_trade(id))));
+
+        let mut trades = GaplessAggTrades::new(fake, rest);
+        assert_eq!(
+            trades.next_message().await,
+            Some(Message::AggTrade(agg_trade(1)))
+        );
+        // cut off while the first page of the gap is requested
+        let next = trades.next_message();
+        assert!(tokio::time::timeout(Duration::from_millis(50), next)
+            .await
+            .is_err());
+
+        let mut ids = Vec::new();
+        while let Some(Message::AggTrade(trade)) = trades.next_message().await {
+            ids.push(trade.trade_id);
+        }
+        assert_eq!(ids, (2..=7).collect::<Vec<_>>());
+    }
+}
@@ -0,0 +1,186 @@
+//! Latest best bid and offer of each symbol.
+//!
+//! Strategies that only act on the current [`BookTicker`] do not need every update, reading
+//! them from a queue means working on stale prices after a burst. [`LatestBbo`] keeps only the
+//! latest ticker of each symbol, watched through a [`tokio::sync::watch`] channel per symbol.
+//!
+//! [`LatestBbo::tap()`] wraps a [`MarketDataSource`], the full stream is still returned, e.g.
+//! to a recorder, while the tickers are published on the side:
+//! ```no_run
+//! use binance_api_async::bbo::LatestBbo;
+//! use binance_api_async::{BinanceApi, Feed, MarketDataSource, SubscribeInfo, Symbol};
+//!
+//! # async fn run() -> Result<(), binance_api_async::Error> {
+//! let mut api = BinanceApi::new();
+//! api.connect().await?;
+//! let latest = LatestBbo::new();
+//! let mut bbo = latest.watch(Symbol::BTCUSDT);
+//! tokio::spawn(async move {
+//!     while bbo.changed().await.is_ok() {
+//!         let ticker = bbo.borrow_and_update().clone();
+//!         println!("{ticker:?}");
+//!     }
+//! });
+//!
+//! let mut source = latest.tap(api);
+//! source.subscribe(&[SubscribeInfo::new(Symbol::BTCUSDT, Feed::BookTicker)], None).await?;
+//! while let Some(msg) = source.next_message().await {
+//!     // record msg
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use tokio::sync::watch;
+
+use crate::messages::BookTicker;
+use crate::{MarketDataSource, Message, SubscribeInfo, Symbol};
+
+/// Latest [`BookTicker`] of each symbol, see the [module](self) documentation.
+///
+/// Clones share the tickers.
+#[derive(Debug, Clone, Default)]
+pub struct LatestBbo {
+    symbols: Arc<Mutex<HashMap<Symbol, watch::Sender<Option<BookTicker>>>>>,
+}
+
+impl LatestBbo {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Watch the ticker of `symbol`, `None` until the first one is received.
+    pub fn watch(&self, symbol: Symbol) -> watch::Receiver<Option<BookTicker>> {
+        let mut symbols = self.symbols.lock().unwrap();
+        symbols
+            .entry(symbol)
+            .or_insert_with(|| watch::channel(None).0)
+            .subscribe()
+    }
+
+    /// The latest ticker of `symbol`.
+    pub fn latest(&self, symbol: &Symbol) -> Option<BookTicker> {
+        let symbols = self.symbols.lock().unwrap();
+        let ticker = symbols.get(symbol)?.borrow().clone();
+        ticker
+    }
+
+    /// Replace the ticker of its symbol, unless `ticker` is older than the current one.
+    ///
+    /// Returns true if the ticker was replaced.
+    pub fn update(&self, ticker: &BookTicker) -> bool {
+        let mut symbols = self.symbols.lock().unwrap();
+        let sender = symbols
+            .entry(ticker.symbol.clone())
+            .or_insert_with(|| watch::channel(None).0);
+        sender.send_if_modified(|current| match current {
+            Some(current) if current.update_id >= ticker.update_id => false,
+            _ => {
+                *current = Some(ticker.clone());
+                true
+            }
+        })
+    }
+
+    /// [`LatestBbo::update()`] with `msg` if it is a [`BookTicker`].
+    pub fn push_message(&self, msg: &Message) -> bool {
+        match msg {
+            Message::BookTicker(ticker) => self.update(ticker),
+            _ => false,
+        }
+    }
+
+    /// Publish the tickers received by `source`.
+    pub fn tap<S: MarketDataSource + Send>(&self, source: S) -> BboTap<S> {
+        BboTap {
+            source,
+            latest: self.clone(),
+        }
+    }
+}
+
+/// A [`MarketDataSource`] publishing its tickers to a [`LatestBbo`], see [`LatestBbo::tap()`].
+#[derive(Debug)]
+pub struct BboTap<S> {
+    source: S,
+    latest: LatestBbo,
+}
+
+impl<S> BboTap<S> {
+    /// The wrapped source.
+    pub fn source_mut(&mut self) -> &mut S {
+        &mut self.source
+    }
+
+    /// Unwrap the source.
+    pub fn into_inner(self) -> S {
+        self.source
+    }
+}
+
+impl<S: MarketDataSource + Send> MarketDataSource for BboTap<S> {
+    async fn next_message(&mut self) -> Option<Message> {
+        let msg = self.source.next_message().await?;
+        self.latest.push_message(&msg);
+        Some(msg)
+    }
+
+    async fn subscribe(&mut self, symbols: &[SubscribeInfo], id: Option<u64>) -> crate::Result<()> {
+        self.source.subscribe(symbols, id).await
+    }
+
+    async fn unsubscribe(&mut self, symbols: Vec<SubscribeInfo>) -> crate::Result<()> {
+        self.source.unsubscribe(symbols).await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_util::{self, FakeSource};
+
+    fn ticker(symbol: Symbol, update_id: u64) -> BookTicker {
+        let (bid, ask) = (update_id.to_string(), (update_id + 1).to_string());
+        BookTicker {
+            update_id,
+            ..test_util::ticker(symbol, &bid, &ask)
+        }
+    }
+
+    #[tokio::test]
+    async fn only_latest() {
+        let fake = FakeSource::new();
+        fake.extend((1..=10).map(|id| Message::BookTicker(ticker(Symbol::BTCUSDT, id))));
+        fake.push(Message::BookTicker(ticker(Symbol::ETHUSDT, 3)));
+        // older than the ticker already published
+        fake.push(Message::BookTicker(ticker(Symbol::BTCUSDT, 4)));
+
+        let latest = LatestBbo::new();
+        let mut btc = latest.watch(Symbol::BTCUSDT);
+        assert_eq!(*btc.borrow(), None);
+
+        let mut source = latest.tap(fake);
+        let mut received = 0;
+        while source.next_message().await.is_some() {
+            received += 1;
+        }
+        // the full stream is passed through
+        assert_eq!(received, 12);
+
+        assert!(btc.has_changed().unwrap());
+        assert_eq!(*btc.borrow_and_update(), Some(ticker(Symbol::BTCUSDT, 10)));
+        assert!(!btc.has_changed().unwrap());
+        assert_eq!(
+            latest.latest(&Symbol::ETHUSDT),
+            Some(ticker(Symbol::ETHUSDT, 3))
+        );
+        assert_eq!(latest.latest(&Symbol::BNBUSDT), None);
+
+        assert!(latest.update(&ticker(Symbol::BTCUSDT, 11)));
+        btc.changed().await.unwrap();
+        assert_eq!(btc.borrow().as_ref().unwrap().update_id, 11);
+    }
+}
@@ -0,0 +1,40 @@
+//! The `collect` command, a collector declared in a configuration file.
+
+use std::path::PathBuf;
+
+use binance_api_async::config::Config;
+use clap::Args;
+use tracing_subscriber::filter::LevelFilter;
+
+use crate::{LogLevel, Result};
+
+/// Run the collector of a TOML or YAML file until SIGTERM or SIGINT, then persist its state.
+///
+/// The `BINANCE_*` environment variables override the settings of the file, see the
+/// `config` module of the library.
+#[derive(Debug, Args)]
+pub struct CollectArgs {
+    /// Configuration file, YAML if it ends with .yaml or .yml, TOML otherwise.
+    #[arg(long, short)]
+    pub config: PathBuf,
+    /// Check the file and exit.
+    #[arg(long)]
+    pub check: bool,
+}
+
+pub async fn run(args: CollectArgs, log_level: LogLevel) -> Result<()> {
+    let config = Config::load(&args.config)?.with_env()?;
+    if let Some(level) = config.log_level {
+        let _ = log_level.reload(LevelFilter::from_level(level));
+    }
+    let streams = config.subscribe_infos()?.len();
+    if args.check {
+        println!(
+            "{}: {streams} streams, {} sinks",
+            args.config.display(),
+            config.sinks.len()
+        );
+        return Ok(());
+    }
+    config.collector().await?.run().await
+}
@@ -0,0 +1,141 @@
+//! The `export` command, recordings converted to the files of a sink.
+
+use std::path::PathBuf;
+
+use binance_api_async::recorder::RecordReader;
+use binance_api_async::sink::csv::CsvSink;
+#[cfg(feature = "parquet")]
+use binance_api_async::sink::parquet::ParquetSink;
+use binance_api_async::{Message, Symbol};
+use clap::{Args, ValueEnum};
+use tracing::{info, warn};
+
+use crate::{parse_time, Result};
+
+/// Format of the recordings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Input {
+    /// Recordings of the record command, compressed or not.
+    Ndjson,
+}
+
+/// Format of the export.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Output {
+    /// One CSV file per message type, trades, klines and book tickers only.
+    Csv,
+    /// Parquet files partitioned by message type, symbol and date, requires the `parquet`
+    /// feature.
+    Parquet,
+}
+
+/// Convert recordings to CSV or Parquet files.
+#[derive(Debug, Args)]
+pub struct ExportArgs {
+    /// Recordings, exported in the order given.
+    #[arg(required = true)]
+    pub files: Vec<PathBuf>,
+    #[arg(long, value_enum, default_value = "ndjson")]
+    pub from: Input,
+    #[arg(long, value_enum)]
+    pub to: Output,
+    /// Directory of the export, created if it does not exist.
+    #[arg(long, short)]
+    pub out: PathBuf,
+    /// Only export messages of these symbols, partial depths name no symbol and are skipped.
+    #[arg(long, short, value_delimiter = ',')]
+    pub symbols: Vec<Symbol>,
+    /// Only export messages received from this time on, e.g. 2024-06-01T12:00:00Z.
+    #[arg(long, value_parser = parse_time)]
+    pub start: Option<u64>,
+    /// Only export messages received before this time.
+    #[arg(long, value_parser = parse_time)]
+    pub end: Option<u64>,
+    /// Rows per Parquet record batch.
+    #[arg(long, default_value_t = 10_000)]
+    pub batch_size: usize,
+}
+
+/// Where the messages are exported to.
+#[allow(clippy::large_enum_variant)]
+enum Sink {
+    Csv(CsvSink),
+    #[cfg(feature = "parquet")]
+    Parquet(ParquetSink),
+}
+
+impl Sink {
+    fn new(args: &ExportArgs) -> Result<Self> {
+        match args.to {
+            Output::Csv => Ok(Sink::Csv(CsvSink::new(&args.out)?)),
+            #[cfg(feature = "parquet")]
+            Output::Parquet => Ok(Sink::Parquet(ParquetSink::new(&args.out, args.batch_size)?)),
+            #[cfg(not(feature = "parquet"))]
+            Output::Parquet => Err(binance_api_async::Error::Custom(
+                "exporting to parquet requires the parquet feature".into(),
+            )),
+        }
+    }
+
+    /// Write `msg`, returns false if the format has no table for it.
+    fn write(&mut self, recv_time: u64, msg: &Message) -> Result<bool> {
+        match self {
+            // CSV rows have no receive time
+            Sink::Csv(sink) => {
+                let _ = recv_time;
+                sink.write(msg)
+            }
+            #[cfg(feature = "parquet")]
+            Sink::Parquet(sink) => sink.write(recv_time, msg),
+        }
+    }
+
+    fn close(&mut self) -> Result<()> {
+        match self {
+            Sink::Csv(sink) => sink.flush(),
+            #[cfg(feature = "parquet")]
+            Sink::Parquet(sink) => sink.close(),
+        }
+    }
+}
+
+pub fn run(args: ExportArgs) -> Result<()> {
+    let Input::Ndjson = args.from;
+    let mut sink = Sink::new(&args)?;
+    let (mut exported, mut filtered, mut unsupported) = (0u64, 0u64, 0u64);
+
+    for path in &args.files {
+        info!("exporting {}", path.display());
+        for record in RecordReader::open(path)? {
+            let record = match record {
+                Ok(record) => record,
+                Err(e) => {
+                    warn!("skipping an unreadable record of {}: {e}", path.display());
+                    continue;
+                }
+            };
+            let in_window = args.start.is_none_or(|start| record.recv_time >= start)
+                && args.end.is_none_or(|end| record.recv_time < end);
+            let symbol = args.symbols.is_empty()
+                || record
+                    .msg
+                    .symbol()
+                    .is_some_and(|s| args.symbols.contains(s));
+            if !in_window || !symbol || matches!(record.msg, Message::SubscribeSuccess { .. }) {
+                filtered += 1;
+            } else if sink.write(record.recv_time, &record.msg)? {
+                exported += 1;
+            } else {
+                unsupported += 1;
+            }
+        }
+    }
+    sink.close()?;
+    info!(
+        "exported {exported} messages to {}, {filtered} filtered out, {unsupported} without a \
+         table in {:?}",
+        args.out.display(),
+        args.to
+    );
+    Ok(())
+}
@@ -0,0 +1,131 @@
+//! The `latency` command, how far the host is from Binance.
+
+use std::time::Duration;
+
+use binance_api_async::rest::RestClient;
+use binance_api_async::{BinanceApi, Feed, SubscribeInfo, Symbol};
+use clap::Args;
+use tracing::{info, warn};
+
+use crate::{live, parse_duration, Result};
+
+/// Measure the delay of the events and the round trip of pings, and print their percentiles.
+///
+/// Spot book tickers carry no event time, the delays are measured on the trades of the
+/// symbol, subscribed along with its book ticker.
+#[derive(Debug, Args)]
+pub struct LatencyArgs {
+    /// Symbol, e.g. btcusdt.
+    #[arg(long, short)]
+    pub symbol: Symbol,
+    /// How long to measure, e.g. 30s or 5m.
+    #[arg(long, short, default_value = "1m", value_parser = parse_duration)]
+    pub duration: Duration,
+    /// Time between pings.
+    #[arg(long, default_value = "1s", value_parser = parse_duration)]
+    pub ping_interval: Duration,
+    /// Do not correct the delays by the clock offset to the REST api.
+    #[arg(long)]
+    pub no_clock_offset: bool,
+    /// Websocket url to connect to instead of Binance.
+    #[arg(long)]
+    pub url: Option<String>,
+    /// Allow a plaintext ws:// url, e.g. a local relay.
+    #[arg(long)]
+    pub allow_plaintext: bool,
+}
+
+pub async fn run(args: LatencyArgs) -> Result<()> {
+    // how far the local clock is behind Binance
+    let offset = if args.no_clock_offset {
+        0
+    } else {
+        RestClient::new().clock_offset().await.unwrap_or_else(|e| {
+            warn!("no clock offset, the delays include the clock skew: {e}");
+            0
+        })
+    };
+    let mut builder = BinanceApi::builder()
+        .ping_interval(Some(args.ping_interval))
+        .allow_plaintext(args.allow_plaintext);
+    if let Some(url) = &args.url {
+        builder = builder.url(url);
+    }
+    let mut api = builder.build();
+    let metrics = api.metrics();
+    let infos = [
+        SubscribeInfo::new(args.symbol.clone(), Feed::BookTicker),
+        SubscribeInfo::new(args.symbol.clone(), Feed::Trade),
+    ];
+    live::connect(&mut api, &infos).await?;
+    info!("measuring for {:?}", args.duration);
+
+    let mut delays = Vec::new();
+    let mut tickers = 0u64;
+    let deadline = tokio::time::sleep(args.duration);
+    tokio::pin!(deadline);
+    loop {
+        tokio::select! {
+            envelope = api.next_envelope() => match envelope {
+                Some(envelope) => {
+                    if envelope.message.event_type() == "bookTicker" {
+                        tickers += 1;
+                    }
+                    if let Some(event_time) = envelope.message.event_time() {
+                        let delay = envelope.recv_time as i64 + offset - event_time as i64;
+                        delays.push(delay as f64);
+                    }
+                }
+                None => {
+                    warn!("disconnected, reconnecting");
+                    live::connect(&mut api, &infos).await?;
+                }
+            },
+            _ = &mut deadline => break,
+        }
+    }
+    api.disconnect().await;
+
+    let mut pings: Vec<f64> = metrics
+        .ping_round_trips()
+        .iter()
+        .map(|rtt| rtt.as_secs_f64() * 1000.0)
+        .collect();
+    let seconds = args.duration.as_secs_f64();
+    println!(
+        "{} for {seconds}s, clock offset {offset} ms",
+        args.symbol.to_string().to_uppercase()
+    );
+    println!(
+        "{:<20}{:>8}{:>9}{:>9}{:>9}{:>9}{:>9}",
+        "", "samples", "min", "p50", "p90", "p99", "max"
+    );
+    summary("event delay (ms)", &mut delays);
+    summary("ping rtt (ms)", &mut pings);
+    println!(
+        "book tickers: {tickers} ({:.1}/s)",
+        tickers as f64 / seconds
+    );
+    Ok(())
+}
+
+/// Print a row of the percentiles of `samples`.
+fn summary(name: &str, samples: &mut [f64]) {
+    let Some(percentiles) = percentiles(samples) else {
+        println!("{name:<20}{:>8}", 0);
+        return;
+    };
+    let [min, p50, p90, p99, max] = percentiles;
+    println!(
+        "{name:<20}{:>8}{min:>9.1}{p50:>9.1}{p90:>9.1}{p99:>9.1}{max:>9.1}",
+        samples.len()
+    );
+}
+
+/// The min, p50, p90, p99 and max of `samples` by nearest rank, `None` if there are none.
+pub fn percentiles(samples: &mut [f64]) -> Option<[f64; 5]> {
+    samples.sort_unstable_by(f64::total_cmp);
+    let (&min, &max) = (samples.first()?, samples.last()?);
+    let rank = |p: usize| samples[(samples.len() * p).div_ceil(100).max(1) - 1];
+    Some([min, rank(50), rank(90), rank(99), max])
+}
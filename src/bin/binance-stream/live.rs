@@ -0,0 +1,82 @@
+//! The live feeds of the commands, connected and subscribed again after a disconnect.
+
+use std::time::Duration;
+
+use binance_api_async::validation::{parse_feed, Market};
+use binance_api_async::{BinanceApi, Feed, SubscribeInfo, Symbol};
+use clap::Args;
+use tracing::{info, warn};
+
+use crate::Result;
+
+/// Attempts before giving up.
+const ATTEMPTS: u32 = 12;
+/// Wait after the first failed attempt, doubled after each one up to a minute.
+const BACKOFF: Duration = Duration::from_secs(1);
+
+/// The feeds to subscribe to.
+#[derive(Debug, Args)]
+pub struct LiveArgs {
+    /// Symbols, e.g. btcusdt,ethusdt.
+    #[arg(long, short, value_delimiter = ',', required = true)]
+    pub symbols: Vec<Symbol>,
+    /// Feeds of every symbol, e.g. aggTrade,trade,bookTicker,depth5@100ms,depth@100ms,kline_1m.
+    #[arg(long, short, value_delimiter = ',', default_value = "trade")]
+    pub feeds: Vec<Feed>,
+    /// Market of the feeds, spot or futures for USDⓈ-M futures.
+    #[arg(long, default_value = "spot")]
+    pub market: Market,
+    /// Websocket url to connect to instead of the streams of the market.
+    #[arg(long)]
+    pub url: Option<String>,
+    /// Allow a plaintext ws:// url, e.g. a local relay.
+    #[arg(long)]
+    pub allow_plaintext: bool,
+}
+
+impl LiveArgs {
+    /// Every feed of every symbol, checked against what the market serves.
+    pub fn subscriptions(&self) -> Result<Vec<SubscribeInfo>> {
+        let mut infos = Vec::new();
+        for feed in &self.feeds {
+            // depths named without a delay have the default delay of the market
+            let feed = parse_feed(&feed.to_string(), self.market)?;
+            for symbol in &self.symbols {
+                infos.push(SubscribeInfo::new(symbol.clone(), feed.clone()));
+            }
+        }
+        Ok(infos)
+    }
+
+    /// A connection to the url, not connected yet.
+    pub fn api(&self) -> BinanceApi {
+        let mut builder = BinanceApi::builder()
+            .market(self.market)
+            .allow_plaintext(self.allow_plaintext);
+        if let Some(url) = &self.url {
+            builder = builder.url(url);
+        }
+        builder.build()
+    }
+}
+
+/// Connect `api` and subscribe to `infos`, retrying with a backoff.
+pub async fn connect(api: &mut BinanceApi, infos: &[SubscribeInfo]) -> Result<()> {
+    let mut backoff = BACKOFF;
+    let mut attempt = 1;
+    loop {
+        match api.connect().await {
+            Ok(()) => break,
+            Err(e) if attempt < ATTEMPTS => {
+                warn!("connect attempt {attempt} failed, retrying in {backoff:?}: {e}");
+                tokio::time::sleep(backoff).await;
+                backoff = (backoff * 2).min(Duration::from_secs(60));
+                attempt += 1;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+    api.subscribe(infos, None).await?;
+    info!("subscribed to {} streams", infos.len());
+    Ok(())
+}
@@ -0,0 +1,244 @@
+//! `binance-stream`, Binance market data from the command line, requires the `cli` feature.
+//!
+//! ```text
+//! binance-stream stream --symbols btcusdt,ethusdt --feeds aggTrade,depth5@100ms | jq .
+//! binance-stream record --symbols btcusdt --feeds aggTrade --out ./data --rotate 1h
+//! binance-stream replay ./data/*.ndjson --symbols btcusdt --feeds aggTrade --speed 10x
+//! binance-stream export ./data/*.ndjson --to parquet --out ./parquet --symbols btcusdt
+//! binance-stream symbols --quote USDT --status TRADING
+//! binance-stream latency --symbol btcusdt --duration 5m
+//! binance-stream view --symbol btcusdt 2>view.log
+//! binance-stream collect --config collector.toml
+//! binance-stream schema --out ./schemas
+//! ```
+//!
+//! The `view` command requires the `tui` feature too, `collect` the `config` feature and
+//! `schema` the `schema` feature.
+//!
+//! Messages are written to stdout, one json object per line, logs to stderr.
+
+#[cfg(feature = "config")]
+mod collect;
+mod export;
+mod latency;
+mod live;
+mod record;
+mod replay;
+#[cfg(feature = "schema")]
+mod schema;
+mod stream;
+mod symbols;
+#[cfg(feature = "tui")]
+mod view;
+
+use binance_api_async::clock::parse_duration;
+use clap::{Parser, Subcommand};
+use tracing_subscriber::filter::LevelFilter;
+use tracing_subscriber::prelude::*;
+use tracing_subscriber::reload;
+#[cfg(feature = "config")]
+use tracing_subscriber::Registry;
+
+type Result<T> = std::result::Result<T, binance_api_async::Error>;
+
+/// Changes the level of the logs once the command is known.
+#[cfg(feature = "config")]
+type LogLevel = reload::Handle<LevelFilter, Registry>;
+
+#[derive(Debug, Parser)]
+#[command(version, about = "Binance market data from the command line")]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    Stream(stream::StreamArgs),
+    Record(record::RecordArgs),
+    Replay(replay::ReplayArgs),
+    Export(export::ExportArgs),
+    Symbols(symbols::SymbolsArgs),
+    Latency(latency::LatencyArgs),
+    #[cfg(feature = "tui")]
+    View(view::ViewArgs),
+    #[cfg(feature = "config")]
+    Collect(collect::CollectArgs),
+    #[cfg(feature = "schema")]
+    Schema(schema::SchemaArgs),
+}
+
+/// A duration longer than zero, e.g. the period of a timer.
+fn parse_period(s: &str) -> Result<std::time::Duration> {
+    let period = parse_duration(s)?;
+    if period.is_zero() {
+        return Err(binance_api_async::Error::Custom(format!(
+            "{s:?} is not longer than zero"
+        )));
+    }
+    Ok(period)
+}
+
+/// An RFC 3339 time, or milliseconds since epoch.
+fn parse_time(s: &str) -> std::result::Result<u64, String> {
+    if let Ok(millis) = s.parse() {
+        return Ok(millis);
+    }
+    chrono::DateTime::parse_from_rfc3339(s)
+        .map(|time| time.timestamp_millis() as u64)
+        .map_err(|e| format!("{s:?} is not a time like 2024-06-01T12:00:00Z: {e}"))
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    // the collector logs at the level of its configuration
+    #[cfg_attr(not(feature = "config"), allow(unused_variables))]
+    let (filter, log_level) = reload::Layer::new(LevelFilter::INFO);
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(tracing_subscriber::fmt::layer().with_writer(std::io::stderr))
+        .init();
+
+    match Cli::parse().command {
+        Command::Stream(args) => stream::run(args).await,
+        Command::Record(args) => record::run(args).await,
+        Command::Replay(args) => replay::run(args).await,
+        Command::Export(args) => export::run(args),
+        Command::Symbols(args) => symbols::run(args).await,
+        Command::Latency(args) => latency::run(args).await,
+        #[cfg(feature = "tui")]
+        Command::View(args) => view::run(args).await,
+        #[cfg(feature = "config")]
+        Command::Collect(args) => collect::run(args, log_level).await,
+        #[cfg(feature = "schema")]
+        Command::Schema(args) => schema::run(args),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use clap::CommandFactory;
+    use std::time::Duration;
+
+    #[test]
+    fn arguments() {
+        Cli::command().debug_assert();
+
+        let cli = Cli::parse_from([
+            "binance-stream",
+            "stream",
+            "--symbols",
+            "btcusdt,ETHUSDT",
+            "--feeds",
+            "aggTrade,depth5@100ms",
+        ]);
+        let Command::Stream(args) = cli.command else {
+            panic!("not the stream command");
+        };
+        assert_eq!(args.live.symbols.len(), 2);
+        assert_eq!(args.live.feeds[1].to_string(), "depth5@100ms");
+        assert!(Cli::try_parse_from(["binance-stream", "stream", "--symbols", "btcusd"]).is_err());
+
+        let cli = Cli::parse_from([
+            "binance-stream",
+            "record",
+            "-s",
+            "btcusdt",
+            "--out",
+            "./data",
+            "--rotate",
+            "1h",
+        ]);
+        let Command::Record(args) = cli.command else {
+            panic!("not the record command");
+        };
+        assert_eq!(args.rotate, Some(Duration::from_secs(3600)));
+        assert_eq!(args.stats, Duration::from_secs(60));
+        assert!(parse_duration("500ms").is_ok_and(|d| d.as_millis() == 500));
+        assert!(parse_duration("h").is_err());
+        assert!(parse_duration("1w").is_err());
+        let zero_stats = ["binance-stream", "record", "-s", "btcusdt", "--stats", "0s"];
+        assert!(Cli::try_parse_from(zero_stats).is_err());
+
+        let cli = Cli::parse_from([
+            "binance-stream",
+            "replay",
+            "a.ndjson",
+            "--serve",
+            "127.0.0.1:9443",
+        ]);
+        let Command::Replay(args) = cli.command else {
+            panic!("not the replay command");
+        };
+        assert_eq!(
+            args.speed,
+            binance_api_async::replay::Speed::AsFastAsPossible
+        );
+        let replay =
+            |args: &[&str]| Cli::try_parse_from([&["binance-stream", "replay"], args].concat());
+        assert!(replay(&["a.ndjson"]).is_err());
+        assert!(replay(&[
+            "a.ndjson",
+            "-s",
+            "btcusdt",
+            "--speed",
+            "10x",
+            "--start",
+            "2024-06-01T00:00:00Z"
+        ])
+        .is_ok());
+        assert!(replay(&["a.ndjson", "-s", "btcusdt", "--speed", "fast"]).is_err());
+
+        let cli = Cli::parse_from([
+            "binance-stream",
+            "export",
+            "a.ndjson",
+            "b.ndjson.zst",
+            "--to",
+            "csv",
+            "--out",
+            "./csv",
+            "--end",
+            "1717200000000",
+        ]);
+        let Command::Export(args) = cli.command else {
+            panic!("not the export command");
+        };
+        assert_eq!((args.files.len(), args.to), (2, export::Output::Csv));
+        assert_eq!(args.end, Some(1_717_200_000_000));
+        let export =
+            |args: &[&str]| Cli::try_parse_from([&["binance-stream", "export"], args].concat());
+        assert!(export(&["a.ndjson", "--to", "json", "--out", "."]).is_err());
+
+        let cli = Cli::parse_from(["binance-stream", "latency", "-s", "btcusdt", "-d", "30s"]);
+        let Command::Latency(args) = cli.command else {
+            panic!("not the latency command");
+        };
+        assert_eq!(args.duration, Duration::from_secs(30));
+        assert_eq!(args.ping_interval, Duration::from_secs(1));
+        let mut samples: Vec<f64> = (1..=100).rev().map(f64::from).collect();
+        assert_eq!(
+            latency::percentiles(&mut samples),
+            Some([1.0, 50.0, 90.0, 99.0, 100.0])
+        );
+        assert_eq!(latency::percentiles(&mut []), None);
+
+        #[cfg(feature = "tui")]
+        {
+            let view =
+                |args: &[&str]| Cli::try_parse_from([&["binance-stream", "view"], args].concat());
+            assert!(view(&["-s", "btcusdt", "--levels", "10"]).is_ok());
+            assert!(view(&["-s", "btcusdt", "--levels", "15"]).is_err());
+        }
+
+        #[cfg(feature = "schema")]
+        {
+            let cli = Cli::parse_from(["binance-stream", "schema", "--out", "./schemas"]);
+            let Command::Schema(args) = cli.command else {
+                panic!("not the schema command");
+            };
+            assert_eq!(args.out, Some("./schemas".into()));
+        }
+    }
+}
@@ -0,0 +1,115 @@
+//! The `record` command, messages recorded to rotating ndjson files.
+
+use std::future::Future;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use binance_api_async::recorder::{Recorder, Rotation};
+use binance_api_async::sink::Sink;
+use binance_api_async::Message;
+use clap::Args;
+use tracing::{error, info, warn};
+
+use crate::live::{self, LiveArgs};
+use crate::{parse_duration, parse_period, Result};
+
+/// Record the messages of feeds, reconnecting when disconnected, until interrupted.
+#[derive(Debug, Args)]
+pub struct RecordArgs {
+    #[command(flatten)]
+    pub live: LiveArgs,
+    /// Directory of the recordings, created if it does not exist.
+    #[arg(long, short, default_value = ".")]
+    pub out: PathBuf,
+    /// Start of the file names.
+    #[arg(long, default_value = "binance")]
+    pub prefix: String,
+    /// Start a new file every period, aligned to the clock, e.g. 1h.
+    #[arg(long, value_parser = parse_duration)]
+    pub rotate: Option<Duration>,
+    /// Start a new file once the current one has this many bytes.
+    #[arg(long)]
+    pub max_bytes: Option<u64>,
+    /// Log the counts of the recording every period, e.g. 30s.
+    #[arg(long, value_parser = parse_period, default_value = "1m")]
+    pub stats: Duration,
+}
+
+pub async fn run(args: RecordArgs) -> Result<()> {
+    let infos = args.live.subscriptions()?;
+    let rotation = Rotation {
+        max_bytes: args.max_bytes,
+        interval: args.rotate,
+    };
+    let mut recorder = Recorder::new(&args.out, &args.prefix, rotation)?;
+    let mut api = args.live.api();
+    let metrics = api.metrics();
+    live::connect(&mut api, &infos).await?;
+
+    let started = Instant::now();
+    let mut recorded: u64 = 0;
+    let mut stats = tokio::time::interval_at((started + args.stats).into(), args.stats);
+    let shutdown = shutdown()?;
+    tokio::pin!(shutdown);
+    loop {
+        tokio::select! {
+            envelope = api.next_envelope() => match envelope {
+                Some(envelope) => match &envelope.message {
+                    Message::SubscribeSuccess { .. } => {}
+                    Message::Error(e) => {
+                        error!("request {:?} failed with code {}: {}", e.id, e.code, e.msg);
+                    }
+                    // as Binance sent it
+                    _ => {
+                        Sink::write(&mut recorder, &envelope).await?;
+                        recorded += 1;
+                    }
+                },
+                None => {
+                    warn!("disconnected, reconnecting");
+                    recorder.flush()?;
+                    live::connect(&mut api, &infos).await?;
+                }
+            },
+            _ = stats.tick() => {
+                let rate = recorded as f64 / started.elapsed().as_secs_f64();
+                let file = recorder.current_path().map(|p| p.display().to_string());
+                info!(
+                    "recorded {recorded} messages, {rate:.1}/s, {} reconnects, {} dropped, to {}",
+                    metrics.reconnects(),
+                    metrics.dropped(),
+                    file.as_deref().unwrap_or("nothing yet"),
+                );
+            }
+            signal = &mut shutdown => {
+                info!("{signal} received, shutting down");
+                recorder.flush()?;
+                info!("recorded {recorded} messages");
+                return Ok(());
+            }
+        }
+    }
+}
+
+/// Completes with the name of the signal on SIGTERM or SIGINT, or on Ctrl-C outside of unix.
+/// The handlers are installed before it is first polled, so no signal is missed.
+fn shutdown() -> Result<impl Future<Output = &'static str>> {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        let mut terminate = signal(SignalKind::terminate())?;
+        let mut interrupt = signal(SignalKind::interrupt())?;
+        Ok(async move {
+            tokio::select! {
+                _ = terminate.recv() => "SIGTERM",
+                _ = interrupt.recv() => "SIGINT",
+            }
+        })
+    }
+    #[cfg(not(unix))]
+    Ok(async {
+        let _ = tokio::signal::ctrl_c().await;
+        "Ctrl-C"
+    })
+}
@@ -0,0 +1,82 @@
+//! The `replay` command, recordings printed as json lines or served to websocket clients.
+
+use std::io::Write;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+
+use binance_api_async::relay::Relay;
+use binance_api_async::replay::{ReplaySource, Speed};
+use binance_api_async::{Feed, Message, SubscribeInfo, Symbol};
+use clap::Args;
+
+use crate::{parse_time, Result};
+
+/// Replay recordings of the record command.
+#[derive(Debug, Args)]
+pub struct ReplayArgs {
+    /// Recordings, merged in receive time order.
+    #[arg(required = true)]
+    pub files: Vec<PathBuf>,
+    /// max, realtime, or times the real time, e.g. 10x or 0.5x.
+    #[arg(long, value_parser = parse_speed, default_value = "max")]
+    pub speed: Speed,
+    /// Symbols to print, e.g. btcusdt,ethusdt.
+    #[arg(long, short, value_delimiter = ',', required_unless_present = "serve")]
+    pub symbols: Vec<Symbol>,
+    /// Feeds of every symbol to print.
+    #[arg(long, short, value_delimiter = ',', default_value = "trade")]
+    pub feeds: Vec<Feed>,
+    /// Replay messages received from this time on, e.g. 2024-06-01T12:00:00Z.
+    #[arg(long, value_parser = parse_time)]
+    pub start: Option<u64>,
+    /// Replay messages received before this time.
+    #[arg(long, value_parser = parse_time)]
+    pub end: Option<u64>,
+    /// Serve the recordings to websocket clients on this address instead of printing them,
+    /// clients subscribe like to Binance.
+    #[arg(long, conflicts_with = "symbols")]
+    pub serve: Option<SocketAddr>,
+}
+
+pub async fn run(args: ReplayArgs) -> Result<()> {
+    let mut source = ReplaySource::from_files(&args.files)?
+        .speed(args.speed)?
+        .time_window(args.start, args.end);
+
+    if let Some(addr) = args.serve {
+        return Relay::replay(source, addr, 1024).await?.run().await;
+    }
+
+    let mut infos = Vec::new();
+    for feed in &args.feeds {
+        for symbol in &args.symbols {
+            infos.push(SubscribeInfo::new(symbol.clone(), feed.clone()));
+        }
+    }
+    source.subscribe(&infos, None).await?;
+    while let Some(msg) = source.next_message().await {
+        if let Message::SubscribeSuccess { .. } = msg {
+            continue;
+        }
+        let line = serde_json::to_string(&msg)?;
+        // the reader of a pipeline has gone, e.g. `| head`
+        if writeln!(std::io::stdout().lock(), "{line}").is_err() {
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// `max`, `realtime` or a multiplier like `10x`.
+fn parse_speed(s: &str) -> std::result::Result<Speed, String> {
+    match s {
+        "max" => Ok(Speed::AsFastAsPossible),
+        "realtime" | "1x" => Ok(Speed::RealTime),
+        _ => s
+            .strip_suffix('x')
+            .and_then(|n| n.parse::<f64>().ok())
+            .filter(|n| *n > 0.0)
+            .map(Speed::Multiplier)
+            .ok_or_else(|| format!("{s:?} is not max, realtime or a multiplier like 10x")),
+    }
+}
@@ -0,0 +1,32 @@
+//! The `schema` command, JSON Schemas of the messages written by the other commands.
+
+use std::path::PathBuf;
+
+use binance_api_async::schema;
+use clap::Args;
+
+use crate::Result;
+
+/// Print the JSON Schema of the messages, or write the schema of every message type to a
+/// directory.
+#[derive(Debug, Args)]
+pub struct SchemaArgs {
+    /// Directory of the schemas, `<type>.schema.json` each, instead of stdout.
+    #[arg(long, short)]
+    pub out: Option<PathBuf>,
+}
+
+pub fn run(args: SchemaArgs) -> Result<()> {
+    match args.out {
+        Some(dir) => {
+            for path in schema::write_schemas(&dir)? {
+                println!("{}", path.display());
+            }
+        }
+        None => println!(
+            "{}",
+            serde_json::to_string_pretty(&schema::message_schema())?
+        ),
+    }
+    Ok(())
+}
@@ -0,0 +1,44 @@
+//! The `stream` command, messages as json lines.
+
+use std::io::Write;
+
+use binance_api_async::Message;
+use clap::Args;
+use tracing::{error, warn};
+
+use crate::live::{self, LiveArgs};
+use crate::Result;
+
+/// Print the messages of feeds as json lines, reconnecting when disconnected.
+#[derive(Debug, Args)]
+pub struct StreamArgs {
+    #[command(flatten)]
+    pub live: LiveArgs,
+}
+
+pub async fn run(args: StreamArgs) -> Result<()> {
+    let infos = args.live.subscriptions()?;
+    let mut api = args.live.api();
+    live::connect(&mut api, &infos).await?;
+
+    loop {
+        let msg = match api.try_next_message().await {
+            Ok(Message::SubscribeSuccess { .. }) => continue,
+            Ok(Message::Error(e)) => {
+                error!("request {:?} failed with code {}: {}", e.id, e.code, e.msg);
+                continue;
+            }
+            Ok(msg) => msg,
+            Err(e) => {
+                warn!("{e}, reconnecting");
+                live::connect(&mut api, &infos).await?;
+                continue;
+            }
+        };
+        let line = serde_json::to_string(&msg)?;
+        // the reader of a pipeline has gone, e.g. `| head`
+        if writeln!(std::io::stdout().lock(), "{line}").is_err() {
+            return Ok(());
+        }
+    }
+}
@@ -0,0 +1,83 @@
+//! The `symbols` command, the symbols of the exchange info.
+
+use std::io::Write;
+
+use binance_api_async::rest::{ExchangeSymbol, RestClient};
+use clap::Args;
+use rust_decimal::Decimal;
+
+use crate::Result;
+
+/// List the symbols of the exchange with their tick and lot sizes.
+#[derive(Debug, Args)]
+pub struct SymbolsArgs {
+    /// Only symbols quoted in this asset, e.g. USDT.
+    #[arg(long)]
+    pub quote: Option<String>,
+    /// Only symbols of this base asset, e.g. BTC.
+    #[arg(long)]
+    pub base: Option<String>,
+    /// Only symbols with this status, e.g. TRADING.
+    #[arg(long)]
+    pub status: Option<String>,
+    /// Also list symbols the streams of this crate can not subscribe to.
+    #[arg(long)]
+    pub all: bool,
+    /// Print the names only, comma separated, for the --symbols of the other commands.
+    #[arg(long)]
+    pub names: bool,
+    /// REST api url to query instead of Binance.
+    #[arg(long)]
+    pub url: Option<String>,
+}
+
+impl SymbolsArgs {
+    fn matches(&self, symbol: &ExchangeSymbol) -> bool {
+        let is = |filter: &Option<String>, value: &str| {
+            filter
+                .as_ref()
+                .is_none_or(|f| f.eq_ignore_ascii_case(value))
+        };
+        (self.all || symbol.symbol.is_some())
+            && is(&self.quote, &symbol.quote_asset)
+            && is(&self.base, &symbol.base_asset)
+            && is(&self.status, &symbol.status)
+    }
+}
+
+pub async fn run(args: SymbolsArgs) -> Result<()> {
+    let client = match &args.url {
+        Some(url) => RestClient::with_url(url),
+        None => RestClient::new(),
+    };
+    let mut symbols = client.exchange_symbols().await?;
+    symbols.retain(|s| args.matches(s));
+    symbols.sort_by(|a, b| a.name.cmp(&b.name));
+
+    let mut out = std::io::stdout().lock();
+    if args.names {
+        let names: Vec<String> = symbols.iter().map(|s| s.name.to_lowercase()).collect();
+        writeln!(out, "{}", names.join(","))?;
+        return Ok(());
+    }
+    let size = |size: Option<Decimal>| size.map_or("-".to_string(), |s| s.to_string());
+    let mut row = |cells: [&str; 6]| {
+        let [name, status, base, quote, tick, lot] = cells;
+        writeln!(
+            out,
+            "{name:<16}{status:<10}{base:<10}{quote:<10}{tick:<16}{lot}"
+        )
+    };
+    row(["SYMBOL", "STATUS", "BASE", "QUOTE", "TICK", "LOT"])?;
+    for s in &symbols {
+        row([
+            &s.name,
+            &s.status,
+            &s.base_asset,
+            &s.quote_asset,
+            &size(s.tick_size),
+            &size(s.step_size),
+        ])?;
+    }
+    Ok(())
+}
@@ -0,0 +1,99 @@
+//! The `view` command, the live order book of a symbol in the terminal.
+
+use std::time::Duration;
+
+use binance_api_async::validation::Market;
+use binance_api_async::viewer::BookView;
+use binance_api_async::{Feed, SubscribeInfo, Symbol};
+use clap::Args;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use tokio::sync::mpsc;
+
+use crate::live::{self, LiveArgs};
+use crate::Result;
+
+/// Show the book, trades and spread of a symbol until q is pressed, logs are best redirected
+/// with 2>view.log.
+#[derive(Debug, Args)]
+pub struct ViewArgs {
+    /// Symbol, e.g. btcusdt.
+    #[arg(long, short)]
+    pub symbol: Symbol,
+    /// Levels of the book.
+    #[arg(long, default_value = "20", value_parser = ["5", "10", "20"])]
+    pub levels: String,
+    /// Trades kept on the tape.
+    #[arg(long, default_value_t = 100)]
+    pub trades: usize,
+    /// Market of the symbol, spot or futures for USDⓈ-M futures.
+    #[arg(long, default_value = "spot")]
+    pub market: Market,
+    /// Websocket url to connect to instead of Binance.
+    #[arg(long)]
+    pub url: Option<String>,
+    /// Allow a plaintext ws:// url, e.g. a local relay.
+    #[arg(long)]
+    pub allow_plaintext: bool,
+}
+
+pub async fn run(args: ViewArgs) -> Result<()> {
+    let depth: Feed = format!("depth{}@100ms", args.levels).parse()?;
+    let live = LiveArgs {
+        symbols: vec![args.symbol.clone()],
+        feeds: vec![depth, Feed::AggTrade],
+        market: args.market,
+        url: args.url,
+        allow_plaintext: args.allow_plaintext,
+    };
+    let infos: Vec<SubscribeInfo> = live.subscriptions()?;
+    let mut api = live.api();
+    live::connect(&mut api, &infos).await?;
+
+    // terminal events are read blocking
+    let (events_tx, mut events) = mpsc::unbounded_channel();
+    std::thread::spawn(move || {
+        while let Ok(event) = event::read() {
+            if events_tx.send(event).is_err() {
+                break;
+            }
+        }
+    });
+
+    let mut view = BookView::new(args.symbol, args.trades);
+    let mut terminal = ratatui::init();
+    let mut redraw = tokio::time::interval(Duration::from_millis(100));
+    let result = loop {
+        tokio::select! {
+            msg = api.try_next_message() => match msg {
+                Ok(msg) => view.push_message(&msg),
+                Err(_) => {
+                    if let Err(e) = live::connect(&mut api, &infos).await {
+                        break Err(e);
+                    }
+                }
+            },
+            _ = redraw.tick() => {
+                if let Err(e) = terminal.draw(|frame| frame.render_widget(&view, frame.area())) {
+                    break Err(e.into());
+                }
+            }
+            Some(event) = events.recv() => {
+                if quits(&event) {
+                    break Ok(());
+                }
+            }
+        }
+    };
+    ratatui::restore();
+    result
+}
+
+/// q, Esc or Ctrl-C, the terminal in raw mode does not send a signal for the latter.
+fn quits(event: &Event) -> bool {
+    let Event::Key(key) = event else {
+        return false;
+    };
+    key.kind == KeyEventKind::Press
+        && (matches!(key.code, KeyCode::Char('q') | KeyCode::Esc)
+            || (key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL)))
+}
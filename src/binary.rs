@@ -0,0 +1,670 @@
+//! Compact binary encoding of messages, requires the `binary` feature.
+//!
+//! Messages are encoded with [postcard](https://docs.rs/postcard), integers as varints and
+//! decimals as mantissa and scale, several times smaller than the Binance json.
+//! Every encoding starts with a format version byte, [`VERSION`] when encoding.
+//! [`decode()`] reads every version written by earlier releases, so archived data stays
+//! readable when the format changes.
+//!
+//! ```
+//! use binance_api_async::{binary, Message};
+//!
+//! let msg = Message::SubscribeSuccess { result: None, id: 1 };
+//! let bytes = binary::encode(&msg).unwrap();
+//! assert_eq!(binary::decode(&bytes).unwrap(), msg);
+//! ```
+
+use rust_decimal::Decimal;
+use serde::de::IntoDeserializer;
+use serde::{Deserialize, Serialize};
+
+use crate::messages::{
+    AggTrade, BookTicker, ContinuousKline, DepthUpdate, FuturesDepthUpdate, Kline, KlineData,
+    PartialDepth, Trade,
+};
+use crate::recorder::Record;
+use crate::sink::symbol_name;
+use crate::{ContractType, Message, Symbol};
+
+/// Format version written by [`encode()`] and [`encode_record()`].
+pub const VERSION: u8 = 1;
+
+/// Encode `msg`.
+pub fn encode(msg: &Message) -> crate::Result<Vec<u8>> {
+    encode_versioned(&v1::Message::from(msg))
+}
+
+/// Decode a message encoded with [`encode()`] by this or an earlier release.
+pub fn decode(bytes: &[u8]) -> crate::Result<Message> {
+    match split_version(bytes)? {
+        (1, bytes) => postcard::from_bytes::<v1::Message>(bytes)?.try_into(),
+        (version, _) => Err(unsupported(version)),
+    }
+}
+
+/// Encode a recorded message with its receive time.
+pub fn encode_record(record: &Record) -> crate::Result<Vec<u8>> {
+    encode_versioned(&(record.recv_time, v1::Message::from(&record.msg)))
+}
+
+/// Decode a record encoded with [`encode_record()`] by this or an earlier release.
+pub fn decode_record(bytes: &[u8]) -> crate::Result<Record> {
+    match split_version(bytes)? {
+        (1, bytes) => {
+            let (recv_time, msg) = postcard::from_bytes::<(u64, v1::Message)>(bytes)?;
+            Ok(Record {
+                recv_time,
+                msg: msg.try_into()?,
+            })
+        }
+        (version, _) => Err(unsupported(version)),
+    }
+}
+
+fn encode_versioned(value: &impl Serialize) -> crate::Result<Vec<u8>> {
+    Ok(postcard::to_extend(value, vec![VERSION])?)
+}
+
+fn split_version(bytes: &[u8]) -> crate::Result<(u8, &[u8])> {
+    bytes
+        .split_first()
+        .map(|(version, rest)| (*version, rest))
+        .ok_or_else(|| crate::Error::Custom("empty binary message".to_string()))
+}
+
+fn unsupported(version: u8) -> crate::Error {
+    crate::Error::Custom(format!("unsupported binary format version {version}"))
+}
+
+/// Symbols are encoded by name, their position in [`Symbol`] changes as symbols are added.
+fn parse_symbol(name: &str) -> crate::Result<Symbol> {
+    Symbol::deserialize(name.into_deserializer())
+        .map_err(|e: serde::de::value::Error| crate::Error::Custom(e.to_string()))
+}
+
+/// Contract types are encoded by name too, e.g. `perpetual`.
+fn parse_contract_type(name: &str) -> crate::Result<ContractType> {
+    ContractType::ALL
+        .into_iter()
+        .find(|c| c.as_str() == name)
+        .ok_or_else(|| crate::Error::Custom(format!("unknown contract type {name}")))
+}
+
+/// Version 1 of the format.
+///
+/// Never change these types, add variants at the end of [`v1::Message`] or a new version.
+mod v1 {
+    use super::*;
+
+    #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+    pub struct Dec {
+        mantissa: i128,
+        scale: u32,
+    }
+
+    impl From<Decimal> for Dec {
+        fn from(value: Decimal) -> Self {
+            Self {
+                mantissa: value.mantissa(),
+                scale: value.scale(),
+            }
+        }
+    }
+
+    impl TryFrom<Dec> for Decimal {
+        type Error = crate::Error;
+
+        fn try_from(value: Dec) -> crate::Result<Self> {
+            Decimal::try_from_i128_with_scale(value.mantissa, value.scale)
+                .map_err(|e| crate::Error::Custom(e.to_string()))
+        }
+    }
+
+    fn levels(levels: &[[Decimal; 2]]) -> Vec<[Dec; 2]> {
+        levels.iter().map(|l| [l[0].into(), l[1].into()]).collect()
+    }
+
+    fn decimal_levels<C: FromIterator<[Decimal; 2]>>(levels: Vec<[Dec; 2]>) -> crate::Result<C> {
+        levels
+            .into_iter()
+            .map(|l| Ok([l[0].try_into()?, l[1].try_into()?]))
+            .collect()
+    }
+
+    #[derive(Debug, Serialize, Deserialize)]
+    pub enum Message {
+        AggTrade {
+            event_time: u64,
+            trade_id: u64,
+            symbol: String,
+            price: Dec,
+            quantity: Dec,
+            // u32 before, varints of both read the same
+            first_trade_id: u64,
+            last_trade_id: u64,
+            trade_time: u64,
+            is_market_maker: bool,
+        },
+        Trade {
+            event_time: u64,
+            symbol: String,
+            trade_id: u64,
+            price: Dec,
+            quantity: Dec,
+            trade_time: u64,
+            is_market_maker: bool,
+        },
+        PartialDepth {
+            last_update_id: u64,
+            bids: Vec<[Dec; 2]>,
+            asks: Vec<[Dec; 2]>,
+        },
+        BookTicker {
+            update_id: u64,
+            symbol: String,
+            best_bid_price: Dec,
+            best_bid_qty: Dec,
+            best_ask_price: Dec,
+            best_ask_qty: Dec,
+        },
+        DepthUpdate {
+            event_time: u64,
+            symbol: String,
+            first_update_id: u64,
+            final_update_id: u64,
+            bids: Vec<[Dec; 2]>,
+            asks: Vec<[Dec; 2]>,
+        },
+        Kline {
+            event_time: u64,
+            symbol: String,
+            open_time: u64,
+            close_time: u64,
+            interval: String,
+            first_trade_id: i64,
+            last_trade_id: i64,
+            open: Dec,
+            close: Dec,
+            high: Dec,
+            low: Dec,
+            volume: Dec,
+            trades: u64,
+            is_closed: bool,
+            quote_volume: Dec,
+            taker_buy_volume: Dec,
+            taker_buy_quote_volume: Dec,
+        },
+        SubscribeSuccess {
+            result: Option<String>,
+            id: u8,
+        },
+        // appended, the variant index is part of the encoding
+        Error {
+            code: i64,
+            msg: String,
+            id: Option<u64>,
+        },
+        /// Replaces [`Message::SubscribeSuccess`], ids are `u64` now.
+        SubscribeSuccessU64 {
+            result: Option<String>,
+            id: u64,
+        },
+        ContinuousKline {
+            event_time: u64,
+            pair: String,
+            contract_type: String,
+            kline: Candle,
+        },
+        FuturesDepthUpdate {
+            event_time: u64,
+            transaction_time: u64,
+            symbol: String,
+            first_update_id: u64,
+            final_update_id: u64,
+            prev_final_update_id: u64,
+            bids: Vec<[Dec; 2]>,
+            asks: Vec<[Dec; 2]>,
+        },
+    }
+
+    /// The kline of a [`Message::ContinuousKline`].
+    #[derive(Debug, Serialize, Deserialize)]
+    pub struct Candle {
+        open_time: u64,
+        close_time: u64,
+        interval: String,
+        first_trade_id: i64,
+        last_trade_id: i64,
+        open: Dec,
+        close: Dec,
+        high: Dec,
+        low: Dec,
+        volume: Dec,
+        trades: u64,
+        is_closed: bool,
+        quote_volume: Dec,
+        taker_buy_volume: Dec,
+        taker_buy_quote_volume: Dec,
+    }
+
+    impl From<&KlineData> for Candle {
+        fn from(k: &KlineData) -> Self {
+            Self {
+                open_time: k.open_time,
+                close_time: k.close_time,
+                interval: k.interval.clone(),
+                first_trade_id: k.first_trade_id,
+                last_trade_id: k.last_trade_id,
+                open: k.open.into(),
+                close: k.close.into(),
+                high: k.high.into(),
+                low: k.low.into(),
+                volume: k.volume.into(),
+                trades: k.trades,
+                is_closed: k.is_closed,
+                quote_volume: k.quote_volume.into(),
+                taker_buy_volume: k.taker_buy_volume.into(),
+                taker_buy_quote_volume: k.taker_buy_quote_volume.into(),
+            }
+        }
+    }
+
+    impl TryFrom<Candle> for KlineData {
+        type Error = crate::Error;
+
+        fn try_from(k: Candle) -> crate::Result<Self> {
+            Ok(KlineData {
+                open_time: k.open_time,
+                close_time: k.close_time,
+                interval: k.interval,
+                first_trade_id: k.first_trade_id,
+                last_trade_id: k.last_trade_id,
+                open: k.open.try_into()?,
+                close: k.close.try_into()?,
+                high: k.high.try_into()?,
+                low: k.low.try_into()?,
+                volume: k.volume.try_into()?,
+                trades: k.trades,
+                is_closed: k.is_closed,
+                quote_volume: k.quote_volume.try_into()?,
+                taker_buy_volume: k.taker_buy_volume.try_into()?,
+                taker_buy_quote_volume: k.taker_buy_quote_volume.try_into()?,
+            })
+        }
+    }
+
+    impl From<&crate::Message> for Message {
+        fn from(msg: &crate::Message) -> Self {
+            match msg {
+                crate::Message::AggTrade(t) => Message::AggTrade {
+                    event_time: t.event_time,
+                    trade_id: t.trade_id,
+                    symbol: symbol_name(&t.symbol),
+                    price: t.price.into(),
+                    quantity: t.quantity.into(),
+                    first_trade_id: t.first_trade_id,
+                    last_trade_id: t.last_trade_id,
+                    trade_time: t.trade_time,
+                    is_market_maker: t.is_market_maker,
+                },
+                crate::Message::Trade(t) => Message::Trade {
+                    event_time: t.event_time,
+                    symbol: symbol_name(&t.symbol),
+                    trade_id: t.trade_id,
+                    price: t.price.into(),
+                    quantity: t.quantity.into(),
+                    trade_time: t.trade_time,
+                    is_market_maker: t.is_market_maker,
+                },
+                crate::Message::PartialDepth(pd) => Message::PartialDepth {
+                    last_update_id: pd.last_update_id,
+                    bids: levels(&pd.bids),
+                    asks: levels(&pd.asks),
+                },
+                crate::Message::BookTicker(bt) => Message::BookTicker {
+                    update_id: bt.update_id,
+                    symbol: symbol_name(&bt.symbol),
+                    best_bid_price: bt.best_bid_price.into(),
+                    best_bid_qty: bt.best_bid_qty.into(),
+                    best_ask_price: bt.best_ask_price.into(),
+                    best_ask_qty: bt.best_ask_qty.into(),
+                },
+                crate::Message::DepthUpdate(du) => Message::DepthUpdate {
+                    event_time: du.event_time,
+                    symbol: symbol_name(&du.symbol),
+                    first_update_id: du.first_update_id,
+                    final_update_id: du.final_update_id,
+                    bids: levels(&du.bids),
+                    asks: levels(&du.asks),
+                },
+                crate::Message::FuturesDepthUpdate(du) => Message::FuturesDepthUpdate {
+                    event_time: du.event_time,
+                    transaction_time: du.transaction_time,
+                    symbol: symbol_name(&du.symbol),
+                    first_update_id: du.first_update_id,
+                    final_update_id: du.final_update_id,
+                    prev_final_update_id: du.prev_final_update_id,
+                    bids: levels(&du.bids),
+                    asks: levels(&du.asks),
+                },
+                crate::Message::Kline(k) => Message::Kline {
+                    event_time: k.event_time,
+                    symbol: symbol_name(&k.symbol),
+                    open_time: k.kline.open_time,
+                    close_time: k.kline.close_time,
+                    interval: k.kline.interval.clone(),
+                    first_trade_id: k.kline.first_trade_id,
+                    last_trade_id: k.kline.last_trade_id,
+                    open: k.kline.open.into(),
+                    close: k.kline.close.into(),
+                    high: k.kline.high.into(),
+                    low: k.kline.low.into(),
+                    volume: k.kline.volume.into(),
+                    trades: k.kline.trades,
+                    is_closed: k.kline.is_closed,
+                    quote_volume: k.kline.quote_volume.into(),
+                    taker_buy_volume: k.kline.taker_buy_volume.into(),
+                    taker_buy_quote_volume: k.kline.taker_buy_quote_volume.into(),
+                },
+                crate::Message::ContinuousKline(k) => Message::ContinuousKline {
+                    event_time: k.event_time,
+                    pair: symbol_name(&k.pair),
+                    contract_type: k.contract_type.as_str().to_string(),
+                    kline: (&k.kline).into(),
+                },
+                crate::Message::SubscribeSuccess { result, id } => Message::SubscribeSuccessU64 {
+                    result: result.clone(),
+                    id: *id,
+                },
+                crate::Message::Error(e) => Message::Error {
+                    code: e.code,
+                    msg: e.msg.clone(),
+                    id: e.id,
+                },
+            }
+        }
+    }
+
+    impl TryFrom<Message> for crate::Message {
+        type Error = crate::Error;
+
+        fn try_from(msg: Message) -> crate::Result<Self> {
+            Ok(match msg {
+                Message::AggTrade {
+                    event_time,
+                    trade_id,
+                    symbol,
+                    price,
+                    quantity,
+                    first_trade_id,
+                    last_trade_id,
+                    trade_time,
+                    is_market_maker,
+                } => crate::Message::AggTrade(AggTrade {
+                    event_time,
+                    trade_id,
+                    symbol: parse_symbol(&symbol)?,
+                    price: price.try_into()?,
+                    quantity: quantity.try_into()?,
+                    first_trade_id,
+                    last_trade_id,
+                    trade_time,
+                    is_market_maker,
+                }),
+                Message::Trade {
+                    event_time,
+                    symbol,
+                    trade_id,
+                    price,
+                    quantity,
+                    trade_time,
+                    is_market_maker,
+                } => crate::Message::Trade(Trade {
+                    event_time,
+                    symbol: parse_symbol(&symbol)?,
+                    trade_id,
+                    price: price.try_into()?,
+                    quantity: quantity.try_into()?,
+                    trade_time,
+                    is_market_maker,
+                }),
+                Message::PartialDepth {
+                    last_update_id,
+                    bids,
+                    asks,
+                } => crate::Message::PartialDepth(Box::new(PartialDepth {
+                    last_update_id,
+                    bids: decimal_levels(bids)?,
+                    asks: decimal_levels(asks)?,
+                })),
+                Message::BookTicker {
+                    update_id,
+                    symbol,
+                    best_bid_price,
+                    best_bid_qty,
+                    best_ask_price,
+                    best_ask_qty,
+                } => crate::Message::BookTicker(BookTicker {
+                    update_id,
+                    symbol: parse_symbol(&symbol)?,
+                    best_bid_price: best_bid_price.try_into()?,
+                    best_bid_qty: best_bid_qty.try_into()?,
+                    best_ask_price: best_ask_price.try_into()?,
+                    best_ask_qty: best_ask_qty.try_into()?,
+                }),
+                Message::DepthUpdate {
+                    event_time,
+                    symbol,
+                    first_update_id,
+                    final_update_id,
+                    bids,
+                    asks,
+                } => crate::Message::DepthUpdate(DepthUpdate {
+                    event_time,
+                    symbol: parse_symbol(&symbol)?,
+                    first_update_id,
+                    final_update_id,
+                    bids: decimal_levels(bids)?,
+                    asks: decimal_levels(asks)?,
+                }),
+                Message::Kline {
+                    event_time,
+                    symbol,
+                    open_time,
+                    close_time,
+                    interval,
+                    first_trade_id,
+                    last_trade_id,
+                    open,
+                    close,
+                    high,
+                    low,
+                    volume,
+                    trades,
+                    is_closed,
+                    quote_volume,
+                    taker_buy_volume,
+                    taker_buy_quote_volume,
+                } => crate::Message::Kline(Kline {
+                    event_time,
+                    symbol: parse_symbol(&symbol)?,
+                    kline: KlineData {
+                        open_time,
+                        close_time,
+                        interval,
+                        first_trade_id,
+                        last_trade_id,
+                        open: open.try_into()?,
+                        close: close.try_into()?,
+                        high: high.try_into()?,
+                        low: low.try_into()?,
+                        volume: volume.try_into()?,
+                        trades,
+                        is_closed,
+                        quote_volume: quote_volume.try_into()?,
+                        taker_buy_volume: taker_buy_volume.try_into()?,
+                        taker_buy_quote_volume: taker_buy_quote_volume.try_into()?,
+                    },
+                }),
+                Message::SubscribeSuccess { result, id } => crate::Message::SubscribeSuccess {
+                    result,
+                    id: id.into(),
+                },
+                Message::SubscribeSuccessU64 { result, id } => {
+                    crate::Message::SubscribeSuccess { result, id }
+                }
+                Message::Error { code, msg, id } => {
+                    crate::Message::Error(crate::messages::ErrorResponse { code, msg, id })
+                }
+                Message::ContinuousKline {
+                    event_time,
+                    pair,
+                    contract_type,
+                    kline,
+                } => crate::Message::ContinuousKline(ContinuousKline {
+                    event_time,
+                    pair: parse_symbol(&pair)?,
+                    contract_type: parse_contract_type(&contract_type)?,
+                    kline: kline.try_into()?,
+                }),
+                Message::FuturesDepthUpdate {
+                    event_time,
+                    transaction_time,
+                    symbol,
+                    first_update_id,
+                    final_update_id,
+                    prev_final_update_id,
+                    bids,
+                    asks,
+                } => crate::Message::FuturesDepthUpdate(FuturesDepthUpdate {
+                    event_time,
+                    transaction_time,
+                    symbol: parse_symbol(&symbol)?,
+                    first_update_id,
+                    final_update_id,
+                    prev_final_update_id,
+                    bids: decimal_levels(bids)?,
+                    asks: decimal_levels(asks)?,
+                }),
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_util::dec;
+
+    #[test]
+    fn round_trip() {
+        let trade = Message::AggTrade(AggTrade {
+            event_time: 1717200000100,
+            trade_id: 424951,
+            symbol: Symbol::BTCUSDT,
+            price: dec("65000.01"),
+            quantity: dec("0.00100000"),
+            first_trade_id: 1,
+            last_trade_id: 3,
+            trade_time: 1717200000099,
+            is_market_maker: true,
+        });
+        let depth = Message::DepthUpdate(DepthUpdate {
+            event_time: 1,
+            symbol: Symbol::ETHBTC,
+            first_update_id: 10,
+            final_update_id: 12,
+            bids: vec![[dec("0.05"), dec("1.5")]],
+            asks: vec![[dec("0.051"), dec("0")]],
+        });
+        let futures_depth = Message::FuturesDepthUpdate(FuturesDepthUpdate {
+            event_time: 2,
+            transaction_time: 1,
+            symbol: Symbol::BTCUSDT,
+            first_update_id: 20,
+            final_update_id: 25,
+            prev_final_update_id: 17,
+            bids: vec![[dec("65000.1"), dec("0.002")]],
+            asks: vec![],
+        });
+
+        let continuous = Message::ContinuousKline(ContinuousKline {
+            event_time: 1607443058651,
+            pair: Symbol::BTCUSDT,
+            contract_type: ContractType::NextQuarter,
+            kline: KlineData {
+                open_time: 1607443020000,
+                close_time: 1607443079999,
+                interval: "1m".to_string(),
+                first_trade_id: 116467658886,
+                last_trade_id: 116468012423,
+                open: dec("18787.00"),
+                close: dec("18804.04"),
+                high: dec("18804.04"),
+                low: dec("18786.54"),
+                volume: dec("197.664"),
+                trades: 543,
+                is_closed: false,
+                quote_volume: dec("3715253.19494"),
+                taker_buy_volume: dec("184.769"),
+                taker_buy_quote_volume: dec("3472925.84746"),
+            },
+        });
+
+        for msg in [trade.clone(), depth, futures_depth, continuous] {
+            let bytes = encode(&msg).unwrap();
+            assert_eq!(bytes[0], VERSION);
+            assert_eq!(decode(&bytes).unwrap(), msg);
+        }
+
+        let json = serde_json::to_vec(&trade).unwrap();
+        assert!(encode(&trade).unwrap().len() * 3 < json.len());
+
+        // above u32::MAX like the BTCUSDT trade ids
+        let Message::AggTrade(mut large) = trade.clone() else {
+            unreachable!()
+        };
+        (large.first_trade_id, large.last_trade_id) = (4_958_000_001, 4_958_000_003);
+        let large = Message::AggTrade(large);
+        assert_eq!(decode(&encode(&large).unwrap()).unwrap(), large);
+
+        let record = Record {
+            recv_time: 1717200000123,
+            msg: trade,
+        };
+        assert_eq!(
+            decode_record(&encode_record(&record).unwrap()).unwrap(),
+            record
+        );
+    }
+
+    #[test]
+    fn subscribe_success_ids() {
+        let large = Message::SubscribeSuccess {
+            result: None,
+            id: 1 << 40,
+        };
+        assert_eq!(decode(&encode(&large).unwrap()).unwrap(), large);
+        // written with a u8 id before
+        let old = [VERSION, 6, 0, 200];
+        assert_eq!(
+            decode(&old).unwrap(),
+            Message::SubscribeSuccess {
+                result: None,
+                id: 200
+            }
+        );
+    }
+
+    #[test]
+    fn unknown_version() {
+        let mut bytes = encode(&Message::SubscribeSuccess {
+            result: None,
+            id: 1,
+        })
+        .unwrap();
+        bytes[0] = VERSION + 1;
+        assert!(decode(&bytes).is_err());
+        assert!(decode(&[]).is_err());
+    }
+}
@@ -0,0 +1,341 @@
+//! Locally managed order book, kept in sync with [`Feed::FullDepth`](crate::Feed::FullDepth).
+//!
+//! Start from a snapshot, the REST endpoint `/api/v3/depth` returns the same payload as
+//! [`PartialDepth`], then [`OrderBook::apply()`] every [`DepthUpdate`] for the symbol.
+//!
+//! Books of futures start from the snapshot of `/fapi/v1/depth` and
+//! [`OrderBook::apply_futures()`] every [`FuturesDepthUpdate`] instead, their update ids are
+//! not consecutive and updates are chained by `prev_final_update_id`.
+//!
+//! # Warm restart
+//! A book can be written to disk with [`OrderBook::save()`] on shutdown and restored with
+//! [`OrderBook::load()`] on startup. Updates older than the stored book are skipped, so only
+//! the delta since shutdown is applied. If Binance no longer has the updates bridging the gap
+//! [`crate::Error::OrderBookOutOfSync`] is returned and a new snapshot is needed.
+//!
+//! **Official docs:** https://binance-docs.github.io/apidocs/spot/en/#how-to-manage-a-local-order-book-correctly
+
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use crate::messages::{DepthUpdate, FuturesDepthUpdate, PartialDepth};
+use crate::{Error, Symbol};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OrderBook {
+    symbol: Symbol,
+    last_update_id: u64,
+    bids: BTreeMap<Decimal, Decimal>,
+    asks: BTreeMap<Decimal, Decimal>,
+}
+
+impl OrderBook {
+    /// Create a book for `symbol` from a depth snapshot.
+    pub fn from_snapshot(symbol: Symbol, snapshot: &PartialDepth) -> Self {
+        Self {
+            symbol,
+            last_update_id: snapshot.last_update_id,
+            bids: snapshot.bids.iter().map(|l| (l[0], l[1])).collect(),
+            asks: snapshot.asks.iter().map(|l| (l[0], l[1])).collect(),
+        }
+    }
+
+    pub fn symbol(&self) -> &Symbol {
+        &self.symbol
+    }
+
+    /// Update id of the last applied snapshot or update.
+    pub fn last_update_id(&self) -> u64 {
+        self.last_update_id
+    }
+
+    /// Apply a diff. depth update to the book.
+    ///
+    /// Returns `Ok(false)` if the update was skipped, because it is older than the book
+    /// or for another symbol.
+    ///
+    /// # Errors
+    /// [`Error::OrderBookOutOfSync`] if updates are missing between the book and `update`,
+    /// the book is left unchanged.
+    pub fn apply(&mut self, update: &DepthUpdate) -> crate::Result<bool> {
+        if update.symbol != self.symbol {
+            warn!(
+                "depth update for {:?} applied to book for {:?}",
+                update.symbol, self.symbol
+            );
+            return Ok(false);
+        }
+
+        if update.final_update_id <= self.last_update_id {
+            return Ok(false);
+        }
+
+        let expected = self.last_update_id + 1;
+        if update.first_update_id > expected {
+            return Err(Error::OrderBookOutOfSync {
+                expected,
+                received: update.first_update_id,
+            });
+        }
+
+        self.update_levels(&update.bids, &update.asks, update.final_update_id);
+        Ok(true)
+    }
+
+    /// Apply a diff. depth update of futures to the book.
+    ///
+    /// Returns `Ok(false)` if the update was skipped, because it is older than the book
+    /// or for another symbol.
+    ///
+    /// # Errors
+    /// [`Error::OrderBookOutOfSync`] if the update before `update` was not applied, i.e. its
+    /// `prev_final_update_id` is after the book. The book is left unchanged.
+    pub fn apply_futures(&mut self, update: &FuturesDepthUpdate) -> crate::Result<bool> {
+        if update.symbol != self.symbol {
+            warn!(
+                "depth update for {:?} applied to book for {:?}",
+                update.symbol, self.symbol
+            );
+            return Ok(false);
+        }
+
+        if update.final_update_id < self.last_update_id {
+            return Ok(false);
+        }
+
+        // the previous update ends at the book, or the update spans the id of the snapshot
+        if update.prev_final_update_id > self.last_update_id {
+            return Err(Error::OrderBookOutOfSync {
+                expected: self.last_update_id,
+                received: update.prev_final_update_id,
+            });
+        }
+
+        self.update_levels(&update.bids, &update.asks, update.final_update_id);
+        Ok(true)
+    }
+
+    fn update_levels(&mut self, bids: &[[Decimal; 2]], asks: &[[Decimal; 2]], update_id: u64) {
+        for [price, qty] in bids {
+            update_level(&mut self.bids, *price, *qty);
+        }
+        for [price, qty] in asks {
+            update_level(&mut self.asks, *price, *qty);
+        }
+        self.last_update_id = update_id;
+    }
+
+    /// Bid levels as [price, volume], best bid first.
+    pub fn bids(&self) -> impl Iterator<Item = [Decimal; 2]> + '_ {
+        self.bids.iter().rev().map(|(p, q)| [*p, *q])
+    }
+
+    /// Ask levels as [price, volume], best ask first.
+    pub fn asks(&self) -> impl Iterator<Item = [Decimal; 2]> + '_ {
+        self.asks.iter().map(|(p, q)| [*p, *q])
+    }
+
+    /// The top `levels` of the book.
+    ///
+    /// Use this to get the analytics of [`PartialDepth`] for the managed book,
+    /// e.g. `book.top(10).imbalance(10)`.
+    pub fn top(&self, levels: usize) -> PartialDepth {
+        PartialDepth {
+            last_update_id: self.last_update_id,
+            bids: self.bids().take(levels).collect(),
+            asks: self.asks().take(levels).collect(),
+        }
+    }
+
+    /// Write the book to `path` as json, replacing any existing file once it is written and
+    /// synced to disk.
+    pub fn save(&self, path: impl AsRef<Path>) -> crate::Result<()> {
+        write_replacing(path.as_ref(), |file| {
+            let mut file = std::io::BufWriter::new(file);
+            serde_json::to_writer(&mut file, self)?;
+            Ok(file.flush()?)
+        })
+    }
+
+    /// Read a book previously written with [`OrderBook::save()`].
+    pub fn load(path: impl AsRef<Path>) -> crate::Result<Self> {
+        let file = std::io::BufReader::new(std::fs::File::open(path)?);
+        Ok(serde_json::from_reader(file)?)
+    }
+}
+
+/// Write `path` with `write` to a temporary file of a unique name in the same directory, then
+/// sync it and rename it over `path`, so a crash leaves either the old or the new file whole.
+pub(crate) fn write_replacing(
+    path: &Path,
+    write: impl FnOnce(&mut File) -> crate::Result<()>,
+) -> crate::Result<()> {
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    let mut tmp = tempfile::NamedTempFile::new_in(dir)?;
+    write(tmp.as_file_mut())?;
+    tmp.as_file().sync_all()?;
+    tmp.persist(path).map_err(|e| e.error)?;
+    // the rename is only durable once the directory is synced
+    #[cfg(unix)]
+    File::open(dir)?.sync_all()?;
+    Ok(())
+}
+
+fn update_level(side: &mut BTreeMap<Decimal, Decimal>, price: Decimal, qty: Decimal) {
+    if qty.is_zero() {
+        side.remove(&price);
+    } else {
+        side.insert(price, qty);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_util::dec;
+    use smallvec::smallvec;
+
+    fn snapshot() -> PartialDepth {
+        PartialDepth {
+            last_update_id: 100,
+            bids: smallvec![[dec("10.0"), dec("1")], [dec("9.5"), dec("2")]],
+            asks: smallvec![[dec("10.5"), dec("1")], [dec("11.0"), dec("3")]],
+        }
+    }
+
+    fn update(
+        first: u64,
+        last: u64,
+        bids: Vec<[Decimal; 2]>,
+        asks: Vec<[Decimal; 2]>,
+    ) -> DepthUpdate {
+        DepthUpdate {
+            event_time: 0,
+            symbol: Symbol::BTCUSDT,
+            first_update_id: first,
+            final_update_id: last,
+            bids,
+            asks,
+        }
+    }
+
+    #[test]
+    fn apply_updates() {
+        let mut book = OrderBook::from_snapshot(Symbol::BTCUSDT, &snapshot());
+
+        // bridges the snapshot, removes 10.0 and adds 9.8
+        let u = update(
+            95,
+            105,
+            vec![[dec("10.0"), dec("0")], [dec("9.8"), dec("4")]],
+            vec![],
+        );
+        assert!(book.apply(&u).unwrap());
+        assert_eq!(book.last_update_id(), 105);
+        assert_eq!(
+            book.bids().collect::<Vec<_>>(),
+            vec![[dec("9.8"), dec("4")], [dec("9.5"), dec("2")]]
+        );
+
+        // stale update is skipped
+        let u = update(101, 104, vec![[dec("9.9"), dec("1")]], vec![]);
+        assert!(!book.apply(&u).unwrap());
+        assert_eq!(book.top(1).bids.to_vec(), vec![[dec("9.8"), dec("4")]]);
+    }
+
+    #[test]
+    fn gap_is_out_of_sync() {
+        let mut book = OrderBook::from_snapshot(Symbol::BTCUSDT, &snapshot());
+        let before = book.clone();
+
+        let u = update(110, 120, vec![], vec![[dec("10.5"), dec("0")]]);
+        assert!(matches!(
+            book.apply(&u),
+            Err(Error::OrderBookOutOfSync {
+                expected: 101,
+                received: 110
+            })
+        ));
+        assert_eq!(book, before);
+    }
+
+    #[test]
+    fn apply_futures_updates() {
+        let mut book = OrderBook::from_snapshot(Symbol::BTCUSDT, &snapshot());
+        let update = |first, last, prev, bids| FuturesDepthUpdate {
+            event_time: 0,
+            transaction_time: 0,
+            symbol: Symbol::BTCUSDT,
+            first_update_id: first,
+            final_update_id: last,
+            prev_final_update_id: prev,
+            bids,
+            asks: vec![],
+        };
+
+        // spans the snapshot, then continues from the last update
+        assert!(book
+            .apply_futures(&update(90, 104, 85, vec![[dec("10.0"), dec("0")]]))
+            .unwrap());
+        assert!(book
+            .apply_futures(&update(107, 110, 104, vec![[dec("9.8"), dec("4")]]))
+            .unwrap());
+        assert_eq!(book.last_update_id(), 110);
+        assert_eq!(book.top(1).bids.to_vec(), vec![[dec("9.8"), dec("4")]]);
+
+        // stale update is skipped
+        assert!(!book.apply_futures(&update(95, 105, 92, vec![])).unwrap());
+
+        let before = book.clone();
+        assert!(matches!(
+            book.apply_futures(&update(120, 125, 115, vec![[dec("9.9"), dec("1")]])),
+            Err(Error::OrderBookOutOfSync {
+                expected: 110,
+                received: 115
+            })
+        ));
+        assert_eq!(book, before);
+    }
+
+    #[test]
+    fn save_and_restore() {
+        let mut book = OrderBook::from_snapshot(Symbol::BTCUSDT, &snapshot());
+        book.apply(&update(101, 101, vec![], vec![[dec("10.25"), dec("5")]]))
+            .unwrap();
+
+        let dir = std::env::temp_dir().join(format!("orderbook_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("book.json");
+        // a file of the same stem is left alone
+        std::fs::write(dir.join("book.tmp"), "other").unwrap();
+        book.save(&path).unwrap();
+        // saved again over the first file, through a temporary file
+        book.save(&path).unwrap();
+        let mut files: Vec<_> = std::fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .collect();
+        files.sort();
+        assert_eq!(files, ["book.json", "book.tmp"]);
+        assert_eq!(std::fs::read_to_string(dir.join("book.tmp")).unwrap(), "other");
+        let mut restored = OrderBook::load(&path).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(book, restored);
+
+        // only the delta after the saved book is applied
+        assert!(!restored.apply(&update(99, 101, vec![], vec![])).unwrap());
+        assert!(restored.apply(&update(102, 103, vec![], vec![])).unwrap());
+        assert_eq!(restored.top(1).asks.to_vec(), vec![[dec("10.25"), dec("5")]]);
+    }
+}
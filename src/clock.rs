@@ -0,0 +1,154 @@
+//! Source of the current time, so time dependent logic can be tested without waiting.
+//!
+//! Code reading the time or sleeping takes a [`Clock`], [`SystemClock`] in production and a
+//! [`TestClock`] in tests, moved forward by the test:
+//! ```
+//! use std::time::Duration;
+//! use binance_api_async::clock::{Clock, TestClock};
+//!
+//! let clock = TestClock::new(1_000);
+//! clock.advance(Duration::from_secs(1));
+//! assert_eq!(clock.now_millis(), 2_000);
+//! ```
+
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::sync::watch;
+
+/// Future returned by [`Clock::sleep()`].
+pub type Sleep<'a> = Pin<Box<dyn Future<Output = ()> + Send + 'a>>;
+
+/// The current time and waiting for time to pass.
+pub trait Clock: std::fmt::Debug + Send + Sync {
+    /// Milliseconds since epoch.
+    fn now_millis(&self) -> u64;
+
+    /// Wait for `duration` to pass.
+    fn sleep(&self, duration: Duration) -> Sleep<'_>;
+}
+
+/// The system clock and tokio timers, or without the `tokio` feature a thread per sleep.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now_millis(&self) -> u64 {
+        chrono::Utc::now().timestamp_millis() as u64
+    }
+
+    #[cfg(feature = "tokio")]
+    fn sleep(&self, duration: Duration) -> Sleep<'_> {
+        Box::pin(tokio::time::sleep(duration))
+    }
+
+    #[cfg(not(feature = "tokio"))]
+    fn sleep(&self, duration: Duration) -> Sleep<'_> {
+        // no runtime to ask for a timer, wake the sleep from a thread of its own
+        let (done, sleeping) = futures::channel::oneshot::channel::<()>();
+        std::thread::spawn(move || {
+            std::thread::sleep(duration);
+            let _ = done.send(());
+        });
+        Box::pin(async move {
+            let _ = sleeping.await;
+        })
+    }
+}
+
+/// A clock that only moves when told to, clones share the same time.
+///
+/// Sleeps finish once the clock has been moved past their end.
+#[derive(Debug, Clone)]
+pub struct TestClock {
+    now: Arc<watch::Sender<u64>>,
+}
+
+impl TestClock {
+    /// A clock at `now`, in milliseconds since epoch.
+    pub fn new(now: u64) -> Self {
+        Self {
+            now: Arc::new(watch::Sender::new(now)),
+        }
+    }
+
+    /// Set the time to `now`, in milliseconds since epoch.
+    pub fn set(&self, now: u64) {
+        self.now.send_replace(now);
+    }
+
+    /// Move the time forward by `duration`.
+    pub fn advance(&self, duration: Duration) {
+        self.now
+            .send_modify(|now| *now += duration.as_millis() as u64);
+    }
+}
+
+impl Clock for TestClock {
+    fn now_millis(&self) -> u64 {
+        *self.now.borrow()
+    }
+
+    fn sleep(&self, duration: Duration) -> Sleep<'_> {
+        let end = self.now_millis() + duration.as_millis() as u64;
+        let mut now = self.now.subscribe();
+        Box::pin(async move {
+            // the sender lives as long as self
+            let _ = now.wait_for(|now| *now >= end).await;
+        })
+    }
+}
+
+/// A number and a unit, e.g. `500ms`, `30s`, `15m`, `1h` or `1d`.
+pub fn parse_duration(text: &str) -> crate::Result<Duration> {
+    let unit = text.trim_start_matches(|c: char| c.is_ascii_digit());
+    let n: u64 = text[..text.len() - unit.len()].parse().map_err(|_| {
+        crate::Error::Custom(format!("duration {text:?} does not start with a number"))
+    })?;
+    let millis = match unit {
+        "ms" => 1,
+        "s" => 1000,
+        "m" => 60_000,
+        "h" => 3_600_000,
+        "d" => 86_400_000,
+        _ => {
+            return Err(crate::Error::Custom(format!(
+                "unknown unit of duration {text:?}, one of ms, s, m, h and d"
+            )))
+        }
+    };
+    n.checked_mul(millis)
+        .map(Duration::from_millis)
+        .ok_or_else(|| crate::Error::Custom(format!("duration {text:?} is too long")))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn durations() {
+        assert_eq!(parse_duration("500ms").unwrap(), Duration::from_millis(500));
+        assert_eq!(
+            parse_duration("2d").unwrap(),
+            Duration::from_secs(2 * 86_400)
+        );
+        assert!(parse_duration("h").is_err());
+        assert!(parse_duration("1w").is_err());
+        assert!(parse_duration("18446744073709551615d").is_err());
+    }
+
+    #[tokio::test]
+    async fn sleeps_until_advanced() {
+        let clock = TestClock::new(0);
+        let mut sleep = clock.sleep(Duration::from_secs(10));
+
+        clock.advance(Duration::from_secs(9));
+        assert!(futures::poll!(sleep.as_mut()).is_pending());
+        clock.clone().advance(Duration::from_secs(1));
+        assert!(futures::poll!(sleep.as_mut()).is_ready());
+        assert_eq!(clock.now_millis(), 10_000);
+    }
+}
@@ -0,0 +1,372 @@
+//! The websocket of a [`BinanceApi`](crate::BinanceApi), read by a task of its own.
+//!
+//! Binance drops connections whose pings are not answered in time. The task answers them as
+//! soon as they are received, however slowly the messages are consumed, and queues the other
+//! frames for [`BinanceApi::next_message()`](crate::BinanceApi::next_message). The queue has no
+//! limit, a slow consumer costs memory instead of the connection. With a message budget, the
+//! frames above it are dropped instead of queued, so a burst can not grow the queue without
+//! bounds, and reported as an [`Event::Flooded`]. Replies to requests are never dropped, so
+//! a request sent during a burst is still answered.
+//!
+//! Pongs of the server need no answer. With a [`Keepalive`] the task also pings the server,
+//! and ends the connection when the pong is late, so a connection that went silent is noticed
+//! and can be reconnected. The round trips of the pings are recorded in the [`Metrics`].
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use futures::stream::{SplitSink, SplitStream};
+use futures::{SinkExt, StreamExt};
+use serde::de::IgnoredAny;
+use serde::Deserialize;
+use tokio::sync::{mpsc, Mutex};
+use tokio::task::JoinHandle;
+use tokio::time::{Instant, MissedTickBehavior};
+use tokio_tungstenite::tungstenite;
+use tracing::{debug, warn, Span};
+use tungstenite::protocol::CloseFrame;
+
+use crate::logging::CONNECTION;
+use crate::metrics::Metrics;
+use crate::WsStream;
+
+type Frame = tungstenite::Result<tungstenite::Message>;
+
+/// What the task queues for [`Connection::next()`].
+#[derive(Debug)]
+pub(crate) enum Event {
+    Frame(Frame),
+    /// `dropped` frames above the budget were dropped, sent before the first frame of the
+    /// next second.
+    Flooded {
+        budget: u32,
+        dropped: u64,
+    },
+    /// The connection was ended by the task, the last event.
+    Failed(crate::Error),
+}
+
+/// Settings of the task, see [`crate::BinanceApiBuilder`].
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct Options {
+    pub(crate) keepalive: Option<Keepalive>,
+    /// most frames queued per second
+    pub(crate) message_budget: Option<u32>,
+}
+
+/// Pings sent by the client, see [`crate::BinanceApiBuilder::ping_interval()`].
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Keepalive {
+    pub(crate) interval: Duration,
+    pub(crate) pong_timeout: Duration,
+}
+
+/// A connected websocket, see the [module](self) documentation.
+pub(crate) struct Connection {
+    sink: Arc<Mutex<SplitSink<WsStream, tungstenite::Message>>>,
+    frames: mpsc::UnboundedReceiver<Event>,
+    task: JoinHandle<()>,
+}
+
+impl Connection {
+    /// Start reading `stream`, logging in `span`.
+    pub(crate) fn new(
+        stream: WsStream,
+        span: Span,
+        options: Options,
+        metrics: Arc<Metrics>,
+    ) -> Self {
+        let (sink, source) = stream.split();
+        let sink = Arc::new(Mutex::new(sink));
+        let (frames_tx, frames) = mpsc::unbounded_channel();
+        let reader = read(source, sink.clone(), frames_tx, span, options, metrics);
+        let task = tokio::spawn(reader);
+        Self { sink, frames, task }
+    }
+
+    /// The next frame that is not a ping or pong, `None` once the connection has ended.
+    ///
+    /// Cancel safe, a frame not returned stays queued.
+    pub(crate) async fn next(&mut self) -> Option<Event> {
+        self.frames.recv().await
+    }
+
+    pub(crate) async fn send(&self, msg: tungstenite::Message) -> tungstenite::Result<()> {
+        self.sink.lock().await.send(msg).await
+    }
+
+    /// Send `frame` to start the close handshake, the reply is returned by
+    /// [`Connection::next()`].
+    pub(crate) async fn close(&self, frame: CloseFrame<'static>) -> tungstenite::Result<()> {
+        self.send(tungstenite::Message::Close(Some(frame))).await
+    }
+}
+
+/// Read `source` until it ends or the receiver of `frames` is dropped.
+async fn read(
+    mut source: SplitStream<WsStream>,
+    sink: Arc<Mutex<SplitSink<WsStream, tungstenite::Message>>>,
+    frames: mpsc::UnboundedSender<Event>,
+    span: Span,
+    options: Options,
+    metrics: Arc<Metrics>,
+) {
+    let keepalive = options.keepalive;
+    let mut budget = options.message_budget.map(Budget::new);
+    let mut pings = keepalive.map(|k| {
+        let mut pings = tokio::time::interval_at(Instant::now() + k.interval, k.interval);
+        pings.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        pings
+    });
+    // deadline of the pong to the first ping not answered, and when it was sent
+    let mut pong_deadline: Option<Instant> = None;
+    let mut ping_sent: Option<Instant> = None;
+
+    loop {
+        let ping = async {
+            match pings.as_mut() {
+                Some(pings) => pings.tick().await,
+                None => std::future::pending().await,
+            }
+        };
+        let pong_timeout = async {
+            match pong_deadline {
+                Some(deadline) => tokio::time::sleep_until(deadline).await,
+                None => std::future::pending().await,
+            }
+        };
+        let frame = tokio::select! {
+            frame = source.next() => frame,
+            _ = ping => {
+                let mut sink = sink.lock().await;
+                if sink.send(tungstenite::Message::Ping(Vec::new())).await.is_ok() {
+                    debug!(target: CONNECTION, parent: &span, "Sent Ping");
+                    let timeout = keepalive.map_or(Duration::ZERO, |k| k.pong_timeout);
+                    pong_deadline.get_or_insert(Instant::now() + timeout);
+                    ping_sent.get_or_insert(Instant::now());
+                }
+                continue;
+            }
+            _ = pong_timeout => {
+                warn!(target: CONNECTION, parent: &span, "No Pong received in time, disconnecting");
+                let _ = sink.lock().await.close().await;
+                let after = keepalive.map_or(Duration::ZERO, |k| k.pong_timeout);
+                let timed_out = crate::Error::Timeout { operation: "pong", after };
+                let _ = frames.send(Event::Failed(timed_out));
+                return;
+            }
+        };
+        let Some(frame) = frame else {
+            return;
+        };
+        match frame {
+            Ok(tungstenite::Message::Ping(vec)) => {
+                debug!(target: CONNECTION, parent: &span, "Received Ping, sending Pong.");
+                let _ = sink
+                    .lock()
+                    .await
+                    .send(tungstenite::Message::Pong(vec))
+                    .await;
+            }
+            // the answer to a ping, or a heartbeat of the server that needs no answer
+            Ok(tungstenite::Message::Pong(_)) => {
+                debug!(target: CONNECTION, parent: &span, "Received Pong");
+                pong_deadline = None;
+                if let Some(sent) = ping_sent.take() {
+                    metrics.record_ping(sent.elapsed());
+                }
+            }
+            frame => {
+                let failed = frame.is_err();
+                let data = matches!(
+                    frame,
+                    Ok(tungstenite::Message::Text(_) | tungstenite::Message::Binary(_))
+                );
+                if let Some(budget) = budget.as_mut().filter(|_| data) {
+                    if let Some(dropped) = budget.flooded() {
+                        let flooded = Event::Flooded {
+                            budget: budget.per_second,
+                            dropped,
+                        };
+                        if frames.send(flooded).is_err() {
+                            return;
+                        }
+                    }
+                    if !budget.take() && !is_reply(&frame) {
+                        budget.dropped += 1;
+                        continue;
+                    }
+                }
+                // the receiver is gone with the connection
+                if frames.send(Event::Frame(frame)).is_err() || failed {
+                    return;
+                }
+            }
+        }
+    }
+}
+
+/// Frames accepted in the current second.
+struct Budget {
+    per_second: u32,
+    window: Instant,
+    used: u32,
+    dropped: u64,
+}
+
+impl Budget {
+    fn new(per_second: u32) -> Self {
+        Self {
+            per_second,
+            window: Instant::now(),
+            used: 0,
+            dropped: 0,
+        }
+    }
+
+    /// The frames dropped in the last second, once it is over.
+    fn flooded(&mut self) -> Option<u64> {
+        if self.window.elapsed() < Duration::from_secs(1) {
+            return None;
+        }
+        self.window = Instant::now();
+        self.used = 0;
+        Some(std::mem::take(&mut self.dropped)).filter(|&dropped| dropped > 0)
+    }
+
+    /// Whether a frame received now is within the budget, counts it if it is.
+    fn take(&mut self) -> bool {
+        if self.used < self.per_second {
+            self.used += 1;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// Fields telling a reply to a request from market data.
+#[derive(Deserialize)]
+struct ReplyProbe {
+    #[serde(rename = "e", default)]
+    event_type: Option<IgnoredAny>,
+    #[serde(default)]
+    id: Option<IgnoredAny>,
+    #[serde(default)]
+    error: Option<IgnoredAny>,
+}
+
+/// Whether `frame` is a reply to a request, an acknowledgement or an error with the `id` of
+/// the request. Only probed for frames above the budget.
+fn is_reply(frame: &Frame) -> bool {
+    let Ok(tungstenite::Message::Text(text)) = frame else {
+        return false;
+    };
+    serde_json::from_str::<ReplyProbe>(text).is_ok_and(|probe| {
+        probe.event_type.is_none() && (probe.id.is_some() || probe.error.is_some())
+    })
+}
+
+impl Drop for Connection {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use crate::messages::ErrorResponse;
+    use crate::test_util::{self, Action, MockServer};
+    use crate::{BinanceApi, Feed, Message, SubscribeInfo, Symbol};
+
+    #[tokio::test]
+    async fn pongs_without_polling() {
+        let server = MockServer::start().await.unwrap();
+        server.script([Action::Text("{}".into()), Action::Ping(b"hi".to_vec())]);
+        let mut api = server.api();
+        api.connect().await.unwrap();
+        api.subscribe(&[SubscribeInfo::new(Symbol::BTCUSDT, Feed::Trade)], None)
+            .await
+            .unwrap();
+
+        // nothing is read, the ping is answered all the same
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while server.pongs().is_empty() {
+                tokio::time::sleep(Duration::from_millis(1)).await;
+            }
+        })
+        .await
+        .expect("ping answered");
+        assert_eq!(server.pongs(), [b"hi".to_vec()]);
+        assert!(api.next_message().await.is_some());
+    }
+
+    #[tokio::test]
+    async fn keepalive() {
+        let server = MockServer::start().await.unwrap();
+        // the server stops reading, and answering pings, once subscribed
+        server.script([Action::Delay(Duration::from_secs(1))]);
+        let mut api = BinanceApi::builder()
+            .url(&server.url())
+            .allow_plaintext(true)
+            .ping_interval(Some(Duration::from_millis(20)))
+            .pong_timeout(Duration::from_millis(100))
+            .build();
+        api.connect().await.unwrap();
+
+        // answered pings keep the connection
+        let read = tokio::time::timeout(Duration::from_millis(200), api.next_message()).await;
+        assert!(read.is_err());
+        assert!(!api.metrics().ping_round_trips().is_empty());
+
+        api.subscribe(&[SubscribeInfo::new(Symbol::BTCUSDT, Feed::Trade)], None)
+            .await
+            .unwrap();
+        assert!(api.next_message().await.is_some());
+        let started = std::time::Instant::now();
+        assert_eq!(api.next_message().await, None);
+        assert!(started.elapsed() < Duration::from_secs(1));
+        assert_eq!(
+            api.metrics().disconnects()[0].reason,
+            "pong timed out after 100ms"
+        );
+    }
+
+    #[tokio::test]
+    async fn message_budget() {
+        let reply = |id| Message::SubscribeSuccess { result: None, id };
+        let trade = |id| Message::Trade(test_util::trade(Symbol::BTCUSDT, id));
+        let error = Message::Error(ErrorResponse {
+            code: 2,
+            msg: "Invalid request".to_string(),
+            id: Some(3),
+        });
+        let server = MockServer::start().await.unwrap();
+        let mut script: Vec<_> = (1..=5).map(|id| Action::message(&trade(id))).collect();
+        script.push(Action::message(&reply(2)));
+        script.push(Action::message(&error));
+        script.push(Action::Delay(Duration::from_millis(1100)));
+        script.push(Action::message(&trade(6)));
+        server.script(script);
+        let mut api = BinanceApi::builder()
+            .url(&server.url())
+            .allow_plaintext(true)
+            .message_budget(Some(2))
+            .build();
+        api.connect().await.unwrap();
+        api.subscribe(&[SubscribeInfo::new(Symbol::BTCUSDT, Feed::Trade)], None)
+            .await
+            .unwrap();
+
+        // the acknowledgement and the first trade are within the budget of the first second,
+        // the replies above it are not dropped
+        assert_eq!(api.next_message().await, Some(reply(1)));
+        assert_eq!(api.next_message().await, Some(trade(1)));
+        assert_eq!(api.next_message().await, Some(reply(2)));
+        assert_eq!(api.next_message().await, Some(error));
+        assert_eq!(api.next_message().await, Some(trade(6)));
+        assert_eq!(api.metrics().dropped(), 4);
+    }
+}
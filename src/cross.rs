@@ -0,0 +1,257 @@
+//! Relationships between symbols, calculated from [`BookTicker`]s.
+//!
+//! Register [`Relation`]s in a [`CrossMonitor`] and pass every [`BookTicker`] to
+//! [`CrossMonitor::update()`], a [`CrossUpdate`] is returned for each relation
+//! involving the symbol once all of its symbols have a ticker.
+//!
+//! Every relation compares a price `a` with a price `b`, for a triangle `a` is the direct pair
+//! and `b` the synthetic price from the two other pairs. Values are in basis points.
+//!
+//! [`Basket`] prices a weighted basket of symbols the same way.
+
+use std::collections::HashMap;
+
+use rust_decimal::Decimal;
+
+use crate::messages::BookTicker;
+use crate::Symbol;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Relation {
+    /// Two symbols quoting the same asset, e.g. BTCUSDT and BTCUSDC.
+    Spread { a: Symbol, b: Symbol },
+    /// A pair and the synthetic price `first * second`,
+    /// e.g. ETHUSDT against ETHBTC * BTCUSDT.
+    Triangle {
+        direct: Symbol,
+        first: Symbol,
+        second: Symbol,
+    },
+}
+
+impl Relation {
+    fn symbols(&self) -> Vec<&Symbol> {
+        match self {
+            Relation::Spread { a, b } => vec![a, b],
+            Relation::Triangle {
+                direct,
+                first,
+                second,
+            } => vec![direct, first, second],
+        }
+    }
+}
+
+/// Deviation between the two sides of a [`Relation`], in basis points.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CrossUpdate {
+    /// Id returned by [`CrossMonitor::add()`].
+    pub id: usize,
+    /// `a` mid over `b` mid.
+    pub deviation: Decimal,
+    /// Selling `a` at the bid and buying `b` at the ask, positive if profitable before fees.
+    pub sell_a_buy_b: Decimal,
+    /// Buying `a` at the ask and selling `b` at the bid, positive if profitable before fees.
+    pub buy_a_sell_b: Decimal,
+}
+
+/// Tracks the latest [`BookTicker`] of each symbol and the registered [`Relation`]s.
+#[derive(Debug, Clone, Default)]
+pub struct CrossMonitor {
+    relations: Vec<Relation>,
+    /// best bid and ask per symbol
+    quotes: HashMap<Symbol, (Decimal, Decimal)>,
+}
+
+impl CrossMonitor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a relation, returns the id used in its [`CrossUpdate`]s.
+    pub fn add(&mut self, relation: Relation) -> usize {
+        self.relations.push(relation);
+        self.relations.len() - 1
+    }
+
+    /// Update the quote of the ticker symbol and recalculate the relations it is part of.
+    pub fn update(&mut self, ticker: &BookTicker) -> Vec<CrossUpdate> {
+        self.quotes.insert(
+            ticker.symbol.clone(),
+            (ticker.best_bid_price, ticker.best_ask_price),
+        );
+
+        self.relations
+            .iter()
+            .enumerate()
+            .filter(|(_, r)| r.symbols().contains(&&ticker.symbol))
+            .filter_map(|(id, r)| self.calculate(id, r))
+            .collect()
+    }
+
+    fn calculate(&self, id: usize, relation: &Relation) -> Option<CrossUpdate> {
+        let ((a_bid, a_ask), (b_bid, b_ask)) = match relation {
+            Relation::Spread { a, b } => (*self.quotes.get(a)?, *self.quotes.get(b)?),
+            Relation::Triangle {
+                direct,
+                first,
+                second,
+            } => {
+                let (first_bid, first_ask) = self.quotes.get(first)?;
+                let (second_bid, second_ask) = self.quotes.get(second)?;
+                (
+                    *self.quotes.get(direct)?,
+                    (first_bid * second_bid, first_ask * second_ask),
+                )
+            }
+        };
+
+        let bps = |x: Decimal, y: Decimal| {
+            (!y.is_zero()).then(|| (x / y - Decimal::ONE) * Decimal::from(10_000))
+        };
+        Some(CrossUpdate {
+            id,
+            deviation: bps(a_bid + a_ask, b_bid + b_ask)?,
+            sell_a_buy_b: bps(a_bid, b_ask)?,
+            buy_a_sell_b: bps(b_bid, a_ask)?,
+        })
+    }
+}
+
+/// Bid, ask and mid price of a [`Basket`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BasketQuote {
+    pub bid: Decimal,
+    pub ask: Decimal,
+    pub mid: Decimal,
+}
+
+/// Weighted basket of symbols, priced from the [`BookTicker`] of each component.
+///
+/// The basket bid is what selling every component would give, so a negative (short) weight
+/// uses the ask of that component, and the other way around for the basket ask.
+#[derive(Debug, Clone)]
+pub struct Basket {
+    weights: Vec<(Symbol, Decimal)>,
+    quotes: HashMap<Symbol, (Decimal, Decimal)>,
+}
+
+impl Basket {
+    /// Create a basket from symbols and their weights, e.g. quantities held.
+    pub fn new(weights: impl IntoIterator<Item = (Symbol, Decimal)>) -> Self {
+        Self {
+            weights: weights.into_iter().collect(),
+            quotes: HashMap::new(),
+        }
+    }
+
+    /// Update a component, returns the new basket quote.
+    ///
+    /// `None` if the ticker is not part of the basket, or not every component has a ticker yet.
+    pub fn update(&mut self, ticker: &BookTicker) -> Option<BasketQuote> {
+        if !self.weights.iter().any(|(s, _)| s == &ticker.symbol) {
+            return None;
+        }
+        self.quotes.insert(
+            ticker.symbol.clone(),
+            (ticker.best_bid_price, ticker.best_ask_price),
+        );
+        self.quote()
+    }
+
+    /// Current basket quote, `None` until every component has a ticker.
+    pub fn quote(&self) -> Option<BasketQuote> {
+        let (mut bid, mut ask) = (Decimal::ZERO, Decimal::ZERO);
+        for (symbol, weight) in &self.weights {
+            let (b, a) = self.quotes.get(symbol)?;
+            if weight.is_sign_negative() {
+                bid += weight * a;
+                ask += weight * b;
+            } else {
+                bid += weight * b;
+                ask += weight * a;
+            }
+        }
+
+        Some(BasketQuote {
+            bid,
+            ask,
+            mid: (bid + ask) / Decimal::TWO,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_util::{dec, ticker};
+
+    #[test]
+    fn triangle() {
+        let mut monitor = CrossMonitor::new();
+        let id = monitor.add(Relation::Triangle {
+            direct: Symbol::ETHUSDT,
+            first: Symbol::ETHBTC,
+            second: Symbol::BTCUSDT,
+        });
+
+        assert!(monitor
+            .update(&ticker(Symbol::ETHBTC, "0.05", "0.05"))
+            .is_empty());
+        assert!(monitor
+            .update(&ticker(Symbol::BTCUSDT, "40000", "40000"))
+            .is_empty());
+
+        // synthetic ETHUSDT is 2000, direct trades 0.5% higher
+        let updates = monitor.update(&ticker(Symbol::ETHUSDT, "2010", "2010"));
+        assert_eq!(updates.len(), 1);
+        assert_eq!(updates[0].id, id);
+        assert_eq!(updates[0].deviation, dec("50"));
+        assert_eq!(updates[0].sell_a_buy_b, dec("50"));
+        assert_eq!(updates[0].buy_a_sell_b.round_dp(4), dec("-49.7512"));
+    }
+
+    #[test]
+    fn spread() {
+        let mut monitor = CrossMonitor::new();
+        monitor.add(Relation::Spread {
+            a: Symbol::BTCUSDT,
+            b: Symbol::BTCUSDC,
+        });
+        monitor.update(&ticker(Symbol::BTCUSDT, "99", "101"));
+
+        let updates = monitor.update(&ticker(Symbol::BTCUSDC, "99", "101"));
+        assert_eq!(updates[0].deviation, dec("0"));
+        assert!(updates[0].sell_a_buy_b < Decimal::ZERO);
+
+        // unrelated symbols give no updates
+        assert!(monitor
+            .update(&ticker(Symbol::ETHUSDT, "1", "2"))
+            .is_empty());
+    }
+
+    #[test]
+    fn basket() {
+        let mut basket = Basket::new([
+            (Symbol::BTCUSDT, dec("0.5")),
+            (Symbol::ETHUSDT, dec("2")),
+            (Symbol::SOLUSDT, dec("-10")),
+        ]);
+
+        assert_eq!(basket.update(&ticker(Symbol::BTCUSDT, "100", "101")), None);
+        assert_eq!(basket.update(&ticker(Symbol::ETHUSDT, "10", "11")), None);
+        assert_eq!(basket.update(&ticker(Symbol::BNBUSDT, "1", "2")), None);
+
+        let quote = basket.update(&ticker(Symbol::SOLUSDT, "1", "2")).unwrap();
+        // 50 + 20 - 20 and 50.5 + 22 - 10
+        assert_eq!(
+            quote,
+            BasketQuote {
+                bid: dec("50"),
+                ask: dec("62.5"),
+                mid: dec("56.25"),
+            }
+        );
+        assert_eq!(basket.quote(), Some(quote));
+    }
+}
@@ -1,17 +1,283 @@
 use derive_more::From;
-use tokio_tungstenite::tungstenite;
+use tungstenite::protocol::{frame::coding::CloseCode, CloseFrame};
 
 #[derive(Debug, From)]
 pub enum Error {
     ReconnectionTimeout,
-    WebSocketError(tungstenite::Error),
+    /// A request was made before [`crate::BinanceApi::connect()`].
+    #[from(ignore)]
+    NotConnected,
+    /// A `ws://` url, without TLS, was not allowed with
+    /// [`crate::BinanceApiBuilder::allow_plaintext()`].
+    #[from(ignore)]
+    PlaintextUrl { url: String },
+    /// The connection was closed, with the close frame if the server sent one.
+    #[from(ignore)]
+    ConnectionClosed { frame: Option<CloseFrame<'static>> },
+    /// A websocket frame could not be parsed into a [`crate::Message`].
+    #[from(ignore)]
+    Parse { payload: String, source: Box<Error> },
+    /// Binance rejected the request `id`, see [`crate::messages::ErrorResponse`].
+    #[from(ignore)]
+    SubscriptionRejected { code: i64, msg: String, id: Option<u64> },
+    /// Streams unsubscribed from without being subscribed, see
+    /// [`crate::BinanceApi::unsubscribe()`].
+    #[from(ignore)]
+    NotSubscribed { streams: Vec<String> },
+    /// The levels or delay of `feed` are not served on `market`, see [`crate::validation`].
+    #[from(ignore)]
+    UnsupportedFeed {
+        feed: crate::Feed,
+        market: crate::validation::Market,
+    },
+    /// More than `budget` messages were received within a second, `dropped` of them were
+    /// dropped, see [`crate::BinanceApiBuilder::message_budget()`].
+    #[from(ignore)]
+    Flooded { budget: u32, dropped: u64 },
+    /// `operation` did not complete within `after`, e.g. the pong of
+    /// [`crate::BinanceApiBuilder::pong_timeout()`].
+    #[from(ignore)]
+    Timeout {
+        operation: &'static str,
+        after: std::time::Duration,
+    },
+    WebSocketError(Box<tungstenite::Error>),
+    Io(std::io::Error),
+    Json(serde_json::Error),
+    #[cfg(feature = "tokio")]
+    Http(reqwest::Error),
+    Decimal(rust_decimal::Error),
+    #[cfg(any(feature = "sqlite", feature = "postgres"))]
+    Database(sqlx::Error),
+    #[cfg(feature = "kafka")]
+    Kafka(rdkafka::error::KafkaError),
+    #[cfg(feature = "redis")]
+    Redis(redis::RedisError),
+    #[cfg(feature = "mqtt")]
+    Mqtt(rumqttc::ClientError),
+    #[cfg(feature = "nats")]
+    Nats(Box<dyn std::error::Error + Send + Sync>),
+    #[cfg(feature = "zmq")]
+    Zmq(zeromq::ZmqError),
+    #[cfg(feature = "arrow")]
+    Arrow(arrow::error::ArrowError),
+    #[cfg(feature = "parquet")]
+    Parquet(parquet::errors::ParquetError),
+    #[cfg(feature = "polars")]
+    Polars(polars::error::PolarsError),
+    #[cfg(feature = "binary")]
+    Binary(postcard::Error),
+    #[cfg(feature = "simd-json")]
+    SimdJson(simd_json::Error),
+    #[cfg(feature = "prometheus")]
+    Prometheus(prometheus::Error),
+    /// A depth update did not continue from the last applied update id,
+    /// the [`crate::book::OrderBook`] needs a new snapshot.
+    #[from(ignore)]
+    OrderBookOutOfSync { expected: u64, received: u64 },
+    /// A price or quantity is not a whole number of ticks or steps of a
+    /// [`crate::fixed::Scale`].
+    #[from(ignore)]
+    OffScale(rust_decimal::Decimal),
+    /// A configuration file is invalid, see [`crate::config`].
+    #[cfg(feature = "config")]
+    #[from(ignore)]
+    Config(String),
+    /// The browser refused an operation of the websocket, see [`crate::web`].
+    #[cfg(feature = "wasm")]
+    #[from(ignore)]
+    Browser(String),
     Custom(String),
 }
-impl std::error::Error for Error {}
+
+/// Why the server closed the connection, see [`Error::close_reason()`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CloseReason {
+    /// The 24 hours a connection is valid for are over.
+    Expired,
+    /// Too many requests, or another breach of the limits, reconnect slower.
+    PolicyViolation,
+    /// The server is going away, e.g. for maintenance.
+    GoingAway,
+    /// Closed normally.
+    Normal,
+    /// Any other close code.
+    Other,
+}
+
+impl CloseReason {
+    /// The reason of a close frame with `code` and `reason`.
+    ///
+    /// Binance closes expired connections and connections of servers going away with the same
+    /// code, told apart by the reason.
+    pub fn of(code: CloseCode, reason: &str) -> Self {
+        match code {
+            CloseCode::Away if reason.contains("24 hours") || reason.contains("expired") => {
+                CloseReason::Expired
+            }
+            CloseCode::Away => CloseReason::GoingAway,
+            CloseCode::Policy => CloseReason::PolicyViolation,
+            CloseCode::Normal => CloseReason::Normal,
+            _ => CloseReason::Other,
+        }
+    }
+}
+
+impl Error {
+    /// Why the server closed the connection, for [`Error::ConnectionClosed`] with a close
+    /// frame, `None` for other errors.
+    pub fn close_reason(&self) -> Option<CloseReason> {
+        match self {
+            Error::ConnectionClosed { frame: Some(frame) } => {
+                Some(CloseReason::of(frame.code, &frame.reason))
+            }
+            _ => None,
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Parse { source, .. } => Some(source.as_ref()),
+            Error::WebSocketError(e) => Some(e.as_ref()),
+            Error::Io(e) => Some(e),
+            Error::Json(e) => Some(e),
+            #[cfg(feature = "tokio")]
+            Error::Http(e) => Some(e),
+            Error::Decimal(e) => Some(e),
+            #[cfg(any(feature = "sqlite", feature = "postgres"))]
+            Error::Database(e) => Some(e),
+            #[cfg(feature = "kafka")]
+            Error::Kafka(e) => Some(e),
+            #[cfg(feature = "redis")]
+            Error::Redis(e) => Some(e),
+            #[cfg(feature = "mqtt")]
+            Error::Mqtt(e) => Some(e),
+            #[cfg(feature = "nats")]
+            Error::Nats(e) => Some(e.as_ref()),
+            #[cfg(feature = "zmq")]
+            Error::Zmq(e) => Some(e),
+            #[cfg(feature = "arrow")]
+            Error::Arrow(e) => Some(e),
+            #[cfg(feature = "parquet")]
+            Error::Parquet(e) => Some(e),
+            #[cfg(feature = "polars")]
+            Error::Polars(e) => Some(e),
+            #[cfg(feature = "binary")]
+            Error::Binary(e) => Some(e),
+            #[cfg(feature = "simd-json")]
+            Error::SimdJson(e) => Some(e),
+            #[cfg(feature = "prometheus")]
+            Error::Prometheus(e) => Some(e),
+            Error::ReconnectionTimeout
+            | Error::NotConnected
+            | Error::PlaintextUrl { .. }
+            | Error::ConnectionClosed { .. }
+            | Error::SubscriptionRejected { .. }
+            | Error::NotSubscribed { .. }
+            | Error::UnsupportedFeed { .. }
+            | Error::Flooded { .. }
+            | Error::Timeout { .. }
+            | Error::OrderBookOutOfSync { .. }
+            | Error::OffScale(_)
+            | Error::Custom(_) => None,
+            #[cfg(feature = "config")]
+            Error::Config(_) => None,
+            #[cfg(feature = "wasm")]
+            Error::Browser(_) => None,
+        }
+    }
+}
+
+// Boxed to keep `Result<T, Error>` small, tungstenite errors are large.
+impl From<tungstenite::Error> for Error {
+    fn from(value: tungstenite::Error) -> Self {
+        Self::WebSocketError(Box::new(value))
+    }
+}
+
+impl From<crate::messages::ErrorResponse> for Error {
+    fn from(value: crate::messages::ErrorResponse) -> Self {
+        Self::SubscriptionRejected {
+            code: value.code,
+            msg: value.msg,
+            id: value.id,
+        }
+    }
+}
 
 impl std::fmt::Display for Error {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{:?}", self)
+        match self {
+            Error::ReconnectionTimeout => write!(f, "reconnection timed out"),
+            Error::NotConnected => write!(f, "not connected"),
+            Error::PlaintextUrl { url } => {
+                write!(f, "{url} is not encrypted, allow it with allow_plaintext()")
+            }
+            Error::ConnectionClosed { frame: Some(frame) } => {
+                write!(f, "connection closed: {} {}", frame.code, frame.reason)
+            }
+            Error::ConnectionClosed { frame: None } => write!(f, "connection closed"),
+            Error::Parse { source, .. } => write!(f, "could not parse frame: {source}"),
+            Error::SubscriptionRejected { code, msg, id } => match id {
+                Some(id) => write!(f, "request {id} rejected with code {code}: {msg}"),
+                None => write!(f, "request rejected with code {code}: {msg}"),
+            },
+            Error::NotSubscribed { streams } => {
+                write!(f, "not subscribed to {}", streams.join(", "))
+            }
+            Error::UnsupportedFeed { feed, market } => {
+                write!(f, "{feed} is not served on {market:?}")
+            }
+            Error::Flooded { budget, dropped } => write!(
+                f,
+                "more than {budget} messages per second, dropped {dropped}"
+            ),
+            Error::Timeout { operation, after } => {
+                write!(f, "{operation} timed out after {after:?}")
+            }
+            Error::WebSocketError(e) => write!(f, "websocket: {e}"),
+            Error::Io(e) => write!(f, "io: {e}"),
+            Error::Json(e) => write!(f, "json: {e}"),
+            #[cfg(feature = "tokio")]
+            Error::Http(e) => write!(f, "http: {e}"),
+            Error::Decimal(e) => write!(f, "decimal: {e}"),
+            #[cfg(any(feature = "sqlite", feature = "postgres"))]
+            Error::Database(e) => write!(f, "database: {e}"),
+            #[cfg(feature = "kafka")]
+            Error::Kafka(e) => write!(f, "kafka: {e}"),
+            #[cfg(feature = "redis")]
+            Error::Redis(e) => write!(f, "redis: {e}"),
+            #[cfg(feature = "mqtt")]
+            Error::Mqtt(e) => write!(f, "mqtt: {e}"),
+            #[cfg(feature = "nats")]
+            Error::Nats(e) => write!(f, "nats: {e}"),
+            #[cfg(feature = "zmq")]
+            Error::Zmq(e) => write!(f, "zmq: {e}"),
+            #[cfg(feature = "arrow")]
+            Error::Arrow(e) => write!(f, "arrow: {e}"),
+            #[cfg(feature = "parquet")]
+            Error::Parquet(e) => write!(f, "parquet: {e}"),
+            #[cfg(feature = "polars")]
+            Error::Polars(e) => write!(f, "polars: {e}"),
+            #[cfg(feature = "binary")]
+            Error::Binary(e) => write!(f, "binary: {e}"),
+            #[cfg(feature = "simd-json")]
+            Error::SimdJson(e) => write!(f, "json: {e}"),
+            #[cfg(feature = "prometheus")]
+            Error::Prometheus(e) => write!(f, "prometheus: {e}"),
+            Error::OrderBookOutOfSync { expected, received } => write!(
+                f,
+                "order book out of sync, expected update {expected}, received {received}"
+            ),
+            Error::OffScale(value) => write!(f, "{value} is not on the scale"),
+            #[cfg(feature = "config")]
+            Error::Config(msg) => write!(f, "invalid configuration: {msg}"),
+            #[cfg(feature = "wasm")]
+            Error::Browser(msg) => write!(f, "browser websocket: {msg}"),
+            Error::Custom(msg) => write!(f, "{msg}"),
+        }
     }
 }
 
@@ -26,11 +292,40 @@ mod test {
         let my_err: Error = ts_error.into();
 
         // Tungstenite Error does not implement Eq
-        if let Error::WebSocketError(tungstenite::Error::AttackAttempt) = my_err {
-            assert!(true)
-        } else {
-            assert!(false)
-        }
+        assert!(matches!(
+            my_err,
+            Error::WebSocketError(e) if matches!(*e, tungstenite::Error::AttackAttempt)
+        ));
+    }
+
+    #[test]
+    fn sources() {
+        use std::error::Error as _;
+
+        let json = serde_json::from_str::<u8>("x").unwrap_err();
+        let parse = Error::Parse {
+            payload: "x".to_string(),
+            source: Box::new(json.into()),
+        };
+        assert!(parse.to_string().starts_with("could not parse frame: json: "));
+        let source = parse.source().unwrap();
+        assert!(source.source().unwrap().is::<serde_json::Error>());
 
+        let rejected = Error::SubscriptionRejected {
+            code: 2,
+            msg: "Invalid request".to_string(),
+            id: Some(3),
+        };
+        assert_eq!(rejected.to_string(), "request 3 rejected with code 2: Invalid request");
+        assert!(rejected.source().is_none());
+    }
+
+    #[test]
+    fn close_reasons() {
+        let expired = "Connection expired after 24 hours";
+        assert_eq!(CloseReason::of(CloseCode::Away, expired), CloseReason::Expired);
+        assert_eq!(CloseReason::of(CloseCode::Away, ""), CloseReason::GoingAway);
+        assert_eq!(CloseReason::of(CloseCode::Error, expired), CloseReason::Other);
+        assert_eq!(Error::NotConnected.close_reason(), None);
     }
 }
@@ -0,0 +1,654 @@
+//! C ABI of a stream client, with the `ffi` feature, to link the crate into C or C++ programs.
+//!
+//! Build a shared or static library with the feature, the declarations are in
+//! `include/binance_api_async.h`:
+//! ```text
+//! cargo rustc --release --lib --features ffi --crate-type cdylib
+//! cargo rustc --release --lib --features ffi --crate-type staticlib
+//! ```
+//!
+//! A client owns a [`BinanceApi`] and the tokio runtime driving it, its functions block the
+//! calling thread. [`binance_client_new_market()`] makes a client of the futures streams. Messages are polled one at a time, as a flat [`BinanceEvent`] or as the
+//! JSON the sinks write, or handed to a callback by [`binance_client_run()`]:
+//! ```text
+//! BinanceClient *client = binance_client_new(NULL);
+//! if (binance_client_connect(client) != 0
+//!     || binance_client_subscribe(client, "btcusdt", "bookTicker", 1) != 0) {
+//!     fprintf(stderr, "%s\n", binance_last_error());
+//! }
+//! BinanceEvent event;
+//! while (binance_client_poll(client, 1000, &event) >= 0) {
+//!     if (event.kind == BINANCE_BOOK_TICKER) {
+//!         printf("%s %f %f\n", event.symbol, event.bid_price, event.ask_price);
+//!     }
+//! }
+//! binance_client_free(client);
+//! ```
+//!
+//! Functions returning an `int` return 0 on success and -1 on failure, the error of the last
+//! failure on the calling thread is returned by [`binance_last_error()`]. The polls return 1
+//! with a message, 0 if none arrived in time.
+
+use std::cell::RefCell;
+use std::ffi::{c_char, c_int, c_void, CStr, CString};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::time::Duration;
+
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+use tokio::runtime::Runtime;
+
+use crate::validation::{parse_feed, Market};
+use crate::{BinanceApi, Error, Message, SubscribeInfo, Symbol};
+
+/// Length of [`BinanceEvent::symbol`], with the terminating NUL.
+pub const SYMBOL_LEN: usize = 32;
+
+/// [`Market::Spot`] in [`binance_client_new_market()`].
+pub const MARKET_SPOT: c_int = 0;
+/// [`Market::UsdFutures`] in [`binance_client_new_market()`].
+pub const MARKET_USD_FUTURES: c_int = 1;
+
+/// A client, see the [module](self) documentation.
+pub struct BinanceClient {
+    runtime: Runtime,
+    api: BinanceApi,
+    /// the feeds are named like the streams of the market
+    market: Market,
+}
+
+impl std::fmt::Debug for BinanceClient {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BinanceClient")
+            .field("connection", &self.api.connection_id())
+            .finish_non_exhaustive()
+    }
+}
+
+/// The type of a [`BinanceEvent`], [`Message::event_type()`] in C.
+#[repr(u32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinanceEventKind {
+    AggTrade = 1,
+    Trade = 2,
+    PartialDepth = 3,
+    BookTicker = 4,
+    DepthUpdate = 5,
+    Kline = 6,
+    Error = 7,
+    SubscribeSuccess = 8,
+    ContinuousKline = 9,
+    FuturesDepthUpdate = 10,
+}
+
+/// A message flattened into the fields C programs read most, see [`BinanceEventKind`].
+///
+/// Fields a message type does not have are 0, the levels of depth updates are only in the
+/// JSON of [`binance_client_poll_json()`]. Prices and quantities are converted to `f64`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BinanceEvent {
+    pub kind: BinanceEventKind,
+    /// The symbol as Binance writes it, e.g. `BTCUSDT`, NUL terminated, empty for partial
+    /// depths, errors and acknowledgements.
+    pub symbol: [c_char; SYMBOL_LEN],
+    /// Binance event time in milliseconds since epoch.
+    pub event_time: u64,
+    /// Local receive time in milliseconds since epoch.
+    pub recv_time: u64,
+    /// The trade id of trades, the update id of books and the request id of replies.
+    pub id: u64,
+    /// Trade price, or close of a kline.
+    pub price: f64,
+    /// Trade quantity, or volume of a kline.
+    pub quantity: f64,
+    pub open: f64,
+    pub high: f64,
+    pub low: f64,
+    /// Best bid and ask of book tickers and partial depths.
+    pub bid_price: f64,
+    pub bid_quantity: f64,
+    pub ask_price: f64,
+    pub ask_quantity: f64,
+    /// The buyer of a trade was the maker, or a kline is closed.
+    pub flag: bool,
+}
+
+/// Called by [`binance_client_run()`] with every message, as a flat event and as JSON, both
+/// valid for the call only. Return 0 to go on, anything else to stop.
+pub type BinanceCallback =
+    extern "C" fn(event: *const BinanceEvent, json: *const c_char, user_data: *mut c_void) -> c_int;
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+fn set_error(e: impl std::fmt::Display) {
+    // a message with a NUL in it is cut before the NUL
+    let message = e.to_string();
+    let message = message.split('\0').next().unwrap_or_default();
+    let message = CString::new(message).unwrap_or_default();
+    LAST_ERROR.with(|last| *last.borrow_mut() = Some(message));
+}
+
+/// Run `f`, returning -1 on an error or a panic, which must not unwind into C.
+fn guard(f: impl FnOnce() -> crate::Result<c_int>) -> c_int {
+    match catch_unwind(AssertUnwindSafe(f)) {
+        Ok(Ok(code)) => code,
+        Ok(Err(e)) => {
+            set_error(e);
+            -1
+        }
+        Err(_) => {
+            set_error("panicked");
+            -1
+        }
+    }
+}
+
+/// The string at `s`, `None` if it is null.
+///
+/// # Safety
+/// `s` is null or a NUL terminated string.
+unsafe fn string<'a>(s: *const c_char) -> crate::Result<Option<&'a str>> {
+    if s.is_null() {
+        return Ok(None);
+    }
+    CStr::from_ptr(s)
+        .to_str()
+        .map(Some)
+        .map_err(|e| Error::Custom(format!("invalid string: {e}")))
+}
+
+/// # Safety
+/// `symbol` and `feed` are NUL terminated strings.
+unsafe fn subscribe_info(
+    symbol: *const c_char,
+    feed: *const c_char,
+    market: Market,
+) -> crate::Result<SubscribeInfo> {
+    let missing = || Error::Custom("the symbol and the feed are required".to_string());
+    let symbol: Symbol = string(symbol)?.ok_or_else(missing)?.parse()?;
+    let feed = parse_feed(string(feed)?.ok_or_else(missing)?, market)?;
+    Ok(SubscribeInfo::new(symbol, feed))
+}
+
+/// # Safety
+/// `client` is a client of [`binance_client_new()`] not freed yet, or null.
+unsafe fn client<'a>(client: *mut BinanceClient) -> crate::Result<&'a mut BinanceClient> {
+    client
+        .as_mut()
+        .ok_or_else(|| Error::Custom("the client is null".to_string()))
+}
+
+/// A client of the websocket at `url`, Binance if `url` is null, null on failure. Not
+/// connected yet, see [`binance_client_connect()`].
+///
+/// # Safety
+/// `url` is null or a NUL terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn binance_client_new(url: *const c_char) -> *mut BinanceClient {
+    binance_client_new_market(url, MARKET_SPOT)
+}
+
+/// A client of the feeds of `market`, [`MARKET_SPOT`] or [`MARKET_USD_FUTURES`], see
+/// [`binance_client_new()`]. Connects to the streams of the market if `url` is null.
+///
+/// # Safety
+/// `url` is null or a NUL terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn binance_client_new_market(
+    url: *const c_char,
+    market: c_int,
+) -> *mut BinanceClient {
+    let result = catch_unwind(|| {
+        let url = string(url)?;
+        let market = match market {
+            MARKET_SPOT => Market::Spot,
+            MARKET_USD_FUTURES => Market::UsdFutures,
+            _ => return Err(Error::Custom(format!("unknown market {market}"))),
+        };
+        // a worker of its own, pings are answered between polls
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(1)
+            .enable_all()
+            .build()?;
+        let mut builder = BinanceApi::builder().market(market);
+        if let Some(url) = url {
+            builder = builder.url(url);
+        }
+        let api = builder.build();
+        Ok::<_, Error>(BinanceClient {
+            runtime,
+            api,
+            market,
+        })
+    });
+    match result {
+        Ok(Ok(client)) => Box::into_raw(Box::new(client)),
+        Ok(Err(e)) => {
+            set_error(e);
+            std::ptr::null_mut()
+        }
+        Err(_) => {
+            set_error("panicked");
+            std::ptr::null_mut()
+        }
+    }
+}
+
+/// Free `client`, disconnecting it. Does nothing if `client` is null.
+///
+/// # Safety
+/// `client` is a client of [`binance_client_new()`] not freed yet, or null.
+#[no_mangle]
+pub unsafe extern "C" fn binance_client_free(client: *mut BinanceClient) {
+    if client.is_null() {
+        return;
+    }
+    let mut client = Box::from_raw(client);
+    let _ = catch_unwind(AssertUnwindSafe(|| {
+        let BinanceClient { runtime, api, .. } = &mut *client;
+        runtime.block_on(api.disconnect());
+    }));
+}
+
+/// Allow the client to connect to a plaintext `ws://` url, e.g. a local relay, if `allow`,
+/// see [`BinanceApiBuilder::allow_plaintext()`](crate::BinanceApiBuilder::allow_plaintext).
+/// Takes effect at the next connect.
+///
+/// # Safety
+/// `client` is a client of [`binance_client_new()`] not freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn binance_client_allow_plaintext(
+    client: *mut BinanceClient,
+    allow: bool,
+) -> c_int {
+    guard(|| {
+        self::client(client)?.api.allow_plaintext = allow;
+        Ok(0)
+    })
+}
+
+/// Connect, or reconnect, the client.
+///
+/// # Safety
+/// `client` is a client of [`binance_client_new()`] not freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn binance_client_connect(client: *mut BinanceClient) -> c_int {
+    guard(|| {
+        let BinanceClient { runtime, api, .. } = self::client(client)?;
+        runtime.block_on(api.connect())?;
+        Ok(0)
+    })
+}
+
+/// Subscribe to the `feed` of `symbol`, e.g. `btcusdt` and `depth5@100ms`, see
+/// [`Feed`](crate::Feed) for the feed names, depths without a delay have the default delay of
+/// the market. Acknowledged by a [`BinanceEventKind::SubscribeSuccess`] with `id`.
+///
+/// # Safety
+/// `client` is a client of [`binance_client_new()`] not freed yet, `symbol` and `feed` are
+/// NUL terminated strings.
+#[no_mangle]
+pub unsafe extern "C" fn binance_client_subscribe(
+    client: *mut BinanceClient,
+    symbol: *const c_char,
+    feed: *const c_char,
+    id: u64,
+) -> c_int {
+    guard(|| {
+        let BinanceClient {
+            runtime,
+            api,
+            market,
+        } = self::client(client)?;
+        let info = subscribe_info(symbol, feed, *market)?;
+        runtime.block_on(api.subscribe(&[info], Some(id)))?;
+        Ok(0)
+    })
+}
+
+/// Unsubscribe from the `feed` of `symbol`, see [`binance_client_subscribe()`].
+///
+/// # Safety
+/// `client` is a client of [`binance_client_new()`] not freed yet, `symbol` and `feed` are
+/// NUL terminated strings.
+#[no_mangle]
+pub unsafe extern "C" fn binance_client_unsubscribe(
+    client: *mut BinanceClient,
+    symbol: *const c_char,
+    feed: *const c_char,
+) -> c_int {
+    guard(|| {
+        let BinanceClient {
+            runtime,
+            api,
+            market,
+        } = self::client(client)?;
+        let info = subscribe_info(symbol, feed, *market)?;
+        runtime.block_on(api.unsubscribe(vec![info]))?;
+        Ok(0)
+    })
+}
+
+/// The next message of `client` within `timeout_ms` milliseconds, with its receive time.
+fn poll(client: &mut BinanceClient, timeout_ms: u64) -> crate::Result<Option<(Message, u64)>> {
+    let BinanceClient { runtime, api, .. } = client;
+    // the timer of the timeout is made within the runtime
+    let next = async {
+        tokio::time::timeout(Duration::from_millis(timeout_ms), api.next_envelope()).await
+    };
+    match runtime.block_on(next) {
+        Ok(Some(envelope)) => Ok(Some((envelope.message, envelope.recv_time))),
+        Ok(None) => Err(api.closed()),
+        Err(_) => Ok(None),
+    }
+}
+
+/// Wait up to `timeout_ms` milliseconds for the next message and write it to `event`.
+///
+/// 1 with a message, 0 if none arrived in time, -1 once disconnected.
+///
+/// # Safety
+/// `client` is a client of [`binance_client_new()`] not freed yet, `event` points to a
+/// [`BinanceEvent`].
+#[no_mangle]
+pub unsafe extern "C" fn binance_client_poll(
+    client: *mut BinanceClient,
+    timeout_ms: u64,
+    event: *mut BinanceEvent,
+) -> c_int {
+    guard(|| {
+        let client = self::client(client)?;
+        if event.is_null() {
+            return Err(Error::Custom("the event is null".to_string()));
+        }
+        match poll(client, timeout_ms)? {
+            Some((msg, recv_time)) => {
+                event.write(BinanceEvent::new(&msg, recv_time));
+                Ok(1)
+            }
+            None => Ok(0),
+        }
+    })
+}
+
+/// Wait up to `timeout_ms` milliseconds for the next message and set `json` to it, as the
+/// sinks serialize messages. Free the string with [`binance_string_free()`].
+///
+/// 1 with a message, 0 if none arrived in time, -1 once disconnected.
+///
+/// # Safety
+/// `client` is a client of [`binance_client_new()`] not freed yet, `json` points to a
+/// `char *`.
+#[no_mangle]
+pub unsafe extern "C" fn binance_client_poll_json(
+    client: *mut BinanceClient,
+    timeout_ms: u64,
+    json: *mut *mut c_char,
+) -> c_int {
+    guard(|| {
+        let client = self::client(client)?;
+        if json.is_null() {
+            return Err(Error::Custom("the json is null".to_string()));
+        }
+        match poll(client, timeout_ms)? {
+            Some((msg, _)) => {
+                json.write(to_json(&msg)?.into_raw());
+                Ok(1)
+            }
+            None => Ok(0),
+        }
+    })
+}
+
+/// Hand every message to `callback` until it returns non-zero, then return 0. -1 once
+/// disconnected.
+///
+/// # Safety
+/// `client` is a client of [`binance_client_new()`] not freed yet. `user_data` is passed to
+/// `callback` as is.
+#[no_mangle]
+pub unsafe extern "C" fn binance_client_run(
+    client: *mut BinanceClient,
+    callback: BinanceCallback,
+    user_data: *mut c_void,
+) -> c_int {
+    guard(|| {
+        let client = self::client(client)?;
+        loop {
+            // a timeout only to not hold one block_on for the whole run
+            let Some((msg, recv_time)) = poll(client, 1000)? else {
+                continue;
+            };
+            let event = BinanceEvent::new(&msg, recv_time);
+            let json = to_json(&msg)?;
+            if callback(&event, json.as_ptr(), user_data) != 0 {
+                return Ok(0);
+            }
+        }
+    })
+}
+
+/// The error of the last failed call on this thread, null if none failed. Valid until the
+/// next failure on the thread.
+#[no_mangle]
+pub extern "C" fn binance_last_error() -> *const c_char {
+    LAST_ERROR.with(|last| match &*last.borrow() {
+        Some(message) => message.as_ptr(),
+        None => std::ptr::null(),
+    })
+}
+
+/// Free a string of [`binance_client_poll_json()`]. Does nothing if `s` is null.
+///
+/// # Safety
+/// `s` is a string of the crate not freed yet, or null.
+#[no_mangle]
+pub unsafe extern "C" fn binance_string_free(s: *mut c_char) {
+    if !s.is_null() {
+        drop(CString::from_raw(s));
+    }
+}
+
+fn to_json(msg: &Message) -> crate::Result<CString> {
+    let json = serde_json::to_string(msg)?;
+    // JSON escapes control characters, a NUL cannot be in it
+    Ok(CString::new(json).expect("no NUL in JSON"))
+}
+
+impl BinanceEvent {
+    fn new(msg: &Message, recv_time: u64) -> Self {
+        let f = |d: &Decimal| d.to_f64().unwrap_or_default();
+        let mut event = Self {
+            kind: BinanceEventKind::Error,
+            symbol: [0; SYMBOL_LEN],
+            event_time: msg.event_time().unwrap_or_default(),
+            recv_time,
+            id: 0,
+            price: 0.0,
+            quantity: 0.0,
+            open: 0.0,
+            high: 0.0,
+            low: 0.0,
+            bid_price: 0.0,
+            bid_quantity: 0.0,
+            ask_price: 0.0,
+            ask_quantity: 0.0,
+            flag: false,
+        };
+        if let Some(symbol) = msg.symbol() {
+            let name = symbol.to_string().to_uppercase();
+            for (c, b) in event
+                .symbol
+                .iter_mut()
+                .zip(name.bytes().take(SYMBOL_LEN - 1))
+            {
+                *c = b as c_char;
+            }
+        }
+        match msg {
+            #[cfg(feature = "trade")]
+            Message::AggTrade(t) => {
+                event.kind = BinanceEventKind::AggTrade;
+                event.id = t.trade_id;
+                (event.price, event.quantity) = (f(&t.price), f(&t.quantity));
+                event.flag = t.is_market_maker;
+            }
+            #[cfg(feature = "trade")]
+            Message::Trade(t) => {
+                event.kind = BinanceEventKind::Trade;
+                event.id = t.trade_id;
+                (event.price, event.quantity) = (f(&t.price), f(&t.quantity));
+                event.flag = t.is_market_maker;
+            }
+            #[cfg(feature = "depth")]
+            Message::PartialDepth(book) => {
+                event.kind = BinanceEventKind::PartialDepth;
+                event.id = book.last_update_id;
+                if let Some([price, quantity]) = book.best_bid() {
+                    (event.bid_price, event.bid_quantity) = (f(&price), f(&quantity));
+                }
+                if let Some([price, quantity]) = book.best_ask() {
+                    (event.ask_price, event.ask_quantity) = (f(&price), f(&quantity));
+                }
+            }
+            #[cfg(feature = "book-ticker")]
+            Message::BookTicker(bt) => {
+                event.kind = BinanceEventKind::BookTicker;
+                event.id = bt.update_id;
+                event.bid_price = f(&bt.best_bid_price);
+                event.bid_quantity = f(&bt.best_bid_qty);
+                event.ask_price = f(&bt.best_ask_price);
+                event.ask_quantity = f(&bt.best_ask_qty);
+            }
+            #[cfg(feature = "depth")]
+            Message::DepthUpdate(du) => {
+                event.kind = BinanceEventKind::DepthUpdate;
+                event.id = du.final_update_id;
+            }
+            #[cfg(feature = "depth")]
+            Message::FuturesDepthUpdate(du) => {
+                event.kind = BinanceEventKind::FuturesDepthUpdate;
+                event.id = du.final_update_id;
+            }
+            #[cfg(feature = "kline")]
+            Message::Kline(k) => {
+                let k = &k.kline;
+                event.kind = BinanceEventKind::Kline;
+                (event.open, event.high, event.low) = (f(&k.open), f(&k.high), f(&k.low));
+                (event.price, event.quantity) = (f(&k.close), f(&k.volume));
+                event.flag = k.is_closed;
+            }
+            #[cfg(feature = "kline")]
+            Message::ContinuousKline(k) => {
+                let k = &k.kline;
+                event.kind = BinanceEventKind::ContinuousKline;
+                (event.open, event.high, event.low) = (f(&k.open), f(&k.high), f(&k.low));
+                (event.price, event.quantity) = (f(&k.close), f(&k.volume));
+                event.flag = k.is_closed;
+            }
+            Message::Error(e) => event.id = e.id.unwrap_or_default(),
+            Message::SubscribeSuccess { id, .. } => {
+                event.kind = BinanceEventKind::SubscribeSuccess;
+                event.id = *id;
+            }
+        }
+        event
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::messages::BookTicker;
+    use crate::test_util::{ticker, Action, MockServer};
+
+    extern "C" fn stop_at_ticker(
+        event: *const BinanceEvent,
+        json: *const c_char,
+        user_data: *mut c_void,
+    ) -> c_int {
+        let (event, json) = unsafe { (&*event, CStr::from_ptr(json)) };
+        let seen = unsafe { &mut *(user_data as *mut Vec<String>) };
+        seen.push(json.to_str().unwrap().to_string());
+        (event.kind == BinanceEventKind::BookTicker) as c_int
+    }
+
+    #[test]
+    fn poll_and_run() {
+        let ticker = Message::BookTicker(BookTicker {
+            update_id: 9,
+            best_ask_qty: Decimal::TWO,
+            ..ticker(Symbol::BTCUSDT, "99.5", "100")
+        });
+        // the server runs on a runtime of its own, the client blocks on its own
+        let runtime = Runtime::new().unwrap();
+        let server = runtime.block_on(MockServer::start()).unwrap();
+        server.script([Action::message(&ticker), Action::message(&ticker)]);
+        let url = CString::new(server.url()).unwrap();
+
+        unsafe {
+            let client = binance_client_new(url.as_ptr());
+            let (symbol, feed) = (c"btcusdt".as_ptr(), c"bookTicker".as_ptr());
+            assert_eq!(binance_client_subscribe(client, symbol, feed, 3), -1);
+            let error = CStr::from_ptr(binance_last_error());
+            assert_eq!(error.to_str().unwrap(), "not connected");
+            assert_eq!(binance_client_allow_plaintext(client, true), 0);
+            assert_eq!(binance_client_connect(client), 0);
+            let unserved = c"depth@500ms".as_ptr();
+            assert_eq!(binance_client_subscribe(client, symbol, unserved, 3), -1);
+            assert_eq!(binance_client_subscribe(client, symbol, feed, 3), 0);
+
+            let mut event = BinanceEvent::new(
+                &Message::SubscribeSuccess {
+                    result: None,
+                    id: 0,
+                },
+                0,
+            );
+            assert_eq!(binance_client_poll(client, 1000, &mut event), 1);
+            assert_eq!(event.kind, BinanceEventKind::SubscribeSuccess);
+            assert_eq!(event.id, 3);
+            assert_eq!(binance_client_poll(client, 1000, &mut event), 1);
+            assert_eq!(event.kind, BinanceEventKind::BookTicker);
+            assert_eq!(CStr::from_ptr(event.symbol.as_ptr()), c"BTCUSDT");
+            assert_eq!(
+                (event.id, event.bid_price, event.ask_quantity),
+                (9, 99.5, 2.0)
+            );
+
+            let mut seen = Vec::<String>::new();
+            let user_data = &mut seen as *mut Vec<String> as *mut c_void;
+            assert_eq!(binance_client_run(client, stop_at_ticker, user_data), 0);
+            assert_eq!(seen, [serde_json::to_string(&ticker).unwrap()]);
+            assert_eq!(binance_client_poll(client, 10, &mut event), 0);
+
+            let mut json = std::ptr::null_mut();
+            assert_eq!(binance_client_poll_json(client, 10, &mut json), 0);
+            binance_client_free(client);
+        }
+    }
+
+    #[test]
+    fn futures_client() {
+        let runtime = Runtime::new().unwrap();
+        let server = runtime.block_on(MockServer::start()).unwrap();
+        let url = CString::new(server.url()).unwrap();
+
+        unsafe {
+            assert!(binance_client_new_market(url.as_ptr(), 7).is_null());
+            let client = binance_client_new_market(url.as_ptr(), MARKET_USD_FUTURES);
+            assert_eq!(binance_client_allow_plaintext(client, true), 0);
+            assert_eq!(binance_client_connect(client), 0);
+            let symbol = c"btcusdt".as_ptr();
+            // the default delay of futures depths, 250ms
+            assert_eq!(binance_client_subscribe(client, symbol, c"depth".as_ptr(), 1), 0);
+            let kline = c"perpetual@continuousKline_1m".as_ptr();
+            assert_eq!(binance_client_subscribe(client, symbol, kline, 2), 0);
+            binance_client_free(client);
+        }
+        assert!(server.subscriptions().contains(&"btcusdt@depth".to_string()));
+    }
+}
@@ -0,0 +1,365 @@
+//! Fixed point prices and quantities.
+//!
+//! Binance only accepts prices that are a multiple of the tick size of a symbol and quantities
+//! that are a multiple of its step size. A [`Scale`] converts them to whole numbers of ticks
+//! and steps, integer math is much faster than [`Decimal`] math and exact. The scales of
+//! symbols are returned by [`RestClient::scales()`](crate::rest::RestClient::scales).
+//!
+//! [`FixedOrderBook`] is an [`OrderBook`](crate::book::OrderBook) keeping its levels in ticks
+//! and steps.
+//! ```
+//! use binance_api_async::fixed::Scale;
+//! use rust_decimal::Decimal;
+//!
+//! let scale = Scale::new(Decimal::new(1, 2), Decimal::new(1, 5));
+//! assert_eq!(scale.ticks(Decimal::new(9700012, 2)), Some(9700012));
+//! assert_eq!(scale.steps(Decimal::new(5, 1)), Some(50000));
+//! assert_eq!(scale.price(9700012), Decimal::new(9700012, 2));
+//! // not a whole number of ticks
+//! assert_eq!(scale.ticks(Decimal::new(1, 3)), None);
+//! ```
+
+#[cfg(feature = "depth")]
+use std::collections::BTreeMap;
+
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+#[cfg(feature = "depth")]
+use tracing::warn;
+
+#[cfg(feature = "depth")]
+use crate::messages::{DepthUpdate, PartialDepth};
+#[cfg(feature = "depth")]
+use crate::{Error, Symbol};
+
+/// Tick size of the prices and step size of the quantities of a symbol.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Scale {
+    tick_size: Decimal,
+    step_size: Decimal,
+}
+
+impl Scale {
+    /// # Panic
+    /// If `tick_size` or `step_size` is not positive.
+    pub fn new(tick_size: Decimal, step_size: Decimal) -> Self {
+        assert!(
+            tick_size.is_sign_positive() && !tick_size.is_zero(),
+            "tick size must be positive"
+        );
+        assert!(
+            step_size.is_sign_positive() && !step_size.is_zero(),
+            "step size must be positive"
+        );
+        Self {
+            tick_size: tick_size.normalize(),
+            step_size: step_size.normalize(),
+        }
+    }
+
+    /// `None` if `tick_size` or `step_size` is not positive, e.g. the 0 exchangeInfo sends
+    /// for a disabled filter.
+    pub fn try_new(tick_size: Decimal, step_size: Decimal) -> Option<Self> {
+        let positive = |size: Decimal| size.is_sign_positive() && !size.is_zero();
+        (positive(tick_size) && positive(step_size)).then(|| Self::new(tick_size, step_size))
+    }
+
+    pub fn tick_size(&self) -> Decimal {
+        self.tick_size
+    }
+
+    pub fn step_size(&self) -> Decimal {
+        self.step_size
+    }
+
+    /// `price` in ticks, `None` if it is not a whole number of ticks or does not fit an `i64`.
+    pub fn ticks(&self, price: Decimal) -> Option<i64> {
+        units(price, self.tick_size)
+    }
+
+    /// `quantity` in steps, `None` if it is not a whole number of steps or does not fit an
+    /// `i64`.
+    pub fn steps(&self, quantity: Decimal) -> Option<i64> {
+        units(quantity, self.step_size)
+    }
+
+    /// Price of `ticks`.
+    pub fn price(&self, ticks: i64) -> Decimal {
+        Decimal::from(ticks) * self.tick_size
+    }
+
+    /// Quantity of `steps`.
+    pub fn quantity(&self, steps: i64) -> Decimal {
+        Decimal::from(steps) * self.step_size
+    }
+
+    /// A [price, volume] level in [ticks, steps].
+    pub fn level(&self, level: [Decimal; 2]) -> Option<[i64; 2]> {
+        Some([self.ticks(level[0])?, self.steps(level[1])?])
+    }
+
+    /// A [ticks, steps] level as [price, volume].
+    pub fn decimal_level(&self, level: [i64; 2]) -> [Decimal; 2] {
+        [self.price(level[0]), self.quantity(level[1])]
+    }
+
+    /// Like [`Scale::level()`], with the error of [`FixedOrderBook`] for levels off the scale.
+    #[cfg(feature = "depth")]
+    fn try_level(&self, level: [Decimal; 2]) -> crate::Result<[i64; 2]> {
+        let [price, quantity] = level;
+        Ok([
+            self.ticks(price).ok_or(Error::OffScale(price))?,
+            self.steps(quantity).ok_or(Error::OffScale(quantity))?,
+        ])
+    }
+}
+
+fn units(value: Decimal, unit: Decimal) -> Option<i64> {
+    let units = value.checked_div(unit)?;
+    if !units.fract().is_zero() {
+        return None;
+    }
+    units.to_i64()
+}
+
+/// Order book with levels in ticks and steps, see [`OrderBook`](crate::book::OrderBook).
+///
+/// Levels are [ticks, steps], use the [`Scale`] of the book to convert them.
+#[cfg(feature = "depth")]
+#[derive(Debug, Clone)]
+pub struct FixedOrderBook {
+    symbol: Symbol,
+    scale: Scale,
+    last_update_id: u64,
+    bids: BTreeMap<i64, i64>,
+    asks: BTreeMap<i64, i64>,
+    /// converted levels of the update being applied, bids first
+    levels: Vec<[i64; 2]>,
+}
+
+#[cfg(feature = "depth")]
+impl FixedOrderBook {
+    /// Create a book for `symbol` from a depth snapshot.
+    ///
+    /// # Errors
+    /// [`Error::OffScale`] if a level is not a whole number of ticks and steps of `scale`.
+    pub fn from_snapshot(
+        symbol: Symbol,
+        scale: Scale,
+        snapshot: &PartialDepth,
+    ) -> crate::Result<Self> {
+        let side = |levels: &[[Decimal; 2]]| {
+            levels
+                .iter()
+                .map(|l| scale.try_level(*l).map(|[p, q]| (p, q)))
+                .collect::<crate::Result<_>>()
+        };
+        Ok(Self {
+            symbol,
+            scale,
+            last_update_id: snapshot.last_update_id,
+            bids: side(&snapshot.bids)?,
+            asks: side(&snapshot.asks)?,
+            levels: Vec::new(),
+        })
+    }
+
+    pub fn symbol(&self) -> &Symbol {
+        &self.symbol
+    }
+
+    pub fn scale(&self) -> &Scale {
+        &self.scale
+    }
+
+    /// Update id of the last applied snapshot or update.
+    pub fn last_update_id(&self) -> u64 {
+        self.last_update_id
+    }
+
+    /// Apply a diff. depth update to the book, see [`OrderBook::apply()`].
+    ///
+    /// # Errors
+    /// [`Error::OrderBookOutOfSync`] if updates are missing between the book and `update`,
+    /// [`Error::OffScale`] if a level is not a whole number of ticks and steps.
+    /// The book is left unchanged.
+    ///
+    /// [`OrderBook::apply()`]: crate::book::OrderBook::apply
+    pub fn apply(&mut self, update: &DepthUpdate) -> crate::Result<bool> {
+        if update.symbol != self.symbol {
+            warn!(
+                "depth update for {:?} applied to book for {:?}",
+                update.symbol, self.symbol
+            );
+            return Ok(false);
+        }
+
+        if update.final_update_id <= self.last_update_id {
+            return Ok(false);
+        }
+
+        let expected = self.last_update_id + 1;
+        if update.first_update_id > expected {
+            return Err(Error::OrderBookOutOfSync {
+                expected,
+                received: update.first_update_id,
+            });
+        }
+
+        self.levels.clear();
+        for level in update.bids.iter().chain(&update.asks) {
+            let level = self.scale.try_level(*level)?;
+            self.levels.push(level);
+        }
+        let (bids, asks) = self.levels.split_at(update.bids.len());
+        for [price, qty] in bids {
+            update_level(&mut self.bids, *price, *qty);
+        }
+        for [price, qty] in asks {
+            update_level(&mut self.asks, *price, *qty);
+        }
+        self.last_update_id = update.final_update_id;
+
+        Ok(true)
+    }
+
+    /// Bid levels as [ticks, steps], best bid first.
+    pub fn bids(&self) -> impl Iterator<Item = [i64; 2]> + '_ {
+        self.bids.iter().rev().map(|(p, q)| [*p, *q])
+    }
+
+    /// Ask levels as [ticks, steps], best ask first.
+    pub fn asks(&self) -> impl Iterator<Item = [i64; 2]> + '_ {
+        self.asks.iter().map(|(p, q)| [*p, *q])
+    }
+
+    pub fn best_bid(&self) -> Option<[i64; 2]> {
+        self.bids().next()
+    }
+
+    pub fn best_ask(&self) -> Option<[i64; 2]> {
+        self.asks().next()
+    }
+
+    /// Difference between best ask and best bid in ticks.
+    pub fn spread(&self) -> Option<i64> {
+        Some(self.best_ask()?[0] - self.best_bid()?[0])
+    }
+
+    /// The top `levels` of the book as decimals, see [`OrderBook::top()`].
+    ///
+    /// [`OrderBook::top()`]: crate::book::OrderBook::top
+    pub fn top(&self, levels: usize) -> PartialDepth {
+        PartialDepth {
+            last_update_id: self.last_update_id,
+            bids: self
+                .bids()
+                .take(levels)
+                .map(|l| self.scale.decimal_level(l))
+                .collect(),
+            asks: self
+                .asks()
+                .take(levels)
+                .map(|l| self.scale.decimal_level(l))
+                .collect(),
+        }
+    }
+}
+
+#[cfg(feature = "depth")]
+impl PartialEq for FixedOrderBook {
+    fn eq(&self, other: &Self) -> bool {
+        self.symbol == other.symbol
+            && self.scale == other.scale
+            && self.last_update_id == other.last_update_id
+            && self.bids == other.bids
+            && self.asks == other.asks
+    }
+}
+
+#[cfg(feature = "depth")]
+impl Eq for FixedOrderBook {}
+
+#[cfg(feature = "depth")]
+fn update_level(side: &mut BTreeMap<i64, i64>, price: i64, qty: i64) {
+    if qty == 0 {
+        side.remove(&price);
+    } else {
+        side.insert(price, qty);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::book::OrderBook;
+    use crate::test_util::dec;
+    use smallvec::smallvec;
+
+    #[test]
+    fn scale() {
+        // as sent by exchangeInfo
+        let scale = Scale::new(dec("0.05000000"), dec("0.00100000"));
+        assert_eq!(scale.ticks(dec("10.15")), Some(203));
+        assert_eq!(scale.ticks(dec("10.17")), None);
+        assert_eq!(scale.ticks(dec("-0.1")), Some(-2));
+        assert_eq!(scale.steps(dec("1.500")), Some(1500));
+        assert_eq!(scale.steps(dec("0.0005")), None);
+        assert_eq!(scale.level([dec("10.15"), dec("0")]), Some([203, 0]));
+        assert_eq!(scale.decimal_level([203, 1500]), [dec("10.15"), dec("1.5")]);
+        assert_eq!(scale.ticks(Decimal::MAX), None);
+
+        assert_eq!(Scale::try_new(dec("0.05"), dec("0.001")), Some(scale));
+        assert_eq!(Scale::try_new(dec("0.00000000"), dec("0.001")), None);
+        assert_eq!(Scale::try_new(dec("0.05"), dec("-1")), None);
+    }
+
+    #[test]
+    fn same_as_decimal_book() {
+        let scale = Scale::new(dec("0.01"), dec("0.001"));
+        let snapshot = PartialDepth {
+            last_update_id: 100,
+            bids: smallvec![[dec("10.00"), dec("1")], [dec("9.50"), dec("2")]],
+            asks: smallvec![[dec("10.50"), dec("1")], [dec("11.00"), dec("3")]],
+        };
+        let mut book = OrderBook::from_snapshot(Symbol::BTCUSDT, &snapshot);
+        let mut fixed = FixedOrderBook::from_snapshot(Symbol::BTCUSDT, scale, &snapshot).unwrap();
+        assert_eq!(fixed.spread(), Some(50));
+
+        let update = |first, last, bids, asks| DepthUpdate {
+            event_time: 0,
+            symbol: Symbol::BTCUSDT,
+            first_update_id: first,
+            final_update_id: last,
+            bids,
+            asks,
+        };
+        let updates = [
+            update(
+                95,
+                105,
+                vec![[dec("10.0"), dec("0")], [dec("9.8"), dec("4")]],
+                vec![],
+            ),
+            update(101, 104, vec![[dec("9.9"), dec("1")]], vec![]),
+            update(106, 107, vec![], vec![[dec("10.25"), dec("0.5")]]),
+        ];
+        for u in &updates {
+            assert_eq!(fixed.apply(u).unwrap(), book.apply(u).unwrap());
+            assert_eq!(fixed.top(10), book.top(10));
+        }
+        assert_eq!(fixed.best_bid(), Some([980, 4000]));
+        assert_eq!(fixed.spread(), Some(45));
+
+        // the book is unchanged by updates off the scale
+        let before = fixed.clone();
+        let off = update(
+            108,
+            108,
+            vec![[dec("9.7"), dec("1")]],
+            vec![[dec("10.251"), dec("1")]],
+        );
+        assert!(matches!(fixed.apply(&off), Err(Error::OffScale(p)) if p == dec("10.251")));
+        assert_eq!(fixed, before);
+    }
+}
@@ -0,0 +1,496 @@
+//! Streaming indicators calculated from the trade and book ticker streams.
+//!
+//! Like [`crate::aggregate`] each indicator tracks **one** symbol, push every
+//! [`AggTrade`](crate::messages::AggTrade) or [`Trade`](crate::messages::Trade) and the
+//! updated value is returned so it can be forwarded together with the message.
+//!
+//! [`TradeFlowTracker`] and [`MidSampler`] track every symbol they receive messages for.
+
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::time::Duration;
+
+use rust_decimal::Decimal;
+
+use crate::messages::{BookTicker, TradeEvent};
+use crate::{Message, Symbol};
+
+/// Volume weighted average price, over the whole session or a rolling window.
+#[derive(Debug, Clone)]
+pub struct Vwap {
+    window: Option<u64>,
+    trades: VecDeque<(u64, Decimal, Decimal)>,
+    price_volume: Decimal,
+    volume: Decimal,
+}
+
+impl Vwap {
+    /// VWAP of every trade since creation or the last [`Vwap::reset()`].
+    pub fn session() -> Self {
+        Self {
+            window: None,
+            trades: VecDeque::new(),
+            price_volume: Decimal::ZERO,
+            volume: Decimal::ZERO,
+        }
+    }
+
+    /// VWAP of the trades made during the last `window`, relative to the latest trade.
+    pub fn rolling(window: Duration) -> Self {
+        Self {
+            window: Some(window.as_millis() as u64),
+            ..Self::session()
+        }
+    }
+
+    /// Add a trade and return the updated VWAP.
+    pub fn push(&mut self, trade: &impl TradeEvent) -> Option<Decimal> {
+        self.update(trade.price(), trade.quantity(), trade.trade_time())
+    }
+
+    /// Add a trade at `price` and `qty` made at `time`, see [`Vwap::push()`].
+    pub fn update(&mut self, price: Decimal, qty: Decimal, time: u64) -> Option<Decimal> {
+        self.price_volume += price * qty;
+        self.volume += qty;
+
+        if let Some(window) = self.window {
+            self.trades.push_back((time, price, qty));
+            while let Some((t, p, q)) = self.trades.front().copied() {
+                if t + window > time {
+                    break;
+                }
+                self.price_volume -= p * q;
+                self.volume -= q;
+                self.trades.pop_front();
+            }
+        }
+
+        self.value()
+    }
+
+    /// Current VWAP, `None` if no volume has been traded.
+    pub fn value(&self) -> Option<Decimal> {
+        if self.volume.is_zero() {
+            return None;
+        }
+        Some(self.price_volume / self.volume)
+    }
+
+    /// Start a new session.
+    pub fn reset(&mut self) {
+        self.trades.clear();
+        self.price_volume = Decimal::ZERO;
+        self.volume = Decimal::ZERO;
+    }
+}
+
+/// Time weighted average price, over the whole session or a rolling window.
+///
+/// Each price is weighted by the time until the next trade,
+/// so the last price only counts once time has passed.
+#[derive(Debug, Clone)]
+pub struct Twap {
+    window: Option<u64>,
+    /// Start time and price of each step in the price series.
+    prices: VecDeque<(u64, Decimal)>,
+    /// Price times duration of the steps dropped from `prices` in session mode.
+    area: Decimal,
+    duration: u64,
+}
+
+impl Twap {
+    /// TWAP since the first trade, or the last [`Twap::reset()`].
+    pub fn session() -> Self {
+        Self {
+            window: None,
+            prices: VecDeque::new(),
+            area: Decimal::ZERO,
+            duration: 0,
+        }
+    }
+
+    /// TWAP over the last `window`.
+    pub fn rolling(window: Duration) -> Self {
+        Self {
+            window: Some(window.as_millis() as u64),
+            ..Self::session()
+        }
+    }
+
+    /// Add a trade and return the TWAP up to the trade time.
+    pub fn push(&mut self, trade: &impl TradeEvent) -> Option<Decimal> {
+        self.update(trade.price(), trade.trade_time())
+    }
+
+    /// Add a trade at `price` made at `time`, see [`Twap::push()`].
+    pub fn update(&mut self, price: Decimal, time: u64) -> Option<Decimal> {
+        if self.window.is_none() {
+            if let Some((start, last)) = self.prices.pop_back() {
+                let elapsed = time.saturating_sub(start);
+                self.area += last * Decimal::from(elapsed);
+                self.duration += elapsed;
+            }
+        }
+        self.prices.push_back((time, price));
+        self.evict(time);
+        self.value(time)
+    }
+
+    /// TWAP up until `now`, in milliseconds since epoch.
+    ///
+    /// If no time has passed since the first trade the price of that trade is returned.
+    pub fn value(&self, now: u64) -> Option<Decimal> {
+        let (mut area, mut duration) = (self.area, self.duration);
+        let start = self.window.map(|w| now.saturating_sub(w)).unwrap_or(0);
+
+        let ends = self.prices.iter().skip(1).map(|(t, _)| *t).chain([now]);
+        for (&(from, price), to) in self.prices.iter().zip(ends) {
+            let (from, to) = (from.max(start), to.min(now));
+            if to > from {
+                area += price * Decimal::from(to - from);
+                duration += to - from;
+            }
+        }
+
+        if duration == 0 {
+            return self.prices.back().map(|(_, p)| *p);
+        }
+        Some(area / Decimal::from(duration))
+    }
+
+    /// Drop prices that are outside the window at `now`,
+    /// call this periodically for rolling windows with few trades.
+    pub fn evict(&mut self, now: u64) {
+        let Some(window) = self.window else {
+            return;
+        };
+        // keep the last step starting before the window, it covers the start of the window
+        while self.prices.len() > 1 && self.prices[1].0 + window <= now {
+            self.prices.pop_front();
+        }
+    }
+
+    /// Start a new session.
+    pub fn reset(&mut self) {
+        self.prices.clear();
+        self.area = Decimal::ZERO;
+        self.duration = 0;
+    }
+}
+
+/// Aggressor volume of one symbol, the cumulative volume delta and rolling imbalance.
+///
+/// The aggressor is the taker of a trade, the buyer if the buyer was **not** the market maker.
+#[derive(Debug, Clone)]
+pub struct TradeFlow {
+    window: u64,
+    /// time and signed quantity, positive for buys
+    trades: VecDeque<(u64, Decimal)>,
+    cumulative_delta: Decimal,
+    buy_volume: Decimal,
+    sell_volume: Decimal,
+}
+
+impl TradeFlow {
+    /// Imbalance is calculated over the trades made during the last `window`.
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window: window.as_millis() as u64,
+            trades: VecDeque::new(),
+            cumulative_delta: Decimal::ZERO,
+            buy_volume: Decimal::ZERO,
+            sell_volume: Decimal::ZERO,
+        }
+    }
+
+    pub fn push(&mut self, trade: &impl TradeEvent) {
+        self.update(
+            trade.quantity(),
+            trade.is_market_maker(),
+            trade.trade_time(),
+        )
+    }
+
+    /// Add a trade of `qty` made at `time`, see [`TradeFlow::push()`].
+    pub fn update(&mut self, qty: Decimal, is_market_maker: bool, time: u64) {
+        let signed = if is_market_maker { -qty } else { qty };
+        self.cumulative_delta += signed;
+        *self.side(signed) += qty;
+        self.trades.push_back((time, signed));
+
+        while let Some((t, q)) = self.trades.front().copied() {
+            if t + self.window > time {
+                break;
+            }
+            *self.side(q) -= q.abs();
+            self.trades.pop_front();
+        }
+    }
+
+    fn side(&mut self, signed: Decimal) -> &mut Decimal {
+        if signed.is_sign_negative() {
+            &mut self.sell_volume
+        } else {
+            &mut self.buy_volume
+        }
+    }
+
+    /// Buy minus sell aggressor volume since creation.
+    pub fn cumulative_delta(&self) -> Decimal {
+        self.cumulative_delta
+    }
+
+    /// Buy minus sell aggressor volume in the window.
+    pub fn delta(&self) -> Decimal {
+        self.buy_volume - self.sell_volume
+    }
+
+    /// `(buy - sell) / (buy + sell)` aggressor volume in the window, from -1 to 1.
+    pub fn imbalance(&self) -> Option<Decimal> {
+        let total = self.buy_volume + self.sell_volume;
+        if total.is_zero() {
+            return None;
+        }
+        Some(self.delta() / total)
+    }
+}
+
+/// Update emitted by [`TradeFlowTracker`] for every trade.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FlowUpdate {
+    pub symbol: Symbol,
+    pub time: u64,
+    pub cumulative_delta: Decimal,
+    pub imbalance: Option<Decimal>,
+}
+
+/// Keeps a [`TradeFlow`] for every symbol it receives trades for.
+#[derive(Debug, Clone)]
+pub struct TradeFlowTracker {
+    window: Duration,
+    flows: HashMap<Symbol, TradeFlow>,
+}
+
+impl TradeFlowTracker {
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window,
+            flows: HashMap::new(),
+        }
+    }
+
+    /// Update with `msg`, returns `None` for messages that are not trades.
+    pub fn push_message(&mut self, msg: &Message) -> Option<FlowUpdate> {
+        match msg {
+            Message::AggTrade(t) => Some(self.push(t)),
+            Message::Trade(t) => Some(self.push(t)),
+            _ => None,
+        }
+    }
+
+    pub fn push(&mut self, trade: &impl TradeEvent) -> FlowUpdate {
+        let flow = self
+            .flows
+            .entry(trade.symbol().clone())
+            .or_insert_with(|| TradeFlow::new(self.window));
+        flow.push(trade);
+
+        FlowUpdate {
+            symbol: trade.symbol().clone(),
+            time: trade.trade_time(),
+            cumulative_delta: flow.cumulative_delta(),
+            imbalance: flow.imbalance(),
+        }
+    }
+
+    pub fn flow(&self, symbol: &Symbol) -> Option<&TradeFlow> {
+        self.flows.get(symbol)
+    }
+}
+
+/// Mid price of a symbol at a point of the [`MidSampler`] grid.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MidSample {
+    pub symbol: Symbol,
+    pub time: u64,
+    pub mid: Decimal,
+}
+
+/// Samples the [`BookTicker`] mid price on a fixed time grid,
+/// giving an evenly spaced series per symbol.
+///
+/// The [`BookTicker`] stream has no event time, so updates are stamped with the local
+/// receive time, in milliseconds since epoch. Each grid point gets the last mid received
+/// at or before it, symbols without any update yet are left out.
+///
+/// Call [`MidSampler::sample()`] on a timer so points are emitted even if no updates arrive.
+#[derive(Debug, Clone)]
+pub struct MidSampler {
+    interval: u64,
+    next: Option<u64>,
+    mids: BTreeMap<Symbol, Decimal>,
+}
+
+impl MidSampler {
+    /// # Panic
+    /// If `interval` is shorter than one millisecond.
+    pub fn new(interval: Duration) -> Self {
+        let interval = interval.as_millis() as u64;
+        assert!(interval > 0, "interval must be at least one millisecond");
+        Self {
+            interval,
+            next: None,
+            mids: BTreeMap::new(),
+        }
+    }
+
+    /// Update the mid of the ticker symbol, received at `now`.
+    ///
+    /// Returns the samples for the grid points passed before `now`.
+    pub fn update(&mut self, ticker: &BookTicker, now: u64) -> Vec<MidSample> {
+        let samples = self.sample(now.saturating_sub(1));
+        self.mids.insert(ticker.symbol.clone(), ticker.mid_price());
+        if self.next.is_none() {
+            self.next = Some(now.next_multiple_of(self.interval));
+        }
+        samples
+    }
+
+    /// Returns the samples for every grid point up to and including `now`.
+    pub fn sample(&mut self, now: u64) -> Vec<MidSample> {
+        let mut samples = Vec::new();
+        let Some(next) = self.next.as_mut() else {
+            return samples;
+        };
+
+        while *next <= now {
+            samples.extend(self.mids.iter().map(|(symbol, mid)| MidSample {
+                symbol: symbol.clone(),
+                time: *next,
+                mid: *mid,
+            }));
+            *next += self.interval;
+        }
+        samples
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_util::{dec, ticker};
+
+    #[test]
+    fn session_vwap() {
+        let mut vwap = Vwap::session();
+        assert_eq!(vwap.value(), None);
+        assert_eq!(vwap.update(dec("10"), dec("1"), 0), Some(dec("10")));
+        assert_eq!(vwap.update(dec("20"), dec("3"), 1), Some(dec("17.5")));
+
+        vwap.reset();
+        assert_eq!(vwap.value(), None);
+    }
+
+    #[test]
+    fn rolling_vwap() {
+        let mut vwap = Vwap::rolling(Duration::from_secs(1));
+        vwap.update(dec("10"), dec("1"), 0);
+        assert_eq!(vwap.update(dec("20"), dec("1"), 500), Some(dec("15")));
+        // the first trade is now outside the window
+        assert_eq!(vwap.update(dec("30"), dec("1"), 1_000), Some(dec("25")));
+    }
+
+    #[test]
+    fn trade_flow() {
+        let mut flow = TradeFlow::new(Duration::from_secs(1));
+        assert_eq!(flow.imbalance(), None);
+
+        flow.update(dec("3"), false, 0);
+        flow.update(dec("1"), true, 500);
+        assert_eq!(flow.cumulative_delta(), dec("2"));
+        assert_eq!(flow.imbalance(), Some(dec("0.5")));
+
+        // the buy leaves the window, the cumulative delta is kept
+        flow.update(dec("1"), true, 1_000);
+        assert_eq!(flow.cumulative_delta(), dec("1"));
+        assert_eq!(flow.delta(), dec("-2"));
+        assert_eq!(flow.imbalance(), Some(dec("-1")));
+    }
+
+    #[test]
+    fn trade_flow_tracker() {
+        let trade = |symbol: Symbol, is_market_maker: bool| crate::messages::Trade {
+            event_time: 0,
+            symbol,
+            trade_id: 1,
+            price: dec("1"),
+            quantity: dec("2"),
+            trade_time: 0,
+            is_market_maker,
+        };
+
+        let mut tracker = TradeFlowTracker::new(Duration::from_secs(60));
+        tracker.push(&trade(Symbol::BTCUSDT, false));
+        let update = tracker
+            .push_message(&Message::Trade(trade(Symbol::ETHUSDT, true)))
+            .unwrap();
+
+        assert_eq!(update.symbol, Symbol::ETHUSDT);
+        assert_eq!(update.cumulative_delta, dec("-2"));
+        assert_eq!(
+            tracker.flow(&Symbol::BTCUSDT).unwrap().cumulative_delta(),
+            dec("2")
+        );
+    }
+
+    #[test]
+    fn sampled_mid() {
+        let mut sampler = MidSampler::new(Duration::from_millis(100));
+        assert!(sampler.sample(1_000).is_empty());
+
+        assert!(sampler
+            .update(&ticker(Symbol::BTCUSDT, "9", "11"), 1_050)
+            .is_empty());
+        assert!(sampler
+            .update(&ticker(Symbol::ETHUSDT, "1", "3"), 1_080)
+            .is_empty());
+
+        // 1_100 and 1_200 are carried forward before the update at 1_250
+        let samples = sampler.update(&ticker(Symbol::BTCUSDT, "19", "21"), 1_250);
+        assert_eq!(samples.len(), 4);
+        assert_eq!(
+            samples[0],
+            MidSample {
+                symbol: Symbol::BTCUSDT,
+                time: 1_100,
+                mid: dec("10")
+            }
+        );
+        assert_eq!(samples[3].time, 1_200);
+
+        let samples = sampler.sample(1_300);
+        assert_eq!(samples.len(), 2);
+        assert_eq!(samples[0].mid, dec("20"));
+        assert_eq!(samples[1].mid, dec("2"));
+    }
+
+    #[test]
+    fn session_twap() {
+        let mut twap = Twap::session();
+        assert_eq!(twap.update(dec("10"), 0), Some(dec("10")));
+        assert_eq!(twap.update(dec("20"), 100), Some(dec("10")));
+        // 10 for 100ms and 20 for 300ms
+        assert_eq!(twap.value(400), Some(dec("17.5")));
+    }
+
+    #[test]
+    fn rolling_twap() {
+        let mut twap = Twap::rolling(Duration::from_millis(100));
+        twap.update(dec("10"), 0);
+        twap.update(dec("20"), 150);
+        // window 100..200, 10 for 50ms and 20 for 50ms
+        assert_eq!(twap.value(200), Some(dec("15")));
+
+        twap.evict(300);
+        assert_eq!(twap.value(300), Some(dec("20")));
+    }
+}
@@ -0,0 +1,152 @@
+//! Parsers turning websocket frames into [`Message`]s.
+//!
+//! [`BinanceApi`](crate::BinanceApi) parses with [`SerdeJson`], or [`SimdJson`] with the
+//! `simd-json` feature. Another [`JsonBackend`] is set with
+//! [`BinanceApiBuilder::json_backend()`](crate::BinanceApiBuilder::json_backend), e.g. a hand
+//! written parser for the hot message types that falls back to serde for the others:
+//! ```
+//! use binance_api_async::json::{JsonBackend, SerdeJson};
+//! use binance_api_async::{BinanceApi, Message};
+//!
+//! #[derive(Debug)]
+//! struct TradesByHand;
+//!
+//! impl JsonBackend for TradesByHand {
+//!     fn parse(&self, text: String) -> Result<Message, binance_api_async::Error> {
+//!         if text.starts_with(r#"{"e":"trade""#) {
+//!             // parse the trade by hand
+//!         }
+//!         SerdeJson.parse(text)
+//!     }
+//! }
+//!
+//! let api = BinanceApi::builder().json_backend(TradesByHand).build();
+//! ```
+
+use std::fmt::Debug;
+use std::sync::Arc;
+
+#[cfg(feature = "simd-json")]
+use crate::messages;
+use crate::Message;
+
+/// Parses websocket text frames, see the [module](self) documentation.
+pub trait JsonBackend: Debug + Send + Sync {
+    /// Parse a text frame, the frame is consumed so it can be parsed in place.
+    fn parse(&self, text: String) -> crate::Result<Message>;
+}
+
+/// Parses with serde_json.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SerdeJson;
+
+impl JsonBackend for SerdeJson {
+    fn parse(&self, text: String) -> crate::Result<Message> {
+        Message::from_json(&text).map_err(|e| crate::Error::Parse {
+            payload: text,
+            source: Box::new(e.into()),
+        })
+    }
+}
+
+/// Parses with simd-json, in place.
+///
+/// The message type is read with serde_json first, like [`Message::from_json()`] does. Strings
+/// are unescaped in place, in the payload of a parse error too.
+#[cfg(feature = "simd-json")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SimdJson;
+
+#[cfg(feature = "simd-json")]
+impl JsonBackend for SimdJson {
+    fn parse(&self, text: String) -> crate::Result<Message> {
+        let message_type = match messages::message_type(&text) {
+            Ok(message_type) => message_type,
+            Err(e) => {
+                return Err(crate::Error::Parse {
+                    payload: text,
+                    source: Box::new(e.into()),
+                })
+            }
+        };
+        let mut bytes = text.into_bytes();
+        messages::parse_message(message_type, bytes.as_mut_slice()).map_err(|e| {
+            crate::Error::Parse {
+                payload: String::from_utf8_lossy(&bytes).into_owned(),
+                source: Box::new(e.into()),
+            }
+        })
+    }
+}
+
+#[cfg(feature = "simd-json")]
+impl messages::Payload for &mut [u8] {
+    type Error = simd_json::Error;
+
+    fn parse<T: serde::de::DeserializeOwned>(self) -> Result<T, Self::Error> {
+        simd_json::from_slice(self)
+    }
+}
+
+/// [`SimdJson`] with the `simd-json` feature, [`SerdeJson`] otherwise.
+#[cfg(feature = "simd-json")]
+pub type DefaultBackend = SimdJson;
+
+/// [`SimdJson`] with the `simd-json` feature, [`SerdeJson`] otherwise.
+#[cfg(not(feature = "simd-json"))]
+pub type DefaultBackend = SerdeJson;
+
+pub(crate) fn default_backend() -> Arc<dyn JsonBackend> {
+    Arc::new(DefaultBackend::default())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_util::{ticker, Action, MockServer};
+    use crate::{BinanceApi, Symbol};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Counts the frames it parses.
+    #[derive(Debug, Default)]
+    struct Counting(AtomicUsize);
+
+    impl JsonBackend for Arc<Counting> {
+        fn parse(&self, text: String) -> crate::Result<Message> {
+            self.0.fetch_add(1, Ordering::Relaxed);
+            SerdeJson.parse(text)
+        }
+    }
+
+    #[tokio::test]
+    async fn custom_backend() {
+        let ticker = Message::BookTicker(ticker(Symbol::BTCUSDT, "1", "2"));
+        let server = MockServer::start().await.unwrap();
+        server.script([Action::message(&ticker), Action::message(&ticker)]);
+
+        let counting = Arc::new(Counting::default());
+        let mut api = BinanceApi::builder()
+            .url(&server.url())
+            .allow_plaintext(true)
+            .json_backend(counting.clone())
+            .build();
+        api.connect().await.unwrap();
+        api.subscribe(
+            &[crate::SubscribeInfo::new(
+                Symbol::BTCUSDT,
+                crate::Feed::BookTicker,
+            )],
+            None,
+        )
+        .await
+        .unwrap();
+
+        assert!(matches!(
+            api.next_message().await,
+            Some(Message::SubscribeSuccess { .. })
+        ));
+        assert_eq!(api.next_message().await, Some(ticker.clone()));
+        assert_eq!(api.next_message().await, Some(ticker));
+        assert_eq!(counting.0.load(Ordering::Relaxed), 3);
+    }
+}
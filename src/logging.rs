@@ -0,0 +1,96 @@
+//! Tracing targets and spans of the crate.
+//!
+//! Events are logged to a few targets, so they can be filtered separately, e.g. with
+//! tracing-subscriber's `EnvFilter`:
+//! ```text
+//! RUST_LOG=info,binance_api_async::parse=error,binance_api_async::relay=warn
+//! ```
+//! Events of the other modules are logged to the path of their module, e.g.
+//! `binance_api_async::relay`.
+//!
+//! Events of a [`BinanceApi`](crate::BinanceApi) are logged in a `connection` span with the
+//! `url` and a process unique `conn` id. Within it, connects are in a `connect` span and
+//! requests in a `subscribe` or `unsubscribe` span with the request `id` and `streams`, which
+//! stays open until [`BinanceApi::next_message()`](crate::BinanceApi::next_message) receives
+//! the acknowledgement of the request. Exported with e.g. tracing-opentelemetry, the spans
+//! time the connects and the round trips of requests.
+//!
+//! Warnings that could be logged for every frame, like frames that do not parse, are logged at
+//! most once per [`WARN_INTERVAL`] for each connection, with the number held back since the
+//! last one.
+
+use std::time::Duration;
+
+use web_time::Instant;
+
+/// Connecting, disconnecting, pings and close frames.
+pub const CONNECTION: &str = "binance_api_async::connection";
+/// Subscribe and unsubscribe requests.
+pub const SUBSCRIPTION: &str = "binance_api_async::subscription";
+/// Frames that could not be parsed.
+pub const PARSE: &str = "binance_api_async::parse";
+
+/// Shortest time between two warnings of the same kind that could be logged for every frame.
+pub const WARN_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Lets through one warning per interval, counting the ones held back.
+#[derive(Debug)]
+pub(crate) struct RateLimited {
+    interval: Duration,
+    last: Option<Instant>,
+    suppressed: u64,
+}
+
+impl Default for RateLimited {
+    fn default() -> Self {
+        Self::new(WARN_INTERVAL)
+    }
+}
+
+impl RateLimited {
+    pub(crate) fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            last: None,
+            suppressed: 0,
+        }
+    }
+
+    /// `Some` with the number of warnings held back since the last one if a warning may be
+    /// logged now, `None` if it is held back.
+    pub(crate) fn allow(&mut self) -> Option<u64> {
+        let now = Instant::now();
+        match self.last {
+            Some(last) if now.duration_since(last) < self.interval => {
+                self.suppressed += 1;
+                None
+            }
+            _ => {
+                self.last = Some(now);
+                Some(std::mem::take(&mut self.suppressed))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn rate_limited() {
+        let mut limited = RateLimited::new(Duration::from_secs(3600));
+        assert_eq!(limited.allow(), Some(0));
+        assert_eq!(limited.allow(), None);
+        assert_eq!(limited.allow(), None);
+
+        let mut every = RateLimited::new(Duration::ZERO);
+        assert_eq!(every.allow(), Some(0));
+        assert_eq!(every.allow(), Some(0));
+
+        // the count held back is reported with the next warning let through
+        limited.interval = Duration::ZERO;
+        assert_eq!(limited.allow(), Some(2));
+        assert_eq!(limited.allow(), Some(0));
+    }
+}
@@ -1,8 +1,6 @@
 mod historical;
 
-use binance_api_async::{
-    messages, BinanceApi, Delay, DepthLevel, Feed, Message, SubscribeInfo, Symbol,
-};
+use binance_api_async::{BinanceApi, Delay, DepthLevel, Feed, Message, SubscribeInfo, Symbol};
 
 use tokio::time::MissedTickBehavior;
 use tracing::{error, info};
@@ -37,7 +35,7 @@ pub async fn main() -> Result<()> {
     reconnection_timer.set_missed_tick_behavior(MissedTickBehavior::Burst);
     reconnection_timer.tick().await;
 
-    api.subscribe(&symbols, None).await;
+    api.subscribe(&symbols, None).await?;
 
     loop {
         tokio::select! {
@@ -48,8 +46,14 @@ pub async fn main() -> Result<()> {
                     Some(msg) => {
                         match msg {
                             Message::AggTrade(_at) => {}
-                            Message::PartialDepth(pd)=>{},
+                            Message::Trade(_t) => {}
+                            Message::PartialDepth(_pd)=>{},
                             Message::BookTicker(bt) => {println!("{bt:?}")}
+                            Message::DepthUpdate(_du) => {}
+                            Message::FuturesDepthUpdate(_du) => {}
+                            Message::Kline(_k) => {}
+                            Message::ContinuousKline(_k) => {}
+                            Message::Error(e) => {error!("Request failed: {e:?}")},
                             Message::SubscribeSuccess { .. } => {info!("Successfully subscribed!")},
                         }
                     },
@@ -85,34 +89,7 @@ pub async fn try_reconnect(api: &mut BinanceApi, symbols: &[SubscribeInfo]) -> R
     }
     info!("Successfully reconnected!");
     info!("Subscribing...");
-    api.subscribe(symbols, None).await;
+    api.subscribe(symbols, None).await?;
 
     Ok(())
 }
-
-const CLEAR: &str = "\x1B[2J\x1B[1;1H";
-
-fn display_ob(book: &messages::PartialDepth) {
-    let (best_bid, best_ask) = (book.bids.first().unwrap()[0], book.asks.first().unwrap()[0]);
-
-    let midprice = (best_bid + best_ask) / rust_decimal::Decimal::TWO;
-    let dollar_spread = best_ask - best_bid;
-    let spread = dollar_spread / best_ask * rust_decimal::Decimal::ONE_HUNDRED;
-
-    let bids = book.bids.iter();
-    let asks = book.asks.iter();
-
-    print!("{CLEAR}");
-
-    println!(" Mid Price: {midprice} Dollar spread: {dollar_spread} Spread: {spread:.3}% ");
-
-    for (bid, ask) in bids.zip(asks) {
-        println!(
-            "{bidvolume:.5} {bidprice:.5} - {askprice:.5} {askvolume:.5}",
-            bidprice = bid[0],
-            bidvolume = bid[1],
-            askprice = ask[0],
-            askvolume = ask[1]
-        );
-    }
-}
@@ -0,0 +1,495 @@
+//! Borrowed messages, for hot paths that only read a few fields.
+//!
+//! A [`MessageRef`] points into the websocket frame it was parsed from: symbols are string
+//! slices and decimals are only parsed when asked for, see [`RawDecimal`]. Parsing does not
+//! allocate except for the levels of depth messages.
+//! ```
+//! use binance_api_async::MessageRef;
+//!
+//! let frame = r#"{"e":"trade","E":1,"s":"BTCUSDT","t":7,"p":"97000.10","q":"0.5","T":1,"m":true}"#;
+//! let Ok(MessageRef::Trade(trade)) = MessageRef::parse(frame) else {
+//!     panic!("not a trade");
+//! };
+//! assert_eq!(trade.symbol, "BTCUSDT");
+//! assert_eq!(trade.price.as_str(), "97000.10");
+//! assert_eq!(trade.quantity.parse().unwrap().to_string(), "0.5");
+//! ```
+
+use rust_decimal::Decimal;
+use serde::de::IntoDeserializer;
+use serde::Deserialize;
+
+#[cfg(feature = "trade")]
+use crate::messages::{AggTrade, Trade};
+#[cfg(feature = "book-ticker")]
+use crate::messages::BookTicker;
+#[cfg(feature = "depth")]
+use crate::messages::{DepthUpdate, FuturesDepthUpdate, PartialDepth};
+#[cfg(feature = "kline")]
+use crate::messages::{ContinuousKline, Kline, KlineData};
+use crate::messages::{message_type, ErrorResponse};
+#[cfg(feature = "kline")]
+use crate::ContractType;
+use crate::{Message, Symbol};
+
+/// A decimal as sent by Binance, parsed on demand.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(transparent)]
+pub struct RawDecimal<'a>(&'a str);
+
+impl<'a> RawDecimal<'a> {
+    /// The decimal as text, e.g. `"0.01000000"`.
+    pub fn as_str(&self) -> &'a str {
+        self.0
+    }
+
+    /// Parse the decimal.
+    pub fn parse(&self) -> Result<Decimal, rust_decimal::Error> {
+        self.0.parse()
+    }
+}
+
+/// Borrowed [`Message`], see the [module](self) documentation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MessageRef<'a> {
+    #[cfg(feature = "trade")]
+    AggTrade(AggTradeRef<'a>),
+    #[cfg(feature = "trade")]
+    Trade(TradeRef<'a>),
+    #[cfg(feature = "depth")]
+    PartialDepth(PartialDepthRef<'a>),
+    #[cfg(feature = "book-ticker")]
+    BookTicker(BookTickerRef<'a>),
+    #[cfg(feature = "depth")]
+    DepthUpdate(DepthUpdateRef<'a>),
+    #[cfg(feature = "depth")]
+    FuturesDepthUpdate(FuturesDepthUpdateRef<'a>),
+    #[cfg(feature = "kline")]
+    Kline(KlineRef<'a>),
+    #[cfg(feature = "kline")]
+    ContinuousKline(ContinuousKlineRef<'a>),
+    /// Owned, error replies are rare.
+    Error(ErrorResponse),
+    SubscribeSuccess { result: Option<&'a str>, id: u64 },
+}
+
+/// Reply to a request, other fields are not expected.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SubscribeSuccessRef<'a> {
+    #[serde(borrow)]
+    result: Option<&'a str>,
+    id: u64,
+}
+
+impl<'a> MessageRef<'a> {
+    /// Parse a websocket frame, the message borrows from `frame`.
+    ///
+    /// The message type is told like [`Message::from_json()`] tells it.
+    pub fn parse(frame: &'a str) -> crate::Result<Self> {
+        let msg = match message_type(frame)? {
+            #[cfg(feature = "trade")]
+            "aggTrade" => MessageRef::AggTrade(serde_json::from_str(frame)?),
+            #[cfg(feature = "trade")]
+            "trade" => MessageRef::Trade(serde_json::from_str(frame)?),
+            #[cfg(feature = "depth")]
+            "partialDepth" => MessageRef::PartialDepth(serde_json::from_str(frame)?),
+            #[cfg(feature = "depth")]
+            "depthUpdate" => MessageRef::DepthUpdate(serde_json::from_str(frame)?),
+            #[cfg(feature = "depth")]
+            "futuresDepthUpdate" => MessageRef::FuturesDepthUpdate(serde_json::from_str(frame)?),
+            #[cfg(feature = "book-ticker")]
+            "bookTicker" => MessageRef::BookTicker(serde_json::from_str(frame)?),
+            #[cfg(feature = "kline")]
+            "kline" => MessageRef::Kline(serde_json::from_str(frame)?),
+            #[cfg(feature = "kline")]
+            "continuous_kline" => MessageRef::ContinuousKline(serde_json::from_str(frame)?),
+            "error" => MessageRef::Error(serde_json::from_str(frame)?),
+            "subscribeSuccess" => {
+                let SubscribeSuccessRef { result, id } = serde_json::from_str(frame)?;
+                MessageRef::SubscribeSuccess { result, id }
+            }
+            other => {
+                return Err(crate::Error::Custom(format!("unknown event type {other}")));
+            }
+        };
+        Ok(msg)
+    }
+
+    /// Symbol of the message, see [`Message::symbol()`].
+    pub fn symbol(&self) -> Option<&'a str> {
+        match self {
+            #[cfg(feature = "trade")]
+            MessageRef::AggTrade(t) => Some(t.symbol),
+            #[cfg(feature = "trade")]
+            MessageRef::Trade(t) => Some(t.symbol),
+            #[cfg(feature = "book-ticker")]
+            MessageRef::BookTicker(bt) => Some(bt.symbol),
+            #[cfg(feature = "depth")]
+            MessageRef::DepthUpdate(du) => Some(du.symbol),
+            #[cfg(feature = "depth")]
+            MessageRef::FuturesDepthUpdate(du) => Some(du.symbol),
+            #[cfg(feature = "kline")]
+            MessageRef::Kline(k) => Some(k.symbol),
+            #[cfg(feature = "kline")]
+            MessageRef::ContinuousKline(k) => Some(k.pair),
+            #[cfg(feature = "depth")]
+            MessageRef::PartialDepth(_) => None,
+            MessageRef::Error(_) => None,
+            MessageRef::SubscribeSuccess { .. } => None,
+        }
+    }
+
+    /// Name of the message type, see [`Message::event_type()`].
+    pub fn event_type(&self) -> &'static str {
+        match self {
+            #[cfg(feature = "trade")]
+            MessageRef::AggTrade(_) => "aggTrade",
+            #[cfg(feature = "trade")]
+            MessageRef::Trade(_) => "trade",
+            #[cfg(feature = "depth")]
+            MessageRef::PartialDepth(_) => "partialDepth",
+            #[cfg(feature = "book-ticker")]
+            MessageRef::BookTicker(_) => "bookTicker",
+            #[cfg(feature = "depth")]
+            MessageRef::DepthUpdate(_) => "depthUpdate",
+            #[cfg(feature = "depth")]
+            MessageRef::FuturesDepthUpdate(_) => "futuresDepthUpdate",
+            #[cfg(feature = "kline")]
+            MessageRef::Kline(_) => "kline",
+            #[cfg(feature = "kline")]
+            MessageRef::ContinuousKline(_) => "continuous_kline",
+            MessageRef::Error(_) => "error",
+            MessageRef::SubscribeSuccess { .. } => "subscribeSuccess",
+        }
+    }
+
+    /// Parse the remaining fields into an owned [`Message`].
+    pub fn to_message(&self) -> crate::Result<Message> {
+        let msg = match self {
+            #[cfg(feature = "trade")]
+            MessageRef::AggTrade(t) => Message::AggTrade(AggTrade {
+                event_time: t.event_time,
+                trade_id: t.trade_id,
+                symbol: parse_symbol(t.symbol)?,
+                price: t.price.parse()?,
+                quantity: t.quantity.parse()?,
+                first_trade_id: t.first_trade_id,
+                last_trade_id: t.last_trade_id,
+                trade_time: t.trade_time,
+                is_market_maker: t.is_market_maker,
+            }),
+            #[cfg(feature = "trade")]
+            MessageRef::Trade(t) => Message::Trade(Trade {
+                event_time: t.event_time,
+                symbol: parse_symbol(t.symbol)?,
+                trade_id: t.trade_id,
+                price: t.price.parse()?,
+                quantity: t.quantity.parse()?,
+                trade_time: t.trade_time,
+                is_market_maker: t.is_market_maker,
+            }),
+            #[cfg(feature = "depth")]
+            MessageRef::PartialDepth(pd) => Message::PartialDepth(Box::new(PartialDepth {
+                last_update_id: pd.last_update_id,
+                bids: parse_levels(&pd.bids)?,
+                asks: parse_levels(&pd.asks)?,
+            })),
+            #[cfg(feature = "book-ticker")]
+            MessageRef::BookTicker(bt) => Message::BookTicker(BookTicker {
+                update_id: bt.update_id,
+                symbol: parse_symbol(bt.symbol)?,
+                best_bid_price: bt.best_bid_price.parse()?,
+                best_bid_qty: bt.best_bid_qty.parse()?,
+                best_ask_price: bt.best_ask_price.parse()?,
+                best_ask_qty: bt.best_ask_qty.parse()?,
+            }),
+            #[cfg(feature = "depth")]
+            MessageRef::DepthUpdate(du) => Message::DepthUpdate(DepthUpdate {
+                event_time: du.event_time,
+                symbol: parse_symbol(du.symbol)?,
+                first_update_id: du.first_update_id,
+                final_update_id: du.final_update_id,
+                bids: parse_levels(&du.bids)?,
+                asks: parse_levels(&du.asks)?,
+            }),
+            #[cfg(feature = "depth")]
+            MessageRef::FuturesDepthUpdate(du) => Message::FuturesDepthUpdate(FuturesDepthUpdate {
+                event_time: du.event_time,
+                transaction_time: du.transaction_time,
+                symbol: parse_symbol(du.symbol)?,
+                first_update_id: du.first_update_id,
+                final_update_id: du.final_update_id,
+                prev_final_update_id: du.prev_final_update_id,
+                bids: parse_levels(&du.bids)?,
+                asks: parse_levels(&du.asks)?,
+            }),
+            #[cfg(feature = "kline")]
+            MessageRef::Kline(k) => Message::Kline(Kline {
+                event_time: k.event_time,
+                symbol: parse_symbol(k.symbol)?,
+                kline: k.kline.to_kline_data()?,
+            }),
+            #[cfg(feature = "kline")]
+            MessageRef::ContinuousKline(k) => Message::ContinuousKline(ContinuousKline {
+                event_time: k.event_time,
+                pair: parse_symbol(k.pair)?,
+                contract_type: k.contract_type,
+                kline: k.kline.to_kline_data()?,
+            }),
+            MessageRef::Error(e) => Message::Error(e.clone()),
+            MessageRef::SubscribeSuccess { result, id } => Message::SubscribeSuccess {
+                result: result.map(str::to_string),
+                id: *id,
+            },
+        };
+        Ok(msg)
+    }
+}
+
+fn parse_symbol(name: &str) -> crate::Result<Symbol> {
+    Symbol::deserialize(name.into_deserializer())
+        .map_err(|e: serde::de::value::Error| crate::Error::Custom(e.to_string()))
+}
+
+#[cfg(feature = "depth")]
+fn parse_levels<C: FromIterator<[Decimal; 2]>>(levels: &[[RawDecimal; 2]]) -> crate::Result<C> {
+    levels
+        .iter()
+        .map(|[price, qty]| Ok([price.parse()?, qty.parse()?]))
+        .collect()
+}
+
+#[cfg(feature = "trade")]
+/// Borrowed [`AggTrade`].
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct AggTradeRef<'a> {
+    #[serde(rename = "E")]
+    pub event_time: u64,
+    #[serde(rename = "a")]
+    pub trade_id: u64,
+    #[serde(rename = "s")]
+    pub symbol: &'a str,
+    #[serde(rename = "p", borrow)]
+    pub price: RawDecimal<'a>,
+    #[serde(rename = "q", borrow)]
+    pub quantity: RawDecimal<'a>,
+    #[serde(rename = "f")]
+    pub first_trade_id: u64,
+    #[serde(rename = "l")]
+    pub last_trade_id: u64,
+    #[serde(rename = "T")]
+    pub trade_time: u64,
+    #[serde(rename = "m")]
+    pub is_market_maker: bool,
+}
+
+#[cfg(feature = "trade")]
+/// Borrowed [`Trade`].
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct TradeRef<'a> {
+    #[serde(rename = "E")]
+    pub event_time: u64,
+    #[serde(rename = "s")]
+    pub symbol: &'a str,
+    #[serde(rename = "t")]
+    pub trade_id: u64,
+    #[serde(rename = "p", borrow)]
+    pub price: RawDecimal<'a>,
+    #[serde(rename = "q", borrow)]
+    pub quantity: RawDecimal<'a>,
+    #[serde(rename = "T")]
+    pub trade_time: u64,
+    #[serde(rename = "m")]
+    pub is_market_maker: bool,
+}
+
+#[cfg(feature = "depth")]
+/// Borrowed [`PartialDepth`], levels are [price, volume].
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PartialDepthRef<'a> {
+    pub last_update_id: u64,
+    #[serde(borrow)]
+    pub bids: Vec<[RawDecimal<'a>; 2]>,
+    #[serde(borrow)]
+    pub asks: Vec<[RawDecimal<'a>; 2]>,
+}
+
+#[cfg(feature = "book-ticker")]
+/// Borrowed [`BookTicker`].
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct BookTickerRef<'a> {
+    #[serde(rename = "u")]
+    pub update_id: u64,
+    #[serde(rename = "s")]
+    pub symbol: &'a str,
+    #[serde(rename = "b", borrow)]
+    pub best_bid_price: RawDecimal<'a>,
+    #[serde(rename = "B", borrow)]
+    pub best_bid_qty: RawDecimal<'a>,
+    #[serde(rename = "a", borrow)]
+    pub best_ask_price: RawDecimal<'a>,
+    #[serde(rename = "A", borrow)]
+    pub best_ask_qty: RawDecimal<'a>,
+}
+
+#[cfg(feature = "depth")]
+/// Borrowed [`DepthUpdate`], levels are [price, volume].
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct DepthUpdateRef<'a> {
+    #[serde(rename = "E")]
+    pub event_time: u64,
+    #[serde(rename = "s")]
+    pub symbol: &'a str,
+    #[serde(rename = "U")]
+    pub first_update_id: u64,
+    #[serde(rename = "u")]
+    pub final_update_id: u64,
+    #[serde(rename = "b", borrow)]
+    pub bids: Vec<[RawDecimal<'a>; 2]>,
+    #[serde(rename = "a", borrow)]
+    pub asks: Vec<[RawDecimal<'a>; 2]>,
+}
+
+#[cfg(feature = "depth")]
+/// Borrowed [`FuturesDepthUpdate`], levels are [price, volume].
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct FuturesDepthUpdateRef<'a> {
+    #[serde(rename = "E")]
+    pub event_time: u64,
+    #[serde(rename = "T")]
+    pub transaction_time: u64,
+    #[serde(rename = "s")]
+    pub symbol: &'a str,
+    #[serde(rename = "U")]
+    pub first_update_id: u64,
+    #[serde(rename = "u")]
+    pub final_update_id: u64,
+    #[serde(rename = "pu")]
+    pub prev_final_update_id: u64,
+    #[serde(rename = "b", borrow)]
+    pub bids: Vec<[RawDecimal<'a>; 2]>,
+    #[serde(rename = "a", borrow)]
+    pub asks: Vec<[RawDecimal<'a>; 2]>,
+}
+
+#[cfg(feature = "kline")]
+/// Borrowed [`Kline`].
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct KlineRef<'a> {
+    #[serde(rename = "E")]
+    pub event_time: u64,
+    #[serde(rename = "s")]
+    pub symbol: &'a str,
+    #[serde(rename = "k", borrow)]
+    pub kline: KlineDataRef<'a>,
+}
+
+#[cfg(feature = "kline")]
+/// Borrowed [`KlineData`].
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct KlineDataRef<'a> {
+    #[serde(rename = "t")]
+    pub open_time: u64,
+    #[serde(rename = "T")]
+    pub close_time: u64,
+    #[serde(rename = "i")]
+    pub interval: &'a str,
+    #[serde(rename = "f")]
+    pub first_trade_id: i64,
+    #[serde(rename = "L")]
+    pub last_trade_id: i64,
+    #[serde(rename = "o", borrow)]
+    pub open: RawDecimal<'a>,
+    #[serde(rename = "c", borrow)]
+    pub close: RawDecimal<'a>,
+    #[serde(rename = "h", borrow)]
+    pub high: RawDecimal<'a>,
+    #[serde(rename = "l", borrow)]
+    pub low: RawDecimal<'a>,
+    #[serde(rename = "v", borrow)]
+    pub volume: RawDecimal<'a>,
+    #[serde(rename = "n")]
+    pub trades: u64,
+    #[serde(rename = "x")]
+    pub is_closed: bool,
+    #[serde(rename = "q", borrow)]
+    pub quote_volume: RawDecimal<'a>,
+    #[serde(rename = "V", borrow)]
+    pub taker_buy_volume: RawDecimal<'a>,
+    #[serde(rename = "Q", borrow)]
+    pub taker_buy_quote_volume: RawDecimal<'a>,
+}
+
+#[cfg(feature = "kline")]
+impl KlineDataRef<'_> {
+    fn to_kline_data(&self) -> crate::Result<KlineData> {
+        Ok(KlineData {
+            open_time: self.open_time,
+            close_time: self.close_time,
+            interval: self.interval.to_string(),
+            first_trade_id: self.first_trade_id,
+            last_trade_id: self.last_trade_id,
+            open: self.open.parse()?,
+            close: self.close.parse()?,
+            high: self.high.parse()?,
+            low: self.low.parse()?,
+            volume: self.volume.parse()?,
+            trades: self.trades,
+            is_closed: self.is_closed,
+            quote_volume: self.quote_volume.parse()?,
+            taker_buy_volume: self.taker_buy_volume.parse()?,
+            taker_buy_quote_volume: self.taker_buy_quote_volume.parse()?,
+        })
+    }
+}
+
+#[cfg(feature = "kline")]
+/// Borrowed [`ContinuousKline`].
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct ContinuousKlineRef<'a> {
+    #[serde(rename = "E")]
+    pub event_time: u64,
+    #[serde(rename = "ps")]
+    pub pair: &'a str,
+    #[serde(rename = "ct")]
+    pub contract_type: ContractType,
+    #[serde(rename = "k", borrow)]
+    pub kline: KlineDataRef<'a>,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn same_as_owned_messages() {
+        let dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures");
+        for entry in std::fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            let payload = std::fs::read_to_string(&path).unwrap();
+            let name = path.display();
+
+            let msg: Message = serde_json::from_str(&payload).unwrap();
+            let msg_ref = MessageRef::parse(&payload)
+                .unwrap_or_else(|e| panic!("{name} does not parse: {e}"));
+            assert_eq!(msg_ref.event_type(), msg.event_type(), "{name}");
+            assert_eq!(
+                msg_ref.symbol().map(str::to_lowercase),
+                msg.symbol().map(Symbol::to_string),
+                "{name}"
+            );
+            assert_eq!(msg_ref.to_message().unwrap(), msg, "{name}");
+        }
+    }
+
+    #[test]
+    fn unknown_messages() {
+        assert!(MessageRef::parse(r#"{"e":"24hrTicker","s":"BTCUSDT"}"#).is_err());
+        assert!(MessageRef::parse(r#"{"s":"BTCUSDT"}"#).is_err());
+        assert!(MessageRef::parse(r#"{"e":"trade""#).is_err());
+        // replies of the websocket api are not subscribe replies
+        assert!(MessageRef::parse(r#"{"id":2,"status":200,"result":null}"#).is_err());
+    }
+}
@@ -1,19 +1,277 @@
 //! My messages will go here. If any messages are missing or have changed, please submit a pull
 //! request or create an issue.
 
+#[cfg(feature = "kline")]
+use super::ContractType;
 use super::Symbol;
+use crate::metrics::MESSAGE_TYPES;
 use rust_decimal::Decimal;
+use serde::de::IgnoredAny;
+use std::borrow::Cow;
+#[cfg(feature = "depth")]
+use serde::de::{DeserializeSeed, MapAccess, SeqAccess, Visitor};
+#[cfg(feature = "depth")]
+use serde::Deserializer;
 use serde::{Deserialize, Serialize};
+#[cfg(feature = "depth")]
+use smallvec::SmallVec;
 
 /// Messages returned by the stream, 
 /// require that you subscribe to the correct feed first.
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+///
+/// The message types are enabled by the `trade`, `depth`, `book-ticker` and `kline` features,
+/// all on by default. Payloads of disabled types are not parsed.
+///
+/// Payloads are told apart by their event type `e`, and without one by a field only their
+/// message type has, see [`Message::event_type()`]. Messages are serialized without the event
+/// type, and parse to the same message again.
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[serde(untagged)]
 pub enum Message {
+    #[cfg(feature = "trade")]
     AggTrade(AggTrade),
-    PartialDepth(PartialDepth),
+    #[cfg(feature = "trade")]
+    Trade(Trade),
+    #[cfg(feature = "depth")]
+    /// Boxed, its levels are inline, see [`DepthLevels`].
+    PartialDepth(Box<PartialDepth>),
+    #[cfg(feature = "book-ticker")]
     BookTicker(BookTicker),
-    SubscribeSuccess { result: Option<String>, id: u8 },
+    #[cfg(feature = "depth")]
+    DepthUpdate(DepthUpdate),
+    #[cfg(feature = "depth")]
+    FuturesDepthUpdate(FuturesDepthUpdate),
+    #[cfg(feature = "kline")]
+    Kline(Kline),
+    #[cfg(feature = "kline")]
+    ContinuousKline(ContinuousKline),
+    /// Error reply to a request, e.g. a subscribe to an invalid stream.
+    ///
+    /// Before [`Message::SubscribeSuccess`], error replies have an `id` too.
+    Error(ErrorResponse),
+    SubscribeSuccess { result: Option<String>, id: u64 },
+}
+
+impl Message {
+    /// Symbol of the message, `None` for messages without one like [`PartialDepth`].
+    pub fn symbol(&self) -> Option<&Symbol> {
+        match self {
+            #[cfg(feature = "trade")]
+            Message::AggTrade(t) => Some(&t.symbol),
+            #[cfg(feature = "trade")]
+            Message::Trade(t) => Some(&t.symbol),
+            #[cfg(feature = "book-ticker")]
+            Message::BookTicker(bt) => Some(&bt.symbol),
+            #[cfg(feature = "depth")]
+            Message::DepthUpdate(du) => Some(&du.symbol),
+            #[cfg(feature = "depth")]
+            Message::FuturesDepthUpdate(du) => Some(&du.symbol),
+            #[cfg(feature = "kline")]
+            Message::Kline(k) => Some(&k.symbol),
+            #[cfg(feature = "kline")]
+            Message::ContinuousKline(k) => Some(&k.pair),
+            #[cfg(feature = "depth")]
+            Message::PartialDepth(_) => None,
+            Message::Error(_) => None,
+            Message::SubscribeSuccess { .. } => None,
+        }
+    }
+
+    /// Name of the message type, the Binance event type where there is one, e.g. `aggTrade`.
+    pub fn event_type(&self) -> &'static str {
+        match self {
+            #[cfg(feature = "trade")]
+            Message::AggTrade(_) => "aggTrade",
+            #[cfg(feature = "trade")]
+            Message::Trade(_) => "trade",
+            #[cfg(feature = "depth")]
+            Message::PartialDepth(_) => "partialDepth",
+            #[cfg(feature = "book-ticker")]
+            Message::BookTicker(_) => "bookTicker",
+            #[cfg(feature = "depth")]
+            Message::DepthUpdate(_) => "depthUpdate",
+            #[cfg(feature = "depth")]
+            Message::FuturesDepthUpdate(_) => "futuresDepthUpdate",
+            #[cfg(feature = "kline")]
+            Message::Kline(_) => "kline",
+            #[cfg(feature = "kline")]
+            Message::ContinuousKline(_) => "continuous_kline",
+            Message::Error(_) => "error",
+            Message::SubscribeSuccess { .. } => "subscribeSuccess",
+        }
+    }
+
+    /// Binance event time in milliseconds since epoch, `None` for messages without one like
+    /// [`BookTicker`].
+    pub fn event_time(&self) -> Option<u64> {
+        match self {
+            #[cfg(feature = "trade")]
+            Message::AggTrade(t) => Some(t.event_time),
+            #[cfg(feature = "trade")]
+            Message::Trade(t) => Some(t.event_time),
+            #[cfg(feature = "depth")]
+            Message::DepthUpdate(du) => Some(du.event_time),
+            #[cfg(feature = "depth")]
+            Message::FuturesDepthUpdate(du) => Some(du.event_time),
+            #[cfg(feature = "kline")]
+            Message::Kline(k) => Some(k.event_time),
+            #[cfg(feature = "kline")]
+            Message::ContinuousKline(k) => Some(k.event_time),
+            #[cfg(feature = "depth")]
+            Message::PartialDepth(_) => None,
+            #[cfg(feature = "book-ticker")]
+            Message::BookTicker(_) => None,
+            Message::Error(_) => None,
+            Message::SubscribeSuccess { .. } => None,
+        }
+    }
+
+    /// Parse a websocket text frame with the [`DefaultBackend`](crate::json::DefaultBackend).
+    ///
+    /// Uses simd-json with the `simd-json` feature, which parses in place and consumes `text`.
+    pub fn from_frame(text: String) -> crate::Result<Message> {
+        use crate::json::JsonBackend;
+        crate::json::DefaultBackend::default().parse(text)
+    }
+
+    /// Parse a payload with serde_json.
+    ///
+    /// The fields telling the message types apart are read first, without allocating, then
+    /// the payload is parsed as its message type.
+    pub fn from_json(json: &str) -> serde_json::Result<Message> {
+        parse_message(message_type(json)?, json)
+    }
+}
+
+impl<'de> Deserialize<'de> for Message {
+    /// Reads the payload as raw json, then parses it like [`Message::from_json()`].
+    ///
+    /// Messages are json only, other formats are not supported.
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        use serde::de::Error;
+
+        let raw = Box::<serde_json::value::RawValue>::deserialize(deserializer)?;
+        Message::from_json(raw.get()).map_err(D::Error::custom)
+    }
+}
+
+/// A payload the message types are parsed from, parsed with serde_json from a `&str`.
+pub(crate) trait Payload {
+    type Error: serde::de::Error;
+
+    fn parse<T: serde::de::DeserializeOwned>(self) -> Result<T, Self::Error>;
+}
+
+impl Payload for &str {
+    type Error = serde_json::Error;
+
+    fn parse<T: serde::de::DeserializeOwned>(self) -> Result<T, Self::Error> {
+        serde_json::from_str(self)
+    }
+}
+
+/// Parse `payload` as the message type named `message_type`, see [`message_type()`].
+pub(crate) fn parse_message<P: Payload>(
+    message_type: &'static str,
+    payload: P,
+) -> Result<Message, P::Error> {
+    use serde::de::Error;
+
+    match message_type {
+        #[cfg(feature = "trade")]
+        "aggTrade" => payload.parse().map(Message::AggTrade),
+        #[cfg(feature = "trade")]
+        "trade" => payload.parse().map(Message::Trade),
+        #[cfg(feature = "depth")]
+        "partialDepth" => payload.parse().map(Message::PartialDepth),
+        #[cfg(feature = "book-ticker")]
+        "bookTicker" => payload.parse().map(Message::BookTicker),
+        #[cfg(feature = "depth")]
+        "depthUpdate" => payload.parse().map(Message::DepthUpdate),
+        #[cfg(feature = "depth")]
+        "futuresDepthUpdate" => payload.parse().map(Message::FuturesDepthUpdate),
+        #[cfg(feature = "kline")]
+        "kline" => payload.parse().map(Message::Kline),
+        #[cfg(feature = "kline")]
+        "continuous_kline" => payload.parse().map(Message::ContinuousKline),
+        "error" => payload.parse().map(Message::Error),
+        "subscribeSuccess" => payload
+            .parse()
+            .map(|SubscribeReply { result, id }| Message::SubscribeSuccess { result, id }),
+        other => Err(P::Error::custom(format_args!("unknown event type {other}"))),
+    }
+}
+
+/// Fields telling the message types apart, the others are skipped.
+#[derive(Deserialize)]
+struct Probe<'a> {
+    #[serde(rename = "e", borrow, default)]
+    event_type: Option<Cow<'a, str>>,
+    #[serde(rename = "lastUpdateId", default)]
+    last_update_id: Option<IgnoredAny>,
+    #[serde(default)]
+    k: Option<IgnoredAny>,
+    #[serde(default)]
+    ps: Option<IgnoredAny>,
+    #[serde(default)]
+    pu: Option<IgnoredAny>,
+    #[serde(rename = "U", default)]
+    first_update_id: Option<IgnoredAny>,
+    #[serde(default)]
+    f: Option<IgnoredAny>,
+    #[serde(default)]
+    t: Option<IgnoredAny>,
+    #[serde(rename = "B", default)]
+    best_bid_qty: Option<IgnoredAny>,
+    #[serde(default)]
+    error: Option<IgnoredAny>,
+    #[serde(default)]
+    code: Option<IgnoredAny>,
+    #[serde(default)]
+    id: Option<IgnoredAny>,
+}
+
+/// Message type of `json`, as [`Message::event_type()`] names it.
+///
+/// Payloads are told apart by their event type, and without one by the fields only their
+/// message type has. An untagged enum would take the first variant whose fields are present,
+/// e.g. a trade with the fields of an aggregate trade.
+pub(crate) fn message_type(json: &str) -> serde_json::Result<&'static str> {
+    use serde::de::Error;
+
+    let probe: Probe = serde_json::from_str(json)?;
+    let message_type = match probe.event_type.as_deref() {
+        // futures depth updates have the event type of spot ones
+        Some("depthUpdate") if probe.pu.is_some() => "futuresDepthUpdate",
+        Some(event_type) => MESSAGE_TYPES
+            .into_iter()
+            .find(|t| *t == event_type)
+            .ok_or_else(|| {
+                serde_json::Error::custom(format_args!("unknown event type {event_type}"))
+            })?,
+        // messages serialized by this crate have no event type either
+        None if probe.last_update_id.is_some() => "partialDepth",
+        None if probe.k.is_some() && probe.ps.is_some() => "continuous_kline",
+        None if probe.k.is_some() => "kline",
+        None if probe.pu.is_some() => "futuresDepthUpdate",
+        None if probe.first_update_id.is_some() => "depthUpdate",
+        None if probe.f.is_some() => "aggTrade",
+        None if probe.t.is_some() => "trade",
+        None if probe.best_bid_qty.is_some() => "bookTicker",
+        None if probe.error.is_some() || probe.code.is_some() => "error",
+        None if probe.id.is_some() => "subscribeSuccess",
+        None => return Err(serde_json::Error::custom(format_args!("unknown message {json}"))),
+    };
+    Ok(message_type)
+}
+
+/// Reply to a request, other fields are not expected.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SubscribeReply {
+    result: Option<String>,
+    id: u64,
 }
 
 impl std::fmt::Display for Message {
@@ -22,71 +280,706 @@ impl std::fmt::Display for Message {
     }
 }
 
+#[cfg(feature = "trade")]
 /// The Aggregate Trade Streams push trade information that is aggregated for a single taker order.
 /// Update Speed: Real-time
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize )]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct AggTrade {
 
-    #[serde(alias = "E")]
+    #[serde(rename = "E")]
     pub event_time: u64,
     
-    #[serde(alias = "a")]
+    #[serde(rename = "a")]
     pub trade_id: u64,
 
-    #[serde(alias = "s")]
+    #[serde(rename = "s")]
     pub symbol: Symbol,
 
-    #[serde(alias = "p")]
+    #[serde(rename = "p")]
     pub price: Decimal,
 
-    #[serde(alias = "q")]
+    #[serde(rename = "q")]
     pub quantity: Decimal,
 
-    #[serde(alias = "f")]
-    pub first_trade_id: u32,
+    #[serde(rename = "f")]
+    pub first_trade_id: u64,
 
-    #[serde(alias = "l")]
-    pub last_trade_id: u32,
+    #[serde(rename = "l")]
+    pub last_trade_id: u64,
 
-    #[serde(alias = "T")]
+    #[serde(rename = "T")]
     pub trade_time: u64,
 
-    #[serde(alias = "m")]
+    #[serde(rename = "m")]
     pub is_market_maker: bool,
 }
 
+#[cfg(feature = "trade")]
+/// The Trade Streams push raw trade information; each trade has a unique buyer and seller.
+/// Update Speed: Real-time
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Trade {
+    #[serde(rename = "E")]
+    pub event_time: u64,
+
+    #[serde(rename = "s")]
+    pub symbol: Symbol,
+
+    #[serde(rename = "t")]
+    pub trade_id: u64,
+
+    #[serde(rename = "p")]
+    pub price: Decimal,
+
+    #[serde(rename = "q")]
+    pub quantity: Decimal,
+
+    #[serde(rename = "T")]
+    pub trade_time: u64,
+
+    #[serde(rename = "m")]
+    pub is_market_maker: bool,
+}
+
+#[cfg(feature = "trade")]
+/// Common fields of [`AggTrade`] and [`Trade`],
+/// lets the aggregation in [`crate::aggregate`] consume either stream.
+pub trait TradeEvent {
+    fn symbol(&self) -> &Symbol;
+    fn price(&self) -> Decimal;
+    fn quantity(&self) -> Decimal;
+    /// Trade time in milliseconds since epoch.
+    fn trade_time(&self) -> u64;
+    /// True if the buyer was the market maker, i.e. the aggressor sold.
+    fn is_market_maker(&self) -> bool;
+}
+
+#[cfg(feature = "trade")]
+impl TradeEvent for AggTrade {
+    fn symbol(&self) -> &Symbol {
+        &self.symbol
+    }
+    fn price(&self) -> Decimal {
+        self.price
+    }
+    fn quantity(&self) -> Decimal {
+        self.quantity
+    }
+    fn trade_time(&self) -> u64 {
+        self.trade_time
+    }
+    fn is_market_maker(&self) -> bool {
+        self.is_market_maker
+    }
+}
+
+#[cfg(feature = "trade")]
+impl TradeEvent for Trade {
+    fn symbol(&self) -> &Symbol {
+        &self.symbol
+    }
+    fn price(&self) -> Decimal {
+        self.price
+    }
+    fn quantity(&self) -> Decimal {
+        self.quantity
+    }
+    fn trade_time(&self) -> u64 {
+        self.trade_time
+    }
+    fn is_market_maker(&self) -> bool {
+        self.is_market_maker
+    }
+}
+
+#[cfg(feature = "depth")]
+/// Levels of a [`PartialDepth`], books of up to 20 levels are stored without allocating.
+pub type DepthLevels = SmallVec<[[Decimal; 2]; 20]>;
+
+#[cfg(feature = "depth")]
 /// Current Value of the Orderbook
 /// Each level of Bids and Asks are Slices of length 2.
 ///
 /// Containing [price, volume] as a [`Decimal`]
 #[derive(Debug, Clone,  PartialEq, Eq, Serialize, Deserialize,)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[serde(rename_all = "camelCase")]
 pub struct PartialDepth {
     pub last_update_id: u64,
-    pub bids: Vec<[Decimal; 2]>,
-    pub asks: Vec<[Decimal; 2]>,
+    pub bids: DepthLevels,
+    pub asks: DepthLevels,
+}
+
+#[cfg(feature = "depth")]
+impl PartialDepth {
+    /// Parse a partial depth payload into `self`, reusing the level buffers.
+    ///
+    /// `self` is partly overwritten when the payload is invalid.
+    pub fn parse_into(&mut self, json: &str) -> crate::Result<()> {
+        parse_in_place(json, PartialDepthSeed(self))
+    }
+
+    /// Best bid as [price, volume], `None` if the bid side is empty.
+    pub fn best_bid(&self) -> Option<[Decimal; 2]> {
+        self.bids.first().copied()
+    }
+
+    /// Best ask as [price, volume], `None` if the ask side is empty.
+    pub fn best_ask(&self) -> Option<[Decimal; 2]> {
+        self.asks.first().copied()
+    }
+
+    /// Mid price between the best bid and best ask.
+    pub fn mid_price(&self) -> Option<Decimal> {
+        let (bid, ask) = (self.best_bid()?, self.best_ask()?);
+        Some(mid_price(bid[0], ask[0]))
+    }
+
+    /// Difference between best ask and best bid.
+    pub fn spread(&self) -> Option<Decimal> {
+        let (bid, ask) = (self.best_bid()?, self.best_ask()?);
+        Some(ask[0] - bid[0])
+    }
+
+    /// Volume imbalance over the top `levels` levels of the book.
+    ///
+    /// `(bid volume - ask volume) / (bid volume + ask volume)`, ranging from -1 (only asks)
+    /// to 1 (only bids). Returns `None` if there is no volume in the book.
+    pub fn imbalance(&self, levels: usize) -> Option<Decimal> {
+        let bid_volume: Decimal = self.bids.iter().take(levels).map(|l| l[1]).sum();
+        let ask_volume: Decimal = self.asks.iter().take(levels).map(|l| l[1]).sum();
+        imbalance(bid_volume, ask_volume)
+    }
+
+    /// Microprice of the top of the book, the mid price weighted by the opposite side volume.
+    ///
+    /// `(bid price * ask volume + ask price * bid volume) / (bid volume + ask volume)`
+    pub fn microprice(&self) -> Option<Decimal> {
+        let (bid, ask) = (self.best_bid()?, self.best_ask()?);
+        microprice(bid, ask)
+    }
+
+    /// Size weighted mid over the top `levels` levels.
+    ///
+    /// The average of the volume weighted bid price and the volume weighted ask price,
+    /// with `levels = 1` this is the same as [`PartialDepth::mid_price()`].
+    pub fn weighted_mid(&self, levels: usize) -> Option<Decimal> {
+        let bid = volume_weighted_price(&self.bids[..levels.min(self.bids.len())])?;
+        let ask = volume_weighted_price(&self.asks[..levels.min(self.asks.len())])?;
+        Some(mid_price(bid, ask))
+    }
 }
 
+#[cfg(feature = "book-ticker")]
+/// Best bid and ask, updated in real time.
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct BookTicker {
     #[serde(rename = "u")]
-    update_id:u64,
+    pub update_id:u64,
 
     #[serde(rename = "s")]
-    symbol:Symbol,
+    pub symbol:Symbol,
 
     // this can be reused in a BBO struct
     #[serde(rename = "b")]
-    best_bid_price:Decimal,
+    pub best_bid_price:Decimal,
 
     #[serde(rename = "B")]
-    best_bid_qty: Decimal,
+    pub best_bid_qty: Decimal,
 
     #[serde(rename = "a")]
-    best_ask_price:Decimal,
+    pub best_ask_price:Decimal,
 
     #[serde(rename = "A")]
-    best_ask_qty: Decimal
+    pub best_ask_qty: Decimal
+}
+
+#[cfg(feature = "book-ticker")]
+impl BookTicker {
+    /// Mid price between the best bid and best ask.
+    pub fn mid_price(&self) -> Decimal {
+        mid_price(self.best_bid_price, self.best_ask_price)
+    }
+
+    /// Difference between best ask and best bid.
+    pub fn spread(&self) -> Decimal {
+        self.best_ask_price - self.best_bid_price
+    }
+
+    /// Volume imbalance of the best bid and ask, see [`PartialDepth::imbalance()`].
+    pub fn imbalance(&self) -> Option<Decimal> {
+        imbalance(self.best_bid_qty, self.best_ask_qty)
+    }
+
+    /// Microprice of the best bid and ask, see [`PartialDepth::microprice()`].
+    pub fn microprice(&self) -> Option<Decimal> {
+        microprice(
+            [self.best_bid_price, self.best_bid_qty],
+            [self.best_ask_price, self.best_ask_qty],
+        )
+    }
+}
+
+#[cfg(feature = "depth")]
+/// Diff. depth update, changed price levels since the last update.
+///
+/// A quantity of zero means that the price level should be removed.
+/// Use [`crate::book::OrderBook`] to maintain a local order book from these.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct DepthUpdate {
+    #[serde(rename = "E")]
+    pub event_time: u64,
+
+    #[serde(rename = "s")]
+    pub symbol: Symbol,
+
+    #[serde(rename = "U")]
+    pub first_update_id: u64,
+
+    #[serde(rename = "u")]
+    pub final_update_id: u64,
+
+    #[serde(rename = "b")]
+    pub bids: Vec<[Decimal; 2]>,
+
+    #[serde(rename = "a")]
+    pub asks: Vec<[Decimal; 2]>,
+}
+
+#[cfg(feature = "depth")]
+impl DepthUpdate {
+    /// Parse a depth update payload into `self`, reusing the level buffers.
+    ///
+    /// `self` is partly overwritten when the payload is invalid.
+    pub fn parse_into(&mut self, json: &str) -> crate::Result<()> {
+        parse_in_place(json, DepthUpdateSeed(self))
+    }
+}
+
+#[cfg(feature = "depth")]
+/// Depth update of a futures contract, sent by both the partial and the diff. depth streams of
+/// futures.
+///
+/// Update ids of futures are not consecutive, an update continues the one whose
+/// `final_update_id` is its `prev_final_update_id`, see
+/// [`OrderBook::apply_futures()`](crate::book::OrderBook::apply_futures).
+/// Partial depth streams send the top levels of the book, see [`FuturesDepthUpdate::levels()`].
+/// **Official docs:** [diff. book depth streams](https://developers.binance.com/docs/derivatives/usds-margined-futures/websocket-market-streams/Diff-Book-Depth-Streams)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct FuturesDepthUpdate {
+    #[serde(rename = "E")]
+    pub event_time: u64,
+
+    /// Time of the last change of the book, in milliseconds since epoch.
+    #[serde(rename = "T")]
+    pub transaction_time: u64,
+
+    #[serde(rename = "s")]
+    pub symbol: Symbol,
+
+    #[serde(rename = "U")]
+    pub first_update_id: u64,
+
+    #[serde(rename = "u")]
+    pub final_update_id: u64,
+
+    /// `final_update_id` of the previous update of the stream.
+    #[serde(rename = "pu")]
+    pub prev_final_update_id: u64,
+
+    #[serde(rename = "b")]
+    pub bids: Vec<[Decimal; 2]>,
+
+    #[serde(rename = "a")]
+    pub asks: Vec<[Decimal; 2]>,
+}
+
+#[cfg(feature = "depth")]
+impl FuturesDepthUpdate {
+    /// The levels of an update of a partial depth stream, the top levels of the book as of
+    /// `final_update_id`.
+    pub fn levels(&self) -> PartialDepth {
+        PartialDepth {
+            last_update_id: self.final_update_id,
+            bids: self.bids.iter().copied().collect(),
+            asks: self.asks.iter().copied().collect(),
+        }
+    }
+}
+
+#[cfg(feature = "kline")]
+/// Update of the current kline for a symbol.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Kline {
+    #[serde(rename = "E")]
+    pub event_time: u64,
+
+    #[serde(rename = "s")]
+    pub symbol: Symbol,
+
+    #[serde(rename = "k")]
+    pub kline: KlineData,
+}
+
+#[cfg(feature = "kline")]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct KlineData {
+    #[serde(rename = "t")]
+    pub open_time: u64,
+
+    #[serde(rename = "T")]
+    pub close_time: u64,
+
+    /// Interval of the kline, e.g. `1m`
+    #[serde(rename = "i")]
+    pub interval: String,
+
+    /// -1 if there were no trades
+    #[serde(rename = "f")]
+    pub first_trade_id: i64,
+
+    /// -1 if there were no trades
+    #[serde(rename = "L")]
+    pub last_trade_id: i64,
+
+    #[serde(rename = "o")]
+    pub open: Decimal,
+
+    #[serde(rename = "c")]
+    pub close: Decimal,
+
+    #[serde(rename = "h")]
+    pub high: Decimal,
+
+    #[serde(rename = "l")]
+    pub low: Decimal,
+
+    /// Base asset volume
+    #[serde(rename = "v")]
+    pub volume: Decimal,
+
+    #[serde(rename = "n")]
+    pub trades: u64,
+
+    /// True once the kline is final
+    #[serde(rename = "x")]
+    pub is_closed: bool,
+
+    /// Quote asset volume
+    #[serde(rename = "q")]
+    pub quote_volume: Decimal,
+
+    #[serde(rename = "V")]
+    pub taker_buy_volume: Decimal,
+
+    #[serde(rename = "Q")]
+    pub taker_buy_quote_volume: Decimal,
+}
+
+#[cfg(feature = "kline")]
+/// Update of the current kline of a futures contract, see
+/// [`Feed::ContinuousKline`](crate::Feed::ContinuousKline).
+///
+/// The trade ids of the kline are update ids of the contract.
+/// **Official docs:** [continuous contract kline](https://developers.binance.com/docs/derivatives/usds-margined-futures/websocket-market-streams/Continuous-Contract-Kline-Candlestick-Streams)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ContinuousKline {
+    #[serde(rename = "E")]
+    pub event_time: u64,
+
+    #[serde(rename = "ps")]
+    pub pair: Symbol,
+
+    #[serde(rename = "ct")]
+    pub contract_type: ContractType,
+
+    #[serde(rename = "k")]
+    pub kline: KlineData,
+}
+
+/// Error reply of Binance to a request.
+///
+/// Binance replies `{"error":{"code":2,"msg":"Invalid request"},"id":1}`, the flat form
+/// `{"code":2,"msg":"Invalid request","id":1}` is accepted as well.
+/// **Official docs:** [error messages](https://developers.binance.com/docs/binance-spot-api-docs/web-socket-streams#error-messages)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(from = "ErrorReply", into = "ErrorReply")]
+pub struct ErrorResponse {
+    pub code: i64,
+    pub msg: String,
+    /// Id of the request, `None` when the request could not be read.
+    pub id: Option<u64>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(untagged)]
+enum ErrorReply {
+    Nested { error: ErrorBody, id: Option<u64> },
+    Flat { code: i64, msg: String, id: Option<u64> },
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+struct ErrorBody {
+    code: i64,
+    msg: String,
+}
+
+impl From<ErrorReply> for ErrorResponse {
+    fn from(reply: ErrorReply) -> Self {
+        match reply {
+            ErrorReply::Nested { error, id } => Self {
+                code: error.code,
+                msg: error.msg,
+                id,
+            },
+            ErrorReply::Flat { code, msg, id } => Self { code, msg, id },
+        }
+    }
+}
+
+impl From<ErrorResponse> for ErrorReply {
+    fn from(response: ErrorResponse) -> Self {
+        ErrorReply::Nested {
+            error: ErrorBody {
+                code: response.code,
+                msg: response.msg,
+            },
+            id: response.id,
+        }
+    }
+}
+
+#[cfg(feature = "depth")]
+fn parse_in_place<'de>(
+    json: &'de str,
+    seed: impl DeserializeSeed<'de, Value = ()>,
+) -> crate::Result<()> {
+    let mut deserializer = serde_json::Deserializer::from_str(json);
+    seed.deserialize(&mut deserializer)?;
+    deserializer.end()?;
+    Ok(())
+}
+
+#[cfg(feature = "depth")]
+/// Level buffers refilled in place.
+trait Levels {
+    fn clear(&mut self);
+    fn push(&mut self, level: [Decimal; 2]);
+}
+
+#[cfg(feature = "depth")]
+impl Levels for Vec<[Decimal; 2]> {
+    fn clear(&mut self) {
+        Vec::clear(self)
+    }
+    fn push(&mut self, level: [Decimal; 2]) {
+        Vec::push(self, level)
+    }
+}
+
+#[cfg(feature = "depth")]
+impl Levels for DepthLevels {
+    fn clear(&mut self) {
+        SmallVec::clear(self)
+    }
+    fn push(&mut self, level: [Decimal; 2]) {
+        SmallVec::push(self, level)
+    }
+}
+
+#[cfg(feature = "depth")]
+/// Deserializes levels into an existing buffer.
+struct LevelsSeed<'a, L>(&'a mut L);
+
+#[cfg(feature = "depth")]
+impl<'de, L: Levels> DeserializeSeed<'de> for LevelsSeed<'_, L> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+#[cfg(feature = "depth")]
+impl<'de, L: Levels> Visitor<'de> for LevelsSeed<'_, L> {
+    type Value = ();
+
+    fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str("a list of [price, quantity] levels")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<(), A::Error> {
+        self.0.clear();
+        while let Some(level) = seq.next_element()? {
+            self.0.push(level);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "depth")]
+#[derive(Deserialize)]
+#[serde(field_identifier, rename_all = "camelCase")]
+enum PartialDepthField {
+    LastUpdateId,
+    Bids,
+    Asks,
+    #[serde(other)]
+    Other,
+}
+
+#[cfg(feature = "depth")]
+/// Deserializes a [`PartialDepth`] into an existing one.
+struct PartialDepthSeed<'a>(&'a mut PartialDepth);
+
+#[cfg(feature = "depth")]
+impl<'de> DeserializeSeed<'de> for PartialDepthSeed<'_> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        deserializer.deserialize_map(self)
+    }
+}
+
+#[cfg(feature = "depth")]
+impl<'de> Visitor<'de> for PartialDepthSeed<'_> {
+    type Value = ();
+
+    fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str("a partial depth")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<(), A::Error> {
+        let mut seen = [false; 3];
+        while let Some(field) = map.next_key()? {
+            match field {
+                PartialDepthField::LastUpdateId => self.0.last_update_id = map.next_value()?,
+                PartialDepthField::Bids => map.next_value_seed(LevelsSeed(&mut self.0.bids))?,
+                PartialDepthField::Asks => map.next_value_seed(LevelsSeed(&mut self.0.asks))?,
+                PartialDepthField::Other => {
+                    map.next_value::<serde::de::IgnoredAny>()?;
+                    continue;
+                }
+            }
+            seen[field as usize] = true;
+        }
+        missing_field(&seen, &["lastUpdateId", "bids", "asks"])
+    }
+}
+
+#[cfg(feature = "depth")]
+#[derive(Deserialize)]
+#[serde(field_identifier)]
+enum DepthUpdateField {
+    #[serde(rename = "E")]
+    EventTime,
+    #[serde(rename = "s")]
+    Symbol,
+    #[serde(rename = "U")]
+    FirstUpdateId,
+    #[serde(rename = "u")]
+    FinalUpdateId,
+    #[serde(rename = "b")]
+    Bids,
+    #[serde(rename = "a")]
+    Asks,
+    #[serde(other)]
+    Other,
+}
+
+#[cfg(feature = "depth")]
+/// Deserializes a [`DepthUpdate`] into an existing one.
+struct DepthUpdateSeed<'a>(&'a mut DepthUpdate);
+
+#[cfg(feature = "depth")]
+impl<'de> DeserializeSeed<'de> for DepthUpdateSeed<'_> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        deserializer.deserialize_map(self)
+    }
+}
+
+#[cfg(feature = "depth")]
+impl<'de> Visitor<'de> for DepthUpdateSeed<'_> {
+    type Value = ();
+
+    fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str("a depth update")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<(), A::Error> {
+        let du = self.0;
+        let mut seen = [false; 6];
+        while let Some(field) = map.next_key()? {
+            match field {
+                DepthUpdateField::EventTime => du.event_time = map.next_value()?,
+                DepthUpdateField::Symbol => du.symbol = map.next_value()?,
+                DepthUpdateField::FirstUpdateId => du.first_update_id = map.next_value()?,
+                DepthUpdateField::FinalUpdateId => du.final_update_id = map.next_value()?,
+                DepthUpdateField::Bids => map.next_value_seed(LevelsSeed(&mut du.bids))?,
+                DepthUpdateField::Asks => map.next_value_seed(LevelsSeed(&mut du.asks))?,
+                DepthUpdateField::Other => {
+                    map.next_value::<serde::de::IgnoredAny>()?;
+                    continue;
+                }
+            }
+            seen[field as usize] = true;
+        }
+        missing_field(&seen, &["E", "s", "U", "u", "b", "a"])
+    }
+}
+
+#[cfg(feature = "depth")]
+/// Error for the first field in `names` that was not `seen`.
+fn missing_field<E: serde::de::Error>(seen: &[bool], names: &[&'static str]) -> Result<(), E> {
+    match seen.iter().position(|seen| !seen) {
+        Some(i) => Err(E::missing_field(names[i])),
+        None => Ok(()),
+    }
+}
+
+#[cfg(any(feature = "depth", feature = "book-ticker"))]
+fn mid_price(bid: Decimal, ask: Decimal) -> Decimal {
+    (bid + ask) / Decimal::TWO
+}
+
+#[cfg(any(feature = "depth", feature = "book-ticker"))]
+fn imbalance(bid_volume: Decimal, ask_volume: Decimal) -> Option<Decimal> {
+    let total = bid_volume + ask_volume;
+    if total.is_zero() {
+        return None;
+    }
+    Some((bid_volume - ask_volume) / total)
+}
+
+#[cfg(any(feature = "depth", feature = "book-ticker"))]
+fn microprice(bid: [Decimal; 2], ask: [Decimal; 2]) -> Option<Decimal> {
+    let total = bid[1] + ask[1];
+    if total.is_zero() {
+        return None;
+    }
+    Some((bid[0] * ask[1] + ask[0] * bid[1]) / total)
+}
+
+#[cfg(feature = "depth")]
+fn volume_weighted_price(levels: &[[Decimal; 2]]) -> Option<Decimal> {
+    let volume: Decimal = levels.iter().map(|l| l[1]).sum();
+    if volume.is_zero() {
+        return None;
+    }
+    Some(levels.iter().map(|l| l[0] * l[1]).sum::<Decimal>() / volume)
 }
 
 // TODO: Implement https://binance-docs.github.io/apidocs/spot/en/#all-market-mini-tickers-stream
@@ -111,6 +1004,19 @@ const AGGTRADEMSG: &str = r#"
 }
 "#;
 
+#[cfg(test)]
+const TRADEMSG: &str = r#"{
+"e":"trade",
+"E":1672515782136,
+"s":"BNBBTC",
+"t":12345,
+"p":"0.001",
+"q":"100",
+"T":1672515782136,
+"m":true,
+"M":true
+}"#;
+
 #[cfg(test)]
 const REALOB: &str = r#"{
 "lastUpdateId":55130421061,
@@ -138,11 +1044,131 @@ const BOOKTICKER: &str = r#"{
 "A":"40.66000000"
 }"#;
 
+#[cfg(test)]
+const DEPTHUPDATE: &str = r#"{
+"e":"depthUpdate",
+"E":1672515782136,
+"s":"BNBBTC",
+"U":157,
+"u":160,
+"b":[["0.0024","10"]],
+"a":[["0.0026","100"]]
+}"#;
+
+#[cfg(test)]
+const FUTURESDEPTHUPDATE: &str = r#"{
+"e":"depthUpdate",
+"E":1571889248277,
+"T":1571889248276,
+"s":"BTCUSDT",
+"U":390497796,
+"u":390497878,
+"pu":390497794,
+"b":[["7403.89","0.002"],["7403.90","3.906"]],
+"a":[["7405.96","3.340"]]
+}"#;
+
+#[cfg(test)]
+const KLINEMSG: &str = r#"{
+"e":"kline",
+"E":1672515782136,
+"s":"BNBBTC",
+"k":{
+  "t":1672515780000,
+  "T":1672515839999,
+  "s":"BNBBTC",
+  "i":"1m",
+  "f":100,
+  "L":200,
+  "o":"0.0010",
+  "c":"0.0020",
+  "h":"0.0025",
+  "l":"0.0015",
+  "v":"1000",
+  "n":100,
+  "x":false,
+  "q":"1.0000",
+  "V":"500",
+  "Q":"0.500",
+  "B":"123456"
+}
+}"#;
+
+#[cfg(test)]
+const CONTINUOUSKLINEMSG: &str = r#"{
+"e":"continuous_kline",
+"E":1607443058651,
+"ps":"BTCUSDT",
+"ct":"PERPETUAL",
+"k":{
+  "t":1607443020000,
+  "T":1607443079999,
+  "i":"1m",
+  "f":116467658886,
+  "L":116468012423,
+  "o":"18787.00",
+  "c":"18804.04",
+  "h":"18804.04",
+  "l":"18786.54",
+  "v":"197.664",
+  "n":543,
+  "x":false,
+  "q":"3715253.19494",
+  "V":"184.769",
+  "Q":"3472925.84746",
+  "B":"0"
+}
+}"#;
+
 #[cfg(test)]
 mod test {
 
     use super::*;
     use rust_decimal::{Decimal, prelude::FromPrimitive};
+    use smallvec::smallvec;
+
+    #[test]
+    fn error_response_parsing() {
+        let nested = r#"{"error":{"code":2,"msg":"Invalid request: unknown variant"},"id":3}"#;
+        let error = ErrorResponse {
+            code: 2,
+            msg: "Invalid request: unknown variant".to_string(),
+            id: Some(3),
+        };
+        let parsed: Message = serde_json::from_str(nested).unwrap();
+        assert_eq!(parsed, Message::Error(error.clone()));
+        assert_eq!(serde_json::to_string(&Message::Error(error)).unwrap(), nested);
+
+        let flat = r#"{"code":3,"msg":"Invalid JSON","id":null}"#;
+        let flat: Message = serde_json::from_str(flat).unwrap();
+        assert!(matches!(flat, Message::Error(ErrorResponse { code: 3, id: None, .. })));
+        // not mistaken for errors
+        let success: Message = serde_json::from_str(r#"{"result":null,"id":1}"#).unwrap();
+        assert_eq!(success, Message::SubscribeSuccess { result: None, id: 1 });
+    }
+
+    #[test]
+    fn ambiguous_payloads() {
+        let parse = |json: &str| serde_json::from_str::<Message>(json);
+        let trade = r#""E":1,"s":"BTCUSDT","t":7,"p":"1","q":"2","T":1,"m":true"#;
+        let aggregated = r#""a":5,"f":7,"l":7"#;
+
+        // the event type decides, not the fields present
+        let msg = parse(&format!(r#"{{"e":"trade",{trade},{aggregated}}}"#)).unwrap();
+        assert_eq!(msg.event_type(), "trade");
+        assert!(parse(&format!(r#"{{"e":"markPriceUpdate",{trade}}}"#)).is_err());
+        assert!(parse(&format!(r#"{{"e":7,{trade}}}"#)).is_err());
+        // without one, the fields only one type has
+        let msg = parse(&format!(r#"{{{trade},{aggregated}}}"#)).unwrap();
+        assert_eq!(msg.event_type(), "aggTrade");
+
+        // replies with an error are errors, replies with other fields are not subscribe replies
+        let error = parse(r#"{"code":1,"msg":"Unknown property","id":2}"#).unwrap();
+        assert_eq!(error.event_type(), "error");
+        assert!(parse(r#"{"id":2,"status":200,"result":null}"#).is_err());
+        assert!(parse(r#"{"result":null}"#).is_err());
+        assert!(parse("[]").is_err());
+    }
 
     #[test]
     fn book_ticker_parsing() {
@@ -169,7 +1195,7 @@ mod test {
 
         let depth = PartialDepth {
             last_update_id: 55130421061,
-            bids: vec![
+            bids: smallvec![
                 [
                     Decimal::from_f64(98655.99000000).unwrap(),
                     Decimal::from_f64(7.22497000).unwrap(),
@@ -191,7 +1217,7 @@ mod test {
                     Decimal::from_f64(0.39110000).unwrap(),
                 ],
             ],
-            asks: vec![
+            asks: smallvec![
                 [
                     Decimal::from_f64(98656.00000000).unwrap(),
                     Decimal::from_f64(0.00892000).unwrap(),
@@ -217,6 +1243,120 @@ mod test {
         assert_eq!(depth, ob_msg)
     }
 
+    #[test]
+    fn book_ticker_analytics() {
+        let bt: BookTicker = serde_json::from_str(BOOKTICKER).unwrap();
+
+        assert_eq!(bt.mid_price(), Decimal::from_str_exact("25.35855").unwrap());
+        assert_eq!(bt.spread(), Decimal::from_str_exact("0.0133").unwrap());
+        // (31.21 - 40.66) / (31.21 + 40.66)
+        assert_eq!(
+            bt.imbalance().unwrap().round_dp(6),
+            Decimal::from_str_exact("-0.131487").unwrap()
+        );
+        // (25.3519 * 40.66 + 25.3652 * 31.21) / 71.87
+        assert_eq!(
+            bt.microprice().unwrap().round_dp(6),
+            Decimal::from_str_exact("25.357676").unwrap()
+        );
+    }
+
+    #[test]
+    fn partial_ob_analytics() {
+        let ob: PartialDepth = serde_json::from_str(REALOB).unwrap();
+
+        assert_eq!(ob.spread(), Decimal::from_str_exact("0.01").ok());
+        assert_eq!(ob.mid_price(), Decimal::from_str_exact("98655.995").ok());
+        assert_eq!(ob.weighted_mid(1), ob.mid_price());
+
+        // (7.22497 - 0.00892) / (7.22497 + 0.00892)
+        assert_eq!(
+            ob.imbalance(1).unwrap().round_dp(6),
+            Decimal::from_str_exact("0.997534").unwrap()
+        );
+        // asking for more levels than available uses the whole book
+        assert_eq!(ob.imbalance(5), ob.imbalance(20));
+        assert_eq!(ob.weighted_mid(5), ob.weighted_mid(20));
+
+        // heavy bid side pushes the microprice towards the ask
+        let micro = ob.microprice().unwrap();
+        assert!(micro > ob.mid_price().unwrap());
+        assert!(micro < ob.best_ask().unwrap()[0]);
+    }
+
+    #[test]
+    fn empty_book_analytics() {
+        let ob = PartialDepth {
+            last_update_id: 1,
+            bids: smallvec![],
+            asks: smallvec![],
+        };
+        assert_eq!(ob.mid_price(), None);
+        assert_eq!(ob.imbalance(5), None);
+        assert_eq!(ob.microprice(), None);
+        assert_eq!(ob.weighted_mid(5), None);
+    }
+
+    #[test]
+    fn depth_update_message() {
+        let msg: Message = serde_json::from_str(DEPTHUPDATE).unwrap();
+
+        let update = DepthUpdate {
+            event_time: 1672515782136,
+            symbol: Symbol::BNBBTC,
+            first_update_id: 157,
+            final_update_id: 160,
+            bids: vec![[
+                Decimal::from_str_exact("0.0024").unwrap(),
+                Decimal::from_str_exact("10").unwrap(),
+            ]],
+            asks: vec![[
+                Decimal::from_str_exact("0.0026").unwrap(),
+                Decimal::from_str_exact("100").unwrap(),
+            ]],
+        };
+        assert_eq!(Message::DepthUpdate(update), msg);
+
+        let msg: Message = serde_json::from_str(FUTURESDEPTHUPDATE).unwrap();
+        let Message::FuturesDepthUpdate(update) = &msg else {
+            panic!("expected futures depth update, got {msg:?}");
+        };
+        assert_eq!(update.transaction_time, 1571889248276);
+        assert_eq!(update.prev_final_update_id, 390497794);
+        assert_eq!(update.levels().last_update_id, 390497878);
+        assert_eq!(update.levels().bids.len(), 2);
+        // serialized without the event type
+        let json = serde_json::to_string(&msg).unwrap();
+        assert_eq!(serde_json::from_str::<Message>(&json).unwrap(), msg);
+    }
+
+    #[test]
+    fn kline_message() {
+        let msg: Message = serde_json::from_str(KLINEMSG).unwrap();
+        let Message::Kline(kline) = msg else {
+            panic!("expected kline, got {msg:?}");
+        };
+
+        assert_eq!(kline.symbol, Symbol::BNBBTC);
+        assert_eq!(kline.kline.open_time, 1672515780000);
+        assert_eq!(kline.kline.interval, "1m");
+        assert_eq!(kline.kline.high, Decimal::from_str_exact("0.0025").unwrap());
+        assert_eq!(kline.kline.trades, 100);
+        assert!(!kline.kline.is_closed);
+
+        let msg: Message = serde_json::from_str(CONTINUOUSKLINEMSG).unwrap();
+        let Message::ContinuousKline(continuous) = &msg else {
+            panic!("expected continuous kline, got {msg:?}");
+        };
+        assert_eq!(continuous.pair, Symbol::BTCUSDT);
+        assert_eq!(continuous.contract_type, ContractType::Perpetual);
+        assert_eq!(continuous.kline.last_trade_id, 116468012423);
+        assert_eq!(msg.event_type(), "continuous_kline");
+        // serialized without the event type
+        let json = serde_json::to_string(&msg).unwrap();
+        assert_eq!(serde_json::from_str::<Message>(&json).unwrap(), msg);
+    }
+
     #[test]
     fn partial_ob_binance_message() {
         let ob_msg: Message = serde_json::from_str(REALOB).unwrap();
@@ -243,6 +1383,38 @@ mod test {
         assert_eq!(t, msg)
     }
 
+    #[test]
+    fn aggtrade_large_trade_ids() {
+        // BTCUSDT trade ids are past u32::MAX
+        let json = r#"{"e":"aggTrade","E":1733000000000,"a":3312345678,"s":"BTCUSDT",
+            "p":"97000.10","q":"0.5","f":4958123456,"l":4958123460,"T":1733000000000,"m":true}"#;
+        let msg: AggTrade = serde_json::from_str(json).unwrap();
+        assert_eq!(msg.trade_id, 3_312_345_678);
+        assert_eq!((msg.first_trade_id, msg.last_trade_id), (4_958_123_456, 4_958_123_460));
+
+        let trade = r#"{"e":"trade","E":1,"s":"BTCUSDT","t":4958123461,"p":"1","q":"1",
+            "T":1,"m":true}"#;
+        let msg: Trade = serde_json::from_str(trade).unwrap();
+        assert_eq!(msg.trade_id, 4_958_123_461);
+    }
+
+    #[test]
+    fn api_message_trade() {
+        let t = Trade {
+            event_time: 1672515782136,
+            symbol: Symbol::BNBBTC,
+            trade_id: 12345,
+            price: Decimal::from_str_exact("0.001").unwrap(),
+            quantity: Decimal::from_str_exact("100").unwrap(),
+            trade_time: 1672515782136,
+            is_market_maker: true,
+        };
+
+        let msg: Message = serde_json::from_str(TRADEMSG).unwrap();
+
+        assert_eq!(Message::Trade(t), msg)
+    }
+
     #[test]
     fn api_message_aggtrade() {
         let t = AggTrade {
@@ -262,4 +1434,213 @@ mod test {
 
         assert_eq!(t, msg)
     }
+
+    /// Every payload in `tests/fixtures` parses to the message type in its file name,
+    /// `<event type>[_<case>].json`, and survives a round trip through json.
+    #[test]
+    fn golden_fixtures() {
+        let dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures");
+        let mut covered = std::collections::HashSet::new();
+
+        for entry in std::fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            let name = path.file_stem().unwrap().to_str().unwrap().to_string();
+
+            let payload = std::fs::read_to_string(&path).unwrap();
+            let msg: Message = serde_json::from_str(&payload)
+                .unwrap_or_else(|e| panic!("{name} does not parse: {e}"));
+            // event types like continuous_kline have an underscore of their own
+            let event_type = msg.event_type();
+            let case = name.strip_prefix(event_type).unwrap_or_else(|| panic!("{name}"));
+            assert!(case.is_empty() || case.starts_with('_'), "{name}");
+
+            let json = serde_json::to_string(&msg).unwrap();
+            assert_eq!(serde_json::from_str::<Message>(&json).unwrap(), msg, "{name}");
+            assert_eq!(Message::from_frame(payload).unwrap(), msg, "{name}");
+            covered.insert(event_type.to_string());
+        }
+
+        let all = [
+            "aggTrade",
+            "trade",
+            "partialDepth",
+            "bookTicker",
+            "depthUpdate",
+            "futuresDepthUpdate",
+            "kline",
+            "continuous_kline",
+            "error",
+            "subscribeSuccess",
+        ];
+        for event_type in all {
+            assert!(covered.contains(event_type), "no fixture for {event_type}");
+        }
+    }
+
+    /// The golden fixtures as json values, by file name.
+    fn fixtures() -> Vec<(String, serde_json::Value)> {
+        let dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures");
+        let mut fixtures: Vec<_> = std::fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| {
+                let path = entry.unwrap().path();
+                let name = path.file_stem().unwrap().to_str().unwrap().to_string();
+                let payload = std::fs::read_to_string(&path).unwrap();
+                (name, serde_json::from_str(&payload).unwrap())
+            })
+            .collect();
+        fixtures.sort_by(|a, b| a.0.cmp(&b.0));
+        fixtures
+    }
+
+    /// `value` as json with the keys of every object in an order picked by `seed`.
+    fn shuffled_json(value: &serde_json::Value, seed: u64) -> String {
+        use std::hash::{Hash, Hasher};
+
+        match value {
+            serde_json::Value::Object(map) => {
+                let mut entries: Vec<_> = map.iter().collect();
+                entries.sort_by_key(|(key, _)| {
+                    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+                    (seed, key).hash(&mut hasher);
+                    hasher.finish()
+                });
+                let fields: Vec<String> = entries
+                    .iter()
+                    .map(|(key, value)| {
+                        let key = serde_json::Value::from(key.as_str());
+                        format!("{key}:{}", shuffled_json(value, seed))
+                    })
+                    .collect();
+                format!("{{{}}}", fields.join(","))
+            }
+            serde_json::Value::Array(items) => {
+                let items: Vec<String> = items.iter().map(|item| shuffled_json(item, seed)).collect();
+                format!("[{}]", items.join(","))
+            }
+            other => other.to_string(),
+        }
+    }
+
+    /// Values at the edges of what the fields hold, or of the wrong type.
+    fn extreme_value() -> impl proptest::strategy::Strategy<Value = serde_json::Value> {
+        use proptest::prelude::*;
+        use serde_json::json;
+
+        prop_oneof![
+            Just(json!(u64::MAX)),
+            Just(json!(i64::MIN)),
+            Just(json!(f64::MAX)),
+            Just(json!(-0.0)),
+            // above Decimal::MAX
+            Just(json!("79228162514264337593543950336")),
+            Just(json!("1e-40")),
+            Just(json!("-0")),
+            Just(json!("")),
+            Just(json!(null)),
+            Just(json!([["1"]])),
+            Just(json!({})),
+            any::<i64>().prop_map(|n| json!(n)),
+            any::<f64>().prop_map(|n| json!(n)),
+            "-?[0-9]{0,40}(\\.[0-9]{0,40})?".prop_map(|n| json!(n)),
+        ]
+    }
+
+    /// Parse `json`, serializing what parsed. Panics only if the parser or serializer does.
+    fn parse(json: &str) -> Option<Message> {
+        let msg = serde_json::from_str::<Message>(json).ok()?;
+        serde_json::to_string(&msg).unwrap();
+        Some(msg)
+    }
+
+    proptest::proptest! {
+        #[test]
+        fn reordered_keys(index: proptest::sample::Index, seed: u64) {
+            let fixtures = fixtures();
+            let (name, value) = index.get(&fixtures);
+            let expected: Message = serde_json::from_value(value.clone()).unwrap();
+            let json = shuffled_json(value, seed);
+            proptest::prop_assert_eq!(parse(&json), Some(expected), "{}", name);
+        }
+
+        #[test]
+        fn missing_fields(index: proptest::sample::Index, field: proptest::sample::Index) {
+            let fixtures = fixtures();
+            let (name, value) = index.get(&fixtures);
+            let mut value = value.clone();
+            let map = value.as_object_mut().unwrap();
+            let key = field.get(&map.keys().cloned().collect::<Vec<_>>()).clone();
+            map.remove(&key);
+
+            let parsed = parse(&value.to_string());
+            // the only optional field
+            if key == "result" {
+                proptest::prop_assert!(parsed.is_some(), "{} without {}", name, key);
+            }
+        }
+
+        #[test]
+        fn extreme_numbers(
+            index: proptest::sample::Index,
+            field: proptest::sample::Index,
+            replacement in extreme_value(),
+        ) {
+            let fixtures = fixtures();
+            let (_, value) = index.get(&fixtures);
+            let mut value = value.clone();
+            let map = value.as_object_mut().unwrap();
+            let key = field.get(&map.keys().cloned().collect::<Vec<_>>()).clone();
+            map.insert(key, replacement);
+
+            if let Some(msg) = parse(&value.to_string()) {
+                let json = serde_json::to_string(&msg).unwrap();
+                proptest::prop_assert_eq!(parse(&json), Some(msg));
+            }
+        }
+
+        #[test]
+        fn truncated_payloads(index: proptest::sample::Index, len: proptest::sample::Index) {
+            let fixtures = fixtures();
+            let json = index.get(&fixtures).1.to_string();
+            parse(&json[..len.index(json.len())]);
+        }
+
+        #[test]
+        fn arbitrary_text(text: String) {
+            parse(&text);
+        }
+    }
+
+    #[test]
+    fn parse_depth_in_place() {
+        let mut ob: PartialDepth = serde_json::from_str(REALOB).unwrap();
+        let expected = ob.clone();
+        ob.bids.truncate(1);
+        ob.last_update_id = 0;
+        ob.parse_into(REALOB).unwrap();
+        assert_eq!(ob, expected);
+        assert!(!ob.bids.spilled());
+
+        let mut update = DepthUpdate {
+            event_time: 0,
+            symbol: Symbol::ETHBTC,
+            first_update_id: 0,
+            final_update_id: 0,
+            bids: Vec::with_capacity(64),
+            asks: Vec::new(),
+        };
+        let bids = update.bids.as_ptr();
+        update.parse_into(DEPTHUPDATE).unwrap();
+        let Message::DepthUpdate(expected) = serde_json::from_str(DEPTHUPDATE).unwrap() else {
+            panic!("not a depth update");
+        };
+        assert_eq!(update, expected);
+        // the buffer was reused
+        assert_eq!(update.bids.as_ptr(), bids);
+
+        assert!(update.parse_into(r#"{"E":1,"s":"BNBBTC","U":1,"u":2,"b":[]}"#).is_err());
+        assert!(ob.parse_into(r#"{"lastUpdateId":1,"bids":[["1"]],"asks":[]}"#).is_err());
+        // the inline levels of partial depths are boxed in messages
+        assert!(std::mem::size_of::<Message>() < std::mem::size_of::<PartialDepth>());
+    }
 }
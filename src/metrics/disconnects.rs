@@ -0,0 +1,165 @@
+//! Log of the disconnects of a connection, with their reason and the data missed.
+//!
+//! Every disconnect is recorded as a [`Disconnect`], returned by
+//! [`Metrics::disconnects()`](super::Metrics::disconnects). Once reconnected, the downtime is
+//! set and the messages missed are estimated from the gaps in the ids of the streams that
+//! number their messages: trades, aggregate trades and diff. depth updates. The estimate
+//! only covers streams subscribed again after the reconnect.
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::time::Duration;
+
+use web_time::Instant;
+
+use crate::{Message, Symbol};
+
+/// Number of disconnects kept, the oldest are dropped.
+pub const MAX_DISCONNECTS: usize = 100;
+
+/// A disconnect, see the [module](self) documentation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Disconnect {
+    /// Time of the disconnect in milliseconds since epoch.
+    pub time: u64,
+    /// Close code sent by the server, `None` when the connection was lost without a close
+    /// frame.
+    pub code: Option<u16>,
+    /// Reason sent by the server, or what ended the connection.
+    pub reason: String,
+    /// Time until the next connect, `None` until reconnected.
+    pub downtime: Option<Duration>,
+    /// Estimated messages missed, counted from the first message of each stream after the
+    /// reconnect.
+    pub missed: u64,
+}
+
+/// Streams that number their messages, and the last id received on each.
+type Key = (Symbol, &'static str);
+
+#[derive(Debug, Default)]
+#[cfg_attr(not(feature = "tokio"), allow(dead_code))]
+pub(crate) struct Disconnects {
+    log: VecDeque<Disconnect>,
+    /// when the last disconnect happened, while disconnected
+    since: Option<Instant>,
+    last_ids: HashMap<Key, u64>,
+    /// streams received since the last connect
+    seen: HashSet<Key>,
+}
+
+#[cfg_attr(not(feature = "tokio"), allow(dead_code))]
+impl Disconnects {
+    pub(crate) fn log(&self) -> Vec<Disconnect> {
+        self.log.iter().cloned().collect()
+    }
+
+    pub(crate) fn disconnected(&mut self, time: u64, code: Option<u16>, reason: String) {
+        if self.log.len() == MAX_DISCONNECTS {
+            self.log.pop_front();
+        }
+        self.log.push_back(Disconnect {
+            time,
+            code,
+            reason,
+            downtime: None,
+            missed: 0,
+        });
+        self.since = Some(Instant::now());
+    }
+
+    pub(crate) fn connected(&mut self) {
+        if let Some(since) = self.since.take() {
+            if let Some(last) = self.log.back_mut() {
+                last.downtime = Some(since.elapsed());
+            }
+        }
+        self.seen.clear();
+    }
+
+    /// Count the ids skipped by `msg` towards the last disconnect, if it is the first message
+    /// of its stream since the reconnect.
+    pub(crate) fn message(&mut self, msg: &Message) {
+        let Some((key, first, last)) = sequence(msg) else {
+            return;
+        };
+        if !self.seen.contains(&key) {
+            let reconnected = self.log.back_mut().filter(|d| d.downtime.is_some());
+            if let (Some(disconnect), Some(previous)) = (reconnected, self.last_ids.get(&key)) {
+                disconnect.missed += first.saturating_sub(previous + 1);
+            }
+            self.seen.insert(key.clone());
+        }
+        self.last_ids.insert(key, last);
+    }
+}
+
+/// The stream of `msg` and its first and last id, for messages numbered without gaps.
+fn sequence(msg: &Message) -> Option<(Key, u64, u64)> {
+    match msg {
+        #[cfg(feature = "trade")]
+        Message::AggTrade(t) => Some(((t.symbol.clone(), "aggTrade"), t.trade_id, t.trade_id)),
+        #[cfg(feature = "trade")]
+        Message::Trade(t) => Some(((t.symbol.clone(), "trade"), t.trade_id, t.trade_id)),
+        #[cfg(feature = "depth")]
+        Message::DepthUpdate(du) => Some((
+            (du.symbol.clone(), "depthUpdate"),
+            du.first_update_id,
+            du.final_update_id,
+        )),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::test_util::{self, Action, MockServer};
+    use crate::{Feed, Message, SubscribeInfo, Symbol};
+
+    fn trade(trade_id: u64) -> Message {
+        Message::Trade(test_util::trade(Symbol::BTCUSDT, trade_id))
+    }
+
+    #[tokio::test]
+    async fn missed_after_reconnect() {
+        let server = MockServer::start().await.unwrap();
+        server.script([
+            Action::message(&trade(1)),
+            Action::message(&trade(2)),
+            Action::expired_close(),
+        ]);
+        let mut api = server.api();
+        let metrics = api.metrics();
+        let info = || [SubscribeInfo::new(Symbol::BTCUSDT, Feed::Trade)];
+
+        api.connect().await.unwrap();
+        api.subscribe(&info(), None).await.unwrap();
+        // the acknowledgement and the trades
+        for _ in 0..3 {
+            assert!(api.next_message().await.is_some());
+        }
+        assert_eq!(api.next_message().await, None);
+        let disconnects = metrics.disconnects();
+        assert_eq!(disconnects.len(), 1);
+        assert_eq!(disconnects[0].code, Some(1001));
+        assert_eq!(disconnects[0].reason, "Connection expired after 24 hours");
+        assert_eq!(disconnects[0].downtime, None);
+        assert_eq!(api.close_frame().map(|f| u16::from(f.code)), Some(1001));
+
+        server.script([Action::message(&trade(10)), Action::message(&trade(11))]);
+        api.connect().await.unwrap();
+        api.subscribe(&info(), None).await.unwrap();
+        for _ in 0..3 {
+            assert!(api.next_message().await.is_some());
+        }
+        let disconnects = metrics.disconnects();
+        assert_eq!(disconnects.len(), 1);
+        assert!(disconnects[0].downtime.is_some());
+        // trades 3 to 9
+        assert_eq!(disconnects[0].missed, 7);
+
+        api.disconnect().await;
+        let disconnects = metrics.disconnects();
+        assert_eq!(disconnects.len(), 2);
+        assert_eq!(disconnects[1].code, Some(1000));
+    }
+}
@@ -0,0 +1,111 @@
+//! Delay between the Binance event time and the local receive time of messages.
+//!
+//! The delay of a message is `recv_time + clock_offset - event_time`, where the clock offset
+//! is how far the local clock is behind the Binance clock. Without it the delays include the
+//! clock skew of the local machine, estimate it with
+//! [`RestClient::clock_offset()`](crate::rest::RestClient::clock_offset) and set it with
+//! [`Metrics::set_clock_offset()`](super::Metrics::set_clock_offset).
+//!
+//! Percentiles are computed over the last [`WINDOW`] messages of each event type.
+
+use std::collections::{HashMap, VecDeque};
+
+use crate::Message;
+
+/// Number of messages of each event type the percentiles are computed over.
+pub const WINDOW: usize = 1000;
+
+/// Percentiles of the delays of an event type in milliseconds, see the
+/// [module](self) documentation.
+///
+/// Delays can be negative when the clock offset is off.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LatencyPercentiles {
+    /// Number of delays the percentiles are computed over, at most [`WINDOW`].
+    pub samples: usize,
+    pub p50: i64,
+    pub p90: i64,
+    pub p99: i64,
+    pub max: i64,
+}
+
+/// Delays of the last [`WINDOW`] messages of each event type.
+#[derive(Debug, Default)]
+pub(crate) struct Latencies {
+    clock_offset: i64,
+    delays: HashMap<&'static str, VecDeque<i64>>,
+}
+
+impl Latencies {
+    pub(crate) fn set_clock_offset(&mut self, offset: i64) {
+        self.clock_offset = offset;
+    }
+
+    /// Record the delay of `msg` received at `recv_time`, if it has an event time.
+    pub(crate) fn record(&mut self, msg: &Message, recv_time: u64) {
+        let Some(event_time) = msg.event_time() else {
+            return;
+        };
+        let delay = recv_time as i64 + self.clock_offset - event_time as i64;
+        let delays = self.delays.entry(msg.event_type()).or_default();
+        if delays.len() == WINDOW {
+            delays.pop_front();
+        }
+        delays.push_back(delay);
+    }
+
+    pub(crate) fn percentiles(&self, event_type: &str) -> Option<LatencyPercentiles> {
+        let mut delays: Vec<i64> = self.delays.get(event_type)?.iter().copied().collect();
+        delays.sort_unstable();
+        // nearest rank
+        let rank = |p: usize| delays[(delays.len() * p).div_ceil(100).max(1) - 1];
+        Some(LatencyPercentiles {
+            samples: delays.len(),
+            p50: rank(50),
+            p90: rank(90),
+            p99: rank(99),
+            max: *delays.last()?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::messages::Trade;
+    use crate::test_util;
+    use crate::Symbol;
+
+    fn trade(event_time: u64) -> Message {
+        Message::Trade(Trade {
+            event_time,
+            trade_time: event_time,
+            ..test_util::trade(Symbol::BTCUSDT, 1)
+        })
+    }
+
+    #[test]
+    fn percentiles() {
+        let mut latencies = Latencies::default();
+        assert_eq!(latencies.percentiles("trade"), None);
+
+        // delays 1..=100, the local clock 5ms behind
+        latencies.set_clock_offset(5);
+        for delay in 1..=100 {
+            latencies.record(&trade(10_000), 10_000 + delay - 5);
+        }
+        let p = latencies.percentiles("trade").unwrap();
+        assert_eq!(
+            (p.samples, p.p50, p.p90, p.p99, p.max),
+            (100, 50, 90, 99, 100)
+        );
+        assert_eq!(latencies.percentiles("aggTrade"), None);
+
+        // only the last messages are kept
+        for _ in 0..WINDOW {
+            latencies.record(&trade(10_000), 10_002);
+        }
+        let p = latencies.percentiles("trade").unwrap();
+        assert_eq!((p.samples, p.p50, p.max), (WINDOW, 7, 7));
+    }
+}
@@ -0,0 +1,382 @@
+//! Counters of a connection, for any monitoring system.
+//!
+//! [`BinanceApi::metrics()`](crate::BinanceApi::metrics) returns the [`Metrics`] of a
+//! connection. They are shared, read them from another task while the connection is in use:
+//! ```no_run
+//! use binance_api_async::BinanceApi;
+//!
+//! # async fn run() -> Result<(), binance_api_async::Error> {
+//! let mut api = BinanceApi::new();
+//! let metrics = api.metrics();
+//! tokio::spawn(async move {
+//!     loop {
+//!         tokio::time::sleep(std::time::Duration::from_secs(10)).await;
+//!         println!("{:?}", metrics.snapshot());
+//!     }
+//! });
+//! api.connect().await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! With the `prometheus` feature, they are exported to Prometheus by [`prometheus`], with the
+//! `opentelemetry` feature to OpenTelemetry by [`otel`].
+
+pub mod disconnects;
+pub mod latency;
+#[cfg(feature = "opentelemetry")]
+pub mod otel;
+#[cfg(feature = "prometheus")]
+pub mod prometheus;
+
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use web_time::Instant;
+
+use crate::clock::{Clock, SystemClock};
+use crate::Message;
+use disconnects::{Disconnect, Disconnects};
+use latency::{Latencies, LatencyPercentiles};
+
+/// Message types counted by [`Metrics::messages()`], as [`Message::event_type()`] names them.
+pub const MESSAGE_TYPES: [&str; 10] = [
+    "aggTrade",
+    "trade",
+    "partialDepth",
+    "bookTicker",
+    "depthUpdate",
+    "futuresDepthUpdate",
+    "kline",
+    "continuous_kline",
+    "subscribeSuccess",
+    "error",
+];
+
+/// Index of the type of `msg` in [`MESSAGE_TYPES`], a new message type has to be counted.
+fn message_index(msg: &Message) -> usize {
+    match msg {
+        #[cfg(feature = "trade")]
+        Message::AggTrade(_) => 0,
+        #[cfg(feature = "trade")]
+        Message::Trade(_) => 1,
+        #[cfg(feature = "depth")]
+        Message::PartialDepth(_) => 2,
+        #[cfg(feature = "book-ticker")]
+        Message::BookTicker(_) => 3,
+        #[cfg(feature = "depth")]
+        Message::DepthUpdate(_) => 4,
+        #[cfg(feature = "depth")]
+        Message::FuturesDepthUpdate(_) => 5,
+        #[cfg(feature = "kline")]
+        Message::Kline(_) => 6,
+        #[cfg(feature = "kline")]
+        Message::ContinuousKline(_) => 7,
+        Message::SubscribeSuccess { .. } => 8,
+        Message::Error(_) => 9,
+    }
+}
+
+/// Reconnects within this time are counted by [`Metrics::recent_reconnects()`].
+pub const STORM_WINDOW: Duration = Duration::from_secs(60);
+
+/// Reconnects within [`STORM_WINDOW`] from which [`Metrics::reconnect_storm()`] is true.
+pub const STORM_RECONNECTS: u64 = 5;
+
+/// Counters and gauges of a connection, see the [module](self) documentation.
+///
+/// Counters start at zero and are never reset, they count over reconnects.
+#[derive(Debug, Default)]
+pub struct Metrics {
+    messages: [AtomicU64; MESSAGE_TYPES.len()],
+    bytes_received: AtomicU64,
+    parse_failures: AtomicU64,
+    unexpected_frames: AtomicU64,
+    dropped: AtomicU64,
+    connects: AtomicU64,
+    connected: AtomicBool,
+    latency: Mutex<Latencies>,
+    disconnects: Mutex<Disconnects>,
+    /// times of the reconnects within the storm window
+    reconnect_times: Mutex<VecDeque<Instant>>,
+    /// round trips of the last pings of the client
+    pings: Mutex<VecDeque<Duration>>,
+    // in microseconds, 0 until the first one
+    connect_duration: AtomicU64,
+    round_trip: AtomicU64,
+}
+
+#[cfg_attr(not(feature = "tokio"), allow(dead_code))]
+impl Metrics {
+    /// Messages received of `event_type`, e.g. `"aggTrade"`, see [`MESSAGE_TYPES`].
+    pub fn messages(&self, event_type: &str) -> u64 {
+        MESSAGE_TYPES
+            .iter()
+            .position(|t| *t == event_type)
+            .map_or(0, |i| self.messages[i].load(Ordering::Relaxed))
+    }
+
+    /// Bytes of the frames received.
+    pub fn bytes_received(&self) -> u64 {
+        self.bytes_received.load(Ordering::Relaxed)
+    }
+
+    /// Frames that could not be parsed into a [`Message`].
+    pub fn parse_failures(&self) -> u64 {
+        self.parse_failures.load(Ordering::Relaxed)
+    }
+
+    /// Frames of a type that carries no messages and is not part of the websocket protocol,
+    /// skipped.
+    pub fn unexpected_frames(&self) -> u64 {
+        self.unexpected_frames.load(Ordering::Relaxed)
+    }
+
+    /// Messages dropped because the consumer was too slow, see
+    /// [`Overflow`](crate::pipeline::Overflow), or skipped by a lagging
+    /// [`Relay`](crate::relay::Relay) client.
+    pub fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+
+    /// Connections made after the first one.
+    pub fn reconnects(&self) -> u64 {
+        self.connects.load(Ordering::Relaxed).saturating_sub(1)
+    }
+
+    /// Reconnects within the last [`STORM_WINDOW`].
+    pub fn recent_reconnects(&self) -> u64 {
+        let mut times = self.reconnect_times.lock().unwrap();
+        while times.front().is_some_and(|t| t.elapsed() > STORM_WINDOW) {
+            times.pop_front();
+        }
+        times.len() as u64
+    }
+
+    /// Whether there were at least [`STORM_RECONNECTS`] reconnects within the last
+    /// [`STORM_WINDOW`].
+    pub fn reconnect_storm(&self) -> bool {
+        self.recent_reconnects() >= STORM_RECONNECTS
+    }
+
+    /// Time the last connect took, until the websocket handshake was done.
+    pub fn connect_duration(&self) -> Option<Duration> {
+        micros(&self.connect_duration)
+    }
+
+    /// Time between sending the last acknowledged request, like a subscribe, and receiving
+    /// its acknowledgement.
+    pub fn round_trip(&self) -> Option<Duration> {
+        micros(&self.round_trip)
+    }
+
+    /// Round trips of the last [`latency::WINDOW`] pings of the client, oldest first, from
+    /// sending the ping to receiving its pong. Empty without a
+    /// [`ping_interval`](crate::BinanceApiBuilder::ping_interval).
+    pub fn ping_round_trips(&self) -> Vec<Duration> {
+        self.pings.lock().unwrap().iter().copied().collect()
+    }
+
+    /// The last [`MAX_DISCONNECTS`](disconnects::MAX_DISCONNECTS) disconnects, oldest first,
+    /// see [`disconnects`].
+    pub fn disconnects(&self) -> Vec<Disconnect> {
+        self.disconnects.lock().unwrap().log()
+    }
+
+    /// Whether the connection is open.
+    pub fn connected(&self) -> bool {
+        self.connected.load(Ordering::Relaxed)
+    }
+
+    /// Percentiles of the delays of the last messages of `event_type`, see [`latency`].
+    ///
+    /// `None` before the first message with an event time, [`BookTicker`]s and
+    /// [`PartialDepth`]s have none.
+    ///
+    /// [`BookTicker`]: crate::messages::BookTicker
+    /// [`PartialDepth`]: crate::messages::PartialDepth
+    pub fn latency(&self, event_type: &str) -> Option<LatencyPercentiles> {
+        self.latency.lock().unwrap().percentiles(event_type)
+    }
+
+    /// Set how many milliseconds the local clock is behind the Binance clock, for
+    /// [`Metrics::latency()`].
+    pub fn set_clock_offset(&self, offset_millis: i64) {
+        self.latency.lock().unwrap().set_clock_offset(offset_millis);
+    }
+
+    /// The current values.
+    pub fn snapshot(&self) -> MetricsSnapshot {
+        MetricsSnapshot {
+            messages: MESSAGE_TYPES.map(|t| (t, self.messages(t))),
+            bytes_received: self.bytes_received(),
+            parse_failures: self.parse_failures(),
+            unexpected_frames: self.unexpected_frames(),
+            dropped: self.dropped(),
+            reconnects: self.reconnects(),
+            connected: self.connected(),
+        }
+    }
+
+    /// Count `msg`, received at `recv_time` in milliseconds since epoch.
+    pub(crate) fn record_message(&self, msg: &Message, recv_time: u64) {
+        self.messages[message_index(msg)].fetch_add(1, Ordering::Relaxed);
+        if msg.event_time().is_some() {
+            self.latency.lock().unwrap().record(msg, recv_time);
+        }
+        self.disconnects.lock().unwrap().message(msg);
+    }
+
+    pub(crate) fn record_bytes(&self, bytes: usize) {
+        self.bytes_received
+            .fetch_add(bytes as u64, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_parse_failure(&self) {
+        self.parse_failures.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_unexpected_frame(&self) {
+        self.unexpected_frames.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_dropped(&self) {
+        self.record_dropped_many(1);
+    }
+
+    pub(crate) fn record_dropped_many(&self, dropped: u64) {
+        self.dropped.fetch_add(dropped, Ordering::Relaxed);
+    }
+
+    /// Count a connect that took `duration`.
+    pub(crate) fn record_connect(&self, duration: Duration) {
+        if self.connects.fetch_add(1, Ordering::Relaxed) > 0 {
+            self.reconnect_times
+                .lock()
+                .unwrap()
+                .push_back(Instant::now());
+        }
+        self.connected.store(true, Ordering::Relaxed);
+        self.disconnects.lock().unwrap().connected();
+        store_micros(&self.connect_duration, duration);
+    }
+
+    pub(crate) fn record_round_trip(&self, duration: Duration) {
+        store_micros(&self.round_trip, duration);
+    }
+
+    pub(crate) fn record_ping(&self, duration: Duration) {
+        let mut pings = self.pings.lock().unwrap();
+        if pings.len() == latency::WINDOW {
+            pings.pop_front();
+        }
+        pings.push_back(duration);
+    }
+
+    /// Record a disconnect with the close `code` and `reason`, once per connection.
+    pub(crate) fn record_disconnect(&self, code: Option<u16>, reason: &str) {
+        if self.connected.swap(false, Ordering::Relaxed) {
+            let time = SystemClock.now_millis();
+            let mut disconnects = self.disconnects.lock().unwrap();
+            disconnects.disconnected(time, code, reason.to_string());
+        }
+    }
+}
+
+fn micros(value: &AtomicU64) -> Option<Duration> {
+    match value.load(Ordering::Relaxed) {
+        0 => None,
+        micros => Some(Duration::from_micros(micros)),
+    }
+}
+
+fn store_micros(value: &AtomicU64, duration: Duration) {
+    // at least 1, 0 is none
+    let micros = (duration.as_micros() as u64).max(1);
+    value.store(micros, Ordering::Relaxed);
+}
+
+/// Values of [`Metrics`] at one point in time.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MetricsSnapshot {
+    /// Messages received by type, in the order of [`MESSAGE_TYPES`].
+    pub messages: [(&'static str, u64); MESSAGE_TYPES.len()],
+    pub bytes_received: u64,
+    pub parse_failures: u64,
+    pub unexpected_frames: u64,
+    pub dropped: u64,
+    pub reconnects: u64,
+    pub connected: bool,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_util::{ticker, Action, MockServer};
+    use crate::{Feed, SubscribeInfo, Symbol};
+
+    #[tokio::test]
+    async fn counts() {
+        let ticker = Message::BookTicker(ticker(Symbol::BTCUSDT, "1", "2"));
+        let server = MockServer::start().await.unwrap();
+        server.script([
+            Action::message(&ticker),
+            Action::malformed(),
+            Action::message(&ticker),
+        ]);
+
+        let mut api = server.api();
+        let metrics = api.metrics();
+        assert!(!metrics.connected());
+        api.connect().await.unwrap();
+        assert!(metrics.connected());
+        let info = SubscribeInfo::new(Symbol::BTCUSDT, Feed::BookTicker);
+        api.subscribe(&[info], None).await.unwrap();
+        for _ in 0..3 {
+            assert!(api.next_message().await.is_some());
+        }
+        assert_eq!(metrics.messages("subscribeSuccess"), 1);
+        assert_eq!(metrics.messages("bookTicker"), 2);
+        assert_eq!(metrics.messages("trade"), 0);
+        assert_eq!(metrics.parse_failures(), 1);
+        assert_eq!(metrics.unexpected_frames(), 0);
+        assert!(metrics.bytes_received() > 0);
+        assert!(metrics.connect_duration().is_some());
+        assert!(metrics.round_trip().is_some());
+
+        server.send(Action::Disconnect);
+        assert_eq!(api.next_message().await, None);
+        assert!(!metrics.connected());
+        assert_eq!(metrics.reconnects(), 0);
+
+        api.connect().await.unwrap();
+        let snapshot = metrics.snapshot();
+        assert_eq!(snapshot.reconnects, 1);
+        assert!(snapshot.connected);
+        assert_eq!(snapshot.messages[3], ("bookTicker", 2));
+        let ack = Message::SubscribeSuccess {
+            result: None,
+            id: 1,
+        };
+        for msg in [ticker, ack] {
+            assert_eq!(MESSAGE_TYPES[message_index(&msg)], msg.event_type());
+        }
+    }
+
+    #[test]
+    fn reconnect_storm() {
+        let metrics = Metrics::default();
+        assert_eq!(metrics.round_trip(), None);
+        for _ in 0..STORM_RECONNECTS {
+            metrics.record_connect(Duration::ZERO);
+            assert!(!metrics.reconnect_storm());
+        }
+        metrics.record_connect(Duration::ZERO);
+        assert_eq!(metrics.recent_reconnects(), STORM_RECONNECTS);
+        assert!(metrics.reconnect_storm());
+        // a connect shorter than the resolution is still recorded
+        assert_eq!(metrics.connect_duration(), Some(Duration::from_micros(1)));
+    }
+}
@@ -0,0 +1,171 @@
+//! Export [`Metrics`] to OpenTelemetry, with the `opentelemetry` feature.
+//!
+//! [`observe()`] registers instruments reading the [`Metrics`] of a connection with a
+//! [`Meter`], exported by the meter provider of the application:
+//! ```no_run
+//! use binance_api_async::metrics::otel;
+//! use binance_api_async::BinanceApi;
+//!
+//! let api = BinanceApi::new();
+//! let meter = opentelemetry::global::meter("binance_api_async");
+//! otel::observe(&meter, api.metrics(), "spot");
+//! ```
+//!
+//! Instruments, with a `connection` attribute:
+//! - `binance.messages{type}`, messages received by event type
+//! - `binance.bytes_received`, `binance.parse_failures`, `binance.unexpected_frames`,
+//!   `binance.dropped` and `binance.reconnects` counters
+//! - `binance.connected` gauge, 1 while connected
+//! - `binance.reconnects.recent` gauge, reconnects within the
+//!   [`STORM_WINDOW`](super::STORM_WINDOW), to alert on reconnect storms
+//! - `binance.connect.duration` and `binance.request.round_trip` gauges in seconds, of the
+//!   last connect and acknowledged request
+//! - `binance.latency{type,quantile}` gauge in seconds, see [`latency`](super::latency)
+//!
+//! The spans of the connection and its requests are tracing spans, see
+//! [`logging`](crate::logging), exported to OpenTelemetry with the tracing-opentelemetry layer.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use opentelemetry::metrics::{AsyncInstrument, Meter};
+use opentelemetry::KeyValue;
+
+use super::{Metrics, MESSAGE_TYPES};
+
+/// Observe `metrics` with `meter`, labelled with `connection` to tell several connections
+/// apart, see the [module](self) documentation.
+///
+/// The instruments are observed for as long as the meter provider exists.
+pub fn observe(meter: &Meter, metrics: Arc<Metrics>, connection: &str) {
+    let attributes = [KeyValue::new("connection", connection.to_string())];
+
+    let counter = |name: &'static str, description: &'static str, read: fn(&Metrics) -> u64| {
+        let metrics = metrics.clone();
+        let attributes = attributes.clone();
+        meter
+            .u64_observable_counter(name)
+            .with_description(description)
+            .with_callback(move |observer| observer.observe(read(&metrics), &attributes))
+            .build();
+    };
+    counter(
+        "binance.bytes_received",
+        "Bytes of the frames received.",
+        Metrics::bytes_received,
+    );
+    counter(
+        "binance.parse_failures",
+        "Frames that could not be parsed.",
+        Metrics::parse_failures,
+    );
+    counter(
+        "binance.unexpected_frames",
+        "Frames of an unexpected type, skipped.",
+        Metrics::unexpected_frames,
+    );
+    counter(
+        "binance.dropped",
+        "Messages dropped for slow consumers.",
+        Metrics::dropped,
+    );
+    counter(
+        "binance.reconnects",
+        "Connections after the first one.",
+        Metrics::reconnects,
+    );
+
+    let gauge = |name: &'static str, description: &'static str, read: fn(&Metrics) -> u64| {
+        let metrics = metrics.clone();
+        let attributes = attributes.clone();
+        meter
+            .u64_observable_gauge(name)
+            .with_description(description)
+            .with_callback(move |observer| observer.observe(read(&metrics), &attributes))
+            .build();
+    };
+    gauge("binance.connected", "1 while connected.", |m| {
+        m.connected().into()
+    });
+    gauge(
+        "binance.reconnects.recent",
+        "Reconnects within the last minute.",
+        Metrics::recent_reconnects,
+    );
+
+    let seconds =
+        |name: &'static str, description: &'static str, read: fn(&Metrics) -> Option<Duration>| {
+            let metrics = metrics.clone();
+            let attributes = attributes.clone();
+            meter
+                .f64_observable_gauge(name)
+                .with_description(description)
+                .with_unit("s")
+                .with_callback(move |observer| {
+                    if let Some(duration) = read(&metrics) {
+                        observer.observe(duration.as_secs_f64(), &attributes);
+                    }
+                })
+                .build();
+        };
+    seconds(
+        "binance.connect.duration",
+        "Duration of the last connect.",
+        Metrics::connect_duration,
+    );
+    seconds(
+        "binance.request.round_trip",
+        "Round trip of the last acknowledged request.",
+        Metrics::round_trip,
+    );
+
+    let messages = (metrics.clone(), connection.to_string());
+    meter
+        .u64_observable_counter("binance.messages")
+        .with_description("Messages received by event type.")
+        .with_callback(move |observer| observe_messages(observer, &messages.0, &messages.1))
+        .build();
+
+    let connection = connection.to_string();
+    meter
+        .f64_observable_gauge("binance.latency")
+        .with_description("Delay between event and receive time.")
+        .with_unit("s")
+        .with_callback(move |observer| observe_latency(observer, &metrics, &connection))
+        .build();
+}
+
+fn observe_messages(observer: &dyn AsyncInstrument<u64>, metrics: &Metrics, connection: &str) {
+    for event_type in MESSAGE_TYPES {
+        observer.observe(
+            metrics.messages(event_type),
+            &[
+                KeyValue::new("connection", connection.to_string()),
+                KeyValue::new("type", event_type),
+            ],
+        );
+    }
+}
+
+fn observe_latency(observer: &dyn AsyncInstrument<f64>, metrics: &Metrics, connection: &str) {
+    for event_type in MESSAGE_TYPES {
+        let Some(p) = metrics.latency(event_type) else {
+            continue;
+        };
+        for (quantile, delay) in [
+            ("0.5", p.p50),
+            ("0.9", p.p90),
+            ("0.99", p.p99),
+            ("1", p.max),
+        ] {
+            observer.observe(
+                delay as f64 / 1000.0,
+                &[
+                    KeyValue::new("connection", connection.to_string()),
+                    KeyValue::new("type", event_type),
+                    KeyValue::new("quantile", quantile),
+                ],
+            );
+        }
+    }
+}
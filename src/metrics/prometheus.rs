@@ -0,0 +1,269 @@
+//! Export [`Metrics`] to Prometheus, with the `prometheus` feature.
+//!
+//! [`PrometheusCollector`] is registered to a [`Registry`], its values are read from the
+//! [`Metrics`] at every scrape. [`serve()`] answers scrapes of a registry, for applications
+//! without an HTTP server of their own:
+//! ```no_run
+//! use binance_api_async::metrics::prometheus::{serve, PrometheusCollector};
+//! use binance_api_async::BinanceApi;
+//! use prometheus::Registry;
+//!
+//! # async fn run() -> Result<(), binance_api_async::Error> {
+//! let api = BinanceApi::new();
+//! let registry = Registry::new();
+//! registry.register(Box::new(PrometheusCollector::new(api.metrics(), "spot")))?;
+//! let listener = tokio::net::TcpListener::bind("0.0.0.0:9100").await?;
+//! tokio::spawn(serve(listener, registry));
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Exported metrics, labelled with the `connection` name:
+//! - `binance_messages_total{type}`, messages received by event type
+//! - `binance_bytes_received_total`
+//! - `binance_parse_failures_total`
+//! - `binance_unexpected_frames_total`
+//! - `binance_dropped_total`
+//! - `binance_reconnects_total`
+//! - `binance_connected`, 1 while connected
+//! - `binance_latency_ms{type,quantile}`, percentiles of the delays of the messages, see
+//!   [`latency`](super::latency)
+
+use std::sync::Arc;
+
+use ::prometheus::core::{Collector, Desc};
+use ::prometheus::proto::MetricFamily;
+use ::prometheus::{
+    Encoder, IntCounter, IntCounterVec, IntGauge, IntGaugeVec, Opts, Registry, TextEncoder,
+};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tracing::{info, warn};
+
+use super::{Metrics, MESSAGE_TYPES};
+
+/// A Prometheus [`Collector`] of [`Metrics`], see the [module](self) documentation.
+#[derive(Debug)]
+pub struct PrometheusCollector {
+    metrics: Arc<Metrics>,
+    connection: String,
+    descs: Vec<Desc>,
+}
+
+impl PrometheusCollector {
+    /// Collect `metrics`, labelled with `connection` to tell several connections apart.
+    pub fn new(metrics: Arc<Metrics>, connection: &str) -> Self {
+        let descs = Families::new(connection)
+            .collectors()
+            .iter()
+            .flat_map(|c| c.desc().into_iter().cloned())
+            .collect();
+        Self {
+            metrics,
+            connection: connection.to_string(),
+            descs,
+        }
+    }
+}
+
+impl Collector for PrometheusCollector {
+    fn desc(&self) -> Vec<&Desc> {
+        self.descs.iter().collect()
+    }
+
+    fn collect(&self) -> Vec<MetricFamily> {
+        let snapshot = self.metrics.snapshot();
+        let families = Families::new(&self.connection);
+        for (event_type, count) in snapshot.messages {
+            families
+                .messages
+                .with_label_values(&[event_type])
+                .inc_by(count);
+        }
+        families.bytes_received.inc_by(snapshot.bytes_received);
+        families.parse_failures.inc_by(snapshot.parse_failures);
+        families
+            .unexpected_frames
+            .inc_by(snapshot.unexpected_frames);
+        families.dropped.inc_by(snapshot.dropped);
+        families.reconnects.inc_by(snapshot.reconnects);
+        families.connected.set(snapshot.connected.into());
+        for event_type in MESSAGE_TYPES {
+            let Some(p) = self.metrics.latency(event_type) else {
+                continue;
+            };
+            for (quantile, delay) in [
+                ("0.5", p.p50),
+                ("0.9", p.p90),
+                ("0.99", p.p99),
+                ("1", p.max),
+            ] {
+                families
+                    .latency
+                    .with_label_values(&[event_type, quantile])
+                    .set(delay);
+            }
+        }
+        families
+            .collectors()
+            .iter()
+            .flat_map(|c| c.collect())
+            .collect()
+    }
+}
+
+/// The exported metrics, created for every scrape as [`Metrics`] can not be reset.
+struct Families {
+    messages: IntCounterVec,
+    bytes_received: IntCounter,
+    parse_failures: IntCounter,
+    unexpected_frames: IntCounter,
+    dropped: IntCounter,
+    reconnects: IntCounter,
+    connected: IntGauge,
+    latency: IntGaugeVec,
+}
+
+impl Families {
+    fn new(connection: &str) -> Self {
+        let opts =
+            |name: &str, help: &str| Opts::new(name, help).const_label("connection", connection);
+        // the names and labels are valid, creating them can not fail
+        let counter = |name, help| IntCounter::with_opts(opts(name, help)).unwrap();
+        let messages = IntCounterVec::new(
+            opts("binance_messages_total", "Messages received by event type."),
+            &["type"],
+        )
+        .unwrap();
+        // every type is exported, also the ones not received yet
+        for event_type in MESSAGE_TYPES {
+            messages.with_label_values(&[event_type]);
+        }
+        Self {
+            messages,
+            bytes_received: counter(
+                "binance_bytes_received_total",
+                "Bytes of the frames received.",
+            ),
+            parse_failures: counter(
+                "binance_parse_failures_total",
+                "Frames that could not be parsed.",
+            ),
+            unexpected_frames: counter(
+                "binance_unexpected_frames_total",
+                "Frames of an unexpected type, skipped.",
+            ),
+            dropped: counter(
+                "binance_dropped_total",
+                "Messages dropped for slow consumers.",
+            ),
+            reconnects: counter(
+                "binance_reconnects_total",
+                "Connections after the first one.",
+            ),
+            connected: IntGauge::with_opts(opts("binance_connected", "1 while connected."))
+                .unwrap(),
+            latency: IntGaugeVec::new(
+                opts(
+                    "binance_latency_ms",
+                    "Delay between event and receive time.",
+                ),
+                &["type", "quantile"],
+            )
+            .unwrap(),
+        }
+    }
+
+    fn collectors(&self) -> [&dyn Collector; 8] {
+        [
+            &self.messages,
+            &self.bytes_received,
+            &self.parse_failures,
+            &self.unexpected_frames,
+            &self.dropped,
+            &self.reconnects,
+            &self.connected,
+            &self.latency,
+        ]
+    }
+}
+
+/// Answer scrapes of `registry` on `listener`, on any path, until accepting fails.
+pub async fn serve(listener: TcpListener, registry: Registry) -> crate::Result<()> {
+    info!("Serving metrics on http://{}", listener.local_addr()?);
+    loop {
+        let (stream, _) = listener.accept().await?;
+        let registry = registry.clone();
+        tokio::spawn(async move {
+            if let Err(e) = scrape(stream, &registry).await {
+                warn!("Failed to answer a metrics scrape: {e}");
+            }
+        });
+    }
+}
+
+async fn scrape(mut stream: TcpStream, registry: &Registry) -> crate::Result<()> {
+    // the request is not looked at, only read up to the end of its headers
+    let mut request = Vec::new();
+    let mut buf = [0; 1024];
+    while !request.ends_with(b"\r\n\r\n") {
+        let read = stream.read(&mut buf).await?;
+        if read == 0 || request.len() > 64 * 1024 {
+            return Ok(());
+        }
+        request.extend_from_slice(&buf[..read]);
+    }
+
+    let encoder = TextEncoder::new();
+    let mut body = Vec::new();
+    encoder.encode(&registry.gather(), &mut body)?;
+    let head = format!(
+        "HTTP/1.1 200 OK\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        encoder.format_type(),
+        body.len()
+    );
+    stream.write_all(head.as_bytes()).await?;
+    stream.write_all(&body).await?;
+    stream.shutdown().await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn scraped() {
+        let metrics = Arc::new(Metrics::default());
+        metrics.record_connect(Duration::from_millis(1));
+        metrics.record_connect(Duration::from_millis(1));
+        metrics.record_parse_failure();
+        let registry = Registry::new();
+        registry
+            .register(Box::new(PrometheusCollector::new(metrics.clone(), "spot")))
+            .unwrap();
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(serve(listener, registry));
+
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream
+            .write_all(b"GET /metrics HTTP/1.1\r\nHost: localhost\r\n\r\n")
+            .await
+            .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        for line in [
+            r#"binance_reconnects_total{connection="spot"} 1"#,
+            r#"binance_parse_failures_total{connection="spot"} 1"#,
+            r#"binance_connected{connection="spot"} 1"#,
+            r#"binance_messages_total{connection="spot",type="aggTrade"} 0"#,
+        ] {
+            assert!(response.lines().any(|l| l == line), "{line} in {response}");
+        }
+    }
+}
@@ -0,0 +1,245 @@
+//! Middlewares running on every message of a source before it is delivered.
+//!
+//! Filtering, enrichment, sampling and metrics are written once as a [`Middleware`] and
+//! layered over any [`MarketDataSource`] with [`Layered`], instead of in the match statement
+//! of every consumer. Middlewares run in the order they were added, a message dropped by one
+//! is not seen by the next:
+//! ```no_run
+//! use binance_api_async::middleware::{self, Layered, Sample};
+//! use binance_api_async::{BinanceApi, MarketDataSource, Message};
+//!
+//! # async fn run() -> Result<(), binance_api_async::Error> {
+//! let mut api = BinanceApi::new();
+//! api.connect().await?;
+//! let mut source = Layered::new(api)
+//!     .layer(middleware::filter(|msg| !matches!(msg, Message::DepthUpdate(_))))
+//!     .layer(Sample::every(10))
+//!     .layer(middleware::inspect(|msg| tracing::debug!("delivering a {}", msg.event_type())));
+//! while let Some(msg) = source.next_message().await {
+//!     println!("{msg}");
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! A closure `FnMut(Message) -> Option<Message>` is a middleware too.
+
+use std::collections::HashMap;
+
+use crate::{MarketDataSource, Message, SubscribeInfo, Symbol};
+
+/// Processes a message before it is delivered.
+pub trait Middleware: Send {
+    /// The message to deliver, changed or not, `None` to drop it.
+    fn call(&mut self, msg: Message) -> Option<Message>;
+}
+
+impl<F> Middleware for F
+where
+    F: FnMut(Message) -> Option<Message> + Send,
+{
+    fn call(&mut self, msg: Message) -> Option<Message> {
+        self(msg)
+    }
+}
+
+/// Deliver the messages `predicate` returns `true` for.
+pub fn filter(mut predicate: impl FnMut(&Message) -> bool + Send) -> impl Middleware {
+    move |msg: Message| predicate(&msg).then_some(msg)
+}
+
+/// Deliver the messages as changed by `f`.
+pub fn map(mut f: impl FnMut(Message) -> Message + Send) -> impl Middleware {
+    move |msg: Message| Some(f(msg))
+}
+
+/// Call `f` on every message, e.g. to count them, and deliver them unchanged.
+pub fn inspect(mut f: impl FnMut(&Message) + Send) -> impl Middleware {
+    move |msg: Message| {
+        f(&msg);
+        Some(msg)
+    }
+}
+
+/// Deliver one message in `n` of each stream, the first one included.
+///
+/// Streams are told apart by symbol and event type, acknowledgements and errors are always
+/// delivered.
+#[derive(Debug, Clone)]
+pub struct Sample {
+    n: u64,
+    /// messages seen by stream
+    seen: HashMap<(Symbol, &'static str), u64>,
+}
+
+impl Sample {
+    /// Keep one message in `n`, every message if `n` is 0 or 1.
+    pub fn every(n: u64) -> Self {
+        Self {
+            n: n.max(1),
+            seen: HashMap::new(),
+        }
+    }
+}
+
+impl Middleware for Sample {
+    fn call(&mut self, msg: Message) -> Option<Message> {
+        let Some(symbol) = msg.symbol() else {
+            return Some(msg);
+        };
+        let seen = self
+            .seen
+            .entry((symbol.clone(), msg.event_type()))
+            .or_default();
+        let keep = seen.is_multiple_of(self.n);
+        *seen += 1;
+        keep.then_some(msg)
+    }
+}
+
+/// A [`MarketDataSource`] whose messages pass through middlewares, see the [module](self)
+/// documentation.
+pub struct Layered<S> {
+    source: S,
+    layers: Vec<Box<dyn Middleware>>,
+}
+
+impl<S: std::fmt::Debug> std::fmt::Debug for Layered<S> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Layered")
+            .field("source", &self.source)
+            .field("layers", &self.layers.len())
+            .finish()
+    }
+}
+
+impl<S> Layered<S> {
+    /// `source` without middlewares.
+    pub fn new(source: S) -> Self {
+        Self {
+            source,
+            layers: Vec::new(),
+        }
+    }
+
+    /// Run `middleware` on the messages, after the middlewares added before.
+    pub fn layer(mut self, middleware: impl Middleware + 'static) -> Self {
+        self.layers.push(Box::new(middleware));
+        self
+    }
+
+    /// Run the middlewares on `msg`, `None` if one of them dropped it.
+    pub fn call(&mut self, msg: Message) -> Option<Message> {
+        self.layers
+            .iter_mut()
+            .try_fold(msg, |msg, layer| layer.call(msg))
+    }
+
+    pub fn get_ref(&self) -> &S {
+        &self.source
+    }
+
+    /// The source, e.g. to read the metrics of a [`BinanceApi`](crate::BinanceApi).
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.source
+    }
+
+    pub fn into_inner(self) -> S {
+        self.source
+    }
+}
+
+impl<S: MarketDataSource + Send> MarketDataSource for Layered<S> {
+    /// The next message delivered by the middlewares, `None` when the source has ended.
+    async fn next_message(&mut self) -> Option<Message> {
+        loop {
+            let msg = self.source.next_message().await?;
+            if let Some(msg) = self.call(msg) {
+                return Some(msg);
+            }
+        }
+    }
+
+    async fn subscribe(&mut self, symbols: &[SubscribeInfo], id: Option<u64>) -> crate::Result<()> {
+        self.source.subscribe(symbols, id).await
+    }
+
+    async fn unsubscribe(&mut self, symbols: Vec<SubscribeInfo>) -> crate::Result<()> {
+        self.source.unsubscribe(symbols).await
+    }
+}
+
+#[cfg(all(test, feature = "trade"))]
+mod test {
+    use super::*;
+    use crate::test_util;
+    use std::collections::VecDeque;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    struct Fake(VecDeque<Message>);
+
+    impl MarketDataSource for Fake {
+        async fn next_message(&mut self) -> Option<Message> {
+            self.0.pop_front()
+        }
+
+        async fn subscribe(
+            &mut self,
+            _symbols: &[SubscribeInfo],
+            _id: Option<u64>,
+        ) -> crate::Result<()> {
+            Ok(())
+        }
+
+        async fn unsubscribe(&mut self, _symbols: Vec<SubscribeInfo>) -> crate::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn trade(symbol: Symbol, trade_id: u64) -> Message {
+        Message::Trade(test_util::trade(symbol, trade_id))
+    }
+
+    #[test]
+    fn layers_run_in_order() {
+        let mut messages: VecDeque<Message> = (0..6).map(|id| trade(Symbol::BTCUSDT, id)).collect();
+        messages.push_back(trade(Symbol::ETHUSDT, 6));
+        messages.push_back(Message::SubscribeSuccess {
+            result: None,
+            id: 1,
+        });
+        let seen = Arc::new(AtomicUsize::new(0));
+        let counter = seen.clone();
+        let mut source = Layered::new(Fake(messages))
+            .layer(filter(
+                |msg| !matches!(msg, Message::Trade(t) if t.trade_id == 0),
+            ))
+            .layer(Sample::every(2))
+            .layer(inspect(move |_| {
+                counter.fetch_add(1, Ordering::Relaxed);
+            }))
+            .layer(map(|msg| match msg {
+                Message::Trade(mut t) => {
+                    t.trade_id *= 10;
+                    Message::Trade(t)
+                }
+                msg => msg,
+            }));
+
+        let ids: Vec<Option<u64>> = futures::executor::block_on(async {
+            let mut ids = Vec::new();
+            while let Some(msg) = source.next_message().await {
+                ids.push(match msg {
+                    Message::Trade(t) => Some(t.trade_id),
+                    _ => None,
+                });
+            }
+            ids
+        });
+        // trade 0 filtered, then every other trade of each symbol, the ack always
+        assert_eq!(ids, vec![Some(10), Some(30), Some(50), Some(60), None]);
+        assert_eq!(seen.load(Ordering::Relaxed), ids.len());
+        assert!(source.into_inner().0.is_empty());
+    }
+}
@@ -0,0 +1,598 @@
+//! Parse messages on worker tasks, off the read loop.
+//!
+//! [`ParsePipeline`] moves a connected [`BinanceApi`] to a reader task that only reads frames
+//! and answers pings, and hands the frames to parser tasks. Bursts of large depth payloads then
+//! do not delay the pongs, and are parsed on several threads of the runtime. Messages are
+//! returned in the order they were received.
+//!
+//! Parsed messages wait in a queue of `capacity` messages for the consumer, what happens
+//! when it is full is set with [`ParsePipeline::overflow()`]. How far the consumer is behind
+//! is returned by [`ParsePipeline::lag()`], the messages dropped by
+//! [`ParsePipeline::dropped_by_stream()`].
+//!
+//! Subscriptions are requested by the reader task with [`BinanceApi::subscribe()`], and the
+//! parsed acknowledgements handed back to it, so they are counted and confirmed as they are
+//! without a pipeline.
+//! ```no_run
+//! use binance_api_async::pipeline::ParsePipeline;
+//! use binance_api_async::{BinanceApi, Delay, Feed, MarketDataSource, SubscribeInfo, Symbol};
+//!
+//! # async fn run() -> Result<(), binance_api_async::Error> {
+//! let mut api = BinanceApi::new();
+//! api.connect().await?;
+//! let mut pipeline = ParsePipeline::spawn(api, 4, 1024);
+//! let depth = Feed::FullDepth { delay: Delay::ONEHUNDRED };
+//! pipeline.subscribe(&[SubscribeInfo::new(Symbol::BTCUSDT, depth)], None).await?;
+//! while let Some(msg) = pipeline.next_message().await {
+//!     println!("{msg}");
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+
+use tokio::sync::{mpsc, oneshot, Notify};
+use tokio::task::JoinHandle;
+use tracing::warn;
+
+use crate::logging::{RateLimited, PARSE};
+use crate::metrics::Metrics;
+use crate::{BinanceApi, Error, MarketDataSource, Message, RawFrame, SubscribeInfo, Symbol};
+
+/// What to do with a parsed message when the queue for the consumer is full.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Overflow {
+    /// Wait for the consumer, reading from the connection stops once the other queues are
+    /// full too.
+    #[default]
+    Block,
+    /// Drop the oldest queued message.
+    DropOldest,
+    /// Drop the new message.
+    DropNewest,
+}
+
+/// The stream of a message, its symbol and event type, see
+/// [`ParsePipeline::dropped_by_stream()`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct StreamKey {
+    /// `None` for messages without a symbol like partial depths.
+    pub symbol: Option<Symbol>,
+    pub event_type: &'static str,
+}
+
+impl StreamKey {
+    pub fn of(msg: &Message) -> Self {
+        Self {
+            symbol: msg.symbol().cloned(),
+            event_type: msg.event_type(),
+        }
+    }
+}
+
+/// Requests for the connection, sent to the reader task with a channel for their result.
+#[derive(Debug)]
+enum Control {
+    Subscribe {
+        symbols: Vec<SubscribeInfo>,
+        id: Option<u64>,
+        result: oneshot::Sender<crate::Result<()>>,
+    },
+    Unsubscribe {
+        symbols: Vec<SubscribeInfo>,
+        result: oneshot::Sender<crate::Result<()>>,
+    },
+}
+
+/// A [`BinanceApi`] connection parsing on worker tasks, see the [module](self) documentation.
+#[derive(Debug)]
+pub struct ParsePipeline {
+    control: mpsc::Sender<Control>,
+    queue: Arc<Queue>,
+    tasks: Vec<JoinHandle<()>>,
+}
+
+impl ParsePipeline {
+    /// Read from `api` with `workers` parser tasks, at least one.
+    ///
+    /// Each stage queues up to `capacity` frames or messages, at least one, see [`Overflow`].
+    pub fn spawn(api: BinanceApi, workers: usize, capacity: usize) -> Self {
+        let capacity = capacity.max(1);
+        let (control, control_rx) = mpsc::channel(16);
+        let (results_tx, results) = mpsc::channel(capacity);
+        let (replies_tx, replies) = mpsc::unbounded_channel();
+        let metrics = api.metrics();
+        let queue = Arc::new(Queue::new(capacity, metrics.clone()));
+
+        let mut tasks = Vec::new();
+        let mut frames = Vec::new();
+        for _ in 0..workers.max(1) {
+            let (frames_tx, mut frames_rx) = mpsc::channel::<(u64, RawFrame)>(capacity);
+            let results_tx = results_tx.clone();
+            let replies_tx = replies_tx.clone();
+            let json = api.session.json();
+            let metrics = metrics.clone();
+            let span = api.span().clone();
+            let mut parse_warnings = RateLimited::default();
+            tasks.push(tokio::spawn(async move {
+                while let Some((seq, frame)) = frames_rx.recv().await {
+                    let msg = match json.parse(frame.text) {
+                        Ok(msg) => {
+                            metrics.record_message(&msg, frame.recv_time);
+                            if let Message::SubscribeSuccess { .. } | Message::Error(_) = msg {
+                                let _ = replies_tx.send(msg.clone());
+                            }
+                            Some(msg)
+                        }
+                        Err(e) => {
+                            metrics.record_parse_failure();
+                            if let Some(suppressed) = parse_warnings.allow() {
+                                warn!(
+                                    target: PARSE,
+                                    parent: &span,
+                                    suppressed,
+                                    "could not parse message: {e}"
+                                );
+                            }
+                            None
+                        }
+                    };
+                    if results_tx.send((seq, msg)).await.is_err() {
+                        return;
+                    }
+                }
+            }));
+            frames.push(frames_tx);
+        }
+        let reader = read(api, frames, results_tx, control_rx, replies);
+        tasks.push(tokio::spawn(reader));
+        tasks.push(tokio::spawn(reorder(results, queue.clone())));
+
+        Self {
+            control,
+            queue,
+            tasks,
+        }
+    }
+
+    /// Handle a full queue with `overflow`, [`Overflow::Block`] by default.
+    pub fn overflow(self, overflow: Overflow) -> Self {
+        self.queue.state.lock().unwrap().overflow = overflow;
+        self
+    }
+
+    /// Number of messages dropped because the queue was full.
+    pub fn dropped(&self) -> u64 {
+        self.queue.state.lock().unwrap().dropped
+    }
+
+    /// Number of messages dropped because the queue was full, by stream.
+    pub fn dropped_by_stream(&self) -> HashMap<StreamKey, u64> {
+        self.queue.state.lock().unwrap().dropped_by_stream.clone()
+    }
+
+    /// Number of parsed messages waiting for the consumer.
+    pub fn lag(&self) -> usize {
+        self.queue.state.lock().unwrap().messages.len()
+    }
+
+    /// Most parsed messages that were waiting for the consumer at once.
+    pub fn max_lag(&self) -> usize {
+        self.queue.state.lock().unwrap().max_len
+    }
+
+    /// Counters of the connection, see [`BinanceApi::metrics()`].
+    pub fn metrics(&self) -> Arc<Metrics> {
+        self.queue.metrics.clone()
+    }
+
+    /// The next message in receive order, `None` when the connection has ended.
+    pub async fn next_message(&mut self) -> Option<Message> {
+        self.queue.pop().await
+    }
+
+    /// Send the request of `control` to the reader task, and wait for its result.
+    async fn request(
+        &mut self,
+        control: impl FnOnce(oneshot::Sender<crate::Result<()>>) -> Control,
+    ) -> crate::Result<()> {
+        let (result, reply) = oneshot::channel();
+        self.control
+            .send(control(result))
+            .await
+            .map_err(|_| Error::NotConnected)?;
+        reply.await.map_err(|_| Error::NotConnected)?
+    }
+}
+
+impl Drop for ParsePipeline {
+    fn drop(&mut self) {
+        for task in &self.tasks {
+            task.abort();
+        }
+    }
+}
+
+impl MarketDataSource for ParsePipeline {
+    async fn next_message(&mut self) -> Option<Message> {
+        ParsePipeline::next_message(self).await
+    }
+
+    async fn subscribe(&mut self, symbols: &[SubscribeInfo], id: Option<u64>) -> crate::Result<()> {
+        let symbols = symbols.to_vec();
+        self.request(|result| Control::Subscribe {
+            symbols,
+            id,
+            result,
+        })
+        .await
+    }
+
+    async fn unsubscribe(&mut self, symbols: Vec<SubscribeInfo>) -> crate::Result<()> {
+        self.request(|result| Control::Unsubscribe { symbols, result })
+            .await
+    }
+}
+
+/// Read frames and hand them to the parser tasks in turn, until the connection ends.
+///
+/// Requests go through the session of `api`, the `replies` to them parsed by the parser tasks
+/// are handed back to it. Requests are served while a frame waits for its parser task, so a
+/// consumer that has not drained the queue still gets their results.
+async fn read(
+    mut api: BinanceApi,
+    frames: Vec<mpsc::Sender<(u64, RawFrame)>>,
+    results: mpsc::Sender<(u64, Option<Message>)>,
+    mut control: mpsc::Receiver<Control>,
+    mut replies: mpsc::UnboundedReceiver<Message>,
+) {
+    let mut seq = 0;
+    // read and waiting for room at its parser task
+    let mut pending: Option<RawFrame> = None;
+    // acknowledgements of requests that needed not be sent
+    let mut acks = VecDeque::new();
+    loop {
+        let worker = &frames[seq as usize % frames.len()];
+        tokio::select! {
+            Some(control) = control.recv() => {
+                match control {
+                    Control::Subscribe { symbols, id, result } => {
+                        let _ = result.send(api.subscribe(&symbols, id).await);
+                    }
+                    Control::Unsubscribe { symbols, result } => {
+                        let _ = result.send(api.unsubscribe(symbols).await);
+                    }
+                }
+                acks.extend(std::iter::from_fn(|| api.session.take_ack()));
+            }
+            Some(reply) = replies.recv() => api.session.replied(&reply),
+            permit = results.reserve(), if !acks.is_empty() => {
+                let Ok(permit) = permit else {
+                    return;
+                };
+                permit.send((seq, acks.pop_front()));
+                seq += 1;
+            }
+            permit = worker.reserve(), if pending.is_some() => {
+                let Ok(permit) = permit else {
+                    return;
+                };
+                permit.send((seq, pending.take().expect("a frame is pending")));
+                seq += 1;
+            }
+            frame = api.next_raw(), if pending.is_none() => {
+                let Some(frame) = frame else {
+                    return;
+                };
+                pending = Some(frame);
+            }
+        }
+    }
+}
+
+/// Queue the parsed messages in sequence order, until the parser tasks have ended.
+async fn reorder(mut results: mpsc::Receiver<(u64, Option<Message>)>, queue: Arc<Queue>) {
+    // parsed messages received before the ones preceding them, by sequence number
+    let mut pending = BTreeMap::new();
+    let mut next_seq = 0;
+    while let Some((seq, msg)) = results.recv().await {
+        pending.insert(seq, msg);
+        while let Some(msg) = pending.remove(&next_seq) {
+            next_seq += 1;
+            // frames that did not parse keep their place in the order
+            if let Some(msg) = msg {
+                queue.push(msg).await;
+            }
+        }
+    }
+    queue.close();
+}
+
+/// Bounded queue of parsed messages, for one producer and one consumer.
+#[derive(Debug)]
+struct Queue {
+    state: Mutex<QueueState>,
+    readable: Notify,
+    writable: Notify,
+    metrics: Arc<Metrics>,
+}
+
+#[derive(Debug)]
+struct QueueState {
+    messages: VecDeque<Message>,
+    capacity: usize,
+    overflow: Overflow,
+    dropped: u64,
+    dropped_by_stream: HashMap<StreamKey, u64>,
+    max_len: usize,
+    closed: bool,
+}
+
+impl QueueState {
+    fn drop_message(&mut self, msg: &Message) {
+        self.dropped += 1;
+        *self
+            .dropped_by_stream
+            .entry(StreamKey::of(msg))
+            .or_default() += 1;
+    }
+}
+
+impl Queue {
+    fn new(capacity: usize, metrics: Arc<Metrics>) -> Self {
+        Self {
+            state: Mutex::new(QueueState {
+                messages: VecDeque::with_capacity(capacity),
+                capacity,
+                overflow: Overflow::default(),
+                dropped: 0,
+                dropped_by_stream: HashMap::new(),
+                max_len: 0,
+                closed: false,
+            }),
+            readable: Notify::new(),
+            writable: Notify::new(),
+            metrics,
+        }
+    }
+
+    async fn push(&self, msg: Message) {
+        loop {
+            {
+                let mut state = self.state.lock().unwrap();
+                if state.messages.len() < state.capacity {
+                    state.messages.push_back(msg);
+                    state.max_len = state.max_len.max(state.messages.len());
+                    break;
+                }
+                match state.overflow {
+                    Overflow::Block => {}
+                    Overflow::DropOldest => {
+                        if let Some(oldest) = state.messages.pop_front() {
+                            state.drop_message(&oldest);
+                        }
+                        state.messages.push_back(msg);
+                        self.metrics.record_dropped();
+                        break;
+                    }
+                    Overflow::DropNewest => {
+                        state.drop_message(&msg);
+                        self.metrics.record_dropped();
+                        return;
+                    }
+                }
+            }
+            self.writable.notified().await;
+        }
+        self.readable.notify_one();
+    }
+
+    async fn pop(&self) -> Option<Message> {
+        loop {
+            {
+                let mut state = self.state.lock().unwrap();
+                if let Some(msg) = state.messages.pop_front() {
+                    self.writable.notify_one();
+                    return Some(msg);
+                }
+                if state.closed {
+                    return None;
+                }
+            }
+            self.readable.notified().await;
+        }
+    }
+
+    fn close(&self) {
+        self.state.lock().unwrap().closed = true;
+        self.readable.notify_one();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::messages::BookTicker;
+    use crate::test_util::{ticker, Action, MockServer};
+    use crate::{Feed, Symbol};
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn parsed_in_order() {
+        let ticker = |update_id| {
+            Message::BookTicker(BookTicker {
+                update_id,
+                ..ticker(Symbol::BTCUSDT, "1", "2")
+            })
+        };
+        let server = MockServer::start().await.unwrap();
+        let mut script: Vec<Action> = (0..200).map(|id| Action::message(&ticker(id))).collect();
+        script.insert(100, Action::malformed());
+        server.script(script);
+
+        let mut api = server.api();
+        api.connect().await.unwrap();
+        let mut pipeline = ParsePipeline::spawn(api, 4, 16);
+        let info = SubscribeInfo::new(Symbol::BTCUSDT, Feed::BookTicker);
+        pipeline.subscribe(&[info], Some(5)).await.unwrap();
+
+        assert!(matches!(
+            pipeline.next_message().await,
+            Some(Message::SubscribeSuccess { id: 5, .. })
+        ));
+        for id in 0..200 {
+            assert_eq!(pipeline.next_message().await, Some(ticker(id)));
+        }
+        assert_eq!(server.subscriptions(), ["btcusdt@bookTicker"]);
+
+        server.send(Action::Disconnect);
+        assert_eq!(pipeline.next_message().await, None);
+    }
+
+    #[tokio::test]
+    async fn zero_capacity() {
+        let ticker = Message::BookTicker(ticker(Symbol::BTCUSDT, "1", "2"));
+        let server = MockServer::start().await.unwrap();
+        server.script([Action::message(&ticker)]);
+        let mut api = server.api();
+        api.connect().await.unwrap();
+        let mut pipeline = ParsePipeline::spawn(api, 1, 0);
+        let info = SubscribeInfo::new(Symbol::BTCUSDT, Feed::BookTicker);
+        pipeline.subscribe(&[info], None).await.unwrap();
+        pipeline.next_message().await;
+        assert_eq!(pipeline.next_message().await, Some(ticker));
+        server.send(Action::Disconnect);
+        assert_eq!(pipeline.next_message().await, None);
+    }
+
+    #[tokio::test]
+    async fn requests_through_the_session() {
+        let server = MockServer::start().await.unwrap();
+        let mut api = server.api();
+        api.connect().await.unwrap();
+        let mut pipeline = ParsePipeline::spawn(api, 2, 16);
+        let info = || SubscribeInfo::new(Symbol::BTCUSDT, Feed::BookTicker);
+
+        pipeline.subscribe(&[info()], Some(5)).await.unwrap();
+        assert!(matches!(
+            pipeline.next_message().await,
+            Some(Message::SubscribeSuccess { id: 5, .. })
+        ));
+        // subscribed already, acknowledged without a request
+        pipeline.subscribe(&[info()], Some(6)).await.unwrap();
+        assert!(matches!(
+            pipeline.next_message().await,
+            Some(Message::SubscribeSuccess { id: 6, .. })
+        ));
+        let depth = Feed::FullDepth {
+            delay: crate::Delay::FIVEHUNDRED,
+        };
+        let unsupported = SubscribeInfo::new(Symbol::BTCUSDT, depth);
+        let rejected = pipeline.subscribe(&[unsupported], None).await;
+        assert!(matches!(rejected, Err(Error::UnsupportedFeed { .. })));
+
+        // the parsed acknowledgement reaches the session
+        while pipeline.metrics().round_trip().is_none() {
+            tokio::time::sleep(std::time::Duration::from_millis(1)).await;
+        }
+
+        pipeline.unsubscribe(vec![info()]).await.unwrap();
+        assert_eq!(server.subscriptions(), ["btcusdt@bookTicker"]);
+        pipeline.unsubscribe(vec![info()]).await.unwrap();
+        // the acknowledgements of both, the last one sent once the server unsubscribed
+        pipeline.next_message().await;
+        pipeline.next_message().await;
+        assert!(server.subscriptions().is_empty());
+    }
+
+    #[tokio::test]
+    async fn overflow() {
+        let ticker = |update_id| {
+            Message::BookTicker(BookTicker {
+                update_id,
+                ..ticker(Symbol::BTCUSDT, "1", "2")
+            })
+        };
+        let server = MockServer::start().await.unwrap();
+        server.script((0..50).map(|id| Action::message(&ticker(id))));
+
+        for (overflow, kept) in [(Overflow::DropOldest, 46..50), (Overflow::DropNewest, 0..3)] {
+            let mut api = server.api();
+            api.connect().await.unwrap();
+            let mut pipeline = ParsePipeline::spawn(api, 2, 4).overflow(overflow);
+            let info = SubscribeInfo::new(Symbol::BTCUSDT, Feed::BookTicker);
+            pipeline.subscribe(&[info], None).await.unwrap();
+
+            // the acknowledgement and 50 tickers into a queue of 4
+            while pipeline.dropped() < 47 {
+                tokio::time::sleep(std::time::Duration::from_millis(1)).await;
+            }
+            if overflow == Overflow::DropNewest {
+                assert!(matches!(
+                    pipeline.next_message().await,
+                    Some(Message::SubscribeSuccess { .. })
+                ));
+            }
+            for id in kept {
+                assert_eq!(pipeline.next_message().await, Some(ticker(id)));
+            }
+            assert_eq!(pipeline.dropped(), 47);
+            assert_eq!(pipeline.metrics().dropped(), 47);
+            assert_eq!(pipeline.max_lag(), 4);
+            let tickers = StreamKey {
+                symbol: Some(Symbol::BTCUSDT),
+                event_type: "bookTicker",
+            };
+            let dropped = pipeline.dropped_by_stream();
+            match overflow {
+                // the acknowledgement too
+                Overflow::DropOldest => assert_eq!(dropped[&tickers], 46),
+                _ => assert_eq!(dropped[&tickers], 47),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn requests_while_the_queue_is_full() {
+        let ticker = |update_id| {
+            Message::BookTicker(BookTicker {
+                update_id,
+                ..ticker(Symbol::BTCUSDT, "1", "2")
+            })
+        };
+        let server = MockServer::start().await.unwrap();
+        server.script((0..50).map(|id| Action::message(&ticker(id))));
+        let mut api = server.api();
+        api.connect().await.unwrap();
+        let mut pipeline = ParsePipeline::spawn(api, 1, 2);
+        let tickers = SubscribeInfo::new(Symbol::BTCUSDT, Feed::BookTicker);
+        pipeline.subscribe(&[tickers], Some(1)).await.unwrap();
+        while pipeline.lag() < 2 {
+            tokio::time::sleep(std::time::Duration::from_millis(1)).await;
+        }
+
+        // the reader waits for the consumer to make room, yet serves the request
+        let trades = SubscribeInfo::new(Symbol::BTCUSDT, Feed::Trade);
+        let subscribed = tokio::time::timeout(
+            std::time::Duration::from_secs(5),
+            pipeline.subscribe(&[trades], Some(2)),
+        )
+        .await;
+        assert!(matches!(subscribed, Ok(Ok(()))));
+
+        let mut ids = Vec::new();
+        while ids.len() < 50 {
+            match pipeline.next_message().await.unwrap() {
+                Message::BookTicker(ticker) => ids.push(ticker.update_id),
+                Message::SubscribeSuccess { .. } => {}
+                msg => panic!("unexpected {msg:?}"),
+            }
+        }
+        assert_eq!(ids, (0..50).collect::<Vec<_>>());
+        assert_eq!(
+            server.subscriptions(),
+            ["btcusdt@bookTicker", "btcusdt@trade"]
+        );
+    }
+}
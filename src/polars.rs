@@ -0,0 +1,267 @@
+//! Conversion of messages to Polars data frames, requires the `polars` feature.
+//!
+//! [`ToPolars::to_polars()`] turns messages buffered from a subscription into a [`DataFrame`]
+//! with one column per field, the columns of [`ToArrow`](crate::arrow::ToArrow), so research
+//! goes from a live stream to analysis without intermediate files.
+//!
+//! Prices and quantities are `Float64`, rounded to the nearest float, Polars computes on floats.
+//! Times are `UInt64` milliseconds since epoch and symbols are upper case strings.
+//! Order book messages and books have one row per price level, with an `is_bid` column for the
+//! side.
+//!
+//! ```no_run
+//! use binance_api_async::messages::Trade;
+//! use binance_api_async::polars::ToPolars;
+//!
+//! # fn trades() -> Vec<Trade> { vec![] }
+//! let trades: Vec<Trade> = trades();
+//! let df = Trade::to_polars(&trades).unwrap();
+//! assert_eq!(df.height(), trades.len());
+//! ```
+
+pub use ::polars::prelude::DataFrame;
+use ::polars::prelude::{Column, NamedFrom, Series};
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+
+use crate::book::OrderBook;
+use crate::messages::{AggTrade, BookTicker, DepthUpdate, Kline, PartialDepth, Trade};
+use crate::sink::symbol_name;
+
+/// A message type, or the [`OrderBook`], that can be converted to a Polars [`DataFrame`].
+pub trait ToPolars: Sized {
+    /// Convert `batch` to a data frame, an empty batch gives the columns without rows.
+    fn to_polars<'a>(batch: impl IntoIterator<Item = &'a Self>) -> crate::Result<DataFrame>
+    where
+        Self: 'a;
+}
+
+impl ToPolars for AggTrade {
+    fn to_polars<'a>(batch: impl IntoIterator<Item = &'a Self>) -> crate::Result<DataFrame> {
+        let rows: Vec<&AggTrade> = batch.into_iter().collect();
+        frame(vec![
+            symbols("symbol", rows.iter().map(|t| &t.symbol)),
+            column("event_time", rows.iter().map(|t| t.event_time)),
+            column("trade_id", rows.iter().map(|t| t.trade_id)),
+            floats("price", rows.iter().map(|t| t.price)),
+            floats("quantity", rows.iter().map(|t| t.quantity)),
+            column("first_trade_id", rows.iter().map(|t| t.first_trade_id)),
+            column("last_trade_id", rows.iter().map(|t| t.last_trade_id)),
+            column("trade_time", rows.iter().map(|t| t.trade_time)),
+            column("is_market_maker", rows.iter().map(|t| t.is_market_maker)),
+        ])
+    }
+}
+
+impl ToPolars for Trade {
+    fn to_polars<'a>(batch: impl IntoIterator<Item = &'a Self>) -> crate::Result<DataFrame> {
+        let rows: Vec<&Trade> = batch.into_iter().collect();
+        frame(vec![
+            symbols("symbol", rows.iter().map(|t| &t.symbol)),
+            column("event_time", rows.iter().map(|t| t.event_time)),
+            column("trade_id", rows.iter().map(|t| t.trade_id)),
+            floats("price", rows.iter().map(|t| t.price)),
+            floats("quantity", rows.iter().map(|t| t.quantity)),
+            column("trade_time", rows.iter().map(|t| t.trade_time)),
+            column("is_market_maker", rows.iter().map(|t| t.is_market_maker)),
+        ])
+    }
+}
+
+impl ToPolars for BookTicker {
+    fn to_polars<'a>(batch: impl IntoIterator<Item = &'a Self>) -> crate::Result<DataFrame> {
+        let rows: Vec<&BookTicker> = batch.into_iter().collect();
+        frame(vec![
+            symbols("symbol", rows.iter().map(|t| &t.symbol)),
+            column("update_id", rows.iter().map(|t| t.update_id)),
+            floats("best_bid_price", rows.iter().map(|t| t.best_bid_price)),
+            floats("best_bid_qty", rows.iter().map(|t| t.best_bid_qty)),
+            floats("best_ask_price", rows.iter().map(|t| t.best_ask_price)),
+            floats("best_ask_qty", rows.iter().map(|t| t.best_ask_qty)),
+        ])
+    }
+}
+
+impl ToPolars for DepthUpdate {
+    /// One row per price level, bids first.
+    fn to_polars<'a>(batch: impl IntoIterator<Item = &'a Self>) -> crate::Result<DataFrame> {
+        let levels: Vec<(&DepthUpdate, bool, &[Decimal; 2])> = batch
+            .into_iter()
+            .flat_map(|du| {
+                let bids = du.bids.iter().map(move |l| (du, true, l));
+                let asks = du.asks.iter().map(move |l| (du, false, l));
+                bids.chain(asks)
+            })
+            .collect();
+        frame(vec![
+            symbols("symbol", levels.iter().map(|l| &l.0.symbol)),
+            column("event_time", levels.iter().map(|l| l.0.event_time)),
+            column(
+                "first_update_id",
+                levels.iter().map(|l| l.0.first_update_id),
+            ),
+            column(
+                "final_update_id",
+                levels.iter().map(|l| l.0.final_update_id),
+            ),
+            column("is_bid", levels.iter().map(|l| l.1)),
+            floats("price", levels.iter().map(|l| l.2[0])),
+            floats("quantity", levels.iter().map(|l| l.2[1])),
+        ])
+    }
+}
+
+impl ToPolars for PartialDepth {
+    /// One row per price level, bids first, `level` is 0 for the best price of each side.
+    fn to_polars<'a>(batch: impl IntoIterator<Item = &'a Self>) -> crate::Result<DataFrame> {
+        let levels: Vec<(u64, bool, u32, &[Decimal; 2])> = batch
+            .into_iter()
+            .flat_map(|pd| {
+                let id = pd.last_update_id;
+                let bids = (0..).zip(&pd.bids).map(move |(i, l)| (id, true, i, l));
+                let asks = (0..).zip(&pd.asks).map(move |(i, l)| (id, false, i, l));
+                bids.chain(asks)
+            })
+            .collect();
+        frame(vec![
+            column("last_update_id", levels.iter().map(|l| l.0)),
+            column("is_bid", levels.iter().map(|l| l.1)),
+            column("level", levels.iter().map(|l| l.2)),
+            floats("price", levels.iter().map(|l| l.3[0])),
+            floats("quantity", levels.iter().map(|l| l.3[1])),
+        ])
+    }
+}
+
+impl ToPolars for OrderBook {
+    /// The columns of [`PartialDepth`] with the symbol of the book, one row per price level of
+    /// every book, e.g. snapshots of a book taken every second.
+    fn to_polars<'a>(batch: impl IntoIterator<Item = &'a Self>) -> crate::Result<DataFrame> {
+        let levels: Vec<(&OrderBook, bool, u32, [Decimal; 2])> = batch
+            .into_iter()
+            .flat_map(|book| {
+                let bids = (0..).zip(book.bids()).map(move |(i, l)| (book, true, i, l));
+                let asks = (0..)
+                    .zip(book.asks())
+                    .map(move |(i, l)| (book, false, i, l));
+                bids.chain(asks)
+            })
+            .collect();
+        frame(vec![
+            symbols("symbol", levels.iter().map(|l| l.0.symbol())),
+            column(
+                "last_update_id",
+                levels.iter().map(|l| l.0.last_update_id()),
+            ),
+            column("is_bid", levels.iter().map(|l| l.1)),
+            column("level", levels.iter().map(|l| l.2)),
+            floats("price", levels.iter().map(|l| l.3[0])),
+            floats("quantity", levels.iter().map(|l| l.3[1])),
+        ])
+    }
+}
+
+impl ToPolars for Kline {
+    fn to_polars<'a>(batch: impl IntoIterator<Item = &'a Self>) -> crate::Result<DataFrame> {
+        let rows: Vec<&Kline> = batch.into_iter().collect();
+        let k = || rows.iter().map(|k| &k.kline);
+        frame(vec![
+            symbols("symbol", rows.iter().map(|k| &k.symbol)),
+            column("event_time", rows.iter().map(|k| k.event_time)),
+            column("interval", k().map(|k| k.interval.as_str())),
+            column("open_time", k().map(|k| k.open_time)),
+            column("close_time", k().map(|k| k.close_time)),
+            floats("open", k().map(|k| k.open)),
+            floats("high", k().map(|k| k.high)),
+            floats("low", k().map(|k| k.low)),
+            floats("close", k().map(|k| k.close)),
+            floats("volume", k().map(|k| k.volume)),
+            floats("quote_volume", k().map(|k| k.quote_volume)),
+            floats("taker_buy_volume", k().map(|k| k.taker_buy_volume)),
+            floats(
+                "taker_buy_quote_volume",
+                k().map(|k| k.taker_buy_quote_volume),
+            ),
+            column("trades", k().map(|k| k.trades)),
+            column("first_trade_id", k().map(|k| k.first_trade_id)),
+            column("last_trade_id", k().map(|k| k.last_trade_id)),
+            column("is_closed", k().map(|k| k.is_closed)),
+        ])
+    }
+}
+
+fn frame(columns: Vec<Column>) -> crate::Result<DataFrame> {
+    Ok(DataFrame::new(columns)?)
+}
+
+fn column<T>(name: &str, values: impl Iterator<Item = T>) -> Column
+where
+    Series: NamedFrom<Vec<T>, [T]>,
+{
+    Series::new(name.into(), values.collect::<Vec<T>>()).into()
+}
+
+fn floats(name: &str, values: impl Iterator<Item = Decimal>) -> Column {
+    // every Decimal has a nearest f64
+    column(name, values.map(|d| d.to_f64().unwrap_or(f64::NAN)))
+}
+
+fn symbols<'a>(name: &str, values: impl Iterator<Item = &'a crate::Symbol>) -> Column {
+    column(name, values.map(symbol_name))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_util::dec;
+    use crate::Symbol;
+    use smallvec::smallvec;
+
+    #[test]
+    fn trades_and_books_to_frames() {
+        let trade = Trade {
+            event_time: 2,
+            symbol: Symbol::BTCUSDT,
+            trade_id: 7,
+            price: dec("65000.01"),
+            quantity: dec("0.5"),
+            trade_time: 1,
+            is_market_maker: true,
+        };
+        let df = Trade::to_polars(&[trade.clone(), trade]).unwrap();
+
+        assert_eq!(df.shape(), (2, 7));
+        let price = df.column("price").unwrap().f64().unwrap();
+        assert_eq!(price.get(0), Some(65000.01));
+        let symbol = df.column("symbol").unwrap().str().unwrap();
+        assert_eq!(symbol.get(1), Some("BTCUSDT"));
+        assert_eq!(Trade::to_polars(&[]).unwrap().shape(), (0, 7));
+
+        let snapshot = PartialDepth {
+            last_update_id: 5,
+            bids: smallvec![[dec("2"), dec("1")], [dec("1"), dec("1")]],
+            asks: smallvec![[dec("3"), dec("1")]],
+        };
+        let book = OrderBook::from_snapshot(Symbol::BTCUSDT, &snapshot);
+        let df = OrderBook::to_polars([&book, &book]).unwrap();
+
+        assert_eq!(df.height(), 6);
+        let level: Vec<_> = df
+            .column("level")
+            .unwrap()
+            .u32()
+            .unwrap()
+            .into_no_null_iter()
+            .collect();
+        assert_eq!(level, vec![0, 1, 0, 0, 1, 0]);
+        let price = df.column("price").unwrap().f64().unwrap();
+        assert_eq!(price.get(1), Some(1.0));
+        assert_eq!(
+            PartialDepth::to_polars([&snapshot])
+                .unwrap()
+                .column("price")
+                .unwrap(),
+            &df.column("price").unwrap().slice(0, 3)
+        );
+    }
+}
@@ -0,0 +1,208 @@
+//! Recycled depth messages, for parsing without allocating.
+//!
+//! A [`Pool`] parses depth payloads into messages released earlier, so their level buffers are
+//! reused. A parsed message is returned in a [`Pooled`] guard, which gives the message back to
+//! the pool when dropped. Once as many messages are in use as the consumer holds at a time,
+//! parsing no longer allocates.
+//! ```no_run
+//! use binance_api_async::messages::DepthUpdate;
+//! use binance_api_async::pool::Pool;
+//! use binance_api_async::{BinanceApi, Feed, Delay, SubscribeInfo, Symbol};
+//!
+//! # async fn run() -> Result<(), binance_api_async::Error> {
+//! let mut api = BinanceApi::new();
+//! api.connect().await?;
+//! let depth = Feed::FullDepth { delay: Delay::ONEHUNDRED };
+//! api.subscribe(&[SubscribeInfo::new(Symbol::BTCUSDT, depth)], None).await?;
+//!
+//! let pool = Pool::<DepthUpdate>::new(8);
+//! while let Some(frame) = api.next_raw().await {
+//!     let Ok(update) = pool.parse(&frame.text) else {
+//!         continue;
+//!     };
+//!     println!("{} bids changed", update.bids.len());
+//!     // the update goes back to the pool here
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use std::ops::{Deref, DerefMut};
+use std::sync::{Arc, Mutex};
+
+use crate::messages::{DepthUpdate, PartialDepth};
+
+/// Messages a [`Pool`] can parse into.
+pub trait Recycle: Sized {
+    /// Parse a new message.
+    fn parse(json: &str) -> crate::Result<Self>;
+
+    /// Parse into `self`, reusing its buffers.
+    fn parse_into(&mut self, json: &str) -> crate::Result<()>;
+}
+
+impl Recycle for DepthUpdate {
+    fn parse(json: &str) -> crate::Result<Self> {
+        Ok(serde_json::from_str(json)?)
+    }
+
+    fn parse_into(&mut self, json: &str) -> crate::Result<()> {
+        DepthUpdate::parse_into(self, json)
+    }
+}
+
+impl Recycle for PartialDepth {
+    fn parse(json: &str) -> crate::Result<Self> {
+        Ok(serde_json::from_str(json)?)
+    }
+
+    fn parse_into(&mut self, json: &str) -> crate::Result<()> {
+        PartialDepth::parse_into(self, json)
+    }
+}
+
+/// Pool of released messages, see the [module](self) documentation.
+///
+/// Clones share the pool.
+#[derive(Debug)]
+pub struct Pool<T> {
+    shared: Arc<Shared<T>>,
+}
+
+#[derive(Debug)]
+struct Shared<T> {
+    free: Mutex<Vec<T>>,
+    capacity: usize,
+}
+
+impl<T> Clone for Pool<T> {
+    fn clone(&self) -> Self {
+        Self {
+            shared: self.shared.clone(),
+        }
+    }
+}
+
+impl<T: Recycle> Pool<T> {
+    /// Pool keeping up to `capacity` released messages, more are dropped.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            shared: Arc::new(Shared {
+                free: Mutex::new(Vec::with_capacity(capacity)),
+                capacity,
+            }),
+        }
+    }
+
+    /// Parse `json` into a released message, or a new one if there is none.
+    ///
+    /// The released message stays in the pool when `json` does not parse.
+    pub fn parse(&self, json: &str) -> crate::Result<Pooled<T>> {
+        let released = self.shared.free.lock().unwrap().pop();
+        let msg = match released {
+            Some(mut msg) => match msg.parse_into(json) {
+                Ok(()) => msg,
+                Err(e) => {
+                    self.shared.release(msg);
+                    return Err(e);
+                }
+            },
+            None => T::parse(json)?,
+        };
+        Ok(Pooled {
+            msg: Some(msg),
+            shared: self.shared.clone(),
+        })
+    }
+
+    /// Number of released messages waiting to be reused.
+    pub fn available(&self) -> usize {
+        self.shared.free.lock().unwrap().len()
+    }
+}
+
+impl<T> Shared<T> {
+    fn release(&self, msg: T) {
+        let mut free = self.free.lock().unwrap();
+        if free.len() < self.capacity {
+            free.push(msg);
+        }
+    }
+}
+
+/// A message from a [`Pool`], given back to it when dropped.
+#[derive(Debug)]
+pub struct Pooled<T> {
+    // only `None` after `into_inner()`
+    msg: Option<T>,
+    shared: Arc<Shared<T>>,
+}
+
+impl<T> Pooled<T> {
+    /// Take the message out of the pool, its buffers are not reused.
+    pub fn into_inner(mut self) -> T {
+        self.msg
+            .take()
+            .expect("pooled message is present until dropped")
+    }
+}
+
+impl<T> Deref for Pooled<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        self.msg
+            .as_ref()
+            .expect("pooled message is present until dropped")
+    }
+}
+
+impl<T> DerefMut for Pooled<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        self.msg
+            .as_mut()
+            .expect("pooled message is present until dropped")
+    }
+}
+
+impl<T> Drop for Pooled<T> {
+    fn drop(&mut self) {
+        if let Some(msg) = self.msg.take() {
+            self.shared.release(msg);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const UPDATE: &str = r#"{"e":"depthUpdate","E":1,"s":"BTCUSDT","U":1,"u":2,"b":[["1.0","2"],["0.9","1"]],"a":[["1.1","3"]]}"#;
+
+    #[test]
+    fn reuses_buffers() {
+        let pool = Pool::<DepthUpdate>::new(1);
+        let first = pool.parse(UPDATE).unwrap();
+        let second = pool.parse(UPDATE).unwrap();
+        assert_eq!(*first, *second);
+        let bids = first.bids.as_ptr();
+        drop(first);
+        // over capacity
+        drop(second);
+        assert_eq!(pool.available(), 1);
+
+        let third = pool.parse(UPDATE).unwrap();
+        assert_eq!(third.bids.as_ptr(), bids);
+        assert_eq!(third.asks.len(), 1);
+        assert_eq!(pool.available(), 0);
+
+        // a payload that does not parse keeps the released message in the pool
+        drop(third);
+        assert!(pool.parse(r#"{"e":"depthUpdate"}"#).is_err());
+        assert_eq!(pool.available(), 1);
+
+        let owned = pool.parse(UPDATE).unwrap().into_inner();
+        assert_eq!(owned.bids.as_ptr(), bids);
+        assert_eq!(pool.available(), 0);
+    }
+}
@@ -0,0 +1,132 @@
+//! Websocket connections through an HTTP proxy, see
+//! [`BinanceApiBuilder::proxy()`](crate::BinanceApiBuilder::proxy).
+//!
+//! The proxy is asked to open a tunnel to the host of the websocket with a `CONNECT` request,
+//! TLS and the websocket handshake then go through the tunnel. The user and password of the
+//! proxy url, if any, are sent as basic authentication.
+
+use std::io::{Error, ErrorKind};
+
+use base64::Engine;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+use tokio_tungstenite::tungstenite::http::Uri;
+
+/// A connection to the host of `url` through the proxy at `proxy`.
+pub(crate) async fn tunnel(proxy: &str, url: &str) -> std::io::Result<TcpStream> {
+    let invalid = |e: &dyn std::fmt::Display| Error::new(ErrorKind::InvalidInput, e.to_string());
+    let proxy: Uri = proxy.parse().map_err(|e| invalid(&e))?;
+    let url: Uri = url.parse().map_err(|e| invalid(&e))?;
+    let (Some(proxy_auth), Some(host)) = (proxy.authority(), url.host()) else {
+        return Err(invalid(&"the proxy and the websocket url need a host"));
+    };
+    let default_port = match url.scheme_str() {
+        Some("wss") => 443,
+        _ => 80,
+    };
+    let port = url.port_u16().unwrap_or(default_port);
+    let target = format!("{host}:{port}");
+
+    let address = match proxy_auth.port_u16() {
+        Some(port) => format!("{}:{port}", proxy_auth.host()),
+        None => format!("{}:80", proxy_auth.host()),
+    };
+    let mut stream = BufReader::new(TcpStream::connect(address).await?);
+    let mut request = format!("CONNECT {target} HTTP/1.1\r\nHost: {target}\r\n");
+    if let Some((credentials, _)) = proxy_auth.as_str().rsplit_once('@') {
+        let encoded = base64::engine::general_purpose::STANDARD.encode(credentials);
+        request.push_str(&format!("Proxy-Authorization: Basic {encoded}\r\n"));
+    }
+    request.push_str("\r\n");
+    stream.write_all(request.as_bytes()).await?;
+
+    // the status line, then headers until an empty line
+    let mut status = String::new();
+    stream.read_line(&mut status).await?;
+    let mut line = String::new();
+    while stream.read_line(&mut line).await? > 2 {
+        line.clear();
+    }
+    let status = status.trim_end();
+    match status.split_whitespace().nth(1) {
+        Some(code) if code.starts_with('2') => Ok(stream.into_inner()),
+        _ => Err(Error::new(
+            ErrorKind::ConnectionRefused,
+            format!("proxy refused the tunnel to {target}: {status}"),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::{Arc, Mutex};
+
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::{TcpListener, TcpStream};
+
+    use crate::test_util::{Action, MockServer};
+    use crate::{BinanceApi, Feed, Message, SubscribeInfo, Symbol};
+
+    /// A proxy opening one tunnel, keeping the request it received.
+    async fn proxy(status: &'static str) -> (String, Arc<Mutex<String>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://user:secret@{}", listener.local_addr().unwrap());
+        let received = Arc::new(Mutex::new(String::new()));
+        let request = received.clone();
+        tokio::spawn(async move {
+            let (mut client, _) = listener.accept().await.unwrap();
+            let mut buf = vec![0; 1024];
+            let n = client.read(&mut buf).await.unwrap();
+            let text = String::from_utf8_lossy(&buf[..n]).to_string();
+            let target = text.split_whitespace().nth(1).unwrap().to_string();
+            *request.lock().unwrap() = text;
+            client
+                .write_all(format!("HTTP/1.1 {status}\r\nVia: test\r\n\r\n").as_bytes())
+                .await
+                .unwrap();
+            if status.starts_with('2') {
+                let mut server = TcpStream::connect(target).await.unwrap();
+                let _ = tokio::io::copy_bidirectional(&mut client, &mut server).await;
+            }
+        });
+        (url, received)
+    }
+
+    #[tokio::test]
+    async fn connect_through_proxy() {
+        let server = MockServer::start().await.unwrap();
+        server.script([Action::Text("{}".into())]);
+        let (url, request) = proxy("200 Connection established").await;
+        let mut api = BinanceApi::builder()
+            .url(&server.url())
+            .allow_plaintext(true)
+            .proxy(Some(&url))
+            .build();
+        api.connect().await.unwrap();
+        api.subscribe(&[SubscribeInfo::new(Symbol::BTCUSDT, Feed::Trade)], None)
+            .await
+            .unwrap();
+        assert!(matches!(
+            api.next_message().await,
+            Some(Message::SubscribeSuccess { .. })
+        ));
+        let request = request.lock().unwrap().clone();
+        let target = server.url().replace("ws://", "");
+        let target = target.trim_end_matches("/ws");
+        assert!(request.starts_with(&format!("CONNECT {target} HTTP/1.1\r\n")));
+        // user:secret
+        assert!(request.contains("Proxy-Authorization: Basic dXNlcjpzZWNyZXQ=\r\n"));
+
+        let (url, _) = proxy("407 Proxy Authentication Required").await;
+        let mut api = BinanceApi::builder()
+            .url(&server.url())
+            .allow_plaintext(true)
+            .proxy(Some(&url))
+            .build();
+        let error = api.connect().await.unwrap_err().to_string();
+        assert!(
+            error.contains("407 Proxy Authentication Required"),
+            "{error}"
+        );
+    }
+}
@@ -0,0 +1,529 @@
+//! Record messages to newline delimited json files.
+//!
+//! Every line is a [`Record`], the frame as Binance sent it and the local receive time in
+//! milliseconds since epoch:
+//! ```text
+//! {"recv_time":1717200000123,"msg":{"e":"aggTrade","E":1717200000100,"a":424951,...}}
+//! ```
+//!
+//! Frames are recorded with [`Recorder::record_raw()`], and by the recorder as a [`Sink`] from
+//! the raw frame of each [`Envelope`]. Messages without a frame, recorded with
+//! [`Recorder::record()`], are serialized by [`Message`], with the field names of Binance but
+//! without the event type `e`.
+//!
+//! Files are written to one directory and rotated on size and/or time, see [`Rotation`].
+//! Writes are buffered and blocking, call [`Recorder::flush()`] before shutting down.
+//!
+//! With the `compression` feature files can be written through a gzip or zstd encoder, see
+//! [`Compression`]. [`RecordReader`] reads recordings back, compressed or not.
+
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+use crate::clock::{Clock, SystemClock};
+use crate::sink::Sink;
+use crate::{Envelope, Message};
+
+/// A recorded message, one line in the recording.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Record {
+    /// Local receive time in milliseconds since epoch.
+    pub recv_time: u64,
+    pub msg: Message,
+}
+
+/// When the [`Recorder`] starts a new file, never by default.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Rotation {
+    /// Start a new file once the current file has reached this many bytes.
+    pub max_bytes: Option<u64>,
+    /// Start a new file every interval, aligned to the clock,
+    /// e.g. one file per hour starting at every whole hour.
+    pub interval: Option<Duration>,
+}
+
+/// Compression of the recorded files, none by default.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Compression {
+    #[default]
+    None,
+    /// `.ndjson.gz` files.
+    #[cfg(feature = "compression")]
+    Gzip,
+    /// `.ndjson.zst` files, smaller and faster than gzip.
+    #[cfg(feature = "compression")]
+    Zstd,
+}
+
+impl Compression {
+    fn extension(self) -> &'static str {
+        match self {
+            Compression::None => "ndjson",
+            #[cfg(feature = "compression")]
+            Compression::Gzip => "ndjson.gz",
+            #[cfg(feature = "compression")]
+            Compression::Zstd => "ndjson.zst",
+        }
+    }
+}
+
+/// Writes [`Record`]s to rotating ndjson files.
+#[derive(Debug)]
+pub struct Recorder {
+    dir: PathBuf,
+    prefix: String,
+    rotation: Rotation,
+    compression: Compression,
+    clock: Arc<dyn Clock>,
+    file: Option<Output>,
+    path: Option<PathBuf>,
+    written: u64,
+    /// end of the current time period, in milliseconds since epoch
+    period_end: u64,
+    sequence: u64,
+}
+
+impl Recorder {
+    /// Record to files named `<prefix>-<utc time>-<n>.ndjson` in `dir`,
+    /// `dir` is created if it does not exist.
+    pub fn new(dir: impl Into<PathBuf>, prefix: &str, rotation: Rotation) -> crate::Result<Self> {
+        let dir = dir.into();
+        std::fs::create_dir_all(&dir)?;
+        Ok(Self {
+            dir,
+            prefix: prefix.to_string(),
+            rotation,
+            compression: Compression::None,
+            clock: Arc::new(SystemClock),
+            file: None,
+            path: None,
+            written: 0,
+            period_end: 0,
+            sequence: 0,
+        })
+    }
+
+    /// Compress the files written from now on,
+    /// [`Rotation::max_bytes`] is then the uncompressed size of a file.
+    pub fn compressed(mut self, compression: Compression) -> Self {
+        self.compression = compression;
+        self
+    }
+
+    /// Read receive times of [`Recorder::record()`] from `clock`.
+    pub fn clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Record `msg`, received now.
+    pub fn record(&mut self, msg: &Message) -> crate::Result<()> {
+        self.record_at(self.clock.now_millis(), msg)
+    }
+
+    /// Record `msg` received at `recv_time`.
+    pub fn record_at(&mut self, recv_time: u64, msg: &Message) -> crate::Result<()> {
+        let line = serde_json::to_string(msg)?;
+        self.record_raw(recv_time, &line)
+    }
+
+    /// Record a raw text frame, as received from Binance, at `recv_time`.
+    pub fn record_raw(&mut self, recv_time: u64, frame: &str) -> crate::Result<()> {
+        let file = self.file_for(recv_time)?;
+        // newlines can only be whitespace in a json frame, inside strings they are escaped
+        let frame = frame.replace(['\n', '\r'], " ");
+        let line = format!("{{\"recv_time\":{recv_time},\"msg\":{frame}}}\n");
+        file.write_all(line.as_bytes())?;
+        self.written += line.len() as u64;
+        Ok(())
+    }
+
+    /// Flush buffered records to the current file.
+    pub fn flush(&mut self) -> crate::Result<()> {
+        if let Some(file) = self.file.as_mut() {
+            file.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Path of the file currently written to.
+    pub fn current_path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    fn file_for(&mut self, now: u64) -> crate::Result<&mut Output> {
+        let full = self
+            .rotation
+            .max_bytes
+            .is_some_and(|max| self.written >= max);
+        let expired = self.rotation.interval.is_some() && now >= self.period_end;
+
+        if self.file.is_none() || full || expired {
+            self.rotate(now)?;
+        }
+        Ok(self.file.as_mut().expect("file opened by rotate"))
+    }
+
+    fn rotate(&mut self, now: u64) -> crate::Result<()> {
+        if let Some(file) = self.file.take() {
+            file.finish()?;
+        }
+
+        if let Some(interval) = self.rotation.interval {
+            let interval = (interval.as_millis() as u64).max(1);
+            self.period_end = now - now % interval + interval;
+        }
+
+        let time = chrono::DateTime::from_timestamp_millis(now as i64)
+            .unwrap_or_default()
+            .format("%Y%m%dT%H%M%S");
+        // files of an earlier recorder in the same second are kept, the next sequence is used
+        let (path, file) = loop {
+            self.sequence += 1;
+            let path = self.dir.join(format!(
+                "{}-{time}-{}.{}",
+                self.prefix,
+                self.sequence,
+                self.compression.extension()
+            ));
+            match Output::create(&path, self.compression) {
+                Ok(file) => break (path, file),
+                Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => continue,
+                Err(e) => return Err(e.into()),
+            }
+        };
+
+        info!("Recording to {}", path.display());
+        self.file = Some(file);
+        self.path = Some(path);
+        self.written = 0;
+        Ok(())
+    }
+}
+
+/// Records the frames as Binance sent them, messages without one serialized.
+impl Sink for Recorder {
+    async fn write(&mut self, envelope: &Envelope) -> crate::Result<()> {
+        match &envelope.raw {
+            Some(frame) => self.record_raw(envelope.recv_time, frame),
+            None => self.record_at(envelope.recv_time, &envelope.message),
+        }
+    }
+
+    async fn flush(&mut self) -> crate::Result<()> {
+        Recorder::flush(self)
+    }
+}
+
+impl Drop for Recorder {
+    fn drop(&mut self) {
+        if let Some(file) = self.file.take() {
+            let _ = file.finish();
+        }
+    }
+}
+
+/// A recording file, compressed or not.
+enum Output {
+    Plain(BufWriter<File>),
+    #[cfg(feature = "compression")]
+    Gzip(flate2::write::GzEncoder<BufWriter<File>>),
+    #[cfg(feature = "compression")]
+    Zstd(zstd::Encoder<'static, BufWriter<File>>),
+}
+
+impl Output {
+    /// Create the file at `path`, [`std::io::ErrorKind::AlreadyExists`] if there is one.
+    fn create(path: &Path, compression: Compression) -> std::io::Result<Self> {
+        let file = OpenOptions::new().write(true).create_new(true).open(path)?;
+        let file = BufWriter::new(file);
+        Ok(match compression {
+            Compression::None => Output::Plain(file),
+            #[cfg(feature = "compression")]
+            Compression::Gzip => {
+                Output::Gzip(flate2::write::GzEncoder::new(file, Default::default()))
+            }
+            #[cfg(feature = "compression")]
+            Compression::Zstd => Output::Zstd(zstd::Encoder::new(file, 0)?),
+        })
+    }
+
+    /// Write the end of the compressed stream and flush the file.
+    fn finish(self) -> std::io::Result<()> {
+        match self {
+            Output::Plain(mut file) => file.flush(),
+            #[cfg(feature = "compression")]
+            Output::Gzip(encoder) => encoder.finish()?.flush(),
+            #[cfg(feature = "compression")]
+            Output::Zstd(encoder) => encoder.finish()?.flush(),
+        }
+    }
+
+    fn writer(&mut self) -> &mut dyn Write {
+        match self {
+            Output::Plain(file) => file,
+            #[cfg(feature = "compression")]
+            Output::Gzip(encoder) => encoder,
+            #[cfg(feature = "compression")]
+            Output::Zstd(encoder) => encoder,
+        }
+    }
+}
+
+impl Write for Output {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.writer().write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.writer().flush()
+    }
+}
+
+impl std::fmt::Debug for Output {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Output")
+    }
+}
+
+/// Reads [`Record`]s from a recording, one line at a time.
+pub struct RecordReader {
+    lines: std::io::Lines<Box<dyn BufRead + Send>>,
+}
+
+impl std::fmt::Debug for RecordReader {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RecordReader").finish_non_exhaustive()
+    }
+}
+
+impl RecordReader {
+    /// Open the recording at `path`, decompressed if the name ends with `.gz` or `.zst`.
+    pub fn open(path: impl AsRef<Path>) -> crate::Result<Self> {
+        let path = path.as_ref();
+        let file = BufReader::new(File::open(path)?);
+        let reader: Box<dyn BufRead + Send> = match path.extension().and_then(|e| e.to_str()) {
+            #[cfg(feature = "compression")]
+            Some("gz") => Box::new(BufReader::new(flate2::bufread::MultiGzDecoder::new(file))),
+            #[cfg(feature = "compression")]
+            Some("zst") => Box::new(BufReader::new(zstd::Decoder::with_buffer(file)?)),
+            #[cfg(not(feature = "compression"))]
+            Some("gz" | "zst") => {
+                return Err(crate::Error::Custom(format!(
+                    "reading {} requires the compression feature",
+                    path.display()
+                )))
+            }
+            _ => Box::new(file),
+        };
+        Ok(Self::new(reader))
+    }
+
+    /// Read uncompressed records from `reader`.
+    pub fn new(reader: impl BufRead + Send + 'static) -> Self {
+        let reader: Box<dyn BufRead + Send> = Box::new(reader);
+        Self {
+            lines: reader.lines(),
+        }
+    }
+}
+
+impl Iterator for RecordReader {
+    type Item = crate::Result<Record>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let line = match self.lines.next()? {
+                Ok(line) => line,
+                Err(e) => return Some(Err(e.into())),
+            };
+            if !line.trim().is_empty() {
+                return Some(serde_json::from_str(&line).map_err(Into::into));
+            }
+        }
+    }
+}
+
+/// Milliseconds since epoch of the local clock.
+#[cfg(any(
+    feature = "parquet",
+    feature = "sqlite",
+    feature = "postgres",
+    feature = "grpc"
+))]
+pub(crate) fn now_millis() -> u64 {
+    SystemClock.now_millis()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::messages::BookTicker;
+    use crate::test_util;
+    use crate::Symbol;
+
+    fn ticker(update_id: u64) -> Message {
+        Message::BookTicker(BookTicker {
+            update_id,
+            ..test_util::ticker(Symbol::BTCUSDT, "1", "2")
+        })
+    }
+
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("recorder_{name}_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    fn read_records(path: &Path) -> Vec<Record> {
+        RecordReader::open(path)
+            .unwrap()
+            .collect::<crate::Result<_>>()
+            .unwrap()
+    }
+
+    #[test]
+    fn records_wire_format() {
+        let dir = test_dir("wire");
+        let mut recorder = Recorder::new(&dir, "btc", Rotation::default()).unwrap();
+
+        recorder.record_at(1, &ticker(1)).unwrap();
+        recorder
+            .record_raw(
+                2,
+                "{\"u\":2,\"s\":\"BTCUSDT\",\n\"b\":\"1\",\"B\":\"1\",\"a\":\"2\",\"A\":\"1\"}",
+            )
+            .unwrap();
+        recorder.flush().unwrap();
+
+        let path = recorder.current_path().unwrap().to_path_buf();
+        let content = std::fs::read_to_string(&path).unwrap();
+        assert!(content.starts_with("{\"recv_time\":1,\"msg\":{\"u\":1,\"s\":\"BTCUSDT\","));
+
+        let records = read_records(&path);
+        assert_eq!(
+            records,
+            vec![
+                Record {
+                    recv_time: 1,
+                    msg: ticker(1)
+                },
+                Record {
+                    recv_time: 2,
+                    msg: ticker(2)
+                },
+            ]
+        );
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn sink_records_frames() {
+        let dir = test_dir("sink");
+        let mut recorder = Recorder::new(&dir, "btc", Rotation::default()).unwrap();
+        let frame = r#"{"e":"bookTicker","u":1,"s":"BTCUSDT","b":"1","B":"1","a":"2","A":"1"}"#;
+        let envelope = |recv_time, raw: Option<&str>| Envelope {
+            message: ticker(1),
+            recv_time,
+            received: std::time::Instant::now(),
+            connection: 0,
+            raw: raw.map(Into::into),
+        };
+        Sink::write(&mut recorder, &envelope(1, Some(frame)))
+            .await
+            .unwrap();
+        // without a frame, e.g. an acknowledgement the session made up
+        Sink::write(&mut recorder, &envelope(2, None)).await.unwrap();
+        recorder.flush().unwrap();
+
+        let path = recorder.current_path().unwrap().to_path_buf();
+        let content = std::fs::read_to_string(&path).unwrap();
+        let lines: Vec<&str> = content.lines().collect();
+        assert_eq!(lines[0], format!("{{\"recv_time\":1,\"msg\":{frame}}}"));
+        assert!(!lines[1].contains("\"e\""));
+        let messages: Vec<Message> = read_records(&path).into_iter().map(|r| r.msg).collect();
+        assert_eq!(messages, vec![ticker(1), ticker(1)]);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn rotates_on_size_and_time() {
+        let dir = test_dir("rotate");
+        let rotation = Rotation {
+            max_bytes: Some(1),
+            interval: None,
+        };
+        let mut recorder = Recorder::new(&dir, "size", rotation).unwrap();
+        recorder.record_at(1, &ticker(1)).unwrap();
+        let first_size = recorder.current_path().unwrap().to_path_buf();
+        recorder.record_at(1, &ticker(2)).unwrap();
+        assert_ne!(recorder.current_path().unwrap(), first_size);
+
+        let rotation = Rotation {
+            max_bytes: None,
+            interval: Some(Duration::from_secs(60)),
+        };
+        let mut recorder = Recorder::new(&dir, "time", rotation.clone()).unwrap();
+        recorder.record_at(60_000, &ticker(1)).unwrap();
+        let first = recorder.current_path().unwrap().to_path_buf();
+        recorder.record_at(119_999, &ticker(2)).unwrap();
+        assert_eq!(recorder.current_path().unwrap(), first);
+        recorder.record_at(120_000, &ticker(3)).unwrap();
+        assert_ne!(recorder.current_path().unwrap(), first);
+        drop(recorder);
+
+        assert_eq!(read_records(&first).len(), 2);
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 4);
+
+        let clock = crate::clock::TestClock::new(180_000);
+        let mut recorder = Recorder::new(&dir, "clock", rotation)
+            .unwrap()
+            .clock(Arc::new(clock.clone()));
+        recorder.record(&ticker(1)).unwrap();
+        let first = recorder.current_path().unwrap().to_path_buf();
+        clock.advance(Duration::from_secs(60));
+        recorder.record(&ticker(2)).unwrap();
+        assert_ne!(recorder.current_path().unwrap(), first);
+        drop(recorder);
+        assert_eq!(read_records(&first)[0].recv_time, 180_000);
+
+        // a restarted recorder keeps the files of the last one
+        let mut recorder = Recorder::new(&dir, "size", Rotation::default()).unwrap();
+        recorder.record_at(1, &ticker(3)).unwrap();
+        drop(recorder);
+        assert_eq!(read_records(&first_size)[0].msg, ticker(1));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[cfg(feature = "compression")]
+    #[test]
+    fn compressed_round_trip() {
+        let dir = test_dir("compressed");
+        for compression in [Compression::Gzip, Compression::Zstd] {
+            let rotation = Rotation {
+                max_bytes: Some(50_000),
+                interval: None,
+            };
+            let mut recorder = Recorder::new(&dir, "c", rotation)
+                .unwrap()
+                .compressed(compression);
+            for i in 0..1000 {
+                recorder.record_at(i, &ticker(i)).unwrap();
+            }
+            let path = recorder.current_path().unwrap().to_path_buf();
+            assert!(path.to_str().unwrap().ends_with(compression.extension()));
+            drop(recorder);
+
+            let records = read_records(&path);
+            assert_eq!(records.last().unwrap().msg, ticker(999));
+            assert!(std::fs::metadata(&path).unwrap().len() < 10_000);
+        }
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,495 @@
+//! Relay one Binance connection to any number of local websocket clients.
+//!
+//! Clients connect to the [`Relay`] and use the Binance subscribe protocol with raw stream
+//! names:
+//! ```text
+//! > {"method":"SUBSCRIBE","params":["btcusdt@aggTrade","ethbtc@depth@100ms"],"id":1}
+//! < {"id":1,"result":null}
+//! > {"method":"LIST_SUBSCRIPTIONS","id":2}
+//! < {"id":2,"result":["btcusdt@aggTrade","ethbtc@depth@100ms"]}
+//! ```
+//! The relay subscribes upstream to a stream when the first client subscribes to it and
+//! unsubscribes when the last client unsubscribes or disconnects. Messages are forwarded as
+//! the JSON of [`Message`], the format of this crate, not the frames Binance sent.
+//!
+//! [`Relay::replay()`] serves recordings the same way, replayed from the first subscription.
+//!
+//! Partial depth streams can not be relayed, their messages do not name the symbol. Diff depth
+//! updates do not tell the speed of their stream, so a symbol is relayed at one depth speed at
+//! a time, subscribing to the other speed fails while it has subscribers.
+//! A client that does not keep up skips the oldest messages once `capacity` messages are
+//! queued for it, they are counted by the [`Metrics::dropped()`] of the upstream connection.
+
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use futures::{SinkExt, StreamExt};
+use serde_json::json;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::{broadcast, mpsc, oneshot};
+use tokio_tungstenite::tungstenite;
+use tracing::{info, warn};
+
+use crate::logging::RateLimited;
+use crate::metrics::Metrics;
+use crate::replay::ReplaySource;
+use crate::request::{Method, Request};
+use crate::subscriptions::Subscriptions;
+use crate::{BinanceApi, Message, SubscribeInfo};
+
+/// Serves the messages of one [`BinanceApi`] connection to local websocket clients.
+pub struct Relay {
+    upstream: Upstream,
+    metrics: Arc<Metrics>,
+    listener: TcpListener,
+    capacity: usize,
+}
+
+/// Where the relayed messages come from.
+#[allow(clippy::large_enum_variant)]
+enum Upstream {
+    Live(BinanceApi),
+    Replay(ReplaySource),
+}
+
+impl Upstream {
+    async fn request(
+        &mut self,
+        method: Method,
+        streams: Vec<String>,
+        id: u64,
+    ) -> crate::Result<()> {
+        let source = match self {
+            Upstream::Live(api) => return api.request(method, streams, id).await,
+            Upstream::Replay(source) => source,
+        };
+        let infos: Vec<SubscribeInfo> = streams.iter().filter_map(|s| subscribe_info(s)).collect();
+        match method {
+            Method::Subscribe => source.subscribe(&infos, Some(id)).await?,
+            Method::Unsubscribe => source.unsubscribe(infos).await,
+            Method::ListSubscriptions => {}
+        }
+        Ok(())
+    }
+
+    async fn next_message(&mut self) -> Option<Message> {
+        match self {
+            Upstream::Live(api) => api.next_message().await,
+            Upstream::Replay(source) => source.next_message().await,
+        }
+    }
+}
+
+impl std::fmt::Debug for Relay {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Relay")
+            .field("listener", &self.listener)
+            .field("capacity", &self.capacity)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Relay {
+    /// Listen for clients on `addr`, relaying from the connected `api`.
+    ///
+    /// Up to `capacity` messages are queued for each client, at least one.
+    pub async fn bind(api: BinanceApi, addr: SocketAddr, capacity: usize) -> crate::Result<Self> {
+        check_capacity(capacity)?;
+        let listener = TcpListener::bind(addr).await?;
+        info!("Relaying on ws://{}", listener.local_addr()?);
+        Ok(Self {
+            metrics: api.metrics(),
+            upstream: Upstream::Live(api),
+            listener,
+            capacity,
+        })
+    }
+
+    /// Listen for clients on `addr`, relaying the recordings of `source`.
+    ///
+    /// Nothing is replayed until a client subscribes, from then on the recordings are replayed
+    /// at the [`Speed`](crate::replay::Speed) of `source`, whether clients keep up or not.
+    pub async fn replay(
+        source: ReplaySource,
+        addr: SocketAddr,
+        capacity: usize,
+    ) -> crate::Result<Self> {
+        check_capacity(capacity)?;
+        let listener = TcpListener::bind(addr).await?;
+        info!("Replaying on ws://{}", listener.local_addr()?);
+        Ok(Self {
+            upstream: Upstream::Replay(source),
+            metrics: Arc::default(),
+            listener,
+            capacity,
+        })
+    }
+
+    /// The address listened on, with the actual port when bound to port 0.
+    pub fn local_addr(&self) -> crate::Result<SocketAddr> {
+        Ok(self.listener.local_addr()?)
+    }
+
+    /// Relay until the upstream connection is closed, or the replay has ended.
+    pub async fn run(mut self) -> crate::Result<()> {
+        let (control, mut requests) = mpsc::channel(64);
+        let (sender, _) = broadcast::channel(self.capacity);
+        let mut subscriptions = Subscriptions::default();
+        let mut id = 0;
+
+        loop {
+            // a replay without subscriptions would skip every message
+            let paused = matches!(self.upstream, Upstream::Replay(_)) && subscriptions.is_empty();
+            tokio::select! {
+                accepted = self.listener.accept() => match accepted {
+                    Ok((stream, addr)) => {
+                        info!("Relay client connected from {addr}");
+                        let metrics = self.metrics.clone();
+                        tokio::spawn(serve(stream, sender.subscribe(), control.clone(), metrics));
+                    }
+                    Err(e) => warn!("Failed to accept a relay client: {e}"),
+                },
+                Some(request) = requests.recv() => {
+                    let (method, streams) = match request {
+                        Control::Subscribe(streams, reply) => {
+                            if let Some(conflict) = depth_conflict(&streams, &subscriptions) {
+                                let _ = reply.send(Err(conflict));
+                                continue;
+                            }
+                            let _ = reply.send(Ok(()));
+                            (Method::Subscribe, subscriptions.add(&streams))
+                        }
+                        Control::Unsubscribe(streams) => {
+                            (Method::Unsubscribe, subscriptions.remove(&streams))
+                        }
+                    };
+                    if !streams.is_empty() {
+                        id += 1;
+                        self.upstream.request(method, streams, id).await?;
+                    }
+                }
+                msg = self.upstream.next_message(), if !paused => match msg {
+                    // responses to the relay's own requests
+                    Some(Message::SubscribeSuccess { .. }) => {}
+                    Some(msg) => {
+                        let key = message_keys(&msg)
+                            .into_iter()
+                            .find(|key| subscriptions.is_active(key));
+                        if let Some(key) = key {
+                            // an error only means that no client is connected
+                            let _ = sender.send(Arc::new((key, msg)));
+                        }
+                    }
+                    None => match &self.upstream {
+                        Upstream::Live(api) => return Err(api.closed()),
+                        Upstream::Replay(_) => return Ok(()),
+                    },
+                },
+            }
+        }
+    }
+}
+
+fn check_capacity(capacity: usize) -> crate::Result<()> {
+    if capacity == 0 {
+        return Err(crate::Error::Custom(
+            "relay: at least one message must be queued per client".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Changes to the upstream subscriptions requested by the clients.
+#[derive(Debug)]
+enum Control {
+    /// Answered with why the streams can not be subscribed, if they can not.
+    Subscribe(Vec<String>, oneshot::Sender<Result<(), String>>),
+    Unsubscribe(Vec<String>),
+}
+
+/// A relayed message and the key of the stream it was received on, see [`stream_key()`].
+type Relayed = Arc<(String, Message)>;
+
+/// Serve one client until it disconnects.
+async fn serve(
+    stream: TcpStream,
+    mut messages: broadcast::Receiver<Relayed>,
+    control: mpsc::Sender<Control>,
+    metrics: Arc<Metrics>,
+) {
+    let ws = match tokio_tungstenite::accept_async(stream).await {
+        Ok(ws) => ws,
+        Err(e) => {
+            warn!("Relay client handshake failed: {e}");
+            return;
+        }
+    };
+    let (mut write, mut read) = ws.split();
+    // keys of the subscribed streams
+    let mut streams: Vec<String> = Vec::new();
+    let mut lag_warnings = RateLimited::default();
+    // skipped messages not reported yet
+    let mut skipped_total = 0;
+
+    loop {
+        tokio::select! {
+            frame = read.next() => match frame {
+                Some(Ok(tungstenite::Message::Text(text))) => {
+                    let reply = match Request::parse(&text) {
+                        Ok(request) => handle(request, &mut streams, &control).await,
+                        Err(reply) => reply,
+                    };
+                    if write.send(tungstenite::Message::Text(reply.to_string())).await.is_err() {
+                        break;
+                    }
+                }
+                Some(Ok(tungstenite::Message::Close(_))) | Some(Err(_)) | None => break,
+                Some(Ok(_)) => {}
+            },
+            msg = messages.recv() => match msg {
+                Ok(relayed) => {
+                    let (key, msg) = &*relayed;
+                    if !streams.contains(key) {
+                        continue;
+                    }
+                    let Ok(text) = serde_json::to_string(msg) else {
+                        continue;
+                    };
+                    if write.send(tungstenite::Message::Text(text)).await.is_err() {
+                        break;
+                    }
+                }
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    skipped_total += skipped;
+                    metrics.record_dropped_many(skipped);
+                    if lag_warnings.allow().is_some() {
+                        warn!("Relay client is lagging, skipped {skipped_total} messages");
+                        skipped_total = 0;
+                    }
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            },
+        }
+    }
+
+    if !streams.is_empty() {
+        let _ = control.send(Control::Unsubscribe(streams)).await;
+    }
+}
+
+/// Apply a client request, returns the reply.
+async fn handle(
+    request: Request,
+    streams: &mut Vec<String>,
+    control: &mpsc::Sender<Control>,
+) -> serde_json::Value {
+    let Request { method, params, id } = request;
+    let error = |msg: String| json!({ "error": { "code": 2, "msg": msg }, "id": id });
+
+    match method {
+        Method::Subscribe => {
+            if let Some(invalid) = params.iter().find(|s| stream_key(s).is_none()) {
+                return error(format!("Invalid or unsupported stream '{invalid}'"));
+            }
+            let mut new: Vec<String> = Vec::new();
+            for key in params.iter().filter_map(|s| stream_key(s)) {
+                if !streams.contains(&key) && !new.contains(&key) {
+                    new.push(key);
+                }
+            }
+            if !new.is_empty() {
+                let (reply, subscribed) = oneshot::channel();
+                let _ = control.send(Control::Subscribe(new.clone(), reply)).await;
+                if let Ok(Err(msg)) = subscribed.await {
+                    return error(msg);
+                }
+                streams.extend(new);
+            }
+            json!({ "result": null, "id": id })
+        }
+        Method::Unsubscribe => {
+            let removed: Vec<String> = params
+                .iter()
+                .filter_map(|s| stream_key(s))
+                .filter(|key| streams.contains(key))
+                .collect();
+            streams.retain(|s| !removed.contains(s));
+            if !removed.is_empty() {
+                let _ = control.send(Control::Unsubscribe(removed)).await;
+            }
+            json!({ "result": null, "id": id })
+        }
+        Method::ListSubscriptions => json!({ "result": streams, "id": id }),
+    }
+}
+
+/// Key matching a stream name with the messages it receives, the stream name with the symbol
+/// in lower case, `None` if it can not be relayed.
+pub(crate) fn stream_key(stream: &str) -> Option<String> {
+    let (symbol, feed) = stream.split_once('@')?;
+    match feed {
+        "aggTrade" | "trade" | "bookTicker" | "depth" | "depth@100ms" | "depth@500ms" => {}
+        _ if feed.starts_with("kline_") || feed.starts_with("continuousKline_") => {}
+        _ => return None,
+    }
+    Some(format!("{}@{feed}", symbol.to_lowercase()))
+}
+
+/// Keys of the diff depth streams of `symbol`, of every speed of spot and futures.
+fn depth_keys(symbol: impl std::fmt::Display) -> [String; 3] {
+    [
+        format!("{symbol}@depth"),
+        format!("{symbol}@depth@100ms"),
+        format!("{symbol}@depth@500ms"),
+    ]
+}
+
+/// Why the stream keys `streams` can not be subscribed along with `subscriptions`, a diff
+/// depth stream of a symbol with a depth stream of another speed.
+fn depth_conflict(streams: &[String], subscriptions: &Subscriptions) -> Option<String> {
+    streams.iter().find_map(|stream| {
+        let symbol = stream
+            .strip_suffix("@depth")
+            .or_else(|| stream.strip_suffix("@depth@100ms"))
+            .or_else(|| stream.strip_suffix("@depth@500ms"))?;
+        let other = depth_keys(symbol).into_iter().find(|key| {
+            key != stream && (subscriptions.is_active(key) || streams.contains(key))
+        })?;
+        Some(format!(
+            "Stream '{stream}' can not be relayed with '{other}', depth updates do not tell their speed"
+        ))
+    })
+}
+
+/// The symbol and feed of a stream name, `None` if it is not a stream of Binance.
+fn subscribe_info(stream: &str) -> Option<SubscribeInfo> {
+    let (symbol, feed) = stream.split_once('@')?;
+    if feed.starts_with("continuousKline_") {
+        // the contract type goes with the pair, `btcusdt_perpetual@continuousKline_1m`
+        let (pair, contract_type) = symbol.split_once('_')?;
+        let feed = format!("{contract_type}@{feed}").parse().ok()?;
+        return Some(SubscribeInfo::new(pair.parse().ok()?, feed));
+    }
+    Some(SubscribeInfo::new(symbol.parse().ok()?, feed.parse().ok()?))
+}
+
+/// Keys of the streams that may have sent `msg`, see [`stream_key()`].
+///
+/// Diff depth updates, of spot and futures, are sent by the depth streams of every speed.
+pub(crate) fn message_keys(msg: &Message) -> Vec<String> {
+    let Some(symbol) = msg.symbol() else {
+        return Vec::new();
+    };
+    match msg {
+        #[cfg(feature = "depth")]
+        Message::DepthUpdate(_) | Message::FuturesDepthUpdate(_) => depth_keys(symbol).into(),
+        #[cfg(feature = "kline")]
+        Message::Kline(k) => vec![format!("{symbol}@kline_{}", k.kline.interval)],
+        #[cfg(feature = "kline")]
+        Message::ContinuousKline(k) => vec![format!(
+            "{symbol}_{}@continuousKline_{}",
+            k.contract_type, k.kline.interval
+        )],
+        _ => vec![format!("{symbol}@{}", msg.event_type())],
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_util;
+    use crate::Symbol;
+
+    #[test]
+    fn stream_keys() {
+        assert_eq!(stream_key("BTCUSDT@depth@100ms").unwrap(), "btcusdt@depth@100ms");
+        assert_eq!(stream_key("btcusdt@depth").unwrap(), "btcusdt@depth");
+        assert_eq!(stream_key("btcusdt@depth@500ms").unwrap(), "btcusdt@depth@500ms");
+        assert_eq!(stream_key("btcusdt@kline_1m").unwrap(), "btcusdt@kline_1m");
+        assert_eq!(
+            stream_key("BTCUSDT_perpetual@continuousKline_1m").unwrap(),
+            "btcusdt_perpetual@continuousKline_1m"
+        );
+        assert_eq!(stream_key("btcusdt@depth5"), None);
+        assert_eq!(stream_key("btcusdt"), None);
+
+        let info = subscribe_info("BTCUSDT@depth@100ms").unwrap();
+        assert_eq!(info.symbol, Symbol::BTCUSDT);
+        assert_eq!(info.feed.to_string(), "depth@100ms");
+        let info = subscribe_info("btcusdt_current_quarter@continuousKline_1m").unwrap();
+        assert_eq!(info.symbol, Symbol::BTCUSDT);
+        assert_eq!(
+            info.stream_name(),
+            "btcusdt_current_quarter@continuousKline_1m"
+        );
+        assert!(subscribe_info("btcusdt@ticker").is_none());
+    }
+
+    #[tokio::test]
+    async fn zero_capacity() {
+        let addr: SocketAddr = "127.0.0.1:0".parse().unwrap();
+        assert!(Relay::bind(BinanceApi::new(), addr, 0).await.is_err());
+        let source = ReplaySource::from_files(Vec::<&str>::new()).unwrap();
+        assert!(Relay::replay(source, addr, 0).await.is_err());
+    }
+
+    #[test]
+    fn one_depth_speed_per_symbol() {
+        let mut subscriptions = Subscriptions::default();
+        subscriptions.add(&["btcusdt@depth@100ms".to_string()]);
+        let slow = ["btcusdt@depth".to_string()];
+        assert!(depth_conflict(&slow, &subscriptions).is_some());
+        assert!(depth_conflict(&["ethbtc@depth".to_string()], &subscriptions).is_none());
+        let both = ["ethbtc@depth".to_string(), "ethbtc@depth@100ms".to_string()];
+        assert!(depth_conflict(&both, &subscriptions).is_some());
+
+        subscriptions.remove(&["btcusdt@depth@100ms".to_string()]);
+        assert!(depth_conflict(&slow, &subscriptions).is_none());
+    }
+
+    #[tokio::test]
+    async fn client_session() {
+        let trade = Message::Trade(test_util::trade(Symbol::BTCUSDT, 1));
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+        let (sender, _) = broadcast::channel(16);
+        let (control, mut requests) = mpsc::channel(16);
+        let server = {
+            let messages = sender.subscribe();
+            tokio::spawn(async move {
+                let (stream, _) = listener.accept().await.unwrap();
+                serve(stream, messages, control, Arc::default()).await;
+            })
+        };
+
+        let (mut client, _) = tokio_tungstenite::connect_async(url).await.unwrap();
+        let subscribe = r#"{"method":"SUBSCRIBE","params":["btcusdt@trade"],"id":7}"#;
+        client
+            .send(tungstenite::Message::Text(subscribe.to_string()))
+            .await
+            .unwrap();
+        let Control::Subscribe(streams, reply) = requests.recv().await.unwrap() else {
+            panic!("not a subscription");
+        };
+        assert_eq!(streams, ["btcusdt@trade"]);
+        reply.send(Ok(())).unwrap();
+        let reply = client.next().await.unwrap().unwrap();
+        assert_eq!(reply.to_text().unwrap(), r#"{"id":7,"result":null}"#);
+
+        // messages of other streams are not sent
+        let agg_trade = Message::AggTrade(test_util::agg_trade(Symbol::BTCUSDT, 1));
+        sender.send(Arc::new(("btcusdt@aggTrade".to_string(), agg_trade))).unwrap();
+        sender.send(Arc::new(("btcusdt@trade".to_string(), trade.clone()))).unwrap();
+        let received = client.next().await.unwrap().unwrap();
+        let received: Message = serde_json::from_str(received.to_text().unwrap()).unwrap();
+        assert_eq!(received, trade);
+
+        client.close(None).await.unwrap();
+        server.await.unwrap();
+        assert!(matches!(
+            requests.recv().await.unwrap(),
+            Control::Unsubscribe(streams) if streams == ["btcusdt@trade"]
+        ));
+    }
+}
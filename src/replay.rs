@@ -0,0 +1,423 @@
+//! Replay recordings as a message stream, for backtesting.
+//!
+//! [`ReplaySource`] has the same methods as [`BinanceApi`](crate::BinanceApi), so code reading
+//! from a live connection reads from recordings without changes. Like the live connection it
+//! only returns messages of subscribed streams, and answers subscriptions with a
+//! [`Message::SubscribeSuccess`].
+//!
+//! Recordings are files written by the [`Recorder`](crate::recorder::Recorder), compressed
+//! or not. The messages of all files are merged in receive time order.
+//!
+//! Messages are returned as fast as possible by default, or paced by their receive times,
+//! see [`Speed`]. [`ReplaySource::time_window()`] limits the replay to part of the recordings.
+
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashSet, VecDeque};
+use std::path::Path;
+use std::time::Duration;
+
+use tokio::time::Instant;
+use tracing::warn;
+
+use crate::recorder::{Record, RecordReader};
+use crate::relay::{message_keys, stream_key};
+use crate::{Feed, Message, SubscribeInfo};
+
+/// How fast [`ReplaySource::next_message()`] returns the messages.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum Speed {
+    /// Without waiting.
+    #[default]
+    AsFastAsPossible,
+    /// Spaced like they were received.
+    RealTime,
+    /// Spaced like they were received, this many times faster, e.g. `0.5` for half the speed.
+    /// Must be positive and finite.
+    Multiplier(f64),
+}
+
+/// Reads recordings back as a message stream, see the [module](self) documentation.
+#[derive(Debug)]
+pub struct ReplaySource {
+    readers: Vec<RecordReader>,
+    /// next record of each reader
+    next: Vec<Option<Record>>,
+    /// receive time of the next record and index of the reader
+    queue: BinaryHeap<Reverse<(u64, usize)>>,
+    streams: HashSet<String>,
+    partial_depth: bool,
+    responses: VecDeque<Message>,
+    speed: Speed,
+    start: Option<u64>,
+    end: Option<u64>,
+    /// wall clock time and receive time of the first paced message
+    anchor: Option<(Instant, u64)>,
+}
+
+impl ReplaySource {
+    /// Replay the recordings at `paths`, every file is read in order of receive time.
+    pub fn from_files(paths: impl IntoIterator<Item = impl AsRef<Path>>) -> crate::Result<Self> {
+        let readers = paths
+            .into_iter()
+            .map(RecordReader::open)
+            .collect::<crate::Result<Vec<_>>>()?;
+        let mut source = Self {
+            next: readers.iter().map(|_| None).collect(),
+            readers,
+            queue: BinaryHeap::new(),
+            streams: HashSet::new(),
+            partial_depth: false,
+            responses: VecDeque::new(),
+            speed: Speed::default(),
+            start: None,
+            end: None,
+            anchor: None,
+        };
+        for i in 0..source.readers.len() {
+            source.advance(i);
+        }
+        Ok(source)
+    }
+
+    /// Return the messages at `speed`, fails with a multiplier that is not positive and finite.
+    pub fn speed(mut self, speed: Speed) -> crate::Result<Self> {
+        if let Speed::Multiplier(multiplier) = speed {
+            if !(multiplier.is_finite() && multiplier > 0.0) {
+                return Err(crate::Error::Custom(format!(
+                    "replay speed multiplier must be positive, not {multiplier}"
+                )));
+            }
+        }
+        self.speed = speed;
+        Ok(self)
+    }
+
+    /// Only replay messages received from `start` until before `end`,
+    /// in milliseconds since epoch.
+    pub fn time_window(mut self, start: Option<u64>, end: Option<u64>) -> Self {
+        self.start = start;
+        self.end = end;
+        self
+    }
+
+    /// Does nothing, there is no connection, present to match [`crate::BinanceApi`].
+    pub async fn connect(&mut self) -> crate::Result<()> {
+        Ok(())
+    }
+
+    /// Stop the replay, [`ReplaySource::next_message()`] returns `None` from now on.
+    pub async fn disconnect(&mut self) {
+        self.queue.clear();
+        self.responses.clear();
+    }
+
+    /// The next message of the subscribed streams, `None` at the end of the recordings.
+    ///
+    /// Waits until the message is due, see [`Speed`].
+    pub async fn next_message(&mut self) -> Option<Message> {
+        if let Some(response) = self.responses.pop_front() {
+            return Some(response);
+        }
+        let record = self.next_record()?;
+        if let Some(due) = self.due(record.recv_time) {
+            tokio::time::sleep_until(due).await;
+        }
+        Some(record.msg)
+    }
+
+    /// The next record of the subscribed streams in the time window, with its receive time.
+    ///
+    /// Returns immediately, whatever the [`Speed`].
+    pub fn next_record(&mut self) -> Option<Record> {
+        loop {
+            let Reverse((recv_time, i)) = self.queue.pop()?;
+            if self.end.is_some_and(|end| recv_time >= end) {
+                self.queue.clear();
+                return None;
+            }
+            let record = self.next[i].take().expect("queued readers have a record");
+            self.advance(i);
+            let started = self.start.is_none_or(|start| recv_time >= start);
+            if started && self.is_subscribed(&record.msg) {
+                return Some(record);
+            }
+        }
+    }
+
+    /// When the message received at `recv_time` is due, `None` if it is due now.
+    fn due(&mut self, recv_time: u64) -> Option<Instant> {
+        let multiplier = match self.speed {
+            Speed::AsFastAsPossible => return None,
+            Speed::RealTime => 1.0,
+            Speed::Multiplier(multiplier) => multiplier,
+        };
+        let (wall, first) = *self.anchor.get_or_insert((Instant::now(), recv_time));
+        let elapsed = recv_time.saturating_sub(first) as f64 / 1000.0;
+        Some(wall + Duration::from_secs_f64(elapsed / multiplier))
+    }
+
+    /// Subscribe to streams, answered with a [`Message::SubscribeSuccess`].
+    ///
+    /// Fails without subscribing to any of `symbols` if one of them can not be replayed.
+    pub async fn subscribe(
+        &mut self,
+        symbols: &[SubscribeInfo],
+        id: Option<u64>,
+    ) -> crate::Result<()> {
+        if symbols.is_empty() {
+            warn!("you must provide SubsribeInfo for atleast one Symbol");
+            return Ok(());
+        }
+        let mut keys = Vec::new();
+        let mut partial_depth = false;
+        for info in symbols {
+            match &info.feed {
+                Feed::PartialDepth { .. } => partial_depth = true,
+                _ => match stream_key(&info.stream_name()) {
+                    Some(key) => keys.push(key),
+                    None => {
+                        return Err(crate::Error::Custom(format!(
+                            "stream {} can not be replayed",
+                            info.stream_name()
+                        )))
+                    }
+                },
+            }
+        }
+        self.partial_depth |= partial_depth;
+        self.streams.extend(keys);
+        self.responses.push_back(Message::SubscribeSuccess {
+            result: None,
+            id: id.unwrap_or(1),
+        });
+        Ok(())
+    }
+
+    /// Unsubscribe from streams.
+    pub async fn unsubscribe(&mut self, symbols: Vec<SubscribeInfo>) {
+        for info in symbols {
+            match &info.feed {
+                Feed::PartialDepth { .. } => self.partial_depth = false,
+                _ => {
+                    if let Some(key) = stream_key(&info.stream_name()) {
+                        self.streams.remove(&key);
+                    }
+                }
+            }
+        }
+    }
+
+    fn is_subscribed(&self, msg: &Message) -> bool {
+        match msg {
+            // responses to the subscriptions of the recording
+            Message::SubscribeSuccess { .. } => false,
+            #[cfg(feature = "depth")]
+            Message::PartialDepth(_) => self.partial_depth,
+            // sent by the partial depth streams of futures too
+            #[cfg(feature = "depth")]
+            Message::FuturesDepthUpdate(_) if self.partial_depth => true,
+            _ => message_keys(msg).iter().any(|key| self.streams.contains(key)),
+        }
+    }
+
+    /// Read the next record of reader `i` and queue it.
+    fn advance(&mut self, i: usize) {
+        for record in self.readers[i].by_ref() {
+            match record {
+                Ok(record) => {
+                    self.queue.push(Reverse((record.recv_time, i)));
+                    self.next[i] = Some(record);
+                    return;
+                }
+                Err(e) => warn!("Skipping unreadable record: {e}"),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::messages::Trade;
+    use crate::recorder::{Recorder, Rotation};
+    use crate::test_util;
+    use crate::Symbol;
+
+    fn trade(symbol: Symbol, trade_id: u64) -> Message {
+        Message::Trade(Trade {
+            event_time: trade_id,
+            trade_time: trade_id,
+            ..test_util::trade(symbol, trade_id)
+        })
+    }
+
+    #[tokio::test]
+    async fn merged_in_time_order() {
+        let dir = std::env::temp_dir().join(format!("replay_merge_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let mut paths = Vec::new();
+        for (prefix, times) in [("a", [1, 4, 5]), ("b", [2, 3, 6])] {
+            let mut recorder = Recorder::new(&dir, prefix, Rotation::default()).unwrap();
+            for t in times {
+                recorder.record_at(t, &trade(Symbol::BTCUSDT, t)).unwrap();
+                recorder.record_at(t, &trade(Symbol::ETHBTC, t)).unwrap();
+            }
+            paths.push(recorder.current_path().unwrap().to_path_buf());
+        }
+
+        let mut source = ReplaySource::from_files(&paths).unwrap();
+        source
+            .subscribe(&[SubscribeInfo::new(Symbol::BTCUSDT, Feed::Trade)], Some(3))
+            .await
+            .unwrap();
+        assert_eq!(
+            source.next_message().await.unwrap(),
+            Message::SubscribeSuccess {
+                result: None,
+                id: 3
+            }
+        );
+        let mut received = Vec::new();
+        while let Some(msg) = source.next_message().await {
+            received.push(msg);
+        }
+        let expected: Vec<Message> = (1..=6).map(|t| trade(Symbol::BTCUSDT, t)).collect();
+        assert_eq!(received, expected);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn paced_time_window() {
+        let dir = std::env::temp_dir().join(format!("replay_paced_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let mut recorder = Recorder::new(&dir, "paced", Rotation::default()).unwrap();
+        for t in [0, 1000, 2000, 3000] {
+            recorder.record_at(t, &trade(Symbol::BTCUSDT, t)).unwrap();
+        }
+        let path = recorder.current_path().unwrap().to_path_buf();
+        drop(recorder);
+
+        let mut source = ReplaySource::from_files([path])
+            .unwrap()
+            .speed(Speed::Multiplier(50.0))
+            .unwrap()
+            .time_window(Some(1000), Some(3000));
+        source
+            .subscribe(&[SubscribeInfo::new(Symbol::BTCUSDT, Feed::Trade)], None)
+            .await
+            .unwrap();
+        source.next_message().await.unwrap();
+
+        let started = std::time::Instant::now();
+        assert_eq!(
+            source.next_message().await.unwrap(),
+            trade(Symbol::BTCUSDT, 1000)
+        );
+        assert_eq!(
+            source.next_message().await.unwrap(),
+            trade(Symbol::BTCUSDT, 2000)
+        );
+        assert_eq!(source.next_message().await, None);
+        // one second of recording at 50 times the speed
+        let elapsed = started.elapsed();
+        assert!(elapsed >= Duration::from_millis(19), "{elapsed:?}");
+        assert!(elapsed < Duration::from_millis(500), "{elapsed:?}");
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn invalid_multipliers() {
+        for multiplier in [0.0, -2.0, f64::NAN, f64::INFINITY] {
+            let source = ReplaySource::from_files(Vec::<&Path>::new()).unwrap();
+            assert!(
+                source.speed(Speed::Multiplier(multiplier)).is_err(),
+                "{multiplier}"
+            );
+        }
+    }
+
+    #[cfg(feature = "kline")]
+    #[tokio::test]
+    async fn continuous_klines() {
+        use crate::messages::ContinuousKline;
+        use crate::{ContractType, Interval};
+
+        let kline = |contract_type: &str| -> Message {
+            let kline: ContinuousKline = serde_json::from_str(&format!(
+                r#"{{"E":1,"ps":"BTCUSDT","ct":"{contract_type}","k":{{"t":0,"T":59999,"i":"1m","f":1,"L":2,"o":"1","c":"2","h":"2","l":"1","v":"1","n":2,"x":false,"q":"1","V":"1","Q":"1"}}}}"#
+            ))
+            .unwrap();
+            Message::ContinuousKline(kline)
+        };
+        let dir = std::env::temp_dir().join(format!("replay_continuous_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let mut recorder = Recorder::new(&dir, "continuous", Rotation::default()).unwrap();
+        recorder.record_at(1, &kline("CURRENT_QUARTER")).unwrap();
+        recorder.record_at(2, &kline("PERPETUAL")).unwrap();
+        let path = recorder.current_path().unwrap().to_path_buf();
+        drop(recorder);
+
+        let mut source = ReplaySource::from_files([path]).unwrap();
+        let feed = Feed::ContinuousKline {
+            contract_type: ContractType::Perpetual,
+            interval: Interval::ONEMINUTE,
+        };
+        source
+            .subscribe(&[SubscribeInfo::new(Symbol::BTCUSDT, feed)], None)
+            .await
+            .unwrap();
+        source.next_message().await.unwrap();
+        assert_eq!(source.next_message().await, Some(kline("PERPETUAL")));
+        assert_eq!(source.next_message().await, None);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[cfg(feature = "depth")]
+    #[tokio::test]
+    async fn futures_depth_speeds() {
+        use crate::messages::FuturesDepthUpdate;
+        use crate::Delay;
+
+        let update = |symbol: Symbol, id: u64| {
+            Message::FuturesDepthUpdate(FuturesDepthUpdate {
+                event_time: id,
+                transaction_time: id,
+                symbol,
+                first_update_id: id,
+                final_update_id: id,
+                prev_final_update_id: id - 1,
+                bids: vec![],
+                asks: vec![],
+            })
+        };
+        let dir = std::env::temp_dir().join(format!("replay_futures_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let mut recorder = Recorder::new(&dir, "futures", Rotation::default()).unwrap();
+        for id in 1..=2 {
+            recorder.record_at(id, &update(Symbol::BTCUSDT, id)).unwrap();
+            recorder.record_at(id, &update(Symbol::ETHBTC, id)).unwrap();
+        }
+        let path = recorder.current_path().unwrap().to_path_buf();
+        drop(recorder);
+
+        let mut source = ReplaySource::from_files([path]).unwrap();
+        let depth = |symbol, delay| SubscribeInfo::new(symbol, Feed::FullDepth { delay });
+        source
+            .subscribe(
+                &[
+                    depth(Symbol::BTCUSDT, Delay::TWOHUNDREDFIFTY),
+                    depth(Symbol::ETHBTC, Delay::FIVEHUNDRED),
+                ],
+                None,
+            )
+            .await
+            .unwrap();
+        source.next_message().await.unwrap();
+        let mut received = Vec::new();
+        while let Some(msg) = source.next_message().await {
+            received.push(msg);
+        }
+        assert_eq!(received.len(), 4);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
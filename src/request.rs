@@ -0,0 +1,77 @@
+//! Requests of the Binance websocket protocol, sent by [`BinanceApi`](crate::BinanceApi) and
+//! answered by the [`Relay`](crate::relay::Relay) and the mock server.
+
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+/// Method of a [`Request`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub(crate) enum Method {
+    Subscribe,
+    Unsubscribe,
+    ListSubscriptions,
+}
+
+/// A request, e.g. `{"method":"SUBSCRIBE","params":["btcusdt@trade"],"id":1}`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct Request {
+    pub(crate) method: Method,
+    /// Stream names, e.g. `btcusdt@trade`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) params: Vec<String>,
+    pub(crate) id: u64,
+}
+
+impl Request {
+    pub(crate) fn new(method: Method, params: Vec<String>, id: u64) -> Self {
+        Self { method, params, id }
+    }
+
+    pub(crate) fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("requests serialize to json")
+    }
+
+    /// Parse a request, or the error reply Binance sends for it.
+    #[cfg_attr(not(feature = "tokio"), allow(dead_code))]
+    pub(crate) fn parse(text: &str) -> Result<Self, serde_json::Value> {
+        serde_json::from_str(text).map_err(|e| {
+            if e.is_syntax() || e.is_eof() {
+                json!({ "error": { "code": 3, "msg": "Invalid JSON" }, "id": null })
+            } else {
+                json!({ "error": { "code": 2, "msg": format!("Invalid request: {e}") }, "id": null })
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn json() {
+        let subscribe = Request::new(Method::Subscribe, vec!["btcusdt@trade".to_string()], 1);
+        assert_eq!(
+            subscribe.to_json(),
+            r#"{"method":"SUBSCRIBE","params":["btcusdt@trade"],"id":1}"#
+        );
+        let list = Request::new(Method::ListSubscriptions, Vec::new(), 2);
+        assert_eq!(list.to_json(), r#"{"method":"LIST_SUBSCRIPTIONS","id":2}"#);
+
+        // escaped like json, not like Debug
+        let odd = Request::new(Method::Unsubscribe, vec![r#"a"b\é"#.to_string()], 3);
+        assert_eq!(
+            odd.to_json(),
+            r#"{"method":"UNSUBSCRIBE","params":["a\"b\\é"],"id":3}"#
+        );
+        for request in [subscribe, list, odd] {
+            assert_eq!(Request::parse(&request.to_json()), Ok(request));
+        }
+
+        let unknown = Request::parse(r#"{"method":"FOO","id":4}"#).unwrap_err();
+        assert_eq!(unknown["error"]["code"], 2);
+        let invalid = Request::parse(r#"{"method":"#).unwrap_err();
+        assert_eq!(invalid["error"]["code"], 3);
+    }
+}
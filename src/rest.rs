@@ -0,0 +1,366 @@
+//! Requests to the Binance REST api, for data the streams do not resend.
+//!
+//! **Official docs:** https://binance-docs.github.io/apidocs/spot/en/#market-data-endpoints
+
+use std::collections::HashMap;
+
+use rust_decimal::Decimal;
+use serde::Deserialize;
+
+use crate::clock::{Clock, SystemClock};
+use crate::fixed::Scale;
+#[cfg(feature = "trade")]
+use crate::messages::AggTrade;
+#[cfg(feature = "depth")]
+use crate::messages::PartialDepth;
+use crate::sink::symbol_name;
+use crate::Symbol;
+
+const RESTURL: &str = "https://api.binance.com";
+const FUTURES_RESTURL: &str = "https://fapi.binance.com";
+
+/// Most levels of each side returned by [`RestClient::depth()`].
+#[cfg(feature = "depth")]
+pub const MAX_DEPTH: u16 = 5000;
+
+/// Most levels of each side returned by [`RestClient::futures_depth()`].
+#[cfg(feature = "depth")]
+pub const MAX_FUTURES_DEPTH: u16 = 1000;
+
+/// Most trades returned by one [`RestClient::agg_trades()`] request.
+#[cfg(feature = "trade")]
+pub const MAX_AGG_TRADES: u16 = 1000;
+
+/// Client of the public market data endpoints.
+#[derive(Debug, Clone)]
+pub struct RestClient {
+    url: String,
+    http: reqwest::Client,
+}
+
+impl Default for RestClient {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RestClient {
+    /// Client of the Binance api.
+    pub fn new() -> Self {
+        Self::with_url(RESTURL)
+    }
+
+    /// Client of the Binance USDⓈ-M futures api, for [`RestClient::futures_depth()`].
+    pub fn futures() -> Self {
+        Self::with_url(FUTURES_RESTURL)
+    }
+
+    /// Client of the api at `url`, e.g. a test server.
+    pub fn with_url(url: &str) -> Self {
+        Self {
+            url: url.trim_end_matches('/').to_string(),
+            http: reqwest::Client::new(),
+        }
+    }
+
+    /// Aggregate trades of `symbol` from trade id `from_id`, at most `limit` of them
+    /// and never more than [`MAX_AGG_TRADES`].
+    ///
+    /// The REST api does not send an event time, it is set to the trade time.
+    #[cfg(feature = "trade")]
+    pub async fn agg_trades(
+        &self,
+        symbol: &Symbol,
+        from_id: u64,
+        limit: u16,
+    ) -> crate::Result<Vec<AggTrade>> {
+        let trades: Vec<RestAggTrade> = self
+            .http
+            .get(format!("{}/api/v3/aggTrades", self.url))
+            .query(&[
+                ("symbol", symbol_name(symbol)),
+                ("fromId", from_id.to_string()),
+                ("limit", limit.min(MAX_AGG_TRADES).to_string()),
+            ])
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        Ok(trades
+            .into_iter()
+            .map(|t| AggTrade {
+                event_time: t.trade_time,
+                trade_id: t.trade_id,
+                symbol: symbol.clone(),
+                price: t.price,
+                quantity: t.quantity,
+                first_trade_id: t.first_trade_id,
+                last_trade_id: t.last_trade_id,
+                trade_time: t.trade_time,
+                is_market_maker: t.is_market_maker,
+            })
+            .collect())
+    }
+
+    /// Snapshot of the order book of `symbol`, `limit` levels of each side, never more than
+    /// [`MAX_DEPTH`], to start an [`OrderBook`](crate::book::OrderBook) from.
+    #[cfg(feature = "depth")]
+    pub async fn depth(&self, symbol: &Symbol, limit: u16) -> crate::Result<PartialDepth> {
+        Ok(self
+            .http
+            .get(format!("{}/api/v3/depth", self.url))
+            .query(&[
+                ("symbol", symbol_name(symbol)),
+                ("limit", limit.min(MAX_DEPTH).to_string()),
+            ])
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?)
+    }
+
+    /// Snapshot of the futures order book of `symbol`, `limit` levels of each side, never
+    /// more than [`MAX_FUTURES_DEPTH`], from a client of the futures api, see
+    /// [`RestClient::futures()`].
+    #[cfg(feature = "depth")]
+    pub async fn futures_depth(&self, symbol: &Symbol, limit: u16) -> crate::Result<PartialDepth> {
+        Ok(self
+            .http
+            .get(format!("{}/fapi/v1/depth", self.url))
+            .query(&[
+                ("symbol", symbol_name(symbol)),
+                ("limit", limit.min(MAX_FUTURES_DEPTH).to_string()),
+            ])
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?)
+    }
+
+    /// Tick and step sizes of `symbols`, from the exchange info.
+    ///
+    /// Symbols unknown to the exchange are left out, as are symbols without a price or lot size
+    /// filter, or with a tick or step size of 0, which Binance sends for a disabled filter.
+    pub async fn scales(&self, symbols: &[Symbol]) -> crate::Result<HashMap<Symbol, Scale>> {
+        let names: Vec<String> = symbols.iter().map(symbol_name).collect();
+        let info: ExchangeInfo = self
+            .http
+            .get(format!("{}/api/v3/exchangeInfo", self.url))
+            .query(&[("symbols", serde_json::to_string(&names)?)])
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        Ok(scales(info, symbols))
+    }
+
+    /// Every symbol of the exchange, with its status, assets and tick and step sizes.
+    pub async fn exchange_symbols(&self) -> crate::Result<Vec<ExchangeSymbol>> {
+        let info: ExchangeInfo = self
+            .http
+            .get(format!("{}/api/v3/exchangeInfo", self.url))
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        Ok(info.symbols.into_iter().map(ExchangeSymbol::from).collect())
+    }
+
+    /// How many milliseconds the local clock is behind the Binance clock, for
+    /// [`Metrics::set_clock_offset()`](crate::metrics::Metrics::set_clock_offset).
+    ///
+    /// The server time is compared to the local time halfway through the request.
+    pub async fn clock_offset(&self) -> crate::Result<i64> {
+        let sent = SystemClock.now_millis();
+        let time: ServerTime = self
+            .http
+            .get(format!("{}/api/v3/time", self.url))
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        let received = SystemClock.now_millis();
+        Ok(time.server_time as i64 - (sent + received).div_ceil(2) as i64)
+    }
+}
+
+/// Aggregate trades of `symbol` from the Binance api, see [`RestClient::agg_trades()`].
+#[cfg(feature = "trade")]
+pub async fn agg_trades(symbol: &Symbol, from_id: u64, limit: u16) -> crate::Result<Vec<AggTrade>> {
+    RestClient::new().agg_trades(symbol, from_id, limit).await
+}
+
+/// An aggregate trade as the REST api sends it, without event time and symbol.
+#[cfg(feature = "trade")]
+#[derive(Debug, Deserialize)]
+struct RestAggTrade {
+    #[serde(rename = "a")]
+    trade_id: u64,
+    #[serde(rename = "p")]
+    price: Decimal,
+    #[serde(rename = "q")]
+    quantity: Decimal,
+    #[serde(rename = "f")]
+    first_trade_id: u64,
+    #[serde(rename = "l")]
+    last_trade_id: u64,
+    #[serde(rename = "T")]
+    trade_time: u64,
+    #[serde(rename = "m")]
+    is_market_maker: bool,
+}
+
+/// A symbol of the exchange info, see [`RestClient::exchange_symbols()`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExchangeSymbol {
+    /// Name as Binance writes it, e.g. `BTCUSDT`.
+    pub name: String,
+    /// The [`Symbol`] of the name, `None` if it can not be subscribed to with this crate.
+    pub symbol: Option<Symbol>,
+    /// E.g. `TRADING` or `BREAK`.
+    pub status: String,
+    pub base_asset: String,
+    pub quote_asset: String,
+    pub tick_size: Option<Decimal>,
+    pub step_size: Option<Decimal>,
+}
+
+impl From<SymbolInfo> for ExchangeSymbol {
+    fn from(info: SymbolInfo) -> Self {
+        let (mut tick_size, mut step_size) = (None, None);
+        for filter in &info.filters {
+            match filter {
+                Filter::Price { tick_size: size } => tick_size = Some(size.normalize()),
+                Filter::LotSize { step_size: size } => step_size = Some(size.normalize()),
+                Filter::Other => {}
+            }
+        }
+        Self {
+            symbol: info.symbol.parse().ok(),
+            name: info.symbol,
+            status: info.status,
+            base_asset: info.base_asset,
+            quote_asset: info.quote_asset,
+            tick_size,
+            step_size,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ServerTime {
+    server_time: u64,
+}
+
+/// The parts of the exchange info that are used.
+#[derive(Debug, Deserialize)]
+struct ExchangeInfo {
+    symbols: Vec<SymbolInfo>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SymbolInfo {
+    symbol: String,
+    #[serde(default)]
+    status: String,
+    #[serde(default)]
+    base_asset: String,
+    #[serde(default)]
+    quote_asset: String,
+    filters: Vec<Filter>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "filterType")]
+enum Filter {
+    #[serde(rename = "PRICE_FILTER", rename_all = "camelCase")]
+    Price { tick_size: Decimal },
+    #[serde(rename = "LOT_SIZE", rename_all = "camelCase")]
+    LotSize { step_size: Decimal },
+    #[serde(other)]
+    Other,
+}
+
+fn scales(info: ExchangeInfo, symbols: &[Symbol]) -> HashMap<Symbol, Scale> {
+    let mut scales = HashMap::new();
+    for symbol in symbols {
+        let name = symbol_name(symbol);
+        let Some(info) = info.symbols.iter().find(|s| s.symbol == name) else {
+            continue;
+        };
+        let mut tick_size = None;
+        let mut step_size = None;
+        for filter in &info.filters {
+            match filter {
+                Filter::Price { tick_size: size } => tick_size = Some(*size),
+                Filter::LotSize { step_size: size } => step_size = Some(*size),
+                Filter::Other => {}
+            }
+        }
+        if let Some(scale) = tick_size
+            .zip(step_size)
+            .and_then(|(tick_size, step_size)| Scale::try_new(tick_size, step_size))
+        {
+            scales.insert(symbol.clone(), scale);
+        }
+    }
+    scales
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn exchange_info() {
+        let info = r#"{
+            "timezone": "UTC",
+            "symbols": [{
+                "symbol": "BTCUSDT",
+                "status": "TRADING",
+                "baseAsset": "BTC",
+                "quoteAsset": "USDT",
+                "filters": [
+                    {"filterType":"PRICE_FILTER","minPrice":"0.01000000","maxPrice":"1000000.00000000","tickSize":"0.01000000"},
+                    {"filterType":"LOT_SIZE","minQty":"0.00001000","maxQty":"9000.00000000","stepSize":"0.00001000"},
+                    {"filterType":"ICEBERG_PARTS","limit":10}
+                ]
+            }, {
+                "symbol": "ETHUSDT",
+                "status": "TRADING",
+                "baseAsset": "ETH",
+                "quoteAsset": "USDT",
+                "filters": [
+                    {"filterType":"PRICE_FILTER","minPrice":"0.00000000","maxPrice":"0.00000000","tickSize":"0.00000000"},
+                    {"filterType":"LOT_SIZE","minQty":"0.00010000","maxQty":"9000.00000000","stepSize":"0.00010000"}
+                ]
+            }]
+        }"#;
+        let parsed: ExchangeInfo = serde_json::from_str(info).unwrap();
+        let listed = ExchangeSymbol::from(parsed.symbols.into_iter().next().unwrap());
+        assert_eq!(listed.symbol, Some(Symbol::BTCUSDT));
+        assert_eq!(
+            (listed.base_asset.as_str(), listed.quote_asset.as_str()),
+            ("BTC", "USDT")
+        );
+        assert_eq!(listed.tick_size, Some(Decimal::new(1, 2)));
+
+        let info: ExchangeInfo = serde_json::from_str(info).unwrap();
+        let scales = scales(info, &[Symbol::BTCUSDT, Symbol::ETHUSDT, Symbol::ETHBTC]);
+
+        // the price filter of ETHUSDT is disabled, ETHBTC is not listed
+        assert_eq!(scales.len(), 1);
+        let scale = scales[&Symbol::BTCUSDT];
+        assert_eq!(scale.tick_size(), Decimal::new(1, 2));
+        assert_eq!(scale.step_size(), Decimal::new(1, 5));
+    }
+}
@@ -0,0 +1,121 @@
+//! JSON Schemas of the messages, with the `schema` feature.
+//!
+//! The schemas describe the messages as the crate serializes them, the JSON written by the
+//! recorder and the sinks, so that services in other languages can generate matching types
+//! and validate recorded data. Serialized messages have no event type, each message type has a
+//! schema of its own and [`message_schema()`] is any of them:
+//! ```
+//! use binance_api_async::schema;
+//!
+//! let schemas = schema::schemas();
+//! let (name, trade) = &schemas[1];
+//! assert_eq!(*name, "trade");
+//! assert!(trade.as_value()["properties"]["p"].is_object());
+//! ```
+//!
+//! `binance-stream schema --out <dir>` writes them to files, see [`write_schemas()`].
+
+use std::path::{Path, PathBuf};
+
+use schemars::schema_for;
+pub use schemars::Schema;
+
+#[cfg(feature = "book-ticker")]
+use crate::messages::BookTicker;
+use crate::messages::ErrorResponse;
+#[cfg(feature = "trade")]
+use crate::messages::{AggTrade, Trade};
+#[cfg(feature = "kline")]
+use crate::messages::{ContinuousKline, Kline};
+#[cfg(feature = "depth")]
+use crate::messages::{DepthUpdate, FuturesDepthUpdate, PartialDepth};
+use crate::Message;
+
+/// The schema of a [`Message`] of any type.
+pub fn message_schema() -> Schema {
+    schema_for!(Message)
+}
+
+/// The schemas of the message types, named as [`Message::event_type()`] names them, then
+/// [`message_schema()`] named `message`.
+///
+/// Acknowledgements have no type of their own, they are only in the `message` schema.
+pub fn schemas() -> Vec<(&'static str, Schema)> {
+    vec![
+        #[cfg(feature = "trade")]
+        ("aggTrade", schema_for!(AggTrade)),
+        #[cfg(feature = "trade")]
+        ("trade", schema_for!(Trade)),
+        #[cfg(feature = "depth")]
+        ("partialDepth", schema_for!(PartialDepth)),
+        #[cfg(feature = "book-ticker")]
+        ("bookTicker", schema_for!(BookTicker)),
+        #[cfg(feature = "depth")]
+        ("depthUpdate", schema_for!(DepthUpdate)),
+        #[cfg(feature = "depth")]
+        ("futuresDepthUpdate", schema_for!(FuturesDepthUpdate)),
+        #[cfg(feature = "kline")]
+        ("kline", schema_for!(Kline)),
+        #[cfg(feature = "kline")]
+        ("continuous_kline", schema_for!(ContinuousKline)),
+        ("error", schema_for!(ErrorResponse)),
+        ("message", message_schema()),
+    ]
+}
+
+/// Write the [`schemas()`] to `dir`, created if missing, as `<name>.schema.json`, and return
+/// the paths written.
+pub fn write_schemas(dir: &Path) -> crate::Result<Vec<PathBuf>> {
+    std::fs::create_dir_all(dir)?;
+    let mut paths = Vec::new();
+    for (name, schema) in schemas() {
+        let path = dir.join(format!("{name}.schema.json"));
+        std::fs::write(&path, serde_json::to_string_pretty(&schema)?)?;
+        paths.push(path);
+    }
+    Ok(paths)
+}
+
+#[cfg(all(test, feature = "trade"))]
+mod test {
+    use super::*;
+    use crate::test_util;
+    use crate::Symbol;
+    use rust_decimal::Decimal;
+
+    #[test]
+    fn schemas_match_serialized_messages() {
+        let trade = Message::Trade(Trade {
+            price: Decimal::new(995, 1),
+            is_market_maker: true,
+            ..test_util::trade(Symbol::BTCUSDT, 2)
+        });
+        let json = serde_json::to_value(&trade).unwrap();
+        let schemas = schemas();
+        let schema = schemas[1].1.as_value();
+        let properties = schema["properties"].as_object().unwrap();
+        let mut fields: Vec<_> = json.as_object().unwrap().keys().collect();
+        let mut expected: Vec<_> = properties.keys().collect();
+        fields.sort();
+        expected.sort();
+        assert_eq!(fields, expected);
+        assert_eq!(properties["s"]["$ref"], "#/$defs/Symbol");
+        assert!(schema["$defs"]["Symbol"]["enum"]
+            .as_array()
+            .unwrap()
+            .contains(&"BTCUSDT".into()));
+
+        let any = message_schema();
+        let variants = any.as_value()["anyOf"].as_array().unwrap();
+        // a variant for each type, the acknowledgement included
+        assert_eq!(variants.len(), schemas.len());
+
+        let dir = std::env::temp_dir().join(format!("schemas_{}", std::process::id()));
+        let paths = write_schemas(&dir).unwrap();
+        assert_eq!(paths.len(), schemas.len());
+        let written: Schema =
+            serde_json::from_str(&std::fs::read_to_string(&paths[1]).unwrap()).unwrap();
+        assert_eq!(written, schemas[1].1);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,565 @@
+//! The Binance websocket protocol without a transport: the subscriptions, the requests and
+//! their acknowledgements, and the frames parsed into [`Message`]s.
+//!
+//! A [`Session`] is driven by the websocket of a connection, [`BinanceApi`](crate::BinanceApi)
+//! drives one over tokio-tungstenite, [`WebSocketClient`](crate::web::WebSocketClient) over
+//! the browser websocket with the `wasm` feature. Requests are returned as text to send, the
+//! frames received are handed to [`Session::receive()`]:
+//! ```
+//! use binance_api_async::session::{Data, Session};
+//! use binance_api_async::{Feed, Message, SubscribeInfo, Symbol};
+//!
+//! let mut session = Session::new();
+//! let trades = [SubscribeInfo::new(Symbol::BTCUSDT, Feed::Trade)];
+//! let request = session.subscribe(&trades, None)?.expect("not subscribed yet");
+//! assert_eq!(
+//!     request.text(),
+//!     r#"{"method":"SUBSCRIBE","params":["btcusdt@trade"],"id":1}"#
+//! );
+//! // once the text is sent
+//! session.sent(request);
+//!
+//! let ack = session.receive(Data::Text(r#"{"result":null,"id":1}"#.into()), 0);
+//! assert_eq!(ack, Some(Message::SubscribeSuccess { result: None, id: 1 }));
+//! assert_eq!(session.subscriptions(), ["btcusdt@trade"]);
+//! # Ok::<(), binance_api_async::Error>(())
+//! ```
+//!
+//! Pings, pongs and close frames are left to the transport. Without the default `tokio`
+//! feature the crate leaves out `BinanceApi` and what is built on it, sessions, messages and
+//! the analytics build for any runtime. Drive a session with the websocket client of yours,
+//! e.g. async-tungstenite on async-std or smol, which reads and writes [`tungstenite`] frames:
+//! ```no_run
+//! use binance_api_async::session::{Data, Session};
+//! use binance_api_async::{Feed, SubscribeInfo, Symbol};
+//! use futures::{Sink, SinkExt, Stream, StreamExt};
+//! use tungstenite::{Error, Message as Frame};
+//!
+//! async fn print_trades<S>(mut socket: S) -> Result<(), Error>
+//! where
+//!     S: Stream<Item = Result<Frame, Error>> + Sink<Frame, Error = Error> + Unpin,
+//! {
+//!     let mut session = Session::new();
+//!     let trades = [SubscribeInfo::new(Symbol::BTCUSDT, Feed::Trade)];
+//!     if let Some(request) = session.subscribe(&trades, None).expect("served on spot") {
+//!         socket.send(Frame::Text(request.text().into())).await?;
+//!         session.sent(request);
+//!     }
+//!     while let Some(frame) = socket.next().await {
+//!         let data = match frame? {
+//!             Frame::Text(text) => Data::Text(text),
+//!             Frame::Binary(bytes) => Data::Binary(bytes),
+//!             Frame::Ping(payload) => {
+//!                 socket.send(Frame::Pong(payload)).await?;
+//!                 continue;
+//!             }
+//!             _ => continue,
+//!         };
+//!         let recv_time = chrono::Utc::now().timestamp_millis() as u64;
+//!         if let Some(msg) = session.receive(data, recv_time) {
+//!             println!("{msg:?}");
+//!         }
+//!     }
+//!     Ok(())
+//! }
+//! ```
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+
+use tracing::{debug, info_span, warn, Span};
+use web_time::Instant;
+
+use crate::json::{self, JsonBackend};
+use crate::logging::{RateLimited, PARSE, SUBSCRIPTION};
+use crate::messages::ErrorResponse;
+use crate::metrics::Metrics;
+use crate::request::{Method, Request};
+use crate::subscriptions::Subscriptions;
+use crate::validation::{validate, Market};
+use crate::{Error, Message, SubscribeInfo};
+
+/// The payload of a data frame.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Data {
+    Text(String),
+    /// UTF-8 text, or with the `compression` feature gzip or zlib compressed text.
+    Binary(Vec<u8>),
+}
+
+/// A request to send as a text frame, see [`Session::sent()`].
+#[derive(Debug)]
+pub struct Outgoing {
+    text: String,
+    id: u64,
+    method: Method,
+    streams: Vec<String>,
+    /// streams to unsubscribe again if the request is not sent
+    added: Vec<String>,
+    /// streams to subscribe again if the request is not sent
+    released: Vec<String>,
+    span: Span,
+}
+
+impl Outgoing {
+    /// The json of the request, e.g.
+    /// `{"method":"SUBSCRIBE","params":["btcusdt@trade"],"id":1}`.
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// The `subscribe` or `unsubscribe` span of the request, see
+    /// [`logging`](crate::logging).
+    pub fn span(&self) -> &Span {
+        &self.span
+    }
+}
+
+/// The protocol state of one connection, see the [module](self) documentation.
+#[derive(Debug)]
+pub struct Session {
+    json: Arc<dyn JsonBackend>,
+    metrics: Arc<Metrics>,
+    span: Span,
+    /// market the feeds are checked against before subscribing
+    market: Market,
+    parse_warnings: RateLimited,
+    /// requests waiting for their acknowledgement by id, with their span
+    pub(crate) pending: HashMap<u64, (Instant, Span)>,
+    /// streams subscribed with [`Session::subscribe()`], since the last reset
+    subscriptions: Subscriptions,
+    /// acknowledgements of requests that were not sent, all their streams were subscribed
+    /// or are still in use
+    acks: VecDeque<Message>,
+    /// streams of unsubscribe requests by id, until they are acknowledged
+    unsubscribing: HashMap<u64, Vec<String>>,
+    /// id of the next request without an id of the caller
+    next_id: u64,
+}
+
+impl Default for Session {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Session {
+    /// A session parsing with the default [`json`] backend, logging in a `session` span.
+    pub fn new() -> Self {
+        Self::with_market(Market::Spot)
+    }
+
+    /// A session subscribing to the feeds of `market`, e.g. on a connection to
+    /// `wss://fstream.binance.com/ws` for [`Market::UsdFutures`].
+    pub fn with_market(market: Market) -> Self {
+        let span = info_span!(target: SUBSCRIPTION, "session");
+        Self::with_parts(json::default_backend(), Arc::default(), span, market)
+    }
+
+    pub(crate) fn with_parts(
+        json: Arc<dyn JsonBackend>,
+        metrics: Arc<Metrics>,
+        span: Span,
+        market: Market,
+    ) -> Self {
+        Self {
+            json,
+            metrics,
+            span,
+            market,
+            parse_warnings: RateLimited::default(),
+            pending: HashMap::new(),
+            subscriptions: Subscriptions::default(),
+            acks: VecDeque::new(),
+            unsubscribing: HashMap::new(),
+            next_id: 1,
+        }
+    }
+
+    /// Counters of the session, messages and parse failures are counted by
+    /// [`Session::receive()`].
+    pub fn metrics(&self) -> Arc<Metrics> {
+        self.metrics.clone()
+    }
+
+    #[cfg(feature = "tokio")]
+    pub(crate) fn json(&self) -> Arc<dyn JsonBackend> {
+        self.json.clone()
+    }
+
+    /// Forget the subscriptions and the requests, for a new connection.
+    pub fn reset(&mut self) {
+        self.subscriptions.clear();
+        self.acks.clear();
+        self.unsubscribing.clear();
+        // requests of the last connection are never acknowledged
+        self.pending.clear();
+    }
+
+    /// The request subscribing to the streams of `symbols` not subscribed yet, see
+    /// [`BinanceApi::subscribe()`](crate::BinanceApi::subscribe) for the counting.
+    ///
+    /// Without an `id` the request gets one of its own, counting up from 1.
+    ///
+    /// `None` if every stream is subscribed already, the acknowledgement with the id is then
+    /// returned by [`Session::take_ack()`], or if `symbols` is empty. [`Error::UnsupportedFeed`] if a
+    /// feed is not served on the market of the session, spot by default.
+    pub fn subscribe(
+        &mut self,
+        symbols: &[SubscribeInfo],
+        id: Option<u64>,
+    ) -> crate::Result<Option<Outgoing>> {
+        if symbols.is_empty() {
+            warn!(
+                target: SUBSCRIPTION,
+                parent: &self.span,
+                "you must provide SubsribeInfo for atleast one Symbol"
+            );
+            return Ok(None);
+        }
+        if let Some(e) = symbols
+            .iter()
+            .find_map(|s| validate(&s.feed, self.market).err())
+        {
+            return Err(e);
+        }
+
+        let streams = stream_names(symbols);
+        let id = id.unwrap_or_else(|| self.next_id());
+        let new = self.subscriptions.add(&streams);
+        if new.is_empty() {
+            debug!(target: SUBSCRIPTION, parent: &self.span, ?streams, "Subscribed already");
+            self.acks
+                .push_back(Message::SubscribeSuccess { result: None, id });
+            return Ok(None);
+        }
+        let mut request = self.request(Method::Subscribe, new, id);
+        request.added = streams;
+        Ok(Some(request))
+    }
+
+    /// The request unsubscribing from the streams of `symbols` without subscriptions left,
+    /// `None` if all of them are still in use, or if `symbols` is empty. The request, or the
+    /// acknowledgement returned by [`Session::take_ack()`] when none is needed, gets an id of
+    /// its own.
+    ///
+    /// Returned with [`Error::NotSubscribed`] and the streams that are not subscribed, the
+    /// others are unsubscribed all the same.
+    pub fn unsubscribe(
+        &mut self,
+        symbols: &[SubscribeInfo],
+    ) -> (Option<Outgoing>, crate::Result<()>) {
+        if symbols.is_empty() {
+            warn!(
+                target: SUBSCRIPTION,
+                parent: &self.span,
+                "you must provide SubsribeInfo for atleast one Symbol"
+            );
+            return (None, Ok(()));
+        }
+
+        let (streams, inactive): (Vec<String>, Vec<String>) = stream_names(symbols)
+            .into_iter()
+            .partition(|stream| self.subscriptions.is_active(stream));
+        let result = if inactive.is_empty() {
+            Ok(())
+        } else {
+            warn!(target: SUBSCRIPTION, parent: &self.span, ?inactive, "Not subscribed");
+            Err(Error::NotSubscribed { streams: inactive })
+        };
+        if streams.is_empty() {
+            return (None, result);
+        }
+
+        let unused = self.subscriptions.release(&streams);
+        // an id of its own, to know which streams an acknowledgement confirms
+        let id = self.next_id();
+        if unused.is_empty() {
+            debug!(target: SUBSCRIPTION, parent: &self.span, ?streams, "Still subscribed");
+            self.acks
+                .push_back(Message::SubscribeSuccess { result: None, id });
+            return (None, result);
+        }
+        let mut request = self.request(Method::Unsubscribe, unused, id);
+        request.released = streams;
+        (Some(request), result)
+    }
+
+    /// The next id counting up, skipping the ids of requests waiting for their
+    /// acknowledgement.
+    fn next_id(&mut self) -> u64 {
+        loop {
+            let id = self.next_id;
+            self.next_id = self.next_id.checked_add(1).unwrap_or(1);
+            if !self.pending.contains_key(&id) {
+                return id;
+            }
+        }
+    }
+
+    /// A request for raw stream names, e.g. `btcusdt@trade`.
+    pub(crate) fn request(&self, method: Method, streams: Vec<String>, id: u64) -> Outgoing {
+        let span = self.request_span(method, &streams, id);
+        Outgoing {
+            text: Request::new(method, streams.clone(), id).to_json(),
+            id,
+            method,
+            streams,
+            added: Vec::new(),
+            released: Vec::new(),
+            span,
+        }
+    }
+
+    /// Wait for the acknowledgement of `request`, once it was sent.
+    pub fn sent(&mut self, request: Outgoing) {
+        debug!(target: SUBSCRIPTION, parent: &request.span, "Sent");
+        if request.method == Method::Unsubscribe {
+            self.unsubscribing.insert(request.id, request.streams);
+        }
+        self.pending
+            .insert(request.id, (Instant::now(), request.span));
+    }
+
+    /// Forget `request`, it could not be sent, the streams it subscribes to are not
+    /// subscribed and the streams it unsubscribes from stay subscribed.
+    pub fn not_sent(&mut self, request: Outgoing) {
+        self.subscriptions.remove(&request.added);
+        self.subscriptions.add(&request.released);
+    }
+
+    /// The acknowledgement of a request that needed not be sent, see
+    /// [`Session::subscribe()`], to be returned before the next message.
+    pub fn take_ack(&mut self) -> Option<Message> {
+        self.acks.pop_front()
+    }
+
+    #[cfg(feature = "tokio")]
+    pub(crate) fn has_acks(&self) -> bool {
+        !self.acks.is_empty()
+    }
+
+    /// The message of a frame received at `recv_time`, in milliseconds since epoch, `None` if
+    /// it could not be decoded or parsed.
+    ///
+    /// Acknowledgements and error replies of requests end their spans.
+    pub fn receive(&mut self, data: Data, recv_time: u64) -> Option<Message> {
+        let text = self.decode(data)?;
+        self.parse(text, recv_time)
+    }
+
+    /// The text of a frame, counting its bytes.
+    pub(crate) fn decode(&mut self, data: Data) -> Option<String> {
+        match data {
+            Data::Text(text) => {
+                self.metrics.record_bytes(text.len());
+                Some(text)
+            }
+            Data::Binary(bytes) => {
+                self.metrics.record_bytes(bytes.len());
+                match crate::decode_binary(bytes) {
+                    Ok(text) => Some(text),
+                    Err(e) => {
+                        self.metrics.record_parse_failure();
+                        if let Some(suppressed) = self.parse_warnings.allow() {
+                            warn!(
+                                target: PARSE,
+                                parent: &self.span,
+                                suppressed,
+                                "could not decode binary frame: {e}"
+                            );
+                        }
+                        None
+                    }
+                }
+            }
+        }
+    }
+
+    pub(crate) fn parse(&mut self, text: String, recv_time: u64) -> Option<Message> {
+        match self.json.parse(text) {
+            Ok(msg) => {
+                self.metrics.record_message(&msg, recv_time);
+                self.replied(&msg);
+                Some(msg)
+            }
+            Err(e) => {
+                self.metrics.record_parse_failure();
+                if let Some(suppressed) = self.parse_warnings.allow() {
+                    warn!(
+                        target: PARSE,
+                        parent: &self.span,
+                        suppressed,
+                        "could not parse message: {e}"
+                    );
+                }
+                None
+            }
+        }
+    }
+
+    /// End the request answered by `msg`, if it is an acknowledgement or an error reply.
+    pub(crate) fn replied(&mut self, msg: &Message) {
+        match msg {
+            Message::SubscribeSuccess { id, .. } => self.acknowledged(*id),
+            Message::Error(e) => self.failed(e),
+            _ => {}
+        }
+    }
+
+    /// Record the round trip of the request `id`, and close its span.
+    fn acknowledged(&mut self, id: u64) {
+        if let Some(streams) = self.unsubscribing.remove(&id) {
+            self.subscriptions.confirm(&streams);
+        }
+        let Some((sent, span)) = self.pending.remove(&id) else {
+            return;
+        };
+        let round_trip = sent.elapsed();
+        self.metrics.record_round_trip(round_trip);
+        debug!(
+            target: SUBSCRIPTION,
+            parent: &span,
+            round_trip_ms = round_trip.as_millis() as u64,
+            "Acknowledged"
+        );
+    }
+
+    /// Log the error reply `e` in the span of its request, and close the span.
+    fn failed(&mut self, e: &ErrorResponse) {
+        if let Some(id) = e.id {
+            self.unsubscribing.remove(&id);
+        }
+        let span =
+            e.id.and_then(|id| self.pending.remove(&id))
+                .map(|(_, span)| span);
+        warn!(
+            target: SUBSCRIPTION,
+            parent: span.as_ref().unwrap_or(&self.span),
+            code = e.code,
+            "Request failed: {}",
+            e.msg
+        );
+    }
+
+    /// Span of a request within the connection span, see [`logging`](crate::logging).
+    fn request_span(&self, method: Method, streams: &[String], id: u64) -> Span {
+        match method {
+            Method::Subscribe => {
+                info_span!(target: SUBSCRIPTION, parent: &self.span, "subscribe", id, ?streams)
+            }
+            Method::Unsubscribe => {
+                info_span!(target: SUBSCRIPTION, parent: &self.span, "unsubscribe", id, ?streams)
+            }
+            Method::ListSubscriptions => {
+                info_span!(target: SUBSCRIPTION, parent: &self.span, "list_subscriptions", id)
+            }
+        }
+    }
+
+    /// Streams subscribed since the last reset, e.g. `btcusdt@trade`, sorted. Unsubscribed
+    /// streams are listed until Binance confirms it.
+    pub fn subscriptions(&self) -> Vec<String> {
+        self.subscriptions.streams()
+    }
+}
+
+/// The stream names of `symbols`, e.g. `btcusdt@trade`.
+pub(crate) fn stream_names(symbols: &[SubscribeInfo]) -> Vec<String> {
+    symbols.iter().map(SubscribeInfo::stream_name).collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{ContractType, Feed, Interval, Symbol};
+
+    #[test]
+    fn requests_and_acks() {
+        let mut session = Session::new();
+        let trades = [SubscribeInfo::new(Symbol::BTCUSDT, Feed::Trade)];
+        assert!(session.subscribe(&[], None).unwrap().is_none());
+
+        // not sent, not subscribed
+        let request = session.subscribe(&trades, Some(3)).unwrap().unwrap();
+        assert_eq!(request.id(), 3);
+        session.not_sent(request);
+        assert!(session.subscriptions().is_empty());
+
+        let request = session.subscribe(&trades, None).unwrap().unwrap();
+        assert_eq!(request.id(), 1);
+        session.sent(request);
+        // concurrent requests without ids are told apart
+        let eth = [SubscribeInfo::new(Symbol::ETHUSDT, Feed::Trade)];
+        let request = session.subscribe(&eth, None).unwrap().unwrap();
+        assert_eq!(request.id(), 2);
+        session.not_sent(request);
+        // subscribed already, acknowledged without a request
+        assert!(session.subscribe(&trades, Some(2)).unwrap().is_none());
+        assert_eq!(
+            session.take_ack(),
+            Some(Message::SubscribeSuccess {
+                result: None,
+                id: 2
+            })
+        );
+        assert_eq!(session.take_ack(), None);
+
+        let (request, result) = session.unsubscribe(&trades);
+        assert!(request.is_none() && result.is_ok());
+        let ack = session.take_ack();
+        assert_eq!(ack, Some(Message::SubscribeSuccess { result: None, id: 3 }));
+        let (request, result) = session.unsubscribe(&trades);
+        assert!(result.is_ok());
+        // not sent, still subscribed
+        session.not_sent(request.unwrap());
+        assert_eq!(session.subscriptions(), ["btcusdt@trade"]);
+        let (request, result) = session.unsubscribe(&trades);
+        let request = request.unwrap();
+        assert!(result.is_ok());
+        assert_eq!(request.id(), 5);
+        assert_eq!(
+            request.text(),
+            r#"{"method":"UNSUBSCRIBE","params":["btcusdt@trade"],"id":5}"#
+        );
+        session.sent(request);
+        assert_eq!(session.subscriptions(), ["btcusdt@trade"]);
+        let ack = Data::Binary(br#"{"result":null,"id":5}"#.to_vec());
+        assert!(session.receive(ack, 0).is_some());
+        assert!(session.subscriptions().is_empty());
+
+        assert_eq!(session.receive(Data::Text("{".into()), 0), None);
+        let metrics = session.metrics();
+        assert_eq!(metrics.parse_failures(), 1);
+        assert_eq!(metrics.messages("subscribeSuccess"), 1);
+        assert!(metrics.round_trip().is_some());
+
+        let (request, result) = session.unsubscribe(&trades);
+        assert!(request.is_none());
+        assert!(matches!(result, Err(Error::NotSubscribed { .. })));
+        let unserved = Feed::FullDepth {
+            delay: crate::Delay::FIVEHUNDRED,
+        };
+        let unserved = [SubscribeInfo::new(Symbol::BTCUSDT, unserved)];
+        assert!(session.subscribe(&unserved, None).is_err());
+
+        let continuous = [SubscribeInfo::new(
+            Symbol::BTCUSDT,
+            Feed::ContinuousKline {
+                contract_type: ContractType::Perpetual,
+                interval: Interval::ONEMINUTE,
+            },
+        )];
+        assert!(session.subscribe(&continuous, None).is_err());
+        let mut futures = Session::with_market(Market::UsdFutures);
+        let request = futures.subscribe(&continuous, Some(2)).unwrap().unwrap();
+        assert_eq!(
+            request.text(),
+            r#"{"method":"SUBSCRIBE","params":["btcusdt_perpetual@continuousKline_1m"],"id":2}"#
+        );
+    }
+}
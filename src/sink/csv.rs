@@ -0,0 +1,356 @@
+//! Typed CSV writers.
+//!
+//! Every message type implementing [`CsvRecord`] has a fixed set of columns,
+//! a [`CsvWriter`] writes them with a header row first.
+//! [`CsvSink`] routes [`Message`]s to one file per type in a directory:
+//! `aggtrade.csv`, `trade.csv`, `kline.csv` and `bookticker.csv`.
+//!
+//! Prices and quantities are written as exact decimals, times in milliseconds since epoch.
+
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::marker::PhantomData;
+use std::path::{Path, PathBuf};
+
+use super::{symbol_name, Sink};
+#[cfg(feature = "book-ticker")]
+use crate::messages::BookTicker;
+#[cfg(feature = "kline")]
+use crate::messages::Kline;
+#[cfg(feature = "trade")]
+use crate::messages::{AggTrade, Trade};
+use crate::{Envelope, Message};
+
+/// A message with a fixed set of CSV columns.
+pub trait CsvRecord {
+    /// Column names, in the order of [`CsvRecord::fields()`].
+    const HEADER: &'static [&'static str];
+    /// File name used by [`CsvSink`].
+    const FILE_NAME: &'static str;
+
+    fn fields(&self) -> Vec<String>;
+}
+
+#[cfg(feature = "trade")]
+impl CsvRecord for AggTrade {
+    const HEADER: &'static [&'static str] = &[
+        "event_time",
+        "symbol",
+        "trade_id",
+        "price",
+        "quantity",
+        "first_trade_id",
+        "last_trade_id",
+        "trade_time",
+        "is_market_maker",
+    ];
+    const FILE_NAME: &'static str = "aggtrade.csv";
+
+    fn fields(&self) -> Vec<String> {
+        vec![
+            self.event_time.to_string(),
+            symbol_name(&self.symbol),
+            self.trade_id.to_string(),
+            self.price.to_string(),
+            self.quantity.to_string(),
+            self.first_trade_id.to_string(),
+            self.last_trade_id.to_string(),
+            self.trade_time.to_string(),
+            self.is_market_maker.to_string(),
+        ]
+    }
+}
+
+#[cfg(feature = "trade")]
+impl CsvRecord for Trade {
+    const HEADER: &'static [&'static str] = &[
+        "event_time",
+        "symbol",
+        "trade_id",
+        "price",
+        "quantity",
+        "trade_time",
+        "is_market_maker",
+    ];
+    const FILE_NAME: &'static str = "trade.csv";
+
+    fn fields(&self) -> Vec<String> {
+        vec![
+            self.event_time.to_string(),
+            symbol_name(&self.symbol),
+            self.trade_id.to_string(),
+            self.price.to_string(),
+            self.quantity.to_string(),
+            self.trade_time.to_string(),
+            self.is_market_maker.to_string(),
+        ]
+    }
+}
+
+#[cfg(feature = "kline")]
+impl CsvRecord for Kline {
+    const HEADER: &'static [&'static str] = &[
+        "event_time",
+        "symbol",
+        "interval",
+        "open_time",
+        "close_time",
+        "open",
+        "high",
+        "low",
+        "close",
+        "volume",
+        "quote_volume",
+        "taker_buy_volume",
+        "taker_buy_quote_volume",
+        "trades",
+        "first_trade_id",
+        "last_trade_id",
+        "is_closed",
+    ];
+    const FILE_NAME: &'static str = "kline.csv";
+
+    fn fields(&self) -> Vec<String> {
+        let k = &self.kline;
+        vec![
+            self.event_time.to_string(),
+            symbol_name(&self.symbol),
+            k.interval.clone(),
+            k.open_time.to_string(),
+            k.close_time.to_string(),
+            k.open.to_string(),
+            k.high.to_string(),
+            k.low.to_string(),
+            k.close.to_string(),
+            k.volume.to_string(),
+            k.quote_volume.to_string(),
+            k.taker_buy_volume.to_string(),
+            k.taker_buy_quote_volume.to_string(),
+            k.trades.to_string(),
+            k.first_trade_id.to_string(),
+            k.last_trade_id.to_string(),
+            k.is_closed.to_string(),
+        ]
+    }
+}
+
+#[cfg(feature = "book-ticker")]
+impl CsvRecord for BookTicker {
+    const HEADER: &'static [&'static str] = &[
+        "update_id",
+        "symbol",
+        "best_bid_price",
+        "best_bid_qty",
+        "best_ask_price",
+        "best_ask_qty",
+    ];
+    const FILE_NAME: &'static str = "bookticker.csv";
+
+    fn fields(&self) -> Vec<String> {
+        vec![
+            self.update_id.to_string(),
+            symbol_name(&self.symbol),
+            self.best_bid_price.to_string(),
+            self.best_bid_qty.to_string(),
+            self.best_ask_price.to_string(),
+            self.best_ask_qty.to_string(),
+        ]
+    }
+}
+
+/// Writes records of one type as CSV.
+#[derive(Debug)]
+pub struct CsvWriter<W: Write, T> {
+    writer: ::csv::Writer<W>,
+    _record: PhantomData<T>,
+}
+
+impl<W: Write, T: CsvRecord> CsvWriter<W, T> {
+    /// Write records to `writer`, starting with the header row.
+    pub fn new(writer: W) -> crate::Result<Self> {
+        let mut csv = Self::without_header(writer);
+        csv.writer.write_record(T::HEADER).map_err(csv_error)?;
+        Ok(csv)
+    }
+
+    fn without_header(writer: W) -> Self {
+        Self {
+            writer: ::csv::Writer::from_writer(writer),
+            _record: PhantomData,
+        }
+    }
+
+    pub fn write(&mut self, record: &T) -> crate::Result<()> {
+        self.writer.write_record(record.fields()).map_err(csv_error)
+    }
+
+    pub fn flush(&mut self) -> crate::Result<()> {
+        Ok(self.writer.flush()?)
+    }
+}
+
+impl<T: CsvRecord> CsvWriter<File, T> {
+    /// Append to the file at `path`, the header is written if the file is new or empty.
+    pub fn append(path: impl AsRef<Path>) -> crate::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        if file.metadata()?.len() == 0 {
+            Self::new(file)
+        } else {
+            Ok(Self::without_header(file))
+        }
+    }
+}
+
+fn csv_error(e: ::csv::Error) -> crate::Error {
+    match e.into_kind() {
+        ::csv::ErrorKind::Io(e) => e.into(),
+        kind => crate::Error::Custom(format!("csv: {kind:?}")),
+    }
+}
+
+/// Writes trades, klines and book tickers to one CSV file per message type in a directory.
+///
+/// Files are appended to and created on the first message of their type.
+#[derive(Debug)]
+pub struct CsvSink {
+    dir: PathBuf,
+    #[cfg(feature = "trade")]
+    agg_trades: Option<CsvWriter<File, AggTrade>>,
+    #[cfg(feature = "trade")]
+    trades: Option<CsvWriter<File, Trade>>,
+    #[cfg(feature = "kline")]
+    klines: Option<CsvWriter<File, Kline>>,
+    #[cfg(feature = "book-ticker")]
+    book_tickers: Option<CsvWriter<File, BookTicker>>,
+}
+
+impl CsvSink {
+    /// Write to files in `dir`, `dir` is created if it does not exist.
+    pub fn new(dir: impl Into<PathBuf>) -> crate::Result<Self> {
+        let dir = dir.into();
+        std::fs::create_dir_all(&dir)?;
+        Ok(Self {
+            dir,
+            #[cfg(feature = "trade")]
+            agg_trades: None,
+            #[cfg(feature = "trade")]
+            trades: None,
+            #[cfg(feature = "kline")]
+            klines: None,
+            #[cfg(feature = "book-ticker")]
+            book_tickers: None,
+        })
+    }
+
+    /// Write `msg` to the file of its type, returns false if the type has no CSV schema.
+    pub fn write(&mut self, msg: &Message) -> crate::Result<bool> {
+        match msg {
+            #[cfg(feature = "trade")]
+            Message::AggTrade(t) => write(&self.dir, &mut self.agg_trades, t)?,
+            #[cfg(feature = "trade")]
+            Message::Trade(t) => write(&self.dir, &mut self.trades, t)?,
+            #[cfg(feature = "kline")]
+            Message::Kline(k) => write(&self.dir, &mut self.klines, k)?,
+            #[cfg(feature = "book-ticker")]
+            Message::BookTicker(bt) => write(&self.dir, &mut self.book_tickers, bt)?,
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+
+    pub fn flush(&mut self) -> crate::Result<()> {
+        #[cfg(feature = "trade")]
+        if let Some(w) = self.agg_trades.as_mut() {
+            w.flush()?;
+        }
+        #[cfg(feature = "trade")]
+        if let Some(w) = self.trades.as_mut() {
+            w.flush()?;
+        }
+        #[cfg(feature = "kline")]
+        if let Some(w) = self.klines.as_mut() {
+            w.flush()?;
+        }
+        #[cfg(feature = "book-ticker")]
+        if let Some(w) = self.book_tickers.as_mut() {
+            w.flush()?;
+        }
+        Ok(())
+    }
+}
+
+impl Sink for CsvSink {
+    async fn write(&mut self, envelope: &Envelope) -> crate::Result<()> {
+        CsvSink::write(self, &envelope.message).map(drop)
+    }
+
+    async fn flush(&mut self) -> crate::Result<()> {
+        CsvSink::flush(self)
+    }
+}
+
+fn write<T: CsvRecord>(
+    dir: &Path,
+    writer: &mut Option<CsvWriter<File, T>>,
+    record: &T,
+) -> crate::Result<()> {
+    let writer = match writer {
+        Some(w) => w,
+        None => writer.insert(CsvWriter::append(dir.join(T::FILE_NAME))?),
+    };
+    writer.write(record)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_util::{self, dec};
+    use crate::Symbol;
+
+    fn trade(trade_id: u64) -> Trade {
+        Trade {
+            event_time: 2,
+            price: dec("0.00100"),
+            quantity: dec("12.5"),
+            is_market_maker: true,
+            ..test_util::trade(Symbol::BTCUSDT, trade_id)
+        }
+    }
+
+    #[test]
+    fn header_and_rows() {
+        let mut writer = CsvWriter::new(Vec::new()).unwrap();
+        writer.write(&trade(7)).unwrap();
+        let out = String::from_utf8(writer.writer.into_inner().unwrap()).unwrap();
+
+        assert_eq!(
+            out,
+            "event_time,symbol,trade_id,price,quantity,trade_time,is_market_maker\n\
+             2,BTCUSDT,7,0.00100,12.5,1,true\n"
+        );
+    }
+
+    #[test]
+    fn sink_appends_per_type() {
+        let dir = std::env::temp_dir().join(format!("csv_sink_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+
+        for id in [1, 2] {
+            let mut sink = CsvSink::new(&dir).unwrap();
+            assert!(sink.write(&Message::Trade(trade(id))).unwrap());
+            assert!(!sink
+                .write(&Message::SubscribeSuccess {
+                    result: None,
+                    id: 1
+                })
+                .unwrap());
+            sink.flush().unwrap();
+        }
+
+        let content = std::fs::read_to_string(dir.join("trade.csv")).unwrap();
+        assert_eq!(content.lines().count(), 3);
+        assert!(content.lines().nth(2).unwrap().starts_with("2,BTCUSDT,2,"));
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
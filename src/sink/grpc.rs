@@ -0,0 +1,470 @@
+//! gRPC server streaming messages to any number of clients, requires the `grpc` feature.
+//!
+//! The `MarketData` service is defined in `proto/market_data.proto`, clients in other
+//! languages generate their stubs from it. `Subscribe(symbols, feeds)` streams every message
+//! published after the call, for the requested symbols and event types, all if empty.
+//!
+//! A client that does not keep up skips the oldest messages once `capacity` messages are
+//! queued for it, instead of slowing down the others.
+
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::sync::Arc;
+
+use futures::{Stream, StreamExt};
+use rust_decimal::Decimal;
+use tokio::net::TcpListener;
+use tokio::sync::broadcast;
+use tokio::task::JoinHandle;
+use tokio_stream::wrappers::{BroadcastStream, TcpListenerStream};
+use tonic::{Request, Response, Status};
+use tracing::{error, info, warn};
+
+use super::{symbol_name, Sink};
+use crate::messages::KlineData;
+use crate::recorder::{now_millis, Record};
+use crate::{Envelope, Message};
+
+/// Messages of `proto/market_data.proto` and the generated service.
+pub mod proto {
+    include!(concat!(env!("OUT_DIR"), "/binance.MarketData.rs"));
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct SubscribeRequest {
+        #[prost(string, repeated, tag = "1")]
+        pub symbols: Vec<String>,
+        #[prost(string, repeated, tag = "2")]
+        pub feeds: Vec<String>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct MarketMessage {
+        #[prost(uint64, tag = "1")]
+        pub recv_time: u64,
+        #[prost(string, tag = "2")]
+        pub symbol: String,
+        #[prost(string, tag = "3")]
+        pub event_type: String,
+        #[prost(oneof = "Payload", tags = "4, 5, 6, 7, 8, 9")]
+        pub payload: Option<Payload>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Oneof)]
+    pub enum Payload {
+        #[prost(message, tag = "4")]
+        AggTrade(AggTrade),
+        #[prost(message, tag = "5")]
+        Trade(Trade),
+        #[prost(message, tag = "6")]
+        BookTicker(BookTicker),
+        #[prost(message, tag = "7")]
+        DepthUpdate(DepthUpdate),
+        #[prost(message, tag = "8")]
+        PartialDepth(PartialDepth),
+        #[prost(message, tag = "9")]
+        Kline(Kline),
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct AggTrade {
+        #[prost(uint64, tag = "1")]
+        pub event_time: u64,
+        #[prost(uint64, tag = "2")]
+        pub trade_id: u64,
+        #[prost(string, tag = "3")]
+        pub price: String,
+        #[prost(string, tag = "4")]
+        pub quantity: String,
+        #[prost(uint64, tag = "5")]
+        pub first_trade_id: u64,
+        #[prost(uint64, tag = "6")]
+        pub last_trade_id: u64,
+        #[prost(uint64, tag = "7")]
+        pub trade_time: u64,
+        #[prost(bool, tag = "8")]
+        pub is_market_maker: bool,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct Trade {
+        #[prost(uint64, tag = "1")]
+        pub event_time: u64,
+        #[prost(uint64, tag = "2")]
+        pub trade_id: u64,
+        #[prost(string, tag = "3")]
+        pub price: String,
+        #[prost(string, tag = "4")]
+        pub quantity: String,
+        #[prost(uint64, tag = "5")]
+        pub trade_time: u64,
+        #[prost(bool, tag = "6")]
+        pub is_market_maker: bool,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct BookTicker {
+        #[prost(uint64, tag = "1")]
+        pub update_id: u64,
+        #[prost(string, tag = "2")]
+        pub best_bid_price: String,
+        #[prost(string, tag = "3")]
+        pub best_bid_qty: String,
+        #[prost(string, tag = "4")]
+        pub best_ask_price: String,
+        #[prost(string, tag = "5")]
+        pub best_ask_qty: String,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct Level {
+        #[prost(string, tag = "1")]
+        pub price: String,
+        #[prost(string, tag = "2")]
+        pub quantity: String,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct DepthUpdate {
+        #[prost(uint64, tag = "1")]
+        pub event_time: u64,
+        #[prost(uint64, tag = "2")]
+        pub first_update_id: u64,
+        #[prost(uint64, tag = "3")]
+        pub final_update_id: u64,
+        #[prost(message, repeated, tag = "4")]
+        pub bids: Vec<Level>,
+        #[prost(message, repeated, tag = "5")]
+        pub asks: Vec<Level>,
+        #[prost(uint64, tag = "6")]
+        pub transaction_time: u64,
+        #[prost(uint64, tag = "7")]
+        pub prev_final_update_id: u64,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct PartialDepth {
+        #[prost(uint64, tag = "1")]
+        pub last_update_id: u64,
+        #[prost(message, repeated, tag = "2")]
+        pub bids: Vec<Level>,
+        #[prost(message, repeated, tag = "3")]
+        pub asks: Vec<Level>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct Kline {
+        #[prost(uint64, tag = "1")]
+        pub event_time: u64,
+        #[prost(string, tag = "2")]
+        pub interval: String,
+        #[prost(uint64, tag = "3")]
+        pub open_time: u64,
+        #[prost(uint64, tag = "4")]
+        pub close_time: u64,
+        #[prost(int64, tag = "5")]
+        pub first_trade_id: i64,
+        #[prost(int64, tag = "6")]
+        pub last_trade_id: i64,
+        #[prost(string, tag = "7")]
+        pub open: String,
+        #[prost(string, tag = "8")]
+        pub high: String,
+        #[prost(string, tag = "9")]
+        pub low: String,
+        #[prost(string, tag = "10")]
+        pub close: String,
+        #[prost(string, tag = "11")]
+        pub volume: String,
+        #[prost(string, tag = "12")]
+        pub quote_volume: String,
+        #[prost(string, tag = "13")]
+        pub taker_buy_volume: String,
+        #[prost(string, tag = "14")]
+        pub taker_buy_quote_volume: String,
+        #[prost(uint64, tag = "15")]
+        pub trades: u64,
+        #[prost(bool, tag = "16")]
+        pub is_closed: bool,
+        #[prost(string, tag = "17")]
+        pub contract_type: String,
+    }
+}
+
+use proto::market_data_server::{MarketData, MarketDataServer};
+use proto::{MarketMessage, Payload, SubscribeRequest};
+
+/// Serves messages over gRPC, see the [module](self) documentation.
+#[derive(Debug)]
+pub struct GrpcSink {
+    sender: broadcast::Sender<Arc<Record>>,
+    local_addr: SocketAddr,
+    server: JoinHandle<()>,
+}
+
+impl GrpcSink {
+    /// Serve on `addr` from a background task, up to `capacity` messages are queued for each
+    /// client, at least one.
+    pub async fn serve(addr: SocketAddr, capacity: usize) -> crate::Result<Self> {
+        if capacity == 0 {
+            return Err(crate::Error::Custom(
+                "grpc: at least one message must be queued per client".to_string(),
+            ));
+        }
+        let listener = TcpListener::bind(addr).await?;
+        let local_addr = listener.local_addr()?;
+        let (sender, _) = broadcast::channel(capacity);
+
+        let service = MarketDataServer::new(Service {
+            sender: sender.clone(),
+        });
+        let server = tokio::spawn(async move {
+            let result = tonic::transport::Server::builder()
+                .add_service(service)
+                .serve_with_incoming(TcpListenerStream::new(listener))
+                .await;
+            if let Err(e) = result {
+                error!("gRPC server stopped: {e}");
+            }
+        });
+
+        info!("Serving gRPC on {local_addr}");
+        Ok(Self {
+            sender,
+            local_addr,
+            server,
+        })
+    }
+
+    /// The address served on, with the actual port when bound to port 0.
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    /// Number of subscribed clients.
+    pub fn clients(&self) -> usize {
+        self.sender.receiver_count()
+    }
+
+    /// Send `msg`, received now, to the subscribed clients.
+    pub fn record(&self, msg: &Message) -> bool {
+        self.send(now_millis(), msg)
+    }
+
+    /// Send `msg` received at `recv_time` to the subscribed clients,
+    /// returns false if it is not sent.
+    ///
+    /// Subscription responses are not sent.
+    pub fn send(&self, recv_time: u64, msg: &Message) -> bool {
+        if let Message::SubscribeSuccess { .. } = msg {
+            return false;
+        }
+        // an error only means that no client is subscribed
+        let _ = self.sender.send(Arc::new(Record {
+            recv_time,
+            msg: msg.clone(),
+        }));
+        true
+    }
+}
+
+impl Sink for GrpcSink {
+    async fn write(&mut self, envelope: &Envelope) -> crate::Result<()> {
+        self.send(envelope.recv_time, &envelope.message);
+        Ok(())
+    }
+
+    /// Messages are queued for the clients as they are sent.
+    async fn flush(&mut self) -> crate::Result<()> {
+        Ok(())
+    }
+}
+
+impl Drop for GrpcSink {
+    fn drop(&mut self) {
+        self.server.abort();
+    }
+}
+
+struct Service {
+    sender: broadcast::Sender<Arc<Record>>,
+}
+
+type MessageStream = Pin<Box<dyn Stream<Item = Result<MarketMessage, Status>> + Send>>;
+
+#[tonic::async_trait]
+impl MarketData for Service {
+    type SubscribeStream = MessageStream;
+
+    async fn subscribe(
+        &self,
+        request: Request<SubscribeRequest>,
+    ) -> Result<Response<MessageStream>, Status> {
+        let SubscribeRequest { symbols, feeds } = request.into_inner();
+        let symbols: Vec<String> = symbols.iter().map(|s| s.to_uppercase()).collect();
+
+        let stream = BroadcastStream::new(self.sender.subscribe()).filter_map(move |record| {
+            let message = match record {
+                Ok(record) => {
+                    let message = market_message(&record);
+                    let wanted = (symbols.is_empty() || symbols.contains(&message.symbol))
+                        && (feeds.is_empty() || feeds.contains(&message.event_type));
+                    wanted.then_some(Ok(message))
+                }
+                Err(e) => {
+                    warn!("gRPC client is lagging: {e}");
+                    None
+                }
+            };
+            futures::future::ready(message)
+        });
+        Ok(Response::new(Box::pin(stream)))
+    }
+}
+
+fn market_message(record: &Record) -> MarketMessage {
+    let msg = &record.msg;
+    let payload = match msg {
+        Message::AggTrade(t) => Some(Payload::AggTrade(proto::AggTrade {
+            event_time: t.event_time,
+            trade_id: t.trade_id,
+            price: t.price.to_string(),
+            quantity: t.quantity.to_string(),
+            first_trade_id: t.first_trade_id,
+            last_trade_id: t.last_trade_id,
+            trade_time: t.trade_time,
+            is_market_maker: t.is_market_maker,
+        })),
+        Message::Trade(t) => Some(Payload::Trade(proto::Trade {
+            event_time: t.event_time,
+            trade_id: t.trade_id,
+            price: t.price.to_string(),
+            quantity: t.quantity.to_string(),
+            trade_time: t.trade_time,
+            is_market_maker: t.is_market_maker,
+        })),
+        Message::BookTicker(bt) => Some(Payload::BookTicker(proto::BookTicker {
+            update_id: bt.update_id,
+            best_bid_price: bt.best_bid_price.to_string(),
+            best_bid_qty: bt.best_bid_qty.to_string(),
+            best_ask_price: bt.best_ask_price.to_string(),
+            best_ask_qty: bt.best_ask_qty.to_string(),
+        })),
+        Message::DepthUpdate(du) => Some(Payload::DepthUpdate(proto::DepthUpdate {
+            event_time: du.event_time,
+            first_update_id: du.first_update_id,
+            final_update_id: du.final_update_id,
+            bids: levels(&du.bids),
+            asks: levels(&du.asks),
+            transaction_time: 0,
+            prev_final_update_id: 0,
+        })),
+        Message::FuturesDepthUpdate(du) => Some(Payload::DepthUpdate(proto::DepthUpdate {
+            event_time: du.event_time,
+            first_update_id: du.first_update_id,
+            final_update_id: du.final_update_id,
+            bids: levels(&du.bids),
+            asks: levels(&du.asks),
+            transaction_time: du.transaction_time,
+            prev_final_update_id: du.prev_final_update_id,
+        })),
+        Message::PartialDepth(pd) => Some(Payload::PartialDepth(proto::PartialDepth {
+            last_update_id: pd.last_update_id,
+            bids: levels(&pd.bids),
+            asks: levels(&pd.asks),
+        })),
+        Message::Kline(k) => Some(Payload::Kline(kline(k.event_time, &k.kline))),
+        Message::ContinuousKline(k) => Some(Payload::Kline(proto::Kline {
+            contract_type: k.contract_type.as_str().to_uppercase(),
+            ..kline(k.event_time, &k.kline)
+        })),
+        Message::Error(_) | Message::SubscribeSuccess { .. } => None,
+    };
+    MarketMessage {
+        recv_time: record.recv_time,
+        symbol: msg.symbol().map(symbol_name).unwrap_or_default(),
+        event_type: msg.event_type().to_string(),
+        payload,
+    }
+}
+
+fn kline(event_time: u64, k: &KlineData) -> proto::Kline {
+    proto::Kline {
+        event_time,
+        interval: k.interval.clone(),
+        open_time: k.open_time,
+        close_time: k.close_time,
+        first_trade_id: k.first_trade_id,
+        last_trade_id: k.last_trade_id,
+        open: k.open.to_string(),
+        high: k.high.to_string(),
+        low: k.low.to_string(),
+        close: k.close.to_string(),
+        volume: k.volume.to_string(),
+        quote_volume: k.quote_volume.to_string(),
+        taker_buy_volume: k.taker_buy_volume.to_string(),
+        taker_buy_quote_volume: k.taker_buy_quote_volume.to_string(),
+        trades: k.trades,
+        is_closed: k.is_closed,
+        contract_type: String::new(),
+    }
+}
+
+fn levels(levels: &[[Decimal; 2]]) -> Vec<proto::Level> {
+    levels
+        .iter()
+        .map(|l| proto::Level {
+            price: l[0].to_string(),
+            quantity: l[1].to_string(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::messages::Trade;
+    use crate::test_util::{self, ticker};
+    use crate::Symbol;
+    use proto::market_data_client::MarketDataClient;
+
+    #[tokio::test]
+    async fn zero_capacity() {
+        let addr = "127.0.0.1:0".parse().unwrap();
+        assert!(GrpcSink::serve(addr, 0).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn filtered_subscription() {
+        let trade = Message::Trade(Trade {
+            price: Decimal::new(650001, 1),
+            ..test_util::trade(Symbol::BTCUSDT, 1)
+        });
+        let ticker = Message::BookTicker(ticker(Symbol::BTCUSDT, "1", "2"));
+
+        let sink = GrpcSink::serve("127.0.0.1:0".parse().unwrap(), 16)
+            .await
+            .unwrap();
+        let mut client = MarketDataClient::connect(format!("http://{}", sink.local_addr()))
+            .await
+            .unwrap();
+        let mut stream = client
+            .subscribe(SubscribeRequest {
+                symbols: vec!["btcusdt".to_string()],
+                feeds: vec!["trade".to_string()],
+            })
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(sink.clients(), 1);
+
+        assert!(sink.send(5, &ticker));
+        assert!(sink.send(6, &trade));
+        let received = stream.message().await.unwrap().unwrap();
+        assert_eq!(received.recv_time, 6);
+        assert_eq!(received.symbol, "BTCUSDT");
+        let Some(Payload::Trade(received)) = received.payload else {
+            panic!("expected a trade, got {:?}", received.payload);
+        };
+        assert_eq!(received.price, "65000.1");
+    }
+}
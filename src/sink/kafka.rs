@@ -0,0 +1,230 @@
+//! Kafka producer, publishing messages as the serde JSON of [`Message`](crate::Message).
+//!
+//! Messages are published to a topic per feed or per symbol, see [`Topics`], with the
+//! upper case symbol as key so all messages of a symbol go to the same partition
+//! and stay in order.
+//!
+//! Messages are queued by the producer and delivered in the background, the deliveries are
+//! checked in batches and waited for by [`KafkaSink::flush()`].
+
+use std::time::Duration;
+
+use futures::FutureExt;
+use rdkafka::config::ClientConfig;
+use rdkafka::error::{KafkaError, RDKafkaErrorCode};
+use rdkafka::producer::future_producer::OwnedDeliveryResult;
+use rdkafka::producer::{DeliveryFuture, FutureProducer, FutureRecord, Producer};
+
+use super::{symbol_name, Sink};
+use crate::{Envelope, Message};
+
+/// How messages are divided into topics, the topic name starts with [`KafkaConfig::prefix`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Topics {
+    /// One topic per message type, e.g. `binance.aggTrade`.
+    #[default]
+    PerFeed,
+    /// One topic per symbol, e.g. `binance.BTCUSDT`,
+    /// messages without a symbol are not published.
+    PerSymbol,
+}
+
+/// Settings of a [`KafkaSink`].
+#[derive(Debug, Clone)]
+pub struct KafkaConfig {
+    /// Comma separated `host:port` list of brokers.
+    pub brokers: String,
+    pub prefix: String,
+    pub topics: Topics,
+    /// Extra librdkafka producer properties, e.g. `("compression.type", "lz4")`.
+    pub properties: Vec<(String, String)>,
+}
+
+impl KafkaConfig {
+    /// Publish to `brokers` with one topic per feed prefixed with `binance`.
+    pub fn new(brokers: &str) -> Self {
+        Self {
+            brokers: brokers.to_string(),
+            prefix: "binance".to_string(),
+            topics: Topics::default(),
+            properties: Vec::new(),
+        }
+    }
+
+    /// Topic `msg` is published to, `None` if it is not published.
+    pub fn topic(&self, msg: &Message) -> Option<String> {
+        let name = match (self.topics, msg) {
+            (_, Message::SubscribeSuccess { .. }) => return None,
+            (Topics::PerFeed, _) => msg.event_type().to_string(),
+            (Topics::PerSymbol, _) => symbol_name(msg.symbol()?),
+        };
+        Some(format!("{}.{name}", self.prefix))
+    }
+}
+
+/// Number of messages sent between checks of their deliveries.
+const DELIVERY_BATCH: usize = 1000;
+
+/// Longest wait for the queued messages, when flushing or when the queue is full.
+const FLUSH_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Publishes messages to Kafka, see the [module](self) documentation.
+pub struct KafkaSink {
+    config: KafkaConfig,
+    producer: FutureProducer,
+    /// deliveries of the messages not known to be delivered yet, oldest first
+    deliveries: Vec<DeliveryFuture>,
+}
+
+impl std::fmt::Debug for KafkaSink {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("KafkaSink")
+            .field("config", &self.config)
+            .field("deliveries", &self.deliveries.len())
+            .finish()
+    }
+}
+
+impl KafkaSink {
+    /// Create the producer, connections to the brokers are made in the background.
+    pub fn new(config: KafkaConfig) -> crate::Result<Self> {
+        let mut client = ClientConfig::new();
+        client.set("bootstrap.servers", &config.brokers);
+        for (key, value) in &config.properties {
+            client.set(key, value);
+        }
+        let producer = client.create()?;
+        Ok(Self {
+            config,
+            producer,
+            deliveries: Vec::new(),
+        })
+    }
+
+    /// Queue `msg` to be published, without waiting for the broker.
+    ///
+    /// Returns false if the message is not published, see [`KafkaConfig::topic()`]. A
+    /// delivery that failed since the last check is returned as an error. Waits for the
+    /// queued messages, at most 10 seconds, if the queue of the producer is full.
+    pub async fn send(&mut self, msg: &Message) -> crate::Result<bool> {
+        let Some(topic) = self.config.topic(msg) else {
+            return Ok(false);
+        };
+        let payload = serde_json::to_vec(msg)?;
+        let key = msg.symbol().map(symbol_name);
+
+        let mut record = FutureRecord::to(&topic).payload(&payload);
+        if let Some(key) = key.as_ref() {
+            record = record.key(key);
+        }
+        let delivery = match self.producer.send_result(record) {
+            Ok(delivery) => delivery,
+            Err((KafkaError::MessageProduction(RDKafkaErrorCode::QueueFull), record)) => {
+                self.flush(FLUSH_TIMEOUT)?;
+                self.producer.send_result(record).map_err(|(e, _)| e)?
+            }
+            Err((e, _)) => return Err(e.into()),
+        };
+        self.deliveries.push(delivery);
+        if self.deliveries.len().is_multiple_of(DELIVERY_BATCH) {
+            self.delivered()?;
+        }
+        Ok(true)
+    }
+
+    /// Wait for every queued message to be delivered, at most `timeout`.
+    ///
+    /// Returns the first delivery that failed since the last check.
+    pub fn flush(&mut self, timeout: Duration) -> crate::Result<()> {
+        self.producer.flush(timeout)?;
+        self.delivered()
+    }
+
+    /// Forget the deliveries that completed, the first one that failed is returned.
+    fn delivered(&mut self) -> crate::Result<()> {
+        let mut failed = None;
+        self.deliveries.retain_mut(|delivery| {
+            let Some(result) = delivery.now_or_never() else {
+                return true;
+            };
+            if let Err(e) = delivery_result(result) {
+                failed.get_or_insert(e);
+            }
+            false
+        });
+        failed.map_or(Ok(()), Err)
+    }
+}
+
+fn delivery_result(
+    result: Result<OwnedDeliveryResult, futures::channel::oneshot::Canceled>,
+) -> crate::Result<()> {
+    match result {
+        Ok(Ok(_)) => Ok(()),
+        Ok(Err((e, _))) => Err(e.into()),
+        // the producer was dropped before the delivery
+        Err(_) => Err(KafkaError::Canceled.into()),
+    }
+}
+
+impl Sink for KafkaSink {
+    async fn write(&mut self, envelope: &Envelope) -> crate::Result<()> {
+        self.send(&envelope.message).await.map(drop)
+    }
+
+    /// Waits at most 10 seconds for the queued messages.
+    async fn flush(&mut self) -> crate::Result<()> {
+        KafkaSink::flush(self, FLUSH_TIMEOUT)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::messages::PartialDepth;
+    use crate::test_util::ticker;
+    use crate::Symbol;
+    use smallvec::smallvec;
+
+    #[test]
+    fn topic_names() {
+        let ticker = Message::BookTicker(ticker(Symbol::ETHBTC, "1", "2"));
+        let depth = Message::PartialDepth(Box::new(PartialDepth {
+            last_update_id: 1,
+            bids: smallvec![],
+            asks: smallvec![],
+        }));
+
+        let mut config = KafkaConfig::new("localhost:9092");
+        assert_eq!(config.topic(&ticker).unwrap(), "binance.bookTicker");
+        assert_eq!(config.topic(&depth).unwrap(), "binance.partialDepth");
+
+        config.topics = Topics::PerSymbol;
+        config.prefix = "spot".to_string();
+        assert_eq!(config.topic(&ticker).unwrap(), "spot.ETHBTC");
+        assert_eq!(config.topic(&depth), None);
+        assert_eq!(
+            config.topic(&Message::SubscribeSuccess {
+                result: None,
+                id: 1
+            }),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn failed_deliveries() {
+        let mut config = KafkaConfig::new("127.0.0.1:1");
+        config.properties = vec![("message.timeout.ms".into(), "100".into())];
+        let mut sink = KafkaSink::new(config).unwrap();
+        let ticker = Message::BookTicker(ticker(Symbol::ETHBTC, "1", "2"));
+
+        // queued without waiting for the broker
+        assert!(sink.send(&ticker).await.unwrap());
+        assert!(matches!(
+            sink.flush(Duration::from_secs(5)),
+            Err(crate::Error::Kafka(_))
+        ));
+        assert!(sink.deliveries.is_empty());
+    }
+}
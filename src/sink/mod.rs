@@ -0,0 +1,399 @@
+//! Sinks writing received messages to files and other systems.
+//!
+//! Files and databases:
+//! - `csv` typed CSV files, one per message type, requires one of the `trade`, `book-ticker`
+//!   and `kline` features.
+//! - `parquet` compressed Parquet files partitioned by symbol and date,
+//!   requires the `parquet` feature.
+//! - `sqlite` a local SQLite database, requires the `sqlite` feature.
+//! - `postgres` a PostgreSQL database written from a background task,
+//!   requires the `postgres` feature.
+//!
+//! Forwarding to other processes:
+//! - `kafka` a Kafka producer, requires the `kafka` feature.
+//! - `redis` a Redis pub/sub or streams publisher, requires the `redis` feature.
+//! - `nats` a NATS publisher with optional JetStream persistence, requires the `nats` feature.
+//! - `mqtt` a MQTT publisher with a topic template, requires the `mqtt` feature.
+//! - `zmq` a ZeroMQ PUB socket, requires the `zmq` feature.
+//! - `grpc` a gRPC server streaming to clients subscribed to symbols and feeds,
+//!   requires the `grpc` feature.
+//! - `uds` a Unix domain socket server sending length prefixed frames, on Unix only.
+//!
+//! Every sink implements [`Sink`], the recorder too. A [`SinkSet`] writes every message to
+//! several sinks at once, each from its own task and queue, so a slow database does not hold
+//! back the files:
+//! ```no_run
+//! use binance_api_async::recorder::{Recorder, Rotation};
+//! use binance_api_async::sink::csv::CsvSink;
+//! use binance_api_async::sink::SinkSet;
+//! use binance_api_async::BinanceApi;
+//!
+//! # async fn run() -> Result<(), binance_api_async::Error> {
+//! let mut sinks = SinkSet::new();
+//! sinks.register("csv", CsvSink::new("./csv")?, 1024);
+//! sinks.register("recorder", Recorder::new("./data", "binance", Rotation::default())?, 1024);
+//! let mut api = BinanceApi::new();
+//! api.connect().await?;
+//! while let Some(envelope) = api.next_envelope().await {
+//!     sinks.write(envelope).await?;
+//! }
+//! sinks.close().await?;
+//! # Ok(())
+//! # }
+//! ```
+
+#[cfg(any(feature = "trade", feature = "book-ticker", feature = "kline"))]
+pub mod csv;
+#[cfg(feature = "grpc")]
+pub mod grpc;
+#[cfg(feature = "kafka")]
+pub mod kafka;
+#[cfg(feature = "mqtt")]
+pub mod mqtt;
+#[cfg(feature = "nats")]
+pub mod nats;
+#[cfg(feature = "parquet")]
+pub mod parquet;
+#[cfg(feature = "postgres")]
+pub mod postgres;
+#[cfg(feature = "redis")]
+pub mod redis;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+#[cfg(all(unix, feature = "tokio"))]
+pub mod uds;
+#[cfg(feature = "zmq")]
+pub mod zmq;
+
+use std::future::Future;
+#[cfg(feature = "tokio")]
+use std::sync::Arc;
+
+#[cfg(feature = "tokio")]
+use tokio::sync::mpsc;
+#[cfg(feature = "tokio")]
+use tokio::task::JoinHandle;
+
+use crate::Envelope;
+
+/// Symbols as Binance sends them, in upper case.
+// used by the rest client, the sinks and the columnar formats, which need one of these
+#[cfg(any(
+    feature = "tokio",
+    feature = "trade",
+    feature = "book-ticker",
+    feature = "kline",
+    feature = "kafka",
+    feature = "nats",
+    feature = "redis"
+))]
+pub(crate) fn symbol_name(symbol: &crate::Symbol) -> String {
+    symbol.to_string().to_uppercase()
+}
+
+/// Topic of `msg` on a broker, `<prefix><separator><SYMBOL><separator><event type>`, e.g.
+/// `binance.BTCUSDT.aggTrade`, or `<prefix><separator><event type>` without a symbol.
+///
+/// `None` for acknowledgements, they are not published.
+#[cfg(any(feature = "nats", feature = "redis", feature = "zmq"))]
+pub(crate) fn topic(prefix: &str, separator: &str, msg: &crate::Message) -> Option<String> {
+    let event = msg.event_type();
+    match msg {
+        crate::Message::SubscribeSuccess { .. } => None,
+        _ => Some(match msg.symbol() {
+            Some(symbol) => format!("{prefix}{separator}{}{separator}{event}", symbol_name(symbol)),
+            None => format!("{prefix}{separator}{event}"),
+        }),
+    }
+}
+
+/// A destination of messages, a file, a database or a broker, see [`SinkSet`].
+pub trait Sink: Send + Sized {
+    /// Write the message of `envelope`, received at its `recv_time`. Messages of a type the
+    /// sink does not store are skipped.
+    fn write(&mut self, envelope: &Envelope) -> impl Future<Output = crate::Result<()>> + Send;
+
+    /// Write what the sink has buffered.
+    fn flush(&mut self) -> impl Future<Output = crate::Result<()>> + Send;
+
+    /// Write what is buffered and release the sink, [`Sink::flush()`] by default.
+    fn close(mut self) -> impl Future<Output = crate::Result<()>> + Send {
+        async move { self.flush().await }
+    }
+}
+
+/// What a sink task is asked to do.
+#[cfg(feature = "tokio")]
+#[derive(Debug)]
+enum Item {
+    Write(Arc<Envelope>),
+    Flush,
+}
+
+/// A sink of a [`SinkSet`], written from its own task.
+#[cfg(feature = "tokio")]
+#[derive(Debug)]
+struct Registered {
+    name: String,
+    queue: mpsc::Sender<Item>,
+    task: JoinHandle<crate::Result<()>>,
+}
+
+#[cfg(feature = "tokio")]
+impl Registered {
+    /// The error the task of the sink ended with.
+    async fn stopped(self) -> crate::Error {
+        let name = self.name;
+        match self.task.await {
+            Ok(Err(e)) => e,
+            Ok(Ok(())) => crate::Error::Custom(format!("sink {name} has stopped")),
+            Err(e) => crate::Error::Custom(format!("sink {name} failed: {e}")),
+        }
+    }
+}
+
+/// Sinks receiving every message, see the [module](self) documentation.
+///
+/// Each sink is written from its own task, through a queue of the capacity it was registered
+/// with. [`SinkSet::write()`] waits only while the queue of a sink is full, the other sinks
+/// go on writing the messages already queued for them meanwhile.
+#[cfg(feature = "tokio")]
+#[derive(Debug, Default)]
+pub struct SinkSet {
+    sinks: Vec<Registered>,
+}
+
+#[cfg(feature = "tokio")]
+impl SinkSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Write every message to `sink` from a new task, with up to `capacity` messages queued
+    /// for it. `name` tells the sink apart in errors and in [`SinkSet::lag()`].
+    pub fn register(
+        &mut self,
+        name: impl Into<String>,
+        sink: impl Sink + 'static,
+        capacity: usize,
+    ) {
+        let (queue, receiver) = mpsc::channel(capacity.max(1));
+        self.sinks.push(Registered {
+            name: name.into(),
+            queue,
+            task: tokio::spawn(drain(sink, receiver)),
+        });
+    }
+
+    /// Names of the sinks, in the order they were registered.
+    pub fn names(&self) -> Vec<&str> {
+        self.sinks.iter().map(|sink| sink.name.as_str()).collect()
+    }
+
+    /// Number of messages queued for each sink.
+    pub fn lag(&self) -> Vec<(&str, usize)> {
+        self.sinks
+            .iter()
+            .map(|sink| (sink.name.as_str(), sink.queue.max_capacity() - sink.queue.capacity()))
+            .collect()
+    }
+
+    /// Queue `envelope` for every sink, waits while a queue is full.
+    ///
+    /// Sinks that have stopped are removed from the set, the other sinks still receive
+    /// `envelope`. Returns the error of the first sink that has stopped.
+    pub async fn write(&mut self, envelope: Envelope) -> crate::Result<()> {
+        self.send(Item::Write(Arc::new(envelope))).await
+    }
+
+    /// Ask every sink to write what it has buffered, after the messages queued before.
+    pub async fn flush(&mut self) -> crate::Result<()> {
+        self.send(Item::Flush).await
+    }
+
+    async fn send(&mut self, item: Item) -> crate::Result<()> {
+        let mut stopped = Vec::new();
+        for (i, sink) in self.sinks.iter().enumerate() {
+            let item = match &item {
+                Item::Write(envelope) => Item::Write(envelope.clone()),
+                Item::Flush => Item::Flush,
+            };
+            if sink.queue.send(item).await.is_err() {
+                stopped.push(i);
+            }
+        }
+        let mut first = None;
+        for i in stopped.into_iter().rev() {
+            // removed last to first, the error of the first stopped sink is kept
+            first = Some(self.sinks.remove(i).stopped().await);
+        }
+        match first {
+            Some(e) => Err(e),
+            None => Ok(()),
+        }
+    }
+
+    /// Write the queued messages and close every sink, returns the first error of a sink.
+    pub async fn close(self) -> crate::Result<()> {
+        let mut closed = Ok(());
+        for Registered { name, queue, task } in self.sinks {
+            drop(queue);
+            let result = task
+                .await
+                .unwrap_or_else(|e| Err(crate::Error::Custom(format!("sink {name} failed: {e}"))));
+            closed = closed.and(result);
+        }
+        closed
+    }
+}
+
+/// Write the items of `queue` to `sink` until the queue is closed, then close the sink.
+#[cfg(feature = "tokio")]
+async fn drain(mut sink: impl Sink, mut queue: mpsc::Receiver<Item>) -> crate::Result<()> {
+    while let Some(item) = queue.recv().await {
+        match item {
+            Item::Write(envelope) => sink.write(&envelope).await?,
+            Item::Flush => sink.flush().await?,
+        }
+    }
+    sink.close().await
+}
+
+#[cfg(all(test, feature = "tokio", feature = "trade"))]
+mod test {
+    use super::*;
+    use crate::messages::Trade;
+    use crate::{Message, Symbol};
+    use rust_decimal::Decimal;
+    use std::sync::Mutex;
+    use std::time::{Duration, Instant};
+
+    /// Collects the trade ids written, fails on `fail_at`.
+    struct Collect {
+        written: Arc<Mutex<Vec<u64>>>,
+        flushed: Arc<Mutex<usize>>,
+        delay: Duration,
+        fail_at: Option<u64>,
+    }
+
+    impl Sink for Collect {
+        async fn write(&mut self, envelope: &Envelope) -> crate::Result<()> {
+            let Message::Trade(t) = &envelope.message else {
+                return Ok(());
+            };
+            if self.fail_at == Some(t.trade_id) {
+                return Err(crate::Error::Custom("full".into()));
+            }
+            tokio::time::sleep(self.delay).await;
+            self.written.lock().unwrap().push(t.trade_id);
+            Ok(())
+        }
+
+        async fn flush(&mut self) -> crate::Result<()> {
+            *self.flushed.lock().unwrap() += 1;
+            Ok(())
+        }
+    }
+
+    fn sink(delay: Duration, fail_at: Option<u64>) -> (Collect, Arc<Mutex<Vec<u64>>>) {
+        let written = Arc::default();
+        let sink = Collect {
+            written: Arc::clone(&written),
+            flushed: Arc::default(),
+            delay,
+            fail_at,
+        };
+        (sink, written)
+    }
+
+    fn envelope(trade_id: u64) -> Envelope {
+        let trade = Trade {
+            event_time: 1,
+            symbol: Symbol::BTCUSDT,
+            trade_id,
+            price: Decimal::ONE,
+            quantity: Decimal::ONE,
+            trade_time: 1,
+            is_market_maker: false,
+        };
+        Envelope {
+            message: Message::Trade(trade),
+            recv_time: 1,
+            received: Instant::now(),
+            connection: 0,
+            raw: None,
+        }
+    }
+
+    #[cfg(any(feature = "nats", feature = "redis", feature = "zmq"))]
+    #[test]
+    fn topics() {
+        let trade = envelope(1).message;
+        assert_eq!(topic("binance", ".", &trade).unwrap(), "binance.BTCUSDT.trade");
+        assert_eq!(topic("binance", ":", &trade).unwrap(), "binance:BTCUSDT:trade");
+
+        let error = Message::Error(crate::messages::ErrorResponse {
+            code: 2,
+            msg: "Invalid request".to_string(),
+            id: Some(1),
+        });
+        assert_eq!(topic("binance", ".", &error).unwrap(), "binance.error");
+        let ack = Message::SubscribeSuccess { result: None, id: 1 };
+        assert_eq!(topic("binance", ".", &ack), None);
+    }
+
+    #[tokio::test]
+    async fn sinks_write_independently() {
+        let (fast, fast_written) = sink(Duration::ZERO, None);
+        let (slow, slow_written) = sink(Duration::from_millis(20), None);
+        let flushed = Arc::clone(&slow.flushed);
+        let mut sinks = SinkSet::new();
+        sinks.register("fast", fast, 1);
+        sinks.register("slow", slow, 100);
+        let started = Instant::now();
+        for id in 0..10 {
+            sinks.write(envelope(id)).await.unwrap();
+        }
+        // queued for the slow sink, written to the fast one without waiting for it
+        assert!(started.elapsed() < Duration::from_millis(100));
+        assert!(sinks.lag()[1].1 > 0);
+        sinks.close().await.unwrap();
+        assert_eq!(*slow_written.lock().unwrap(), (0..10).collect::<Vec<_>>());
+        assert_eq!(*fast_written.lock().unwrap(), (0..10).collect::<Vec<_>>());
+        // closed with the default, a flush
+        assert_eq!(*flushed.lock().unwrap(), 1);
+
+        let (failing, _) = sink(Duration::ZERO, Some(1));
+        let mut sinks = SinkSet::new();
+        sinks.register("failing", failing, 1);
+        let mut result = Ok(());
+        for id in 0..5 {
+            result = result.and(sinks.write(envelope(id)).await);
+        }
+        assert!(matches!(result, Err(crate::Error::Custom(msg)) if msg == "full"));
+        assert!(sinks.names().is_empty());
+    }
+
+    #[tokio::test]
+    async fn stopped_sink_does_not_hold_back_the_others() {
+        let (first, first_written) = sink(Duration::ZERO, None);
+        let (failing, _) = sink(Duration::ZERO, Some(0));
+        let (last, last_written) = sink(Duration::ZERO, None);
+        let mut sinks = SinkSet::new();
+        sinks.register("first", first, 10);
+        sinks.register("failing", failing, 10);
+        sinks.register("last", last, 10);
+        sinks.write(envelope(0)).await.unwrap();
+        // the failing sink has stopped once its queue is closed
+        while !sinks.sinks[1].queue.is_closed() {
+            tokio::task::yield_now().await;
+        }
+
+        let result = sinks.write(envelope(1)).await;
+        assert!(matches!(result, Err(crate::Error::Custom(msg)) if msg == "full"));
+        assert_eq!(sinks.names(), ["first", "last"]);
+        sinks.close().await.unwrap();
+        assert_eq!(*first_written.lock().unwrap(), [0, 1]);
+        assert_eq!(*last_written.lock().unwrap(), [0, 1]);
+    }
+}
@@ -0,0 +1,158 @@
+//! MQTT publisher, re-publishing messages to a broker as JSON payloads of
+//! [`Message`](crate::Message).
+//!
+//! Topics are made from [`MqttConfig::topic`], a template where `{symbol}` is replaced by the
+//! upper case symbol and `{event}` by the event type, e.g. `binance/BTCUSDT/bookTicker`.
+//! Messages without a symbol are not published when the template contains `{symbol}`.
+//!
+//! The connection to the broker is driven by a background task, which reconnects when the
+//! connection is lost.
+
+use std::time::Duration;
+
+pub use rumqttc::QoS;
+use rumqttc::{AsyncClient, MqttOptions};
+use tokio::task::JoinHandle;
+use tracing::warn;
+
+use super::{symbol_name, Sink};
+use crate::{Envelope, Message};
+
+/// Settings of a [`MqttSink`].
+#[derive(Debug, Clone)]
+pub struct MqttConfig {
+    pub host: String,
+    pub port: u16,
+    pub client_id: String,
+    /// Topic template, see the [module](self) documentation.
+    pub topic: String,
+    pub qos: QoS,
+    /// Let the broker keep the last message of each topic for new subscribers.
+    pub retain: bool,
+}
+
+impl MqttConfig {
+    /// Publish to `binance/{symbol}/{event}` at most once on the broker at `host:port`.
+    pub fn new(host: &str, port: u16) -> Self {
+        Self {
+            host: host.to_string(),
+            port,
+            client_id: "binance_api_async".to_string(),
+            topic: "binance/{symbol}/{event}".to_string(),
+            qos: QoS::AtMostOnce,
+            retain: false,
+        }
+    }
+
+    /// Topic `msg` is published to, `None` if it is not published.
+    pub fn topic(&self, msg: &Message) -> Option<String> {
+        if let Message::SubscribeSuccess { .. } = msg {
+            return None;
+        }
+        let mut topic = self.topic.replace("{event}", msg.event_type());
+        if topic.contains("{symbol}") {
+            topic = topic.replace("{symbol}", &symbol_name(msg.symbol()?));
+        }
+        Some(topic)
+    }
+}
+
+/// Publishes messages to a MQTT broker, see the [module](self) documentation.
+#[derive(Debug)]
+pub struct MqttSink {
+    config: MqttConfig,
+    client: AsyncClient,
+    event_loop: JoinHandle<()>,
+}
+
+impl MqttSink {
+    /// Start the connection to the broker, messages published before it is connected are
+    /// queued, up to `capacity`.
+    pub fn connect(config: MqttConfig, capacity: usize) -> Self {
+        let mut options = MqttOptions::new(&config.client_id, &config.host, config.port);
+        options.set_keep_alive(Duration::from_secs(30));
+        let (client, mut event_loop) = AsyncClient::new(options, capacity);
+
+        let event_loop = tokio::spawn(async move {
+            loop {
+                if let Err(e) = event_loop.poll().await {
+                    warn!("MQTT connection error, reconnecting: {e}");
+                    tokio::time::sleep(Duration::from_secs(1)).await;
+                }
+            }
+        });
+
+        Self {
+            config,
+            client,
+            event_loop,
+        }
+    }
+
+    /// Publish `msg`, returns false if it is not published, see [`MqttConfig::topic()`].
+    ///
+    /// Waits if the queue to the broker is full.
+    pub async fn publish(&self, msg: &Message) -> crate::Result<bool> {
+        let Some(topic) = self.config.topic(msg) else {
+            return Ok(false);
+        };
+        let payload = serde_json::to_vec(msg)?;
+        self.client
+            .publish(topic, self.config.qos, self.config.retain, payload)
+            .await?;
+        Ok(true)
+    }
+
+    /// Disconnect from the broker.
+    pub async fn disconnect(self) -> crate::Result<()> {
+        self.client.disconnect().await?;
+        Ok(())
+    }
+}
+
+impl Sink for MqttSink {
+    async fn write(&mut self, envelope: &Envelope) -> crate::Result<()> {
+        self.publish(&envelope.message).await.map(drop)
+    }
+
+    /// Messages are sent by the event loop task as soon as they are queued.
+    async fn flush(&mut self) -> crate::Result<()> {
+        Ok(())
+    }
+
+    async fn close(self) -> crate::Result<()> {
+        self.disconnect().await
+    }
+}
+
+impl Drop for MqttSink {
+    fn drop(&mut self) {
+        self.event_loop.abort();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::messages::PartialDepth;
+    use crate::test_util::ticker;
+    use crate::Symbol;
+    use smallvec::smallvec;
+
+    #[test]
+    fn topic_template() {
+        let ticker = Message::BookTicker(ticker(Symbol::BTCUSDT, "1", "2"));
+        let depth = Message::PartialDepth(Box::new(PartialDepth {
+            last_update_id: 1,
+            bids: smallvec![],
+            asks: smallvec![],
+        }));
+
+        let mut config = MqttConfig::new("localhost", 1883);
+        assert_eq!(config.topic(&ticker).unwrap(), "binance/BTCUSDT/bookTicker");
+        assert_eq!(config.topic(&depth), None);
+
+        config.topic = "market/{event}".to_string();
+        assert_eq!(config.topic(&depth).unwrap(), "market/partialDepth");
+    }
+}
@@ -0,0 +1,123 @@
+//! NATS publisher, the payloads are the JSON [`Message`](crate::Message) serializes to.
+//!
+//! Messages are published to subjects `<prefix>.<SYMBOL>.<event type>`,
+//! e.g. `binance.spot.BTCUSDT.aggTrade`, so consumers can subscribe with wildcards like
+//! `binance.spot.*.bookTicker` or `binance.spot.BTCUSDT.>`.
+//!
+//! With [`NatsConfig::jetstream`] set the messages are also persisted in a JetStream stream
+//! capturing every subject under the prefix, and each publish waits for the acknowledgement.
+
+use async_nats::jetstream::{self, stream};
+
+use super::{topic, Sink};
+use crate::{Envelope, Message};
+
+/// Settings of a [`NatsSink`].
+#[derive(Debug, Clone)]
+pub struct NatsConfig {
+    /// e.g. `nats://127.0.0.1:4222`
+    pub url: String,
+    pub prefix: String,
+    /// Name of the JetStream stream to persist the messages in, created if it does not exist.
+    pub jetstream: Option<String>,
+}
+
+impl NatsConfig {
+    /// Publish to subjects prefixed with `binance.spot` on the server at `url`.
+    pub fn new(url: &str) -> Self {
+        Self {
+            url: url.to_string(),
+            prefix: "binance.spot".to_string(),
+            jetstream: None,
+        }
+    }
+
+    /// Subject `msg` is published to, `None` if it is not published.
+    ///
+    /// Messages without a symbol are published to `<prefix>.<event type>`.
+    pub fn subject(&self, msg: &Message) -> Option<String> {
+        topic(&self.prefix, ".", msg)
+    }
+}
+
+/// Publishes messages to NATS, see the [module](self) documentation.
+#[derive(Debug, Clone)]
+pub struct NatsSink {
+    config: NatsConfig,
+    client: async_nats::Client,
+    jetstream: Option<jetstream::Context>,
+}
+
+impl NatsSink {
+    pub async fn connect(config: NatsConfig) -> crate::Result<Self> {
+        let client = async_nats::connect(config.url.as_str())
+            .await
+            .map_err(nats_error)?;
+
+        let jetstream = match &config.jetstream {
+            Some(name) => {
+                let context = jetstream::new(client.clone());
+                context
+                    .get_or_create_stream(stream::Config {
+                        name: name.clone(),
+                        subjects: vec![format!("{}.>", config.prefix)],
+                        ..Default::default()
+                    })
+                    .await
+                    .map_err(nats_error)?;
+                Some(context)
+            }
+            None => None,
+        };
+
+        Ok(Self {
+            config,
+            client,
+            jetstream,
+        })
+    }
+
+    /// Publish `msg`, returns false if it is not published, see [`NatsConfig::subject()`].
+    pub async fn publish(&self, msg: &Message) -> crate::Result<bool> {
+        let Some(subject) = self.config.subject(msg) else {
+            return Ok(false);
+        };
+        let payload = serde_json::to_vec(msg)?;
+
+        match &self.jetstream {
+            Some(context) => {
+                context
+                    .publish(subject, payload.into())
+                    .await
+                    .map_err(nats_error)?
+                    .await
+                    .map_err(nats_error)?;
+            }
+            None => self
+                .client
+                .publish(subject, payload.into())
+                .await
+                .map_err(nats_error)?,
+        }
+        Ok(true)
+    }
+
+    /// Send every buffered message to the server.
+    pub async fn flush(&self) -> crate::Result<()> {
+        self.client.flush().await.map_err(nats_error)
+    }
+}
+
+impl Sink for NatsSink {
+    async fn write(&mut self, envelope: &Envelope) -> crate::Result<()> {
+        self.publish(&envelope.message).await.map(drop)
+    }
+
+    async fn flush(&mut self) -> crate::Result<()> {
+        NatsSink::flush(self).await
+    }
+}
+
+fn nats_error(e: impl std::error::Error + Send + Sync + 'static) -> crate::Error {
+    crate::Error::Nats(Box::new(e))
+}
@@ -0,0 +1,367 @@
+//! Zstd compressed Parquet files, partitioned by message type, symbol and date.
+//!
+//! ```text
+//! <dir>/aggtrade/symbol=BTCUSDT/date=2024-06-01/part-1717200000123.parquet
+//! ```
+//!
+//! Messages are buffered and written as one Arrow record batch per `batch_size` rows of a
+//! partition. A file is finished when its symbol receives a message on a later (UTC) date,
+//! or when the sink is closed, a file is not readable before it is finished.
+//!
+//! Every table has a `recv_time` column, the local receive time in milliseconds since epoch.
+//! The other columns are those of [`ToArrow`], without the symbol.
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::path::PathBuf;
+
+use ::parquet::arrow::ArrowWriter;
+use ::parquet::basic::{Compression, ZstdLevel};
+use ::parquet::file::properties::WriterProperties;
+use chrono::NaiveDate;
+use tracing::info;
+
+use super::{symbol_name, Sink};
+use crate::arrow::{u64s, RecordBatch, ToArrow};
+use crate::recorder::now_millis;
+use crate::{Envelope, Message, Symbol};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Table {
+    AggTrade,
+    Trade,
+    BookTicker,
+    Depth,
+    Kline,
+}
+
+impl Table {
+    fn of(msg: &Message) -> Option<(Table, &Symbol)> {
+        match msg {
+            Message::AggTrade(t) => Some((Table::AggTrade, &t.symbol)),
+            Message::Trade(t) => Some((Table::Trade, &t.symbol)),
+            Message::BookTicker(bt) => Some((Table::BookTicker, &bt.symbol)),
+            Message::DepthUpdate(du) => Some((Table::Depth, &du.symbol)),
+            Message::Kline(k) => Some((Table::Kline, &k.symbol)),
+            _ => None,
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Table::AggTrade => "aggtrade",
+            Table::Trade => "trade",
+            Table::BookTicker => "bookticker",
+            Table::Depth => "depth",
+            Table::Kline => "kline",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct Partition {
+    table: Table,
+    symbol: Symbol,
+    date: NaiveDate,
+}
+
+struct PartitionWriter {
+    rows: Vec<(u64, Message)>,
+    writer: ArrowWriter<File>,
+}
+
+/// Writes messages to Parquet files, see the [module](self) documentation.
+pub struct ParquetSink {
+    dir: PathBuf,
+    batch_size: usize,
+    partitions: HashMap<Partition, PartitionWriter>,
+}
+
+impl std::fmt::Debug for ParquetSink {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ParquetSink")
+            .field("dir", &self.dir)
+            .field("batch_size", &self.batch_size)
+            .field("partitions", &self.partitions.keys())
+            .finish()
+    }
+}
+
+impl ParquetSink {
+    /// Write to `dir`, buffering up to `batch_size` messages per partition.
+    pub fn new(dir: impl Into<PathBuf>, batch_size: usize) -> crate::Result<Self> {
+        let dir = dir.into();
+        std::fs::create_dir_all(&dir)?;
+        Ok(Self {
+            dir,
+            batch_size: batch_size.max(1),
+            partitions: HashMap::new(),
+        })
+    }
+
+    /// Write `msg`, received now.
+    pub fn record(&mut self, msg: &Message) -> crate::Result<bool> {
+        self.write(now_millis(), msg)
+    }
+
+    /// Write `msg` received at `recv_time`, returns false if the message type is not stored.
+    pub fn write(&mut self, recv_time: u64, msg: &Message) -> crate::Result<bool> {
+        let Some((table, symbol)) = Table::of(msg) else {
+            return Ok(false);
+        };
+        let date = chrono::DateTime::from_timestamp_millis(recv_time as i64)
+            .unwrap_or_default()
+            .date_naive();
+        let partition = Partition {
+            table,
+            symbol: symbol.clone(),
+            date,
+        };
+
+        if !self.partitions.contains_key(&partition) {
+            // a new date finishes the files of the previous dates
+            let previous: Vec<Partition> = self
+                .partitions
+                .keys()
+                .filter(|p| p.table == table && &p.symbol == symbol && p.date < date)
+                .cloned()
+                .collect();
+            for p in previous {
+                if let Some(writer) = self.partitions.remove(&p) {
+                    finish(table, writer)?;
+                }
+            }
+
+            let writer = self.create(&partition, recv_time)?;
+            self.partitions.insert(partition.clone(), writer);
+        }
+
+        let writer = self.partitions.get_mut(&partition).expect("inserted above");
+        writer.rows.push((recv_time, msg.clone()));
+        if writer.rows.len() >= self.batch_size {
+            write_batch(table, writer)?;
+        }
+        Ok(true)
+    }
+
+    /// Write the buffered messages of every partition.
+    pub fn flush(&mut self) -> crate::Result<()> {
+        for (partition, writer) in self.partitions.iter_mut() {
+            write_batch(partition.table, writer)?;
+            writer.writer.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Write the buffered messages and finish every file.
+    pub fn close(&mut self) -> crate::Result<()> {
+        for (partition, writer) in std::mem::take(&mut self.partitions) {
+            finish(partition.table, writer)?;
+        }
+        Ok(())
+    }
+
+    fn create(&self, partition: &Partition, recv_time: u64) -> crate::Result<PartitionWriter> {
+        let dir = self
+            .dir
+            .join(partition.table.name())
+            .join(format!("symbol={}", symbol_name(&partition.symbol)))
+            .join(format!("date={}", partition.date.format("%Y-%m-%d")));
+        std::fs::create_dir_all(&dir)?;
+
+        let path = dir.join(format!("part-{recv_time}.parquet"));
+        info!("Writing parquet to {}", path.display());
+
+        let props = WriterProperties::builder()
+            .set_compression(Compression::ZSTD(ZstdLevel::default()))
+            .build();
+        let schema = record_batch(partition.table, &[])?.schema();
+        Ok(PartitionWriter {
+            rows: Vec::new(),
+            writer: ArrowWriter::try_new(File::create(path)?, schema, Some(props))?,
+        })
+    }
+}
+
+impl Sink for ParquetSink {
+    async fn write(&mut self, envelope: &Envelope) -> crate::Result<()> {
+        ParquetSink::write(self, envelope.recv_time, &envelope.message).map(drop)
+    }
+
+    async fn flush(&mut self) -> crate::Result<()> {
+        ParquetSink::flush(self)
+    }
+
+    async fn close(mut self) -> crate::Result<()> {
+        ParquetSink::close(&mut self)
+    }
+}
+
+impl Drop for ParquetSink {
+    fn drop(&mut self) {
+        let _ = self.close();
+    }
+}
+
+fn write_batch(table: Table, writer: &mut PartitionWriter) -> crate::Result<()> {
+    if writer.rows.is_empty() {
+        return Ok(());
+    }
+    let batch = record_batch(table, &writer.rows)?;
+    writer.writer.write(&batch)?;
+    writer.rows.clear();
+    Ok(())
+}
+
+fn finish(table: Table, mut writer: PartitionWriter) -> crate::Result<()> {
+    write_batch(table, &mut writer)?;
+    writer.writer.close()?;
+    Ok(())
+}
+
+fn record_batch(table: Table, rows: &[(u64, Message)]) -> crate::Result<RecordBatch> {
+    match table {
+        Table::AggTrade => with_recv_time(
+            rows,
+            |m| match m {
+                Message::AggTrade(t) => Some(t),
+                _ => None,
+            },
+            |_| 1,
+        ),
+        Table::Trade => with_recv_time(
+            rows,
+            |m| match m {
+                Message::Trade(t) => Some(t),
+                _ => None,
+            },
+            |_| 1,
+        ),
+        Table::BookTicker => with_recv_time(
+            rows,
+            |m| match m {
+                Message::BookTicker(bt) => Some(bt),
+                _ => None,
+            },
+            |_| 1,
+        ),
+        Table::Depth => with_recv_time(
+            rows,
+            |m| match m {
+                Message::DepthUpdate(du) => Some(du),
+                _ => None,
+            },
+            |du| du.bids.len() + du.asks.len(),
+        ),
+        Table::Kline => with_recv_time(
+            rows,
+            |m| match m {
+                Message::Kline(k) => Some(k),
+                _ => None,
+            },
+            |_| 1,
+        ),
+    }
+}
+
+/// Convert the messages picked from `rows` with [`ToArrow`], with a `recv_time` column first.
+///
+/// The symbol column is left out, it is part of the partition path.
+fn with_recv_time<T: ToArrow>(
+    rows: &[(u64, Message)],
+    pick: fn(&Message) -> Option<&T>,
+    rows_per_message: fn(&T) -> usize,
+) -> crate::Result<RecordBatch> {
+    let picked: Vec<(u64, &T)> = rows
+        .iter()
+        .filter_map(|(r, m)| Some((*r, pick(m)?)))
+        .collect();
+    let recv_times = picked
+        .iter()
+        .flat_map(|(r, t)| std::iter::repeat_n(*r, rows_per_message(t)));
+    let batch = T::to_arrow(picked.iter().map(|(_, t)| *t))?;
+
+    let mut columns = vec![("recv_time", u64s(recv_times))];
+    let schema = batch.schema();
+    for (field, column) in schema.fields().iter().zip(batch.columns()) {
+        if field.name() != "symbol" {
+            columns.push((field.name().as_str(), column.clone()));
+        }
+    }
+    Ok(RecordBatch::try_from_iter(columns)?)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::messages::{DepthUpdate, Trade};
+    use crate::test_util::{self, dec};
+    use ::arrow::array::Decimal128Array;
+    use ::parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+
+    fn trade(trade_id: u64) -> Message {
+        Message::Trade(Trade {
+            price: dec("65000.01"),
+            quantity: dec("0.00012"),
+            ..test_util::trade(Symbol::BTCUSDT, trade_id)
+        })
+    }
+
+    fn read_rows(path: &std::path::Path) -> Vec<RecordBatch> {
+        let file = File::open(path).unwrap();
+        ParquetRecordBatchReaderBuilder::try_new(file)
+            .unwrap()
+            .build()
+            .unwrap()
+            .map(|b| b.unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn partitioned_by_date() {
+        let dir = std::env::temp_dir().join(format!("parquet_sink_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+
+        // 2024-06-01T00:00:00Z
+        let midnight = 1_717_200_000_000;
+        let day = 86_400_000;
+
+        let mut sink = ParquetSink::new(&dir, 2).unwrap();
+        for id in 0..3 {
+            assert!(sink.write(midnight + id, &trade(id)).unwrap());
+        }
+        let depth = Message::DepthUpdate(DepthUpdate {
+            event_time: 1,
+            symbol: Symbol::BTCUSDT,
+            first_update_id: 1,
+            final_update_id: 2,
+            bids: vec![[dec("1"), dec("2")], [dec("0.5"), dec("1")]],
+            asks: vec![[dec("3"), dec("0")]],
+        });
+        assert!(sink.write(midnight, &depth).unwrap());
+        // finishes the trade file of the first day
+        assert!(sink.write(midnight + day, &trade(3)).unwrap());
+
+        let first = dir.join(format!(
+            "trade/symbol=BTCUSDT/date=2024-06-01/part-{midnight}.parquet"
+        ));
+        let batches = read_rows(&first);
+        assert_eq!(batches.iter().map(|b| b.num_rows()).sum::<usize>(), 3);
+        let price = batches[0]
+            .column_by_name("price")
+            .unwrap()
+            .as_any()
+            .downcast_ref::<Decimal128Array>()
+            .unwrap();
+        assert_eq!(price.value_as_string(0), "65000.01000000");
+
+        ParquetSink::close(&mut sink).unwrap();
+        let depth = dir.join(format!(
+            "depth/symbol=BTCUSDT/date=2024-06-01/part-{midnight}.parquet"
+        ));
+        assert_eq!(read_rows(&depth)[0].num_rows(), 3);
+        assert!(dir.join("trade/symbol=BTCUSDT/date=2024-06-02").exists());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
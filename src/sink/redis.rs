@@ -0,0 +1,111 @@
+//! Redis publisher, forwarding messages serialized to JSON, which parse back into a
+//! [`Message`](crate::Message).
+//!
+//! Messages are published on pub/sub channels or appended to streams named
+//! `<prefix>:<SYMBOL>:<event type>`, e.g. `binance:BTCUSDT:aggTrade`, see [`RedisConfig::key()`].
+//! Stream entries have a single field `data` with the message.
+//!
+//! The connection is re-established automatically when it is lost,
+//! messages published while disconnected return an error.
+
+use redis::aio::ConnectionManager;
+use redis::streams::StreamMaxlen;
+use redis::AsyncCommands;
+
+use super::{topic, Sink};
+use crate::{Envelope, Message};
+
+/// How messages are forwarded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RedisMode {
+    /// `PUBLISH` to channels, only subscribers connected at the time receive the message.
+    #[default]
+    PubSub,
+    /// `XADD` to streams, trimmed to about `max_len` entries if set.
+    Stream { max_len: Option<usize> },
+}
+
+/// Settings of a [`RedisSink`].
+#[derive(Debug, Clone)]
+pub struct RedisConfig {
+    /// e.g. `redis://127.0.0.1:6379`
+    pub url: String,
+    pub prefix: String,
+    pub mode: RedisMode,
+}
+
+impl RedisConfig {
+    /// Publish to pub/sub channels prefixed with `binance` on the server at `url`.
+    pub fn new(url: &str) -> Self {
+        Self {
+            url: url.to_string(),
+            prefix: "binance".to_string(),
+            mode: RedisMode::default(),
+        }
+    }
+
+    /// Channel or stream `msg` is published to, `None` if it is not published.
+    ///
+    /// Messages without a symbol are published to `<prefix>:<event type>`.
+    pub fn key(&self, msg: &Message) -> Option<String> {
+        topic(&self.prefix, ":", msg)
+    }
+}
+
+/// Forwards messages to Redis, see the [module](self) documentation.
+#[derive(Clone)]
+pub struct RedisSink {
+    config: RedisConfig,
+    connection: ConnectionManager,
+}
+
+impl std::fmt::Debug for RedisSink {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RedisSink")
+            .field("config", &self.config)
+            .finish()
+    }
+}
+
+impl RedisSink {
+    pub async fn connect(config: RedisConfig) -> crate::Result<Self> {
+        let client = redis::Client::open(config.url.as_str())?;
+        let connection = ConnectionManager::new(client).await?;
+        Ok(Self { config, connection })
+    }
+
+    /// Publish `msg`, returns false if it is not published, see [`RedisConfig::key()`].
+    pub async fn publish(&mut self, msg: &Message) -> crate::Result<bool> {
+        let Some(key) = self.config.key(msg) else {
+            return Ok(false);
+        };
+        let payload = serde_json::to_string(msg)?;
+
+        match self.config.mode {
+            RedisMode::PubSub => {
+                let _: i64 = self.connection.publish(key, payload).await?;
+            }
+            RedisMode::Stream { max_len: None } => {
+                let _: String = self.connection.xadd(key, "*", &[("data", payload)]).await?;
+            }
+            RedisMode::Stream { max_len: Some(len) } => {
+                let _: String = self
+                    .connection
+                    .xadd_maxlen(key, StreamMaxlen::Approx(len), "*", &[("data", payload)])
+                    .await?;
+            }
+        }
+        Ok(true)
+    }
+}
+
+impl Sink for RedisSink {
+    async fn write(&mut self, envelope: &Envelope) -> crate::Result<()> {
+        self.publish(&envelope.message).await.map(drop)
+    }
+
+    /// Messages are sent as they are published.
+    async fn flush(&mut self) -> crate::Result<()> {
+        Ok(())
+    }
+}
@@ -0,0 +1,305 @@
+//! SQLite database, for local storage without a database server.
+//!
+//! [`SqliteSink::connect()`] creates the database file and tables if they do not exist and
+//! enables WAL mode, so the database can be read while recording.
+//!
+//! Messages are buffered and inserted in one transaction per batch. Prices and quantities are
+//! stored as text to keep them exact, depth update levels as json arrays of `[price, qty]`.
+//! Every table has a `recv_time` column, the local receive time in milliseconds since epoch.
+
+use std::path::Path;
+
+use sqlx::sqlite::{SqliteConnectOptions, SqliteJournalMode, SqlitePool, SqliteSynchronous};
+use sqlx::{Sqlite, Transaction};
+
+use super::{symbol_name, Sink};
+use crate::recorder::now_millis;
+use crate::{Envelope, Message};
+
+const SCHEMA: &str = r#"
+CREATE TABLE IF NOT EXISTS agg_trades (
+    recv_time INTEGER NOT NULL,
+    event_time INTEGER NOT NULL,
+    symbol TEXT NOT NULL,
+    trade_id INTEGER NOT NULL,
+    price TEXT NOT NULL,
+    quantity TEXT NOT NULL,
+    first_trade_id INTEGER NOT NULL,
+    last_trade_id INTEGER NOT NULL,
+    trade_time INTEGER NOT NULL,
+    is_market_maker INTEGER NOT NULL
+);
+CREATE INDEX IF NOT EXISTS agg_trades_symbol_time ON agg_trades (symbol, trade_time);
+
+CREATE TABLE IF NOT EXISTS trades (
+    recv_time INTEGER NOT NULL,
+    event_time INTEGER NOT NULL,
+    symbol TEXT NOT NULL,
+    trade_id INTEGER NOT NULL,
+    price TEXT NOT NULL,
+    quantity TEXT NOT NULL,
+    trade_time INTEGER NOT NULL,
+    is_market_maker INTEGER NOT NULL
+);
+CREATE INDEX IF NOT EXISTS trades_symbol_time ON trades (symbol, trade_time);
+
+CREATE TABLE IF NOT EXISTS book_tickers (
+    recv_time INTEGER NOT NULL,
+    update_id INTEGER NOT NULL,
+    symbol TEXT NOT NULL,
+    best_bid_price TEXT NOT NULL,
+    best_bid_qty TEXT NOT NULL,
+    best_ask_price TEXT NOT NULL,
+    best_ask_qty TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS book_tickers_symbol_time ON book_tickers (symbol, recv_time);
+
+CREATE TABLE IF NOT EXISTS depth_updates (
+    recv_time INTEGER NOT NULL,
+    event_time INTEGER NOT NULL,
+    symbol TEXT NOT NULL,
+    first_update_id INTEGER NOT NULL,
+    final_update_id INTEGER NOT NULL,
+    bids TEXT NOT NULL,
+    asks TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS depth_updates_symbol_time ON depth_updates (symbol, event_time);
+
+CREATE TABLE IF NOT EXISTS klines (
+    recv_time INTEGER NOT NULL,
+    event_time INTEGER NOT NULL,
+    symbol TEXT NOT NULL,
+    interval TEXT NOT NULL,
+    open_time INTEGER NOT NULL,
+    close_time INTEGER NOT NULL,
+    open TEXT NOT NULL,
+    high TEXT NOT NULL,
+    low TEXT NOT NULL,
+    close TEXT NOT NULL,
+    volume TEXT NOT NULL,
+    quote_volume TEXT NOT NULL,
+    trades INTEGER NOT NULL,
+    is_closed INTEGER NOT NULL
+);
+CREATE INDEX IF NOT EXISTS klines_symbol_time ON klines (symbol, open_time);
+"#;
+
+/// Writes messages to a SQLite database, see the [module](self) documentation.
+#[derive(Debug)]
+pub struct SqliteSink {
+    pool: SqlitePool,
+    batch_size: usize,
+    rows: Vec<(u64, Message)>,
+}
+
+impl SqliteSink {
+    /// Open or create the database at `path`, inserting `batch_size` messages at a time.
+    pub async fn connect(path: impl AsRef<Path>, batch_size: usize) -> crate::Result<Self> {
+        let options = SqliteConnectOptions::new()
+            .filename(path)
+            .create_if_missing(true)
+            .journal_mode(SqliteJournalMode::Wal)
+            .synchronous(SqliteSynchronous::Normal);
+        // a single writer, sqlite serializes writes anyway
+        let pool = sqlx::pool::PoolOptions::new()
+            .max_connections(1)
+            .connect_with(options)
+            .await?;
+        sqlx::raw_sql(SCHEMA).execute(&pool).await?;
+
+        Ok(Self {
+            pool,
+            batch_size: batch_size.max(1),
+            rows: Vec::new(),
+        })
+    }
+
+    /// The connection pool, e.g. to query the recorded data.
+    pub fn pool(&self) -> &SqlitePool {
+        &self.pool
+    }
+
+    /// Write `msg`, received now.
+    pub async fn record(&mut self, msg: &Message) -> crate::Result<bool> {
+        self.write(now_millis(), msg).await
+    }
+
+    /// Write `msg` received at `recv_time`, returns false if the message type is not stored.
+    pub async fn write(&mut self, recv_time: u64, msg: &Message) -> crate::Result<bool> {
+        if !matches!(
+            msg,
+            Message::AggTrade(_)
+                | Message::Trade(_)
+                | Message::BookTicker(_)
+                | Message::DepthUpdate(_)
+                | Message::Kline(_)
+        ) {
+            return Ok(false);
+        }
+
+        self.rows.push((recv_time, msg.clone()));
+        if self.rows.len() >= self.batch_size {
+            self.flush().await?;
+        }
+        Ok(true)
+    }
+
+    /// Insert the buffered messages.
+    pub async fn flush(&mut self) -> crate::Result<()> {
+        if self.rows.is_empty() {
+            return Ok(());
+        }
+        let mut tx = self.pool.begin().await?;
+        for (recv_time, msg) in &self.rows {
+            insert(&mut tx, *recv_time as i64, msg).await?;
+        }
+        tx.commit().await?;
+        self.rows.clear();
+        Ok(())
+    }
+
+    /// Insert the buffered messages and close the database.
+    pub async fn close(mut self) -> crate::Result<()> {
+        self.flush().await?;
+        self.pool.close().await;
+        Ok(())
+    }
+}
+
+impl Sink for SqliteSink {
+    async fn write(&mut self, envelope: &Envelope) -> crate::Result<()> {
+        SqliteSink::write(self, envelope.recv_time, &envelope.message)
+            .await
+            .map(drop)
+    }
+
+    async fn flush(&mut self) -> crate::Result<()> {
+        SqliteSink::flush(self).await
+    }
+
+    async fn close(self) -> crate::Result<()> {
+        SqliteSink::close(self).await
+    }
+}
+
+async fn insert(
+    tx: &mut Transaction<'_, Sqlite>,
+    recv_time: i64,
+    msg: &Message,
+) -> crate::Result<()> {
+    let query = match msg {
+        Message::AggTrade(t) => {
+            sqlx::query("INSERT INTO agg_trades VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)")
+                .bind(recv_time)
+                .bind(t.event_time as i64)
+                .bind(symbol_name(&t.symbol))
+                .bind(t.trade_id as i64)
+                .bind(t.price.to_string())
+                .bind(t.quantity.to_string())
+                .bind(t.first_trade_id as i64)
+                .bind(t.last_trade_id as i64)
+                .bind(t.trade_time as i64)
+                .bind(t.is_market_maker)
+        }
+        Message::Trade(t) => sqlx::query("INSERT INTO trades VALUES (?, ?, ?, ?, ?, ?, ?, ?)")
+            .bind(recv_time)
+            .bind(t.event_time as i64)
+            .bind(symbol_name(&t.symbol))
+            .bind(t.trade_id as i64)
+            .bind(t.price.to_string())
+            .bind(t.quantity.to_string())
+            .bind(t.trade_time as i64)
+            .bind(t.is_market_maker),
+        Message::BookTicker(bt) => {
+            sqlx::query("INSERT INTO book_tickers VALUES (?, ?, ?, ?, ?, ?, ?)")
+                .bind(recv_time)
+                .bind(bt.update_id as i64)
+                .bind(symbol_name(&bt.symbol))
+                .bind(bt.best_bid_price.to_string())
+                .bind(bt.best_bid_qty.to_string())
+                .bind(bt.best_ask_price.to_string())
+                .bind(bt.best_ask_qty.to_string())
+        }
+        Message::DepthUpdate(du) => {
+            sqlx::query("INSERT INTO depth_updates VALUES (?, ?, ?, ?, ?, ?, ?)")
+                .bind(recv_time)
+                .bind(du.event_time as i64)
+                .bind(symbol_name(&du.symbol))
+                .bind(du.first_update_id as i64)
+                .bind(du.final_update_id as i64)
+                .bind(serde_json::to_string(&du.bids)?)
+                .bind(serde_json::to_string(&du.asks)?)
+        }
+        Message::Kline(k) => {
+            sqlx::query("INSERT INTO klines VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)")
+                .bind(recv_time)
+                .bind(k.event_time as i64)
+                .bind(symbol_name(&k.symbol))
+                .bind(k.kline.interval.clone())
+                .bind(k.kline.open_time as i64)
+                .bind(k.kline.close_time as i64)
+                .bind(k.kline.open.to_string())
+                .bind(k.kline.high.to_string())
+                .bind(k.kline.low.to_string())
+                .bind(k.kline.close.to_string())
+                .bind(k.kline.volume.to_string())
+                .bind(k.kline.quote_volume.to_string())
+                .bind(k.kline.trades as i64)
+                .bind(k.kline.is_closed)
+        }
+        _ => return Ok(()),
+    };
+    query.execute(&mut **tx).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::messages::Trade;
+    use crate::test_util::{self, dec};
+    use crate::Symbol;
+    use sqlx::Row;
+
+    fn trade(trade_id: u64) -> Message {
+        Message::Trade(Trade {
+            price: dec("65000.010"),
+            ..test_util::trade(Symbol::BTCUSDT, trade_id)
+        })
+    }
+
+    #[tokio::test]
+    async fn batched_inserts() {
+        let path = std::env::temp_dir().join(format!("sqlite_sink_{}.db", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let mut sink = SqliteSink::connect(&path, 2).await.unwrap();
+        for id in 0..3 {
+            assert!(sink.write(id, &trade(id)).await.unwrap());
+        }
+
+        let count = |pool: SqlitePool| async move {
+            sqlx::query("SELECT count(*) AS n, max(price) AS price FROM trades")
+                .fetch_one(&pool)
+                .await
+                .map(|row| {
+                    (
+                        row.get::<i64, _>("n"),
+                        row.get::<Option<String>, _>("price"),
+                    )
+                })
+                .unwrap()
+        };
+        assert_eq!(
+            count(sink.pool().clone()).await,
+            (2, Some("65000.010".to_string()))
+        );
+
+        sink.close().await.unwrap();
+        let sink = SqliteSink::connect(&path, 2).await.unwrap();
+        assert_eq!(count(sink.pool().clone()).await.0, 3);
+
+        sink.close().await.unwrap();
+        let _ = std::fs::remove_file(&path);
+    }
+}
@@ -0,0 +1,219 @@
+//! Unix domain socket server, streaming messages to colocated processes.
+//!
+//! Every connected client receives every published message as a frame, a 4 byte big endian
+//! length followed by the message, as JSON or in the [`crate::binary`] encoding, see
+//! [`Encoding`]. [`read_frame()`] reads frames on the client side.
+//!
+//! Each client is served from its own task. A client that does not keep up skips the oldest
+//! messages once `capacity` messages are queued for it, instead of slowing down the others.
+
+use std::os::unix::fs::FileTypeExt;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt};
+use tokio::net::{UnixListener, UnixStream};
+use tokio::sync::broadcast;
+use tokio::task::JoinHandle;
+use tracing::{info, warn};
+
+use super::Sink;
+use crate::{Envelope, Message};
+
+/// Encoding of the messages in the frames.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Encoding {
+    /// JSON of the [`Message`](crate::Message), the frames read back with `serde_json`.
+    #[default]
+    Json,
+    /// See [`crate::binary`].
+    #[cfg(feature = "binary")]
+    Binary,
+}
+
+/// Serves messages on a Unix domain socket, see the [module](self) documentation.
+#[derive(Debug)]
+pub struct UdsSink {
+    path: PathBuf,
+    encoding: Encoding,
+    sender: broadcast::Sender<Arc<[u8]>>,
+    accept: JoinHandle<()>,
+}
+
+impl UdsSink {
+    /// Listen on a socket at `path`, replacing a socket left by an earlier process. Any
+    /// other file at `path` is kept, and returned as an error.
+    ///
+    /// Up to `capacity` messages are queued for each client, at least one.
+    pub fn bind(
+        path: impl Into<PathBuf>,
+        encoding: Encoding,
+        capacity: usize,
+    ) -> crate::Result<Self> {
+        let path = path.into();
+        if capacity == 0 {
+            return Err(crate::Error::Custom(
+                "uds: at least one message must be queued per client".to_string(),
+            ));
+        }
+        match std::fs::symlink_metadata(&path) {
+            Ok(metadata) if metadata.file_type().is_socket() => std::fs::remove_file(&path)?,
+            Ok(_) => {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::AlreadyExists,
+                    format!("{} exists and is not a socket", path.display()),
+                )
+                .into())
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e.into()),
+        }
+        let listener = UnixListener::bind(&path)?;
+        let (sender, _) = broadcast::channel(capacity);
+
+        let clients = sender.clone();
+        let accept = tokio::spawn(async move {
+            loop {
+                match listener.accept().await {
+                    Ok((stream, _)) => {
+                        tokio::spawn(serve(stream, clients.subscribe()));
+                    }
+                    Err(e) => warn!("Failed to accept a unix socket client: {e}"),
+                }
+            }
+        });
+
+        info!("Serving messages on {}", path.display());
+        Ok(Self {
+            path,
+            encoding,
+            sender,
+            accept,
+        })
+    }
+
+    /// Path of the socket.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Number of connected clients.
+    pub fn clients(&self) -> usize {
+        self.sender.receiver_count()
+    }
+
+    /// Send `msg` to every connected client, returns false if it is not sent.
+    ///
+    /// Subscription responses are not sent.
+    pub fn publish(&self, msg: &Message) -> crate::Result<bool> {
+        if let Message::SubscribeSuccess { .. } = msg {
+            return Ok(false);
+        }
+        let payload = match self.encoding {
+            Encoding::Json => serde_json::to_vec(msg)?,
+            #[cfg(feature = "binary")]
+            Encoding::Binary => crate::binary::encode(msg)?,
+        };
+        let mut frame = Vec::with_capacity(4 + payload.len());
+        frame.extend_from_slice(&(payload.len() as u32).to_be_bytes());
+        frame.extend_from_slice(&payload);
+
+        // an error only means that no client is connected
+        let _ = self.sender.send(frame.into());
+        Ok(true)
+    }
+}
+
+impl Sink for UdsSink {
+    async fn write(&mut self, envelope: &Envelope) -> crate::Result<()> {
+        self.publish(&envelope.message).map(drop)
+    }
+
+    /// Messages are queued for the clients as they are published.
+    async fn flush(&mut self) -> crate::Result<()> {
+        Ok(())
+    }
+}
+
+impl Drop for UdsSink {
+    fn drop(&mut self) {
+        self.accept.abort();
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+async fn serve(mut stream: UnixStream, mut frames: broadcast::Receiver<Arc<[u8]>>) {
+    loop {
+        match frames.recv().await {
+            Ok(frame) => {
+                if stream.write_all(&frame).await.is_err() {
+                    // the client disconnected
+                    return;
+                }
+            }
+            Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                warn!("Unix socket client is lagging, skipped {skipped} messages");
+            }
+            Err(broadcast::error::RecvError::Closed) => return,
+        }
+    }
+}
+
+/// Read the next frame sent by a [`UdsSink`], `None` when the sink has closed the socket.
+pub async fn read_frame(reader: &mut (impl AsyncRead + Unpin)) -> crate::Result<Option<Vec<u8>>> {
+    let mut len = [0; 4];
+    match reader.read_exact(&mut len).await {
+        Ok(_) => {}
+        Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e.into()),
+    }
+    let mut payload = vec![0; u32::from_be_bytes(len) as usize];
+    reader.read_exact(&mut payload).await?;
+    Ok(Some(payload))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_util::ticker;
+    use crate::Symbol;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn frames_to_clients() {
+        let ticker = Message::BookTicker(ticker(Symbol::BTCUSDT, "1", "2"));
+        let path = std::env::temp_dir().join(format!("uds_sink_{}.sock", std::process::id()));
+
+        let sink = UdsSink::bind(&path, Encoding::Json, 16).unwrap();
+        let mut client = UnixStream::connect(&path).await.unwrap();
+        while sink.clients() == 0 {
+            tokio::time::sleep(Duration::from_millis(1)).await;
+        }
+
+        assert!(sink.publish(&ticker).unwrap());
+        assert!(!sink
+            .publish(&Message::SubscribeSuccess {
+                result: None,
+                id: 1
+            })
+            .unwrap());
+        let frame = read_frame(&mut client).await.unwrap().unwrap();
+        assert_eq!(serde_json::from_slice::<Message>(&frame).unwrap(), ticker);
+
+        drop(sink);
+        assert!(!path.exists());
+    }
+
+    #[tokio::test]
+    async fn keeps_other_files() {
+        let path = std::env::temp_dir().join(format!("uds_sink_{}.txt", std::process::id()));
+        std::fs::write(&path, "data").unwrap();
+        assert!(UdsSink::bind(&path, Encoding::Json, 16).is_err());
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "data");
+        std::fs::remove_file(&path).unwrap();
+
+        let path = std::env::temp_dir().join(format!("uds_sink_{}_0.sock", std::process::id()));
+        assert!(UdsSink::bind(&path, Encoding::Json, 0).is_err());
+        assert!(!path.exists());
+    }
+}
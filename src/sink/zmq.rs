@@ -0,0 +1,109 @@
+//! ZeroMQ PUB socket, for local IPC with processes in other languages.
+//!
+//! Every message is sent as two frames, the topic `<prefix>.<SYMBOL>.<event type>`,
+//! e.g. `binance.BTCUSDT.aggTrade`, and the message serialized with `serde_json`, without the
+//! event type `e` Binance sends.
+//! Subscribers filter on topic prefixes, `binance.BTCUSDT.` receives everything for BTCUSDT.
+//!
+//! Messages are dropped for subscribers that do not keep up, as with any PUB socket.
+
+use zeromq::{Endpoint, PubSocket, Socket, SocketSend, ZmqMessage};
+
+use super::{topic, Sink};
+use crate::{Envelope, Message};
+
+/// Publishes messages on a ZeroMQ PUB socket, see the [module](self) documentation.
+pub struct ZmqSink {
+    socket: PubSocket,
+    prefix: String,
+    endpoint: Endpoint,
+}
+
+impl std::fmt::Debug for ZmqSink {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ZmqSink")
+            .field("prefix", &self.prefix)
+            .field("endpoint", &self.endpoint)
+            .finish()
+    }
+}
+
+impl ZmqSink {
+    /// Bind to `endpoint`, e.g. `tcp://127.0.0.1:5556` or `ipc:///tmp/binance.ipc`,
+    /// with topics starting with `prefix`.
+    pub async fn bind(endpoint: &str, prefix: &str) -> crate::Result<Self> {
+        let mut socket = PubSocket::new();
+        let endpoint = socket.bind(endpoint).await?;
+        Ok(Self {
+            socket,
+            prefix: prefix.to_string(),
+            endpoint,
+        })
+    }
+
+    /// The bound endpoint, with the actual port when bound to port 0.
+    pub fn endpoint(&self) -> &Endpoint {
+        &self.endpoint
+    }
+
+    /// Topic of `msg`, `None` if it is not published.
+    ///
+    /// Messages without a symbol have the topic `<prefix>.<event type>`.
+    pub fn topic(&self, msg: &Message) -> Option<String> {
+        topic(&self.prefix, ".", msg)
+    }
+
+    /// Publish `msg`, returns false if it is not published, see [`ZmqSink::topic()`].
+    pub async fn publish(&mut self, msg: &Message) -> crate::Result<bool> {
+        let Some(topic) = self.topic(msg) else {
+            return Ok(false);
+        };
+        let mut frames = ZmqMessage::from(serde_json::to_vec(msg)?);
+        frames.prepend(&ZmqMessage::from(topic));
+        self.socket.send(frames).await?;
+        Ok(true)
+    }
+}
+
+impl Sink for ZmqSink {
+    async fn write(&mut self, envelope: &Envelope) -> crate::Result<()> {
+        self.publish(&envelope.message).await.map(drop)
+    }
+
+    /// Messages are sent as they are published.
+    async fn flush(&mut self) -> crate::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_util;
+    use crate::Symbol;
+    use std::time::Duration;
+    use zeromq::{SocketRecv, SubSocket};
+
+    #[tokio::test]
+    async fn topic_and_payload_frames() {
+        let trade = Message::Trade(test_util::trade(Symbol::BTCUSDT, 1));
+
+        let mut sink = ZmqSink::bind("tcp://127.0.0.1:0", "binance").await.unwrap();
+        let mut sub = SubSocket::new();
+        sub.connect(&sink.endpoint().to_string()).await.unwrap();
+        sub.subscribe("binance.BTCUSDT.").await.unwrap();
+
+        // the subscription reaches the publisher asynchronously, publish until received
+        let received = loop {
+            assert!(sink.publish(&trade).await.unwrap());
+            if let Ok(msg) = tokio::time::timeout(Duration::from_millis(50), sub.recv()).await {
+                break msg.unwrap();
+            }
+        };
+
+        let frames = received.into_vec();
+        assert_eq!(frames[0].as_ref(), b"binance.BTCUSDT.trade");
+        let msg: Message = serde_json::from_slice(&frames[1]).unwrap();
+        assert_eq!(msg, trade);
+    }
+}
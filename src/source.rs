@@ -0,0 +1,146 @@
+//! Sources of market data, live or replayed.
+//!
+//! Write strategies and recorders against [`MarketDataSource`] and wire them to a
+//! [`BinanceApi`] connection, a [`ReplaySource`] or a fake in tests:
+//! ```no_run
+//! use binance_api_async::{Feed, MarketDataSource, Message, SubscribeInfo, Symbol};
+//!
+//! async fn count_trades(
+//!     source: &mut impl MarketDataSource,
+//! ) -> Result<usize, binance_api_async::Error> {
+//!     let info = SubscribeInfo::new(Symbol::BTCUSDT, Feed::AggTrade);
+//!     source.subscribe(&[info], None).await?;
+//!     let mut trades = 0;
+//!     while let Some(msg) = source.next_message().await {
+//!         if let Message::AggTrade(_) = msg {
+//!             trades += 1;
+//!         }
+//!     }
+//!     Ok(trades)
+//! }
+//! ```
+
+use std::future::Future;
+
+#[cfg(feature = "tokio")]
+use crate::replay::ReplaySource;
+#[cfg(feature = "tokio")]
+use crate::BinanceApi;
+use crate::{Message, SubscribeInfo};
+
+/// A stream of [`Message`]s for subscribed feeds.
+pub trait MarketDataSource {
+    /// The next message, `None` when the source has ended.
+    fn next_message(&mut self) -> impl Future<Output = Option<Message>> + Send;
+
+    /// Subscribe to feeds, answered with a [`Message::SubscribeSuccess`] with `id`.
+    ///
+    /// An error if the subscribe was not sent, e.g.
+    /// [`Error::UnsupportedFeed`](crate::Error::UnsupportedFeed).
+    fn subscribe(
+        &mut self,
+        symbols: &[SubscribeInfo],
+        id: Option<u64>,
+    ) -> impl Future<Output = crate::Result<()>> + Send;
+
+    /// Unsubscribe from feeds.
+    ///
+    /// An error if a stream was not subscribed, e.g.
+    /// [`Error::NotSubscribed`](crate::Error::NotSubscribed), or if the unsubscribe was not
+    /// sent.
+    fn unsubscribe(
+        &mut self,
+        symbols: Vec<SubscribeInfo>,
+    ) -> impl Future<Output = crate::Result<()>> + Send;
+}
+
+#[cfg(feature = "tokio")]
+impl MarketDataSource for BinanceApi {
+    async fn next_message(&mut self) -> Option<Message> {
+        BinanceApi::next_message(self).await
+    }
+
+    async fn subscribe(&mut self, symbols: &[SubscribeInfo], id: Option<u64>) -> crate::Result<()> {
+        BinanceApi::subscribe(self, symbols, id).await
+    }
+
+    async fn unsubscribe(&mut self, symbols: Vec<SubscribeInfo>) -> crate::Result<()> {
+        BinanceApi::unsubscribe(self, symbols).await
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl MarketDataSource for ReplaySource {
+    async fn next_message(&mut self) -> Option<Message> {
+        ReplaySource::next_message(self).await
+    }
+
+    async fn subscribe(&mut self, symbols: &[SubscribeInfo], id: Option<u64>) -> crate::Result<()> {
+        ReplaySource::subscribe(self, symbols, id).await
+    }
+
+    async fn unsubscribe(&mut self, symbols: Vec<SubscribeInfo>) -> crate::Result<()> {
+        ReplaySource::unsubscribe(self, symbols).await;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{Feed, Symbol};
+    use std::collections::VecDeque;
+
+    struct Fake {
+        messages: VecDeque<Message>,
+    }
+
+    impl MarketDataSource for Fake {
+        async fn next_message(&mut self) -> Option<Message> {
+            self.messages.pop_front()
+        }
+
+        async fn subscribe(
+            &mut self,
+            _symbols: &[SubscribeInfo],
+            id: Option<u64>,
+        ) -> crate::Result<()> {
+            self.messages.push_front(Message::SubscribeSuccess {
+                result: None,
+                id: id.unwrap_or(1),
+            });
+            Ok(())
+        }
+
+        async fn unsubscribe(&mut self, _symbols: Vec<SubscribeInfo>) -> crate::Result<()> {
+            Ok(())
+        }
+    }
+
+    async fn first_message(source: &mut impl MarketDataSource) -> Option<Message> {
+        let info = SubscribeInfo::new(Symbol::BTCUSDT, Feed::Trade);
+        source.subscribe(&[info], Some(2)).await.unwrap();
+        source.next_message().await
+    }
+
+    #[tokio::test]
+    async fn generic_over_sources() {
+        let mut fake = Fake {
+            messages: VecDeque::new(),
+        };
+        assert_eq!(
+            first_message(&mut fake).await,
+            Some(Message::SubscribeSuccess {
+                result: None,
+                id: 2
+            })
+        );
+
+        let mut replay = ReplaySource::from_files(Vec::<&str>::new()).unwrap();
+        assert!(matches!(
+            first_message(&mut replay).await,
+            Some(Message::SubscribeSuccess { id: 2, .. })
+        ));
+        assert_eq!(replay.next_message().await, None);
+    }
+}
@@ -0,0 +1,343 @@
+//! Detect symbols that stopped receiving messages on a feed.
+//!
+//! A subscription can break without a close frame, the connection stays open but the
+//! messages of a stream stop. Streams like the [`BookTicker`](crate::messages::BookTicker) of
+//! liquid pairs are never silent for long, a [`StaleMonitor`] reports a [`Stale`] event when
+//! a watched stream has been silent for longer than its limit. It is reported once, and again
+//! only after a message of the stream was received in between.
+//!
+//! [`StaleMonitor::tap()`] wraps a [`MarketDataSource`] and returns the stale events between
+//! the messages, when they happen:
+//! ```no_run
+//! use std::time::Duration;
+//! use binance_api_async::stale::{StaleEvent, StaleMonitor};
+//! use binance_api_async::{BinanceApi, Feed, MarketDataSource, SubscribeInfo, Symbol};
+//!
+//! # async fn run() -> Result<(), binance_api_async::Error> {
+//! let mut api = BinanceApi::new();
+//! api.connect().await?;
+//! api.subscribe(&[SubscribeInfo::new(Symbol::BTCUSDT, Feed::BookTicker)], None).await?;
+//!
+//! let mut monitor = StaleMonitor::new();
+//! monitor.watch(Symbol::BTCUSDT, &Feed::BookTicker, Duration::from_secs(5));
+//! let mut source = monitor.tap(api);
+//! while let Some(event) = source.next_event().await {
+//!     match event {
+//!         StaleEvent::Message(msg) => println!("{msg}"),
+//!         StaleEvent::Stale(stale) => println!("no {} for {:?}", stale.event_type, stale.silent_for),
+//!     }
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::VecDeque;
+use std::pin::pin;
+use std::sync::Arc;
+use std::time::Duration;
+
+use futures::future::{self, Either};
+
+use crate::clock::{Clock, SystemClock};
+use crate::{Feed, MarketDataSource, Message, Symbol};
+
+/// A watched stream that has been silent for longer than its limit.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Stale {
+    pub symbol: Symbol,
+    /// Event type of the messages of the feed, see [`Message::event_type()`].
+    pub event_type: &'static str,
+    /// Time since the last message, or since the stream was watched.
+    pub silent_for: Duration,
+}
+
+/// A message or a stale stream, see [`StaleTap::next_event()`].
+#[allow(clippy::large_enum_variant)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StaleEvent {
+    Message(Message),
+    Stale(Stale),
+}
+
+#[derive(Debug, Clone)]
+struct Watch {
+    symbol: Symbol,
+    event_type: &'static str,
+    limit: Duration,
+    /// time of the last message in milliseconds since epoch
+    last: u64,
+    reported: bool,
+}
+
+impl Watch {
+    fn deadline(&self) -> u64 {
+        self.last + self.limit.as_millis() as u64
+    }
+}
+
+/// Watches streams for silence, see the [module](self) documentation.
+#[derive(Debug, Clone)]
+pub struct StaleMonitor {
+    clock: Arc<dyn Clock>,
+    watches: Vec<Watch>,
+}
+
+impl Default for StaleMonitor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl StaleMonitor {
+    pub fn new() -> Self {
+        Self::with_clock(Arc::new(SystemClock))
+    }
+
+    /// A monitor reading the time from `clock`, e.g. a [`TestClock`](crate::clock::TestClock).
+    pub fn with_clock(clock: Arc<dyn Clock>) -> Self {
+        Self {
+            clock,
+            watches: Vec::new(),
+        }
+    }
+
+    /// Report `feed` of `symbol` as stale after `limit` without a message, starting now.
+    ///
+    /// Klines of all intervals are watched together, a feed watched again replaces the limit.
+    ///
+    /// Returns false for [`Feed::PartialDepth`], it is not watched, its messages do not name
+    /// the symbol.
+    pub fn watch(&mut self, symbol: Symbol, feed: &Feed, limit: Duration) -> bool {
+        let Some(event_type) = event_type_of(feed) else {
+            return false;
+        };
+        self.unwatch(&symbol, feed);
+        self.watches.push(Watch {
+            symbol,
+            event_type,
+            limit,
+            last: self.clock.now_millis(),
+            reported: false,
+        });
+        true
+    }
+
+    /// Stop watching `feed` of `symbol`, returns false if it was not watched.
+    pub fn unwatch(&mut self, symbol: &Symbol, feed: &Feed) -> bool {
+        let Some(event_type) = event_type_of(feed) else {
+            return false;
+        };
+        let len = self.watches.len();
+        self.watches
+            .retain(|w| &w.symbol != symbol || w.event_type != event_type);
+        self.watches.len() != len
+    }
+
+    /// Record `msg` as received now.
+    pub fn push_message(&mut self, msg: &Message) {
+        let Some(symbol) = msg.symbol() else {
+            return;
+        };
+        let now = self.clock.now_millis();
+        // the depth streams of futures send futures depth updates
+        let event_type = match msg.event_type() {
+            "futuresDepthUpdate" => "depthUpdate",
+            event_type => event_type,
+        };
+        for watch in &mut self.watches {
+            if watch.event_type == event_type && &watch.symbol == symbol {
+                watch.last = now;
+                watch.reported = false;
+            }
+        }
+    }
+
+    /// The streams that became stale since the last check.
+    pub fn check(&mut self) -> Vec<Stale> {
+        let now = self.clock.now_millis();
+        let mut stale = Vec::new();
+        for watch in &mut self.watches {
+            if !watch.reported && now >= watch.deadline() {
+                watch.reported = true;
+                stale.push(Stale {
+                    symbol: watch.symbol.clone(),
+                    event_type: watch.event_type,
+                    silent_for: Duration::from_millis(now - watch.last),
+                });
+            }
+        }
+        stale
+    }
+
+    /// Time until the next stream becomes stale, `None` if all of them are reported already.
+    pub fn next_deadline(&self) -> Option<Duration> {
+        let now = self.clock.now_millis();
+        self.watches
+            .iter()
+            .filter(|w| !w.reported)
+            .map(|w| Duration::from_millis(w.deadline().saturating_sub(now)))
+            .min()
+    }
+
+    /// Return the stale events of `source` between its messages, see [`StaleTap`].
+    pub fn tap<S: MarketDataSource + Send>(self, source: S) -> StaleTap<S> {
+        StaleTap {
+            source,
+            monitor: self,
+            pending: VecDeque::new(),
+        }
+    }
+}
+
+/// Event type of the messages of `feed`, `None` for partial depth, its messages do not name
+/// the symbol.
+fn event_type_of(feed: &Feed) -> Option<&'static str> {
+    match feed {
+        Feed::AggTrade => Some("aggTrade"),
+        Feed::Trade => Some("trade"),
+        Feed::BookTicker => Some("bookTicker"),
+        Feed::Kline { .. } => Some("kline"),
+        Feed::ContinuousKline { .. } => Some("continuous_kline"),
+        Feed::FullDepth { .. } => Some("depthUpdate"),
+        Feed::PartialDepth { .. } => None,
+    }
+}
+
+/// A [`MarketDataSource`] with a [`StaleMonitor`], see [`StaleMonitor::tap()`].
+///
+/// As a [`MarketDataSource`] it returns the messages only, the stale events are returned by
+/// [`StaleTap::next_event()`].
+#[derive(Debug)]
+pub struct StaleTap<S> {
+    source: S,
+    monitor: StaleMonitor,
+    pending: VecDeque<Stale>,
+}
+
+impl<S: MarketDataSource + Send> StaleTap<S> {
+    /// The next message, or a stale stream as soon as it becomes stale.
+    ///
+    /// `None` when the source has ended.
+    pub async fn next_event(&mut self) -> Option<StaleEvent> {
+        loop {
+            self.pending.extend(self.monitor.check());
+            if let Some(stale) = self.pending.pop_front() {
+                return Some(StaleEvent::Stale(stale));
+            }
+            let deadline = self.monitor.next_deadline();
+            let clock = self.monitor.clock.clone();
+            let timeout = async {
+                match deadline {
+                    Some(deadline) => clock.sleep(deadline).await,
+                    None => std::future::pending().await,
+                }
+            };
+            let (next, timeout) = (pin!(self.source.next_message()), pin!(timeout));
+            if let Either::Left((msg, _)) = future::select(next, timeout).await {
+                let msg = msg?;
+                self.monitor.push_message(&msg);
+                return Some(StaleEvent::Message(msg));
+            }
+        }
+    }
+
+    /// The monitor, to change the watched streams.
+    pub fn monitor_mut(&mut self) -> &mut StaleMonitor {
+        &mut self.monitor
+    }
+
+    /// The wrapped source.
+    pub fn source_mut(&mut self) -> &mut S {
+        &mut self.source
+    }
+}
+
+impl<S: MarketDataSource + Send> MarketDataSource for StaleTap<S> {
+    async fn next_message(&mut self) -> Option<Message> {
+        let msg = self.source.next_message().await?;
+        self.monitor.push_message(&msg);
+        Some(msg)
+    }
+
+    async fn subscribe(
+        &mut self,
+        symbols: &[crate::SubscribeInfo],
+        id: Option<u64>,
+    ) -> crate::Result<()> {
+        self.source.subscribe(symbols, id).await
+    }
+
+    async fn unsubscribe(&mut self, symbols: Vec<crate::SubscribeInfo>) -> crate::Result<()> {
+        self.source.unsubscribe(symbols).await
+    }
+}
+
+#[cfg(all(test, feature = "book-ticker"))]
+mod test {
+    use super::*;
+    use crate::clock::TestClock;
+    use crate::test_util::{self, Action, MockServer};
+    use crate::SubscribeInfo;
+
+    fn ticker(symbol: Symbol) -> Message {
+        Message::BookTicker(test_util::ticker(symbol, "1", "2"))
+    }
+
+    #[tokio::test]
+    async fn silent_stream() {
+        let clock = Arc::new(TestClock::new(0));
+        let mut monitor = StaleMonitor::with_clock(clock.clone());
+        let limit = Duration::from_secs(5);
+        assert!(monitor.watch(Symbol::BTCUSDT, &Feed::BookTicker, limit));
+        assert!(monitor.watch(Symbol::ETHUSDT, &Feed::BookTicker, limit));
+        let depth = Feed::PartialDepth {
+            levels: crate::DepthLevel::FIVE,
+            delay: crate::Delay::ONEHUNDRED,
+        };
+        assert!(!monitor.watch(Symbol::BTCUSDT, &depth, limit));
+        assert_eq!(monitor.next_deadline(), Some(limit));
+
+        clock.advance(Duration::from_secs(4));
+        monitor.push_message(&ticker(Symbol::BTCUSDT));
+        assert!(monitor.check().is_empty());
+        clock.advance(Duration::from_secs(1));
+        let stale = monitor.check();
+        assert_eq!(stale.len(), 1);
+        assert_eq!(stale[0].symbol, Symbol::ETHUSDT);
+        assert_eq!(stale[0].silent_for, limit);
+        // reported once until a message is received
+        assert!(monitor.check().is_empty());
+        assert_eq!(monitor.next_deadline(), Some(Duration::from_secs(4)));
+        assert!(monitor.unwatch(&Symbol::ETHUSDT, &Feed::BookTicker));
+
+        let server = MockServer::start().await.unwrap();
+        server.script([Action::message(&ticker(Symbol::BTCUSDT))]);
+        let mut api = server.api();
+        api.connect().await.unwrap();
+        api.subscribe(
+            &[SubscribeInfo::new(Symbol::BTCUSDT, Feed::BookTicker)],
+            None,
+        )
+        .await
+        .unwrap();
+        let mut tap = monitor.tap(api);
+        assert!(matches!(
+            tap.next_event().await,
+            Some(StaleEvent::Message(_))
+        ));
+        assert_eq!(
+            tap.next_event().await,
+            Some(StaleEvent::Message(ticker(Symbol::BTCUSDT)))
+        );
+
+        // the connection stays open without messages
+        let advance = clock.clone();
+        tokio::spawn(async move { advance.advance(Duration::from_secs(6)) });
+        let Some(StaleEvent::Stale(stale)) = tap.next_event().await else {
+            panic!("no stale event");
+        };
+        assert_eq!(stale.symbol, Symbol::BTCUSDT);
+        assert_eq!(stale.event_type, "bookTicker");
+        assert_eq!(stale.silent_for, Duration::from_secs(6));
+    }
+}
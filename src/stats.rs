@@ -0,0 +1,375 @@
+//! Rolling window statistics from the trade stream.
+//!
+//! [`Stats`] holds the window for one symbol, [`StatsTracker`] routes every trade
+//! [`Message`] to the [`Stats`] of its symbol.
+//!
+//! Windows are relative to the time of the latest trade, not the local clock.
+//!
+//! [`SessionTracker`] keeps the open, high, low, last and volume of the current session
+//! per symbol, e.g. the current UTC day.
+
+use std::collections::{HashMap, VecDeque};
+use std::time::Duration;
+
+use chrono::{DateTime, LocalResult, NaiveDate, NaiveTime, Offset, TimeDelta, TimeZone, Utc};
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+
+use crate::aggregate::Candle;
+use crate::messages::TradeEvent;
+use crate::{Message, Symbol};
+
+/// Statistics over the trades of one symbol made during the last `window`.
+#[derive(Debug, Clone)]
+pub struct Stats {
+    window: u64,
+    /// time, price and quantity of each trade in the window
+    trades: VecDeque<(u64, Decimal, Decimal)>,
+}
+
+impl Stats {
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window: window.as_millis() as u64,
+            trades: VecDeque::new(),
+        }
+    }
+
+    pub fn push(&mut self, trade: &impl TradeEvent) {
+        self.update(trade.price(), trade.quantity(), trade.trade_time())
+    }
+
+    /// Add a trade at `price` and `qty` made at `time`, see [`Stats::push()`].
+    pub fn update(&mut self, price: Decimal, qty: Decimal, time: u64) {
+        self.trades.push_back((time, price, qty));
+        while let Some((t, ..)) = self.trades.front() {
+            if t + self.window > time {
+                break;
+            }
+            self.trades.pop_front();
+        }
+    }
+
+    /// Number of trades in the window.
+    pub fn trades(&self) -> usize {
+        self.trades.len()
+    }
+
+    /// Base asset volume traded in the window.
+    pub fn volume(&self) -> Decimal {
+        self.trades.iter().map(|(_, _, q)| *q).sum()
+    }
+
+    /// Log returns between consecutive trades in the window.
+    pub fn log_returns(&self) -> impl Iterator<Item = f64> + '_ {
+        let prices = self.trades.iter().filter_map(|(_, p, _)| p.to_f64());
+        prices
+            .clone()
+            .zip(prices.skip(1))
+            .map(|(from, to)| (to / from).ln())
+    }
+
+    /// Log return from the first to the last trade in the window.
+    pub fn window_return(&self) -> Option<f64> {
+        let first = self.trades.front()?.1.to_f64()?;
+        let last = self.trades.back()?.1.to_f64()?;
+        Some((last / first).ln())
+    }
+
+    /// Realized volatility, the square root of the sum of squared log returns in the window.
+    ///
+    /// `None` if there are less than two trades.
+    pub fn realized_volatility(&self) -> Option<f64> {
+        if self.trades.len() < 2 {
+            return None;
+        }
+        Some(self.log_returns().map(|r| r * r).sum::<f64>().sqrt())
+    }
+
+    /// Trades per second over the window.
+    pub fn trade_rate(&self) -> f64 {
+        self.trades.len() as f64 * 1000.0 / self.window as f64
+    }
+
+    /// Mean base asset quantity per trade.
+    pub fn average_trade_size(&self) -> Option<Decimal> {
+        if self.trades.is_empty() {
+            return None;
+        }
+        Some(self.volume() / Decimal::from(self.trades.len()))
+    }
+}
+
+/// Keeps a [`Stats`] for every symbol it receives trades for.
+#[derive(Debug, Clone)]
+pub struct StatsTracker {
+    window: Duration,
+    stats: HashMap<Symbol, Stats>,
+}
+
+impl StatsTracker {
+    /// Track statistics over `window` for every symbol.
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window,
+            stats: HashMap::new(),
+        }
+    }
+
+    /// Update the statistics with `msg`, other messages than trades are ignored.
+    pub fn push_message(&mut self, msg: &Message) {
+        match msg {
+            Message::AggTrade(t) => self.push(t),
+            Message::Trade(t) => self.push(t),
+            _ => {}
+        }
+    }
+
+    pub fn push(&mut self, trade: &impl TradeEvent) {
+        self.stats
+            .entry(trade.symbol().clone())
+            .or_insert_with(|| Stats::new(self.window))
+            .push(trade);
+    }
+
+    /// Statistics for `symbol`, `None` if no trades have been received for it.
+    pub fn stats(&self, symbol: &Symbol) -> Option<&Stats> {
+        self.stats.get(symbol)
+    }
+}
+
+/// Session statistics per symbol, sessions are one day long.
+///
+/// The session of each symbol is a [`Candle`] spanning the session, `close` is the last price.
+/// A trade in a later session starts a new one.
+#[derive(Debug, Clone)]
+pub struct SessionTracker<Tz: TimeZone = Utc> {
+    tz: Tz,
+    /// local time in `tz` the sessions start at
+    start: NaiveTime,
+    sessions: HashMap<Symbol, Candle>,
+}
+
+impl Default for SessionTracker {
+    fn default() -> Self {
+        Self::utc()
+    }
+}
+
+impl SessionTracker {
+    /// Sessions from midnight to midnight UTC.
+    pub fn utc() -> Self {
+        Self::new(Utc, NaiveTime::MIN)
+    }
+}
+
+impl<Tz: TimeZone> SessionTracker<Tz> {
+    /// Sessions starting at `start` each day, local time in `tz`, e.g. a `chrono_tz::Tz`.
+    ///
+    /// Sessions follow daylight saving time, the sessions across a change are an hour shorter
+    /// or longer. A start skipped by the change is moved forward by it.
+    pub fn new(tz: Tz, start: NaiveTime) -> Self {
+        Self {
+            tz,
+            start,
+            sessions: HashMap::new(),
+        }
+    }
+
+    /// Update with `msg`, other messages than trades are ignored.
+    ///
+    /// Returns the previous session of the symbol if `msg` starts a new one.
+    pub fn push_message(&mut self, msg: &Message) -> Option<Candle> {
+        match msg {
+            Message::AggTrade(t) => self.push(t),
+            Message::Trade(t) => self.push(t),
+            _ => None,
+        }
+    }
+
+    pub fn push(&mut self, trade: &impl TradeEvent) -> Option<Candle> {
+        let (price, qty, time) = (trade.price(), trade.quantity(), trade.trade_time());
+
+        if let Some(session) = self.sessions.get_mut(trade.symbol()) {
+            if time <= session.close_time {
+                session.update(price, qty);
+                return None;
+            }
+        }
+
+        let (open_time, close_time) = self.bounds(time as i64);
+        let session = Candle::new(open_time as u64, close_time as u64, price, qty);
+        self.sessions.insert(trade.symbol().clone(), session)
+    }
+
+    /// Open and close time of the session `time` is in, in milliseconds.
+    fn bounds(&self, time: i64) -> (i64, i64) {
+        let utc = DateTime::from_timestamp_millis(time).expect("trade time in range");
+        let mut date = utc.with_timezone(&self.tz).date_naive();
+        let mut start = self.start_of(date);
+        while time < start {
+            date = date.pred_opt().expect("date in range");
+            start = self.start_of(date);
+        }
+        let mut end = self.start_of(date.succ_opt().expect("date in range"));
+        while time >= end {
+            date = date.succ_opt().expect("date in range");
+            start = end;
+            end = self.start_of(date.succ_opt().expect("date in range"));
+        }
+        (start, end - 1)
+    }
+
+    /// Start of the session on `date`, in milliseconds.
+    fn start_of(&self, date: NaiveDate) -> i64 {
+        let local = date.and_time(self.start);
+        match self.tz.from_local_datetime(&local) {
+            LocalResult::Single(start) | LocalResult::Ambiguous(start, _) => {
+                start.timestamp_millis()
+            }
+            LocalResult::None => {
+                // skipped by the clock, taken at the offset before the change
+                let before = self
+                    .tz
+                    .offset_from_utc_datetime(&(local - TimeDelta::days(1)));
+                let offset = TimeDelta::seconds(before.fix().local_minus_utc().into());
+                (local - offset).and_utc().timestamp_millis()
+            }
+        }
+    }
+
+    /// Current session of `symbol`.
+    pub fn session(&self, symbol: &Symbol) -> Option<&Candle> {
+        self.sessions.get(symbol)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::messages::Trade;
+    use crate::test_util::{self, dec};
+
+    #[test]
+    fn rolling_window() {
+        let mut stats = Stats::new(Duration::from_secs(2));
+        assert_eq!(stats.realized_volatility(), None);
+        assert_eq!(stats.average_trade_size(), None);
+
+        stats.update(dec("100"), dec("1"), 0);
+        stats.update(dec("110"), dec("2"), 1_000);
+        stats.update(dec("100"), dec("3"), 1_500);
+
+        assert_eq!(stats.trades(), 3);
+        assert_eq!(stats.trade_rate(), 1.5);
+        assert_eq!(stats.average_trade_size(), Some(dec("2")));
+
+        let returns: Vec<f64> = stats.log_returns().collect();
+        assert_eq!(returns.len(), 2);
+        assert!((returns[0] + returns[1]).abs() < 1e-12);
+        assert!(stats.window_return().unwrap().abs() < 1e-12);
+
+        let expected = (2.0 * 1.1f64.ln().powi(2)).sqrt();
+        assert!((stats.realized_volatility().unwrap() - expected).abs() < 1e-12);
+
+        // first trade leaves the window
+        stats.update(dec("100"), dec("1"), 2_000);
+        assert_eq!(stats.trades(), 3);
+        assert_eq!(stats.volume(), dec("6"));
+    }
+
+    #[test]
+    fn tracker_per_symbol() {
+        let trade = |symbol: Symbol, price: &str| {
+            Message::Trade(Trade {
+                price: dec(price),
+                ..test_util::trade(symbol, 1)
+            })
+        };
+
+        let mut tracker = StatsTracker::new(Duration::from_secs(60));
+        tracker.push_message(&trade(Symbol::BTCUSDT, "100"));
+        tracker.push_message(&trade(Symbol::BTCUSDT, "101"));
+        tracker.push_message(&trade(Symbol::ETHUSDT, "10"));
+
+        assert_eq!(tracker.stats(&Symbol::BTCUSDT).unwrap().trades(), 2);
+        assert_eq!(tracker.stats(&Symbol::ETHUSDT).unwrap().trades(), 1);
+        assert!(tracker.stats(&Symbol::BNBUSDT).is_none());
+    }
+
+    #[test]
+    fn sessions() {
+        let trade = |price: &str, trade_time: u64| Trade {
+            event_time: trade_time,
+            symbol: Symbol::BTCUSDT,
+            trade_id: 1,
+            price: dec(price),
+            quantity: dec("1"),
+            trade_time,
+            is_market_maker: false,
+        };
+
+        // 2024-06-01T00:00:00Z
+        let midnight = 1_717_200_000_000;
+        let hour = 3_600_000;
+
+        let mut utc = SessionTracker::default();
+        // sessions at 08:00 in UTC+02:00, 06:00 UTC
+        let mut custom = SessionTracker::new(
+            chrono::FixedOffset::east_opt(2 * 3600).unwrap(),
+            NaiveTime::from_hms_opt(8, 0, 0).unwrap(),
+        );
+
+        for (price, time) in [("10", 1), ("12", 5 * hour), ("9", 7 * hour)] {
+            assert_eq!(utc.push(&trade(price, midnight + time)), None);
+        }
+        let session = utc.session(&Symbol::BTCUSDT).unwrap();
+        assert_eq!(
+            (session.open, session.high, session.low),
+            (dec("10"), dec("12"), dec("9"))
+        );
+        assert_eq!((session.close, session.trades), (dec("9"), 3));
+        assert_eq!(session.open_time, midnight);
+
+        let finished = utc.push(&trade("11", midnight + 24 * hour)).unwrap();
+        assert_eq!(finished.close_time, midnight + 24 * hour - 1);
+
+        assert_eq!(custom.push(&trade("10", midnight + hour)), None);
+        let finished = custom.push(&trade("12", midnight + 6 * hour)).unwrap();
+        assert_eq!(finished.open_time, midnight - 18 * hour);
+        assert_eq!(
+            custom.session(&Symbol::BTCUSDT).unwrap().open_time,
+            midnight + 6 * hour
+        );
+    }
+
+    #[test]
+    fn sessions_follow_daylight_saving_time() {
+        let trade = |trade_time: DateTime<Utc>| Trade {
+            trade_time: trade_time.timestamp_millis() as u64,
+            ..test_util::trade(Symbol::BTCUSDT, 1)
+        };
+        let at = |day, hour, min| Utc.with_ymd_and_hms(2024, 3, day, hour, min, 0).unwrap();
+        let ms = |time: DateTime<Utc>| time.timestamp_millis() as u64;
+
+        // New York moves from UTC-5 to UTC-4 on 2024-03-10
+        let five_pm = NaiveTime::from_hms_opt(17, 0, 0).unwrap();
+        let mut new_york = SessionTracker::new(chrono_tz::America::New_York, five_pm);
+        assert_eq!(new_york.push(&trade(at(10, 20, 30))), None);
+        let session = new_york.session(&Symbol::BTCUSDT).unwrap();
+        assert_eq!(session.open_time, ms(at(9, 22, 0)));
+        assert_eq!(session.close_time, ms(at(10, 21, 0)) - 1);
+        let finished = new_york.push(&trade(at(10, 21, 0))).unwrap();
+        assert_eq!(finished.close_time, ms(at(10, 21, 0)) - 1);
+        let session = new_york.session(&Symbol::BTCUSDT).unwrap();
+        assert_eq!(session.close_time, ms(at(11, 21, 0)) - 1);
+
+        // 02:30 does not exist in Berlin on 2024-03-31, the session starts at 03:30 CEST
+        let half_past_two = NaiveTime::from_hms_opt(2, 30, 0).unwrap();
+        let mut berlin = SessionTracker::new(chrono_tz::Europe::Berlin, half_past_two);
+        berlin.push(&trade(at(31, 1, 0)));
+        let session = berlin.session(&Symbol::BTCUSDT).unwrap();
+        assert_eq!(session.open_time, ms(at(30, 1, 30)));
+        assert_eq!(session.close_time, ms(at(31, 1, 30)) - 1);
+    }
+}
@@ -0,0 +1,114 @@
+//! Reference counted subscriptions, so streams shared by several subscribers are subscribed
+//! once and unsubscribed when the last subscriber leaves.
+
+use std::collections::HashMap;
+
+/// Number of subscribers of each stream, by stream name, e.g. `btcusdt@trade`.
+#[derive(Debug, Default)]
+pub(crate) struct Subscriptions {
+    subscribers: HashMap<String, usize>,
+}
+
+impl Subscriptions {
+    /// Add a subscriber to `streams`, returns the streams without other subscribers.
+    pub(crate) fn add(&mut self, streams: &[String]) -> Vec<String> {
+        let mut new = Vec::new();
+        for stream in streams {
+            let subscribers = self.subscribers.entry(stream.clone()).or_default();
+            *subscribers += 1;
+            if *subscribers == 1 {
+                new.push(stream.clone());
+            }
+        }
+        new
+    }
+
+    /// Remove a subscriber from `streams`, returns the streams without subscribers left.
+    pub(crate) fn remove(&mut self, streams: &[String]) -> Vec<String> {
+        let mut unused = Vec::new();
+        for stream in streams {
+            if let Some(subscribers) = self.subscribers.get_mut(stream) {
+                *subscribers -= 1;
+                if *subscribers == 0 {
+                    self.subscribers.remove(stream);
+                    unused.push(stream.clone());
+                }
+            }
+        }
+        unused
+    }
+
+    /// Remove a subscriber from `streams`, returns the streams without subscribers left.
+    ///
+    /// Unlike [`Subscriptions::remove()`], the streams stay listed until they are confirmed.
+    pub(crate) fn release(&mut self, streams: &[String]) -> Vec<String> {
+        let mut unused = Vec::new();
+        for stream in streams {
+            if let Some(subscribers) = self.subscribers.get_mut(stream).filter(|n| **n > 0) {
+                *subscribers -= 1;
+                if *subscribers == 0 {
+                    unused.push(stream.clone());
+                }
+            }
+        }
+        unused
+    }
+
+    /// Stop listing the released `streams`, unless they were subscribed again since.
+    pub(crate) fn confirm(&mut self, streams: &[String]) {
+        for stream in streams {
+            if self.subscribers.get(stream) == Some(&0) {
+                self.subscribers.remove(stream);
+            }
+        }
+    }
+
+    /// Whether `stream` has subscribers.
+    pub(crate) fn is_active(&self, stream: &str) -> bool {
+        self.subscribers.get(stream).is_some_and(|&n| n > 0)
+    }
+
+    /// The streams with subscribers, and released streams not confirmed yet, sorted.
+    pub(crate) fn streams(&self) -> Vec<String> {
+        let mut streams: Vec<String> = self.subscribers.keys().cloned().collect();
+        streams.sort_unstable();
+        streams
+    }
+
+    #[cfg_attr(not(feature = "tokio"), allow(dead_code))]
+    pub(crate) fn is_empty(&self) -> bool {
+        self.subscribers.is_empty()
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.subscribers.clear();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn shared_subscriptions() {
+        let mut subscriptions = Subscriptions::default();
+        let streams = vec!["btcusdt@trade".to_string(), "ethbtc@trade".to_string()];
+
+        assert_eq!(subscriptions.add(&streams), streams);
+        assert!(subscriptions.add(&streams[..1]).is_empty());
+        assert_eq!(subscriptions.streams(), streams);
+        assert_eq!(subscriptions.remove(&streams), vec!["ethbtc@trade"]);
+        assert_eq!(subscriptions.remove(&streams), vec!["btcusdt@trade"]);
+        assert!(subscriptions.remove(&streams).is_empty());
+        assert!(subscriptions.streams().is_empty());
+
+        subscriptions.add(&streams);
+        assert_eq!(subscriptions.release(&streams), streams);
+        assert!(!subscriptions.is_active("btcusdt@trade"));
+        assert_eq!(subscriptions.streams(), streams);
+        // subscribed again before the confirmation
+        assert_eq!(subscriptions.add(&streams[..1]), &streams[..1]);
+        subscriptions.confirm(&streams);
+        assert_eq!(subscriptions.streams(), &streams[..1]);
+    }
+}
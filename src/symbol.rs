@@ -7,6 +7,7 @@ use crate::{Feed, SubscribeInfo};
 /// All available symbols on binance, updated 2024-11-17
 /// Based on this [list](https://support.binance.us/hc/en-us/articles/360049417674-List-of-supported-cryptocurrencies) 
 #[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Ord, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub enum Symbol {
     AAVEUSDT,
     ACHUSDT,
@@ -367,6 +368,20 @@ impl std::fmt::Display for Symbol {
     }
 }
 
+/// Parse a symbol from its name in any case, e.g. `btcusdt` or `BTCUSDT`.
+impl std::str::FromStr for Symbol {
+    type Err = crate::Error;
+
+    fn from_str(s: &str) -> crate::Result<Self> {
+        let name = s.to_lowercase();
+        subscribe_msg_all_symbols(Feed::Trade)
+            .into_iter()
+            .map(|info| info.symbol)
+            .find(|symbol| symbol.to_string() == name)
+            .ok_or_else(|| crate::Error::Custom(format!("unknown symbol {s}")))
+    }
+}
+
 pub fn subscribe_msg_all_symbols(feed: Feed) -> Vec<SubscribeInfo> {
     vec![
         SubscribeInfo::new(Symbol::AAVEUSDT, feed.clone()),
@@ -0,0 +1,208 @@
+//! In-memory tape of recent trades.
+//!
+//! [`Tape`] keeps the latest trades of one symbol, bounded by count and optionally age.
+//! [`TradeTape`] keeps a [`Tape`] per symbol from the trade [`Message`]s it receives.
+//!
+//! Query durations are relative to the latest trade on the tape.
+
+use std::collections::{HashMap, VecDeque};
+use std::time::Duration;
+
+use rust_decimal::Decimal;
+
+use crate::messages::TradeEvent;
+use crate::{Message, Symbol};
+
+/// A trade on the [`Tape`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TapeEntry {
+    /// Trade time in milliseconds since epoch.
+    pub time: u64,
+    pub price: Decimal,
+    pub quantity: Decimal,
+    pub is_market_maker: bool,
+}
+
+/// Ring buffer of the latest trades of one symbol, oldest first.
+#[derive(Debug, Clone)]
+pub struct Tape {
+    capacity: usize,
+    max_age: Option<u64>,
+    trades: VecDeque<TapeEntry>,
+}
+
+impl Tape {
+    /// Keep at most `capacity` trades, at least the latest one, and if set, only trades
+    /// younger than `max_age`.
+    pub fn new(capacity: usize, max_age: Option<Duration>) -> Self {
+        let capacity = capacity.max(1);
+        Self {
+            capacity,
+            max_age: max_age.map(|d| d.as_millis() as u64),
+            trades: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    pub fn push(&mut self, trade: &impl TradeEvent) {
+        self.push_entry(TapeEntry {
+            time: trade.trade_time(),
+            price: trade.price(),
+            quantity: trade.quantity(),
+            is_market_maker: trade.is_market_maker(),
+        })
+    }
+
+    pub fn push_entry(&mut self, entry: TapeEntry) {
+        let time = entry.time;
+        if self.trades.len() == self.capacity {
+            self.trades.pop_front();
+        }
+        self.trades.push_back(entry);
+
+        if let Some(max_age) = self.max_age {
+            while self
+                .trades
+                .front()
+                .is_some_and(|t| t.time + max_age <= time)
+            {
+                self.trades.pop_front();
+            }
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.trades.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.trades.is_empty()
+    }
+
+    /// The latest trade.
+    pub fn last(&self) -> Option<&TapeEntry> {
+        self.trades.back()
+    }
+
+    /// All trades on the tape, oldest first.
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = &TapeEntry> {
+        self.trades.iter()
+    }
+
+    /// Trades made from `from` up to and including `to`, in milliseconds since epoch.
+    pub fn range(&self, from: u64, to: u64) -> impl Iterator<Item = &TapeEntry> {
+        let start = self.trades.partition_point(|t| t.time < from);
+        let end = self.trades.partition_point(|t| t.time <= to);
+        self.trades.range(start..end.max(start))
+    }
+
+    /// Trades made during the last `duration`, e.g. the last 5 seconds.
+    pub fn within(&self, duration: Duration) -> impl Iterator<Item = &TapeEntry> {
+        let to = self.last().map(|t| t.time).unwrap_or(0);
+        let from = (to + 1).saturating_sub(duration.as_millis() as u64);
+        self.range(from, to)
+    }
+
+    /// Largest trade by quantity during the last `duration`, the first one if several are equal.
+    pub fn largest(&self, duration: Duration) -> Option<&TapeEntry> {
+        self.within(duration)
+            .reduce(|max, t| if t.quantity > max.quantity { t } else { max })
+    }
+}
+
+/// A [`Tape`] for every symbol it receives trades for.
+#[derive(Debug, Clone)]
+pub struct TradeTape {
+    capacity: usize,
+    max_age: Option<Duration>,
+    tapes: HashMap<Symbol, Tape>,
+}
+
+impl TradeTape {
+    /// See [`Tape::new()`], the limits apply to each symbol.
+    pub fn new(capacity: usize, max_age: Option<Duration>) -> Self {
+        Self {
+            capacity,
+            max_age,
+            tapes: HashMap::new(),
+        }
+    }
+
+    /// Add `msg` to the tape of its symbol, other messages than trades are ignored.
+    pub fn push_message(&mut self, msg: &Message) {
+        match msg {
+            Message::AggTrade(t) => self.push(t),
+            Message::Trade(t) => self.push(t),
+            _ => {}
+        }
+    }
+
+    pub fn push(&mut self, trade: &impl TradeEvent) {
+        self.tapes
+            .entry(trade.symbol().clone())
+            .or_insert_with(|| Tape::new(self.capacity, self.max_age))
+            .push(trade);
+    }
+
+    pub fn tape(&self, symbol: &Symbol) -> Option<&Tape> {
+        self.tapes.get(symbol)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn entry(time: u64, qty: u64) -> TapeEntry {
+        TapeEntry {
+            time,
+            price: Decimal::ONE,
+            quantity: Decimal::from(qty),
+            is_market_maker: false,
+        }
+    }
+
+    #[test]
+    fn bounded_by_capacity_and_age() {
+        let mut tape = Tape::new(3, None);
+        for t in 0..5 {
+            tape.push_entry(entry(t, 1));
+        }
+        assert_eq!(
+            tape.iter().map(|t| t.time).collect::<Vec<_>>(),
+            vec![2, 3, 4]
+        );
+
+        let mut tape = Tape::new(100, Some(Duration::from_secs(1)));
+        tape.push_entry(entry(0, 1));
+        tape.push_entry(entry(500, 1));
+        tape.push_entry(entry(1_000, 1));
+        assert_eq!(tape.len(), 2);
+        assert_eq!(tape.last(), Some(&entry(1_000, 1)));
+
+        let mut tape = Tape::new(0, None);
+        tape.push_entry(entry(0, 1));
+        tape.push_entry(entry(1, 1));
+        assert_eq!(tape.len(), 1);
+    }
+
+    #[test]
+    fn time_range_queries() {
+        let mut tape = Tape::new(100, None);
+        assert_eq!(tape.largest(Duration::from_secs(60)), None);
+
+        tape.push_entry(entry(1_000, 9));
+        tape.push_entry(entry(5_000, 2));
+        tape.push_entry(entry(7_000, 5));
+        tape.push_entry(entry(9_000, 5));
+        tape.push_entry(entry(10_000, 1));
+
+        assert_eq!(tape.range(5_000, 9_000).count(), 3);
+        assert_eq!(tape.range(9_500, 9_600).count(), 0);
+        assert_eq!(tape.within(Duration::from_secs(5)).count(), 3);
+        assert_eq!(tape.largest(Duration::from_secs(5)), Some(&entry(7_000, 5)));
+        assert_eq!(
+            tape.largest(Duration::from_secs(60)),
+            Some(&entry(1_000, 9))
+        );
+    }
+}
@@ -0,0 +1,555 @@
+//! Test support, requires the `test-util` feature.
+//!
+//! [`MockServer`] is a local websocket server speaking the Binance protocol, to test
+//! connection logic without the internet.
+//!
+//! Faults are scripted with [`Action`]s like any payload, e.g. [`Action::Disconnect`] or
+//! [`Action::rate_limit_close()`], to cover reconnection and error handling.
+//!
+//! [`FakeSource`] skips the socket, for unit tests of code generic over
+//! [`MarketDataSource`](crate::MarketDataSource).
+//!
+//! [`dec()`], [`ticker()`], [`trade()`] and [`agg_trade()`] build test data.
+//!
+//! ```no_run
+//! use binance_api_async::test_util::{Action, MockServer};
+//! use binance_api_async::{Feed, SubscribeInfo, Symbol};
+//!
+//! # async fn test() {
+//! let server = MockServer::start().await.unwrap();
+//! server.script([Action::Text(r#"{"u":1,"s":"BTCUSDT","b":"1","B":"1","a":"2","A":"1"}"#.into())]);
+//!
+//! let mut api = server.api();
+//! api.connect().await.unwrap();
+//! api.subscribe(&[SubscribeInfo::new(Symbol::BTCUSDT, Feed::BookTicker)], None).await.unwrap();
+//! let ack = api.next_message().await;
+//! let ticker = api.next_message().await;
+//! # }
+//! ```
+
+use std::collections::VecDeque;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use futures::{SinkExt, StreamExt};
+use rust_decimal::Decimal;
+use serde_json::json;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::broadcast;
+use tokio::task::JoinHandle;
+use tokio_tungstenite::tungstenite;
+use tungstenite::handshake::server::{Request as Handshake, Response};
+use tungstenite::http::HeaderMap;
+use tungstenite::protocol::{frame::coding::CloseCode, CloseFrame};
+
+#[cfg(feature = "book-ticker")]
+use crate::messages::BookTicker;
+#[cfg(feature = "trade")]
+use crate::messages::{AggTrade, Trade};
+use crate::request::{Method, Request};
+#[cfg(any(feature = "trade", feature = "book-ticker"))]
+use crate::Symbol;
+use crate::{MarketDataSource, Message, SubscribeInfo};
+
+/// Something the [`MockServer`] sends to its clients.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Action {
+    /// A text frame, e.g. a payload in the Binance wire format.
+    Text(String),
+    /// A binary frame, e.g. compressed json.
+    Binary(Vec<u8>),
+    /// A ping frame with this payload.
+    Ping(Vec<u8>),
+    /// Wait before the next action, without reading from the client,
+    /// so its pings are answered late.
+    Delay(Duration),
+    /// Drop the connection without a close frame.
+    Disconnect,
+    /// Send a close frame and drop the connection.
+    Close { code: u16, reason: String },
+}
+
+impl Action {
+    /// A text frame with `msg` serialized as json, the field names of Binance without the
+    /// event type `e`, which [`Message`] parses back the same.
+    pub fn message(msg: &Message) -> Self {
+        Action::Text(serde_json::to_string(msg).expect("messages serialize to json"))
+    }
+
+    /// A text frame with truncated json.
+    pub fn malformed() -> Self {
+        Action::Text(r#"{"e":"aggTrade","E":1717200000100,"s":"BTCU"#.to_string())
+    }
+
+    /// The close at the end of the 24 hours a connection is valid.
+    pub fn expired_close() -> Self {
+        Action::Close {
+            code: 1001,
+            reason: "Connection expired after 24 hours".to_string(),
+        }
+    }
+
+    /// The close after sending more than 5 requests per second.
+    pub fn rate_limit_close() -> Self {
+        Action::Close {
+            code: 1008,
+            reason: "Too many requests".to_string(),
+        }
+    }
+}
+
+/// What the clients of a [`MockServer`] did.
+#[derive(Debug, Default)]
+struct State {
+    connections: usize,
+    subscriptions: Vec<String>,
+    pongs: Vec<Vec<u8>>,
+    /// headers of the last handshake
+    headers: HeaderMap,
+    script: Vec<Action>,
+}
+
+/// Local websocket server speaking the Binance protocol, see the [module](self) documentation.
+///
+/// Subscription requests are acknowledged like Binance does, pings are answered and pongs
+/// recorded. Clients are sent the [`MockServer::script()`] once subscribed, and every
+/// [`MockServer::send()`] while connected.
+#[derive(Debug)]
+pub struct MockServer {
+    addr: SocketAddr,
+    state: Arc<Mutex<State>>,
+    live: broadcast::Sender<Action>,
+    task: JoinHandle<()>,
+}
+
+impl MockServer {
+    /// Start listening on a free local port.
+    pub async fn start() -> crate::Result<Self> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        let state = Arc::new(Mutex::new(State::default()));
+        let (live, _) = broadcast::channel(1024);
+
+        let task = {
+            let state = state.clone();
+            let live = live.clone();
+            tokio::spawn(async move {
+                while let Ok((stream, _)) = listener.accept().await {
+                    state.lock().unwrap().connections += 1;
+                    tokio::spawn(serve(stream, state.clone(), live.subscribe()));
+                }
+            })
+        };
+
+        Ok(Self {
+            addr,
+            state,
+            live,
+            task,
+        })
+    }
+
+    /// Url to connect to, a `ws://` url, see
+    /// [`crate::BinanceApiBuilder::allow_plaintext()`].
+    pub fn url(&self) -> String {
+        format!("ws://{}/ws", self.addr)
+    }
+
+    /// A client of the server, not connected yet.
+    pub fn api(&self) -> crate::BinanceApi {
+        crate::BinanceApi::builder()
+            .url(&self.url())
+            .allow_plaintext(true)
+            .build()
+    }
+
+    /// Actions played to every client after its first subscription is acknowledged.
+    pub fn script(&self, actions: impl IntoIterator<Item = Action>) {
+        self.state.lock().unwrap().script = actions.into_iter().collect();
+    }
+
+    /// Play `action` to the connected clients.
+    pub fn send(&self, action: Action) {
+        // an error only means that no client is connected
+        let _ = self.live.send(action);
+    }
+
+    /// Number of connections accepted so far.
+    pub fn connections(&self) -> usize {
+        self.state.lock().unwrap().connections
+    }
+
+    /// Streams the clients are subscribed to, e.g. `btcusdt@aggTrade`.
+    pub fn subscriptions(&self) -> Vec<String> {
+        self.state.lock().unwrap().subscriptions.clone()
+    }
+
+    /// Payloads of the pongs received from the clients.
+    pub fn pongs(&self) -> Vec<Vec<u8>> {
+        self.state.lock().unwrap().pongs.clone()
+    }
+
+    /// Headers of the last websocket handshake, e.g. to check the `User-Agent` of a client.
+    pub fn handshake_headers(&self) -> HeaderMap {
+        self.state.lock().unwrap().headers.clone()
+    }
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+async fn serve(stream: TcpStream, state: Arc<Mutex<State>>, mut live: broadcast::Receiver<Action>) {
+    // the error response is the type tungstenite asks for
+    #[allow(clippy::result_large_err)]
+    let record = |request: &Handshake, response: Response| {
+        state.lock().unwrap().headers = request.headers().clone();
+        Ok(response)
+    };
+    let Ok(mut ws) = tokio_tungstenite::accept_hdr_async(stream, record).await else {
+        return;
+    };
+    let mut subscribed = false;
+
+    loop {
+        let actions = tokio::select! {
+            frame = ws.next() => match frame {
+                Some(Ok(tungstenite::Message::Text(text))) => {
+                    let reply = match Request::parse(&text) {
+                        Ok(request) => handle(request, &state),
+                        Err(reply) => reply,
+                    };
+                    let mut actions = vec![Action::Text(reply.to_string())];
+                    if !subscribed && !state.lock().unwrap().subscriptions.is_empty() {
+                        subscribed = true;
+                        actions.extend(state.lock().unwrap().script.iter().cloned());
+                    }
+                    actions
+                }
+                Some(Ok(tungstenite::Message::Pong(data))) => {
+                    state.lock().unwrap().pongs.push(data);
+                    continue;
+                }
+                Some(Ok(tungstenite::Message::Close(_))) | Some(Err(_)) | None => return,
+                // pings are answered by tungstenite
+                Some(Ok(_)) => continue,
+            },
+            action = live.recv() => match action {
+                Ok(action) => vec![action],
+                Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => return,
+            },
+        };
+
+        for action in actions {
+            let frame = match action {
+                Action::Text(text) => tungstenite::Message::Text(text),
+                Action::Binary(data) => tungstenite::Message::Binary(data),
+                Action::Ping(data) => tungstenite::Message::Ping(data),
+                Action::Delay(delay) => {
+                    tokio::time::sleep(delay).await;
+                    continue;
+                }
+                Action::Disconnect => return,
+                Action::Close { code, reason } => {
+                    let frame = CloseFrame {
+                        code: CloseCode::from(code),
+                        reason: reason.into(),
+                    };
+                    let _ = ws.send(tungstenite::Message::Close(Some(frame))).await;
+                    return;
+                }
+            };
+            if ws.send(frame).await.is_err() {
+                return;
+            }
+        }
+    }
+}
+
+/// Apply a subscription request, returns the reply.
+fn handle(request: Request, state: &Mutex<State>) -> serde_json::Value {
+    let Request { method, params, id } = request;
+    let mut state = state.lock().unwrap();
+    match method {
+        Method::Subscribe => {
+            for stream in params {
+                if !state.subscriptions.contains(&stream) {
+                    state.subscriptions.push(stream);
+                }
+            }
+            json!({ "result": null, "id": id })
+        }
+        Method::Unsubscribe => {
+            state.subscriptions.retain(|s| !params.contains(s));
+            json!({ "result": null, "id": id })
+        }
+        Method::ListSubscriptions => json!({ "result": state.subscriptions, "id": id }),
+    }
+}
+
+#[derive(Debug, Default)]
+struct FakeState {
+    messages: VecDeque<Message>,
+    responses: VecDeque<Message>,
+    subscriptions: Vec<String>,
+}
+
+/// In memory [`MarketDataSource`], returns the messages pushed by the test.
+///
+/// Clones share the messages, so the test keeps a clone to push to and to inspect the
+/// subscriptions while the code under test reads. Subscriptions are answered with a
+/// [`Message::SubscribeSuccess`] before any pushed message, and
+/// [`FakeSource::next_message()`](MarketDataSource::next_message) returns `None` once every
+/// message was read.
+///
+/// ```no_run
+/// use binance_api_async::test_util::FakeSource;
+/// use binance_api_async::{MarketDataSource, Message};
+///
+/// # async fn test(ticker: Message) {
+/// let source = FakeSource::new();
+/// source.push(ticker.clone());
+///
+/// let mut reader = source.clone();
+/// assert_eq!(reader.next_message().await, Some(ticker));
+/// assert_eq!(reader.next_message().await, None);
+/// # }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct FakeSource {
+    state: Arc<Mutex<FakeState>>,
+}
+
+impl FakeSource {
+    /// A source without messages.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Queue `msg` after the messages pushed before.
+    pub fn push(&self, msg: Message) {
+        self.state.lock().unwrap().messages.push_back(msg);
+    }
+
+    /// Queue `msgs` after the messages pushed before.
+    pub fn extend(&self, msgs: impl IntoIterator<Item = Message>) {
+        self.state.lock().unwrap().messages.extend(msgs);
+    }
+
+    /// Number of messages not read yet, not counting the subscription responses.
+    pub fn pending(&self) -> usize {
+        self.state.lock().unwrap().messages.len()
+    }
+
+    /// Streams subscribed to, e.g. `btcusdt@aggTrade`.
+    pub fn subscriptions(&self) -> Vec<String> {
+        self.state.lock().unwrap().subscriptions.clone()
+    }
+}
+
+impl MarketDataSource for FakeSource {
+    async fn next_message(&mut self) -> Option<Message> {
+        let mut state = self.state.lock().unwrap();
+        match state.responses.pop_front() {
+            Some(response) => Some(response),
+            None => state.messages.pop_front(),
+        }
+    }
+
+    async fn subscribe(&mut self, symbols: &[SubscribeInfo], id: Option<u64>) -> crate::Result<()> {
+        let mut state = self.state.lock().unwrap();
+        for info in symbols {
+            let stream = info.stream_name();
+            if !state.subscriptions.contains(&stream) {
+                state.subscriptions.push(stream);
+            }
+        }
+        state.responses.push_back(Message::SubscribeSuccess {
+            result: None,
+            id: id.unwrap_or(1),
+        });
+        Ok(())
+    }
+
+    async fn unsubscribe(&mut self, symbols: Vec<SubscribeInfo>) -> crate::Result<()> {
+        let streams: Vec<String> = symbols.iter().map(SubscribeInfo::stream_name).collect();
+        let mut state = self.state.lock().unwrap();
+        let inactive: Vec<String> = streams
+            .iter()
+            .filter(|s| !state.subscriptions.contains(s))
+            .cloned()
+            .collect();
+        state.subscriptions.retain(|s| !streams.contains(s));
+        if inactive.is_empty() {
+            Ok(())
+        } else {
+            Err(crate::Error::NotSubscribed { streams: inactive })
+        }
+    }
+}
+
+/// `s` as a decimal, keeping its scale.
+pub fn dec(s: &str) -> Decimal {
+    Decimal::from_str_exact(s).expect("a decimal")
+}
+
+/// Book ticker of `symbol` with update id 1 and a quantity of 1 at `bid` and `ask`.
+///
+/// Other fields are set with struct update syntax, e.g.
+/// `BookTicker { update_id, ..ticker(Symbol::BTCUSDT, "1", "2") }`.
+#[cfg(feature = "book-ticker")]
+pub fn ticker(symbol: Symbol, bid: &str, ask: &str) -> BookTicker {
+    BookTicker {
+        update_id: 1,
+        symbol,
+        best_bid_price: dec(bid),
+        best_bid_qty: Decimal::ONE,
+        best_ask_price: dec(ask),
+        best_ask_qty: Decimal::ONE,
+    }
+}
+
+/// Trade `trade_id` of `symbol` at 1, with a quantity of 1 and event and trade times of 1.
+#[cfg(feature = "trade")]
+pub fn trade(symbol: Symbol, trade_id: u64) -> Trade {
+    Trade {
+        event_time: 1,
+        symbol,
+        trade_id,
+        price: Decimal::ONE,
+        quantity: Decimal::ONE,
+        trade_time: 1,
+        is_market_maker: false,
+    }
+}
+
+/// Aggregate trade `trade_id` of `symbol` like [`trade()`], of the single trade `trade_id`.
+#[cfg(feature = "trade")]
+pub fn agg_trade(symbol: Symbol, trade_id: u64) -> AggTrade {
+    AggTrade {
+        event_time: 1,
+        trade_id,
+        symbol,
+        price: Decimal::ONE,
+        quantity: Decimal::ONE,
+        first_trade_id: trade_id,
+        last_trade_id: trade_id,
+        trade_time: 1,
+        is_market_maker: false,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{Feed, SubscribeInfo, Symbol};
+    use rust_decimal::Decimal;
+
+    #[tokio::test]
+    async fn subscribe_and_receive() {
+        let ticker = Message::BookTicker(ticker(Symbol::BTCUSDT, "1", "2"));
+        let server = MockServer::start().await.unwrap();
+        server.script([Action::Ping(b"hi".to_vec()), Action::message(&ticker)]);
+
+        let mut api = server.api();
+        api.connect().await.unwrap();
+        api.subscribe(
+            &[SubscribeInfo::new(Symbol::BTCUSDT, Feed::BookTicker)],
+            Some(4),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(
+            api.next_message().await.unwrap(),
+            Message::SubscribeSuccess {
+                result: None,
+                id: 4
+            }
+        );
+        assert_eq!(api.next_message().await.unwrap(), ticker);
+        assert_eq!(server.connections(), 1);
+        assert_eq!(server.subscriptions(), ["btcusdt@bookTicker"]);
+
+        server.send(Action::message(&ticker));
+        assert_eq!(api.next_message().await.unwrap(), ticker);
+        // the pong was sent while reading the ticker, the server reads it concurrently
+        while server.pongs().is_empty() {
+            tokio::time::sleep(std::time::Duration::from_millis(1)).await;
+        }
+        assert_eq!(server.pongs(), [b"hi".to_vec()]);
+    }
+
+    #[tokio::test]
+    async fn scripted_faults() {
+        let ticker = Message::BookTicker(ticker(Symbol::BTCUSDT, "1", "2"));
+        let info = || [SubscribeInfo::new(Symbol::BTCUSDT, Feed::BookTicker)];
+
+        let server = MockServer::start().await.unwrap();
+        server.script([
+            Action::malformed(),
+            Action::Delay(Duration::from_millis(20)),
+            Action::message(&ticker),
+            Action::rate_limit_close(),
+        ]);
+        let mut api = server.api();
+        api.connect().await.unwrap();
+        api.subscribe(&info(), None).await.unwrap();
+        api.next_message().await.unwrap();
+
+        let started = std::time::Instant::now();
+        // the malformed payload is skipped
+        assert_eq!(api.next_message().await.unwrap(), ticker);
+        assert!(started.elapsed() >= Duration::from_millis(20));
+        assert_eq!(api.next_message().await, None);
+
+        server.script([Action::Disconnect]);
+        let mut api = server.api();
+        api.connect().await.unwrap();
+        api.subscribe(&info(), None).await.unwrap();
+        api.next_message().await.unwrap();
+        assert_eq!(api.next_message().await, None);
+        assert_eq!(server.connections(), 2);
+    }
+
+    #[tokio::test]
+    async fn fake_source() {
+        let ticker = Message::BookTicker(ticker(Symbol::BTCUSDT, "1", "2"));
+        let source = FakeSource::new();
+        source.extend([ticker.clone(), ticker.clone()]);
+
+        // a strategy reading best bids until the source ends
+        async fn best_bids(mut source: impl MarketDataSource) -> Vec<Decimal> {
+            let info = SubscribeInfo::new(Symbol::BTCUSDT, Feed::BookTicker);
+            source.subscribe(&[info], Some(7)).await.unwrap();
+            assert!(matches!(
+                source.next_message().await,
+                Some(Message::SubscribeSuccess { id: 7, .. })
+            ));
+            let mut bids = Vec::new();
+            while let Some(msg) = source.next_message().await {
+                if let Message::BookTicker(ticker) = msg {
+                    bids.push(ticker.best_bid_price);
+                }
+            }
+            bids
+        }
+
+        assert_eq!(best_bids(source.clone()).await, [Decimal::ONE; 2]);
+        assert_eq!(source.pending(), 0);
+        assert_eq!(source.subscriptions(), ["btcusdt@bookTicker"]);
+
+        let mut reader = source.clone();
+        let bbo = || vec![SubscribeInfo::new(Symbol::BTCUSDT, Feed::BookTicker)];
+        reader.unsubscribe(bbo()).await.unwrap();
+        assert!(source.subscriptions().is_empty());
+        assert!(matches!(
+            reader.unsubscribe(bbo()).await,
+            Err(crate::Error::NotSubscribed { streams }) if streams == ["btcusdt@bookTicker"]
+        ));
+    }
+}
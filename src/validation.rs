@@ -0,0 +1,213 @@
+//! Depth levels and delays Binance serves, checked before subscribing.
+//!
+//! Binance does not reject a subscribe to a depth stream with a delay it does not serve, the
+//! stream stays silent. [`BinanceApi::subscribe()`](crate::BinanceApi::subscribe) checks the
+//! feeds against [`DEPTH_RULES`] first and sends nothing if one is not served, check them
+//! yourself with [`validate()`]:
+//! ```
+//! use binance_api_async::validation::{validate, Market};
+//! use binance_api_async::{Delay, DepthLevel, Feed};
+//!
+//! let feed = Feed::PartialDepth { levels: DepthLevel::FIVE, delay: Delay::FIVEHUNDRED };
+//! assert!(validate(&feed, Market::Spot).is_err());
+//! assert!(validate(&feed, Market::UsdFutures).is_ok());
+//! ```
+//!
+//! Depth stream names without a delay have the default delay of their market, parse them with
+//! [`parse_feed()`] to get the delay of futures.
+
+use crate::{Delay, Error, Feed};
+
+/// Market the feeds are served on, the depth levels and delays differ.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum Market {
+    #[default]
+    Spot,
+    /// USDⓈ-M futures.
+    UsdFutures,
+}
+
+impl std::fmt::Display for Market {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Market::Spot => "spot",
+            Market::UsdFutures => "futures",
+        })
+    }
+}
+
+/// Parse a market from `spot` or `futures`, for USDⓈ-M futures.
+impl std::str::FromStr for Market {
+    type Err = Error;
+
+    fn from_str(s: &str) -> crate::Result<Self> {
+        match s {
+            "spot" => Ok(Market::Spot),
+            "futures" => Ok(Market::UsdFutures),
+            _ => Err(Error::Custom(format!("unknown market {s}"))),
+        }
+    }
+}
+
+/// Depth stream of a market, with the levels and delays it is served with.
+#[derive(Debug)]
+pub struct DepthRule {
+    pub market: Market,
+    /// [`Feed::PartialDepth`] if true, [`Feed::FullDepth`] otherwise.
+    pub partial: bool,
+    /// Levels of partial depths, empty for full depths.
+    pub levels: &'static [u8],
+    pub delays: &'static [Delay],
+}
+
+/// The depth streams of every market.
+///
+/// **Official docs:** [spot](https://developers.binance.com/docs/binance-spot-api-docs/web-socket-streams#partial-book-depth-streams),
+/// [futures](https://developers.binance.com/docs/derivatives/usds-margined-futures/websocket-market-streams/Partial-Book-Depth-Streams)
+pub const DEPTH_RULES: [DepthRule; 4] = [
+    DepthRule {
+        market: Market::Spot,
+        partial: true,
+        levels: &[5, 10, 20],
+        delays: &[Delay::ONEHUNDRED, Delay::ONETHOUSAND],
+    },
+    DepthRule {
+        market: Market::Spot,
+        partial: false,
+        levels: &[],
+        delays: &[Delay::ONEHUNDRED, Delay::ONETHOUSAND],
+    },
+    DepthRule {
+        market: Market::UsdFutures,
+        partial: true,
+        levels: &[5, 10, 20],
+        delays: &[
+            Delay::ONEHUNDRED,
+            Delay::TWOHUNDREDFIFTY,
+            Delay::FIVEHUNDRED,
+        ],
+    },
+    DepthRule {
+        market: Market::UsdFutures,
+        partial: false,
+        levels: &[],
+        delays: &[
+            Delay::ONEHUNDRED,
+            Delay::TWOHUNDREDFIFTY,
+            Delay::FIVEHUNDRED,
+        ],
+    },
+];
+
+/// Check that `feed` is served on `market`, [`Error::UnsupportedFeed`] if not.
+///
+/// [`Feed::ContinuousKline`] is served on futures only, other feeds without levels or delays
+/// are always valid.
+pub fn validate(feed: &Feed, market: Market) -> crate::Result<()> {
+    let (partial, levels, delay) = match feed {
+        Feed::PartialDepth { levels, delay } => (true, Some(levels.0), delay),
+        Feed::FullDepth { delay } => (false, None, delay),
+        Feed::ContinuousKline { .. } if market == Market::Spot => {
+            return Err(Error::UnsupportedFeed {
+                feed: feed.clone(),
+                market,
+            })
+        }
+        _ => return Ok(()),
+    };
+    let served = DEPTH_RULES
+        .iter()
+        .filter(|rule| rule.market == market && rule.partial == partial)
+        .any(|rule| levels.is_none_or(|l| rule.levels.contains(&l)) && rule.delays.contains(delay));
+    if served {
+        Ok(())
+    } else {
+        Err(Error::UnsupportedFeed {
+            feed: feed.clone(),
+            market,
+        })
+    }
+}
+
+/// The delay of depth streams named without one on `market`.
+pub fn default_delay(market: Market) -> Delay {
+    match market {
+        Market::Spot => Delay::ONETHOUSAND,
+        Market::UsdFutures => Delay::TWOHUNDREDFIFTY,
+    }
+}
+
+/// Parse the stream name `feed` of `market` and [`validate()`] it.
+///
+/// [`Feed`]'s `FromStr` gives depths without a delay the default of spot, here they have the
+/// [`default_delay()`] of `market`.
+pub fn parse_feed(feed: &str, market: Market) -> crate::Result<Feed> {
+    let mut parsed: Feed = feed.parse()?;
+    if !feed.contains("ms") {
+        if let Feed::PartialDepth { delay, .. } | Feed::FullDepth { delay } = &mut parsed {
+            *delay = default_delay(market);
+        }
+    }
+    validate(&parsed, market)?;
+    Ok(parsed)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::DepthLevel;
+
+    #[test]
+    fn depth_rules() {
+        let partial = |delay| Feed::PartialDepth {
+            levels: DepthLevel::TWENTY,
+            delay,
+        };
+        assert!(validate(&partial(Delay::ONETHOUSAND), Market::Spot).is_ok());
+        assert!(validate(&partial(Delay::ONETHOUSAND), Market::UsdFutures).is_err());
+        assert!(validate(&partial(Delay::TWOHUNDREDFIFTY), Market::UsdFutures).is_ok());
+        assert!(matches!(
+            validate(&partial(Delay::TWOHUNDREDFIFTY), Market::Spot),
+            Err(Error::UnsupportedFeed {
+                market: Market::Spot,
+                ..
+            })
+        ));
+        let full = Feed::FullDepth {
+            delay: Delay::FIVEHUNDRED,
+        };
+        assert!(validate(&full, Market::Spot).is_err());
+        assert!(validate(&Feed::Trade, Market::Spot).is_ok());
+    }
+
+    #[test]
+    fn default_delays() {
+        assert_eq!(
+            parse_feed("depth", Market::UsdFutures).unwrap(),
+            Feed::FullDepth {
+                delay: Delay::TWOHUNDREDFIFTY
+            }
+        );
+        assert_eq!(
+            parse_feed("depth5", Market::Spot).unwrap(),
+            Feed::PartialDepth {
+                levels: DepthLevel::FIVE,
+                delay: Delay::ONETHOUSAND
+            }
+        );
+        assert_eq!(
+            parse_feed("depth10@500ms", Market::UsdFutures).unwrap(),
+            Feed::PartialDepth {
+                levels: DepthLevel::TEN,
+                delay: Delay::FIVEHUNDRED
+            }
+        );
+        assert!(parse_feed("depth@500ms", Market::Spot).is_err());
+        assert_eq!("futures".parse::<Market>().unwrap(), Market::UsdFutures);
+        assert_eq!(Market::Spot.to_string().parse::<Market>().unwrap(), Market::Spot);
+        assert!("usd".parse::<Market>().is_err());
+        // the same stream name on both markets
+        let feed = parse_feed("depth20", Market::UsdFutures).unwrap();
+        assert_eq!(feed.to_string(), "depth20");
+    }
+}
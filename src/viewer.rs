@@ -0,0 +1,283 @@
+//! Terminal order book viewer of one symbol, with the `tui` feature.
+//!
+//! A [`BookView`] keeps the latest partial depth, the recent trades and the spread of one
+//! symbol from the messages it is pushed, and draws them as a [ratatui] widget: the price
+//! ladder, asks above bids, the trades tape, newest first, and a line of spread stats.
+//! ```no_run
+//! use binance_api_async::viewer::BookView;
+//! use binance_api_async::{BinanceApi, Delay, DepthLevel, Feed, SubscribeInfo, Symbol};
+//!
+//! # async fn run() -> Result<(), binance_api_async::Error> {
+//! let mut api = BinanceApi::new();
+//! api.connect().await?;
+//! let depth = Feed::PartialDepth { levels: DepthLevel::TWENTY, delay: Delay::ONEHUNDRED };
+//! api.subscribe(
+//!     &[
+//!         SubscribeInfo::new(Symbol::BTCUSDT, depth),
+//!         SubscribeInfo::new(Symbol::BTCUSDT, Feed::AggTrade),
+//!     ],
+//!     None,
+//! )
+//! .await;
+//!
+//! let mut view = BookView::new(Symbol::BTCUSDT, 100);
+//! let mut terminal = ratatui::init();
+//! while let Some(msg) = api.next_message().await {
+//!     view.push_message(&msg);
+//!     terminal.draw(|frame| frame.render_widget(&view, frame.area()))?;
+//! }
+//! ratatui::restore();
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Partial depth messages do not name their symbol, subscribe to the depth of one symbol only.
+//! The `view` command of `binance-stream` runs the viewer.
+
+use ratatui::buffer::Buffer;
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Paragraph, Row, Table, Widget};
+use rust_decimal::Decimal;
+
+use crate::messages::{PartialDepth, TradeEvent};
+use crate::tape::Tape;
+use crate::{Message, Symbol};
+
+/// The spread of the book, now and since the view was created.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpreadStats {
+    pub mid_price: Decimal,
+    pub spread: Decimal,
+    /// Spread in percent of the best ask.
+    pub spread_percent: Decimal,
+    /// Volume imbalance of the levels, see [`PartialDepth::imbalance()`].
+    pub imbalance: Option<Decimal>,
+    pub min_spread: Decimal,
+    pub max_spread: Decimal,
+    pub avg_spread: Decimal,
+}
+
+/// Order book, trades and spread of one symbol, see the [module](self) documentation.
+#[derive(Debug, Clone)]
+pub struct BookView {
+    symbol: Symbol,
+    book: Option<PartialDepth>,
+    tape: Tape,
+    /// min, max and sum of the spreads of all books, and their count
+    spreads: Option<(Decimal, Decimal, Decimal, u64)>,
+}
+
+impl BookView {
+    /// A view of `symbol` keeping its last `trades` trades.
+    pub fn new(symbol: Symbol, trades: usize) -> Self {
+        Self {
+            symbol,
+            book: None,
+            tape: Tape::new(trades, None),
+            spreads: None,
+        }
+    }
+
+    /// Show `msg` if it is a partial depth or a trade of the symbol, other messages are
+    /// ignored.
+    pub fn push_message(&mut self, msg: &Message) {
+        match msg {
+            Message::PartialDepth(book) => self.push_book(book.as_ref().clone()),
+            Message::AggTrade(t) if t.symbol() == &self.symbol => self.tape.push(t),
+            Message::Trade(t) if t.symbol() == &self.symbol => self.tape.push(t),
+            _ => {}
+        }
+    }
+
+    fn push_book(&mut self, book: PartialDepth) {
+        if let Some(spread) = book.spread() {
+            let (min, max, sum, n) = self
+                .spreads
+                .get_or_insert((spread, spread, Decimal::ZERO, 0));
+            *min = (*min).min(spread);
+            *max = (*max).max(spread);
+            *sum += spread;
+            *n += 1;
+        }
+        self.book = Some(book);
+    }
+
+    /// The spread stats, `None` until a book with both sides was pushed.
+    pub fn stats(&self) -> Option<SpreadStats> {
+        let book = self.book.as_ref()?;
+        let (min, max, sum, n) = self.spreads?;
+        let spread = book.spread()?;
+        let best_ask = book.best_ask()?[0];
+        Some(SpreadStats {
+            mid_price: book.mid_price()?,
+            spread,
+            spread_percent: if best_ask.is_zero() {
+                Decimal::ZERO
+            } else {
+                spread / best_ask * Decimal::ONE_HUNDRED
+            },
+            imbalance: book.imbalance(book.bids.len().max(book.asks.len())),
+            min_spread: min,
+            max_spread: max,
+            avg_spread: sum / Decimal::from(n),
+        })
+    }
+
+    pub fn symbol(&self) -> &Symbol {
+        &self.symbol
+    }
+
+    /// The last partial depth pushed.
+    pub fn book(&self) -> Option<&PartialDepth> {
+        self.book.as_ref()
+    }
+
+    pub fn tape(&self) -> &Tape {
+        &self.tape
+    }
+
+    /// The symbol as Binance writes it, e.g. `BTCUSDT`.
+    fn name(&self) -> String {
+        self.symbol.to_string().to_uppercase()
+    }
+
+    fn stats_line(&self) -> Line<'static> {
+        let Some(s) = self.stats() else {
+            return Line::from(format!("{} waiting for the book", self.name()));
+        };
+        let imbalance = s.imbalance.map_or("-".to_string(), |i| format!("{i:.3}"));
+        Line::from(format!(
+            "{}  mid {}  spread {} ({:.3}%)  imbalance {imbalance}  spread min {} avg {} max {}",
+            self.name(),
+            s.mid_price.normalize(),
+            s.spread,
+            s.spread_percent,
+            s.min_spread,
+            s.avg_spread.round_dp(8).normalize(),
+            s.max_spread,
+        ))
+    }
+
+    fn ladder(&self) -> Table<'static> {
+        let level = |[price, quantity]: [Decimal; 2], color| {
+            Row::new([price.to_string(), quantity.to_string()]).style(Style::new().fg(color))
+        };
+        let (asks, bids) = match &self.book {
+            Some(book) => (book.asks.as_slice(), book.bids.as_slice()),
+            None => (&[][..], &[][..]),
+        };
+        let rows = asks
+            .iter()
+            .rev()
+            .map(|l| level(*l, Color::Red))
+            .chain(bids.iter().map(|l| level(*l, Color::Green)));
+        Table::new(rows, [Constraint::Fill(1), Constraint::Fill(1)])
+            .header(Row::new(["Price", "Quantity"]))
+            .block(Block::bordered().title("Book"))
+    }
+
+    fn trades(&self) -> Table<'static> {
+        let rows = self.tape.iter().rev().map(|t| {
+            let time = chrono::DateTime::from_timestamp_millis(t.time as i64)
+                .unwrap_or_default()
+                .format("%H:%M:%S%.3f");
+            // the buyer made the market, the aggressor sold
+            let color = if t.is_market_maker {
+                Color::Red
+            } else {
+                Color::Green
+            };
+            Row::new([
+                time.to_string(),
+                t.price.to_string(),
+                t.quantity.to_string(),
+            ])
+            .style(Style::new().fg(color))
+        });
+        let widths = [
+            Constraint::Length(12),
+            Constraint::Fill(1),
+            Constraint::Fill(1),
+        ];
+        Table::new(rows, widths)
+            .header(Row::new(["Time", "Price", "Quantity"]))
+            .block(Block::bordered().title("Trades"))
+    }
+}
+
+impl Widget for &BookView {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let [stats, panes] =
+            Layout::vertical([Constraint::Length(1), Constraint::Fill(1)]).areas(area);
+        let [ladder, trades] =
+            Layout::horizontal([Constraint::Percentage(50), Constraint::Percentage(50)])
+                .areas(panes);
+        Paragraph::new(self.stats_line()).render(stats, buf);
+        Widget::render(self.ladder(), ladder, buf);
+        Widget::render(self.trades(), trades, buf);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::messages::AggTrade;
+    use crate::test_util;
+    use smallvec::smallvec;
+
+    fn book(bid: i64, ask: i64) -> Message {
+        Message::PartialDepth(Box::new(PartialDepth {
+            last_update_id: 1,
+            bids: smallvec![
+                [Decimal::from(bid), Decimal::ONE],
+                [Decimal::from(bid - 1), Decimal::TWO]
+            ],
+            asks: smallvec![[Decimal::from(ask), Decimal::ONE]],
+        }))
+    }
+
+    fn trade(symbol: Symbol, trade_id: u64) -> Message {
+        Message::AggTrade(AggTrade {
+            event_time: trade_id,
+            price: Decimal::from(trade_id),
+            trade_time: trade_id,
+            ..test_util::agg_trade(symbol, trade_id)
+        })
+    }
+
+    #[test]
+    fn ladder_tape_and_stats() {
+        let mut view = BookView::new(Symbol::BTCUSDT, 2);
+        assert_eq!(view.stats(), None);
+
+        view.push_message(&book(99, 101));
+        view.push_message(&book(99, 100));
+        for id in 1..=3 {
+            view.push_message(&trade(Symbol::BTCUSDT, id));
+        }
+        view.push_message(&trade(Symbol::ETHBTC, 4));
+
+        let stats = view.stats().unwrap();
+        assert_eq!(stats.mid_price, Decimal::new(995, 1));
+        assert_eq!(stats.spread, Decimal::ONE);
+        assert_eq!(stats.spread_percent, Decimal::ONE);
+        assert_eq!(stats.max_spread, Decimal::TWO);
+        assert_eq!(stats.avg_spread, Decimal::new(15, 1));
+        assert_eq!(view.tape().len(), 2);
+
+        let area = Rect::new(0, 0, 100, 10);
+        let mut buf = Buffer::empty(area);
+        (&view).render(area, &mut buf);
+        let lines: Vec<String> = (0..area.height)
+            .map(|y| (0..area.width).map(|x| buf[(x, y)].symbol()).collect())
+            .collect();
+        assert!(lines[0].starts_with("BTCUSDT  mid 99.5  spread 1 (1.000%)"));
+        assert!(lines[0].contains("min 1 avg 1.5 max 2"));
+        // the ask above the bids, the newest trade first
+        assert!(lines[3].contains("100 ") && lines[3].contains(" 3 "));
+        assert!(lines[4].contains("99 ") && lines[4].contains(" 2 "));
+        assert!(lines[5].contains("98 "));
+    }
+}
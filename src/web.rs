@@ -0,0 +1,269 @@
+//! Streams in the browser, with the `wasm` feature.
+//!
+//! [`WebSocketClient`] drives a [`Session`] over the browser websocket of web-sys instead of
+//! tokio-tungstenite, a web dashboard gets the same [`Message`]s and subscription bookkeeping
+//! as [`BinanceApi`](crate::BinanceApi):
+//! ```no_run
+//! use binance_api_async::web::WebSocketClient;
+//! use binance_api_async::{Feed, SubscribeInfo, Symbol};
+//!
+//! # async fn run() -> Result<(), binance_api_async::Error> {
+//! let mut client = WebSocketClient::connect("wss://stream.binance.com:9443/ws").await?;
+//! client.subscribe(&[SubscribeInfo::new(Symbol::BTCUSDT, Feed::Trade)], None)?;
+//! while let Some(msg) = client.next_message().await {
+//!     println!("{msg:?}");
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! The browser answers pings itself, and reconnecting is left to the page, connect a new
+//! client once [`WebSocketClient::next_message()`] returns `None`. Build for
+//! `wasm32-unknown-unknown`, outside a browser the websocket is not available.
+
+use futures::channel::mpsc::{self, UnboundedReceiver, UnboundedSender};
+use futures::StreamExt;
+use js_sys::{ArrayBuffer, Uint8Array};
+use tracing::{error, info, warn};
+use tungstenite::protocol::{frame::coding::CloseCode, CloseFrame};
+use wasm_bindgen::closure::Closure;
+use wasm_bindgen::{JsCast, JsValue};
+use web_sys::{BinaryType, CloseEvent, Event, MessageEvent, WebSocket};
+
+use crate::logging::{CONNECTION, SUBSCRIPTION};
+use crate::session::{Data, Outgoing, Session};
+use crate::{Error, Message, SubscribeInfo};
+
+/// What the websocket reported, in the order of its callbacks.
+enum SocketEvent {
+    Open,
+    Data(Data),
+    Error,
+    Close { code: u16, reason: String },
+}
+
+/// The callbacks of the websocket, dropped with the client.
+struct Handlers {
+    _open: Closure<dyn FnMut(Event)>,
+    _message: Closure<dyn FnMut(MessageEvent)>,
+    _error: Closure<dyn FnMut(Event)>,
+    _close: Closure<dyn FnMut(CloseEvent)>,
+}
+
+/// A Binance stream connection over the browser websocket, see the [module](self)
+/// documentation.
+pub struct WebSocketClient {
+    socket: WebSocket,
+    session: Session,
+    events: UnboundedReceiver<SocketEvent>,
+    /// close frame of the server, once closed
+    close_frame: Option<CloseFrame<'static>>,
+    _handlers: Handlers,
+}
+
+impl std::fmt::Debug for WebSocketClient {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WebSocketClient")
+            .field("url", &self.socket.url())
+            .field("session", &self.session)
+            .field("close_frame", &self.close_frame)
+            .finish_non_exhaustive()
+    }
+}
+
+impl WebSocketClient {
+    /// Open a websocket to `url`, e.g. `wss://stream.binance.com:9443/ws`.
+    ///
+    /// [`Error::Browser`] if the browser refuses the url, [`Error::ConnectionClosed`] if the
+    /// connection could not be opened.
+    pub async fn connect(url: &str) -> crate::Result<Self> {
+        Self::with_session(url, Session::new()).await
+    }
+
+    /// Open a websocket to `url`, driving `session`, e.g. to count into the
+    /// [`Metrics`](crate::metrics::Metrics) of a session created beforehand. The session is
+    /// reset.
+    pub async fn with_session(url: &str, mut session: Session) -> crate::Result<Self> {
+        let socket = WebSocket::new(url).map_err(browser_error)?;
+        // binary frames as ArrayBuffer rather than Blob, to read them synchronously
+        socket.set_binary_type(BinaryType::Arraybuffer);
+        let (sender, events) = mpsc::unbounded();
+        let handlers = Handlers::attach(&socket, sender);
+        session.reset();
+        let mut client = Self {
+            socket,
+            session,
+            events,
+            close_frame: None,
+            _handlers: handlers,
+        };
+        loop {
+            match client.events.next().await {
+                Some(SocketEvent::Open) => break,
+                Some(SocketEvent::Close { code, reason }) => {
+                    client.closed(code, reason);
+                    return Err(Error::ConnectionClosed {
+                        frame: client.close_frame.take(),
+                    });
+                }
+                // the close event follows with the code
+                Some(SocketEvent::Error | SocketEvent::Data(_)) => {}
+                None => return Err(Error::ConnectionClosed { frame: None }),
+            }
+        }
+        info!(target: CONNECTION, url, "Connected");
+        Ok(client)
+    }
+
+    /// Subscribe to the streams of `symbols`, see
+    /// [`BinanceApi::subscribe()`](crate::BinanceApi::subscribe).
+    ///
+    /// [`Error::UnsupportedFeed`] if a feed is not served on spot, [`Error::Browser`] if the
+    /// request could not be sent.
+    pub fn subscribe(&mut self, symbols: &[SubscribeInfo], id: Option<u64>) -> crate::Result<()> {
+        match self.session.subscribe(symbols, id)? {
+            Some(request) => self.send(request),
+            None => Ok(()),
+        }
+    }
+
+    /// Unsubscribe from the streams of `symbols`, see
+    /// [`BinanceApi::unsubscribe()`](crate::BinanceApi::unsubscribe).
+    pub fn unsubscribe(&mut self, symbols: &[SubscribeInfo]) -> crate::Result<()> {
+        let (request, result) = self.session.unsubscribe(symbols);
+        if let Some(request) = request {
+            self.send(request)?;
+        }
+        result
+    }
+
+    fn send(&mut self, request: Outgoing) -> crate::Result<()> {
+        match self.socket.send_with_str(request.text()) {
+            Ok(()) => {
+                self.session.sent(request);
+                Ok(())
+            }
+            Err(e) => {
+                let e = browser_error(e);
+                error!(target: SUBSCRIPTION, parent: request.span(), "Error when sending: {e}");
+                self.session.not_sent(request);
+                Err(e)
+            }
+        }
+    }
+
+    /// The next message, `None` once the connection is closed, see
+    /// [`WebSocketClient::close_frame()`].
+    pub async fn next_message(&mut self) -> Option<Message> {
+        if let Some(ack) = self.session.take_ack() {
+            return Some(ack);
+        }
+        loop {
+            match self.events.next().await? {
+                SocketEvent::Data(data) => {
+                    let recv_time = js_sys::Date::now() as u64;
+                    if let Some(msg) = self.session.receive(data, recv_time) {
+                        return Some(msg);
+                    }
+                }
+                SocketEvent::Close { code, reason } => {
+                    self.closed(code, reason);
+                    return None;
+                }
+                SocketEvent::Error => warn!(target: CONNECTION, "Websocket error"),
+                SocketEvent::Open => {}
+            }
+        }
+    }
+
+    fn closed(&mut self, code: u16, reason: String) {
+        info!(target: CONNECTION, code, reason, "Closed by server");
+        self.close_frame = Some(CloseFrame {
+            code: CloseCode::from(code),
+            reason: reason.into(),
+        });
+    }
+
+    /// The close frame of the server, `None` while connected.
+    pub fn close_frame(&self) -> Option<&CloseFrame<'static>> {
+        self.close_frame.as_ref()
+    }
+
+    /// The session of the connection, with its subscriptions and
+    /// [`metrics`](Session::metrics).
+    pub fn session(&self) -> &Session {
+        &self.session
+    }
+
+    /// Close the websocket.
+    pub fn disconnect(self) {
+        drop(self);
+    }
+}
+
+impl Drop for WebSocketClient {
+    fn drop(&mut self) {
+        // the callbacks are dropped with the client, the socket must not call them anymore
+        self.socket.set_onopen(None);
+        self.socket.set_onmessage(None);
+        self.socket.set_onerror(None);
+        self.socket.set_onclose(None);
+        let _ = self.socket.close();
+    }
+}
+
+impl Handlers {
+    /// Forward the events of `socket` to `sender`.
+    fn attach(socket: &WebSocket, sender: UnboundedSender<SocketEvent>) -> Self {
+        let forward = |sender: &UnboundedSender<SocketEvent>| {
+            let sender = sender.clone();
+            move |event: SocketEvent| {
+                let _ = sender.unbounded_send(event);
+            }
+        };
+
+        let send = forward(&sender);
+        let open = Closure::<dyn FnMut(Event)>::new(move |_| send(SocketEvent::Open));
+        let send = forward(&sender);
+        let message = Closure::<dyn FnMut(MessageEvent)>::new(move |event: MessageEvent| {
+            let data = event.data();
+            if let Some(text) = data.as_string() {
+                send(SocketEvent::Data(Data::Text(text)));
+            } else if let Some(buffer) = data.dyn_ref::<ArrayBuffer>() {
+                send(SocketEvent::Data(Data::Binary(
+                    Uint8Array::new(buffer).to_vec(),
+                )));
+            }
+        });
+        let send = forward(&sender);
+        let error = Closure::<dyn FnMut(Event)>::new(move |_| send(SocketEvent::Error));
+        let send = forward(&sender);
+        let close = Closure::<dyn FnMut(CloseEvent)>::new(move |event: CloseEvent| {
+            send(SocketEvent::Close {
+                code: event.code(),
+                reason: event.reason(),
+            })
+        });
+
+        socket.set_onopen(Some(open.as_ref().unchecked_ref()));
+        socket.set_onmessage(Some(message.as_ref().unchecked_ref()));
+        socket.set_onerror(Some(error.as_ref().unchecked_ref()));
+        socket.set_onclose(Some(close.as_ref().unchecked_ref()));
+        Self {
+            _open: open,
+            _message: message,
+            _error: error,
+            _close: close,
+        }
+    }
+}
+
+/// [`Error::Browser`] of an exception thrown by the browser.
+fn browser_error(e: JsValue) -> Error {
+    let msg = e
+        .dyn_ref::<js_sys::Error>()
+        .map(|e| String::from(e.message()))
+        .or_else(|| e.as_string())
+        .unwrap_or_else(|| format!("{e:?}"));
+    Error::Browser(msg)
+}